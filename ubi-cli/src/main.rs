@@ -36,7 +36,7 @@ async fn main() {
     };
     let status = match make_ubi(&matches, &ubi_exe_path) {
         Ok((mut u, post_run)) => match u.install_binary().await {
-            Ok(()) => {
+            Ok(_) => {
                 if let Some(post_run) = post_run {
                     post_run();
                 }
@@ -124,6 +124,21 @@ fn cmd() -> Command {
                     " when this is set.",
                 )),
         )
+        .arg(
+            Arg::new("post-install")
+                .long("post-install")
+                .value_name("command")
+                .allow_hyphen_values(true)
+                .help(concat!(
+                    "A command to run for each installed executable after it is installed, given",
+                    " as a single argument, like `--post-install 'codesign --sign - {}'`. The",
+                    " command is split on whitespace, and no other shell quoting is supported.",
+                    " Each `{}` in the command is replaced with the path of the installed",
+                    " executable. If there is no `{}`, the path is added to the end of the command.",
+                    " If the command fails, `ubi` exits with an error. You cannot pass",
+                    " `--extract-all` when this is set.",
+                )),
+        )
         .arg(
             Arg::new("matching")
                 .long("matching")
@@ -226,6 +241,9 @@ fn make_ubi<'a>(
     if matches.get_flag("extract-all") {
         builder = builder.extract_all();
     }
+    if let Some(command) = matches.get_one::<String>("post-install") {
+        builder = builder.post_install_command(&command.split_whitespace().collect::<Vec<_>>());
+    }
     if let Some(ft) = matches.get_one::<String>("forge") {
         builder = builder.forge(ForgeType::from_str(ft)?);
     }
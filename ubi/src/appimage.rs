@@ -0,0 +1,70 @@
+// An AppImage is an ELF executable with a squashfs filesystem appended to it. We don't have a
+// squashfs reader, but every AppImage runtime supports the `--appimage-extract` flag, which
+// unpacks that filesystem into a `squashfs-root` directory in the current directory without
+// needing FUSE. This means the AppImage must be one that can run on this platform.
+
+use crate::ubi::new_temp_dir;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tempfile::TempDir;
+
+/// Runs the `AppImage` at `appimage` to unpack its contents. This returns the temp dir that the
+/// contents are in, which is deleted when it's dropped, along with the path of the unpacked tree
+/// in that dir. The `AppImage` is copied before it's run, so the file at `appimage` is not changed.
+/// The temp dir is created in `temp_dir`, or in the system's temp directory when that's `None`.
+pub(crate) fn extract(appimage: &Path, temp_dir: Option<&Path>) -> Result<(TempDir, PathBuf)> {
+    let td = new_temp_dir(temp_dir)?;
+    let file_name = appimage
+        .file_name()
+        .ok_or_else(|| anyhow!("{} has no file name", appimage.display()))?;
+    let exe = td.path().join(file_name);
+    fs::copy(appimage, &exe)
+        .with_context(|| format!("could not copy {} to {}", appimage.display(), exe.display()))?;
+    make_executable(&exe)?;
+
+    let mut cmd = Command::new(&exe);
+    cmd.arg("--appimage-extract").current_dir(td.path());
+    debug!("running {cmd:?}");
+    let output = cmd
+        .output()
+        .with_context(|| format!("could not run {}", appimage.display()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "running {} with --appimage-extract failed ({}): {}",
+            appimage.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        ));
+    }
+
+    let root = td.path().join("squashfs-root");
+    if !root.is_dir() {
+        return Err(anyhow!(
+            "running {} with --appimage-extract did not create a squashfs-root directory",
+            appimage.display(),
+        ));
+    }
+
+    Ok((td, root))
+}
+
+#[cfg(target_family = "unix")]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("could not make {} executable", path.display()))
+}
+
+#[cfg(target_family = "windows")]
+fn make_executable(path: &Path) -> Result<()> {
+    Err(anyhow!(
+        "cannot extract the AppImage at {} because AppImages can only be run on Linux",
+        path.display(),
+    ))
+}
@@ -0,0 +1,228 @@
+// This reads just enough of an executable's header to tell which CPU architecture it was built for.
+// It understands ELF, Mach-O (including universal binaries), and PE files. Anything else, like a
+// shell script, has no architecture as far as we're concerned.
+//
+// See https://refspecs.linuxfoundation.org/elf/gabi4+/ch4.eheader.html,
+// https://github.com/apple-oss-distributions/xnu/blob/main/EXTERNAL_HEADERS/mach-o/loader.h, and
+// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format for the header layouts.
+
+use platforms::{Arch, Platform, OS};
+
+/// How many bytes from the start of a file we need to find its architecture. A PE file's header
+/// can be anywhere, but in practice it's always near the start.
+pub(crate) const HEADER_LEN: usize = 4096;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const MACHO_MAGICS: &[[u8; 4]] = &[
+    [0xfe, 0xed, 0xfa, 0xce],
+    [0xfe, 0xed, 0xfa, 0xcf],
+    [0xce, 0xfa, 0xed, 0xfe],
+    [0xcf, 0xfa, 0xed, 0xfe],
+];
+const MACHO_FAT_MAGIC: &[u8] = b"\xca\xfe\xba\xbe";
+// Java class files start with the same magic as a universal binary. The next field is the class
+// file version there, which is always much larger than the number of architectures in a
+// universal binary.
+const MAX_FAT_ARCHES: u32 = 32;
+const CPU_ARCH_ABI64: u32 = 0x0100_0000;
+const CPU_TYPE_X86: u32 = 7;
+const CPU_TYPE_ARM: u32 = 12;
+const CPU_TYPE_POWERPC: u32 = 18;
+
+/// Returns every architecture that the executable starting with `header` was built for. This is
+/// empty if the header isn't one we know how to read. Only universal binaries have more than one
+/// architecture.
+pub(crate) fn arches_in_header(header: &[u8]) -> Vec<Arch> {
+    if header.starts_with(ELF_MAGIC) {
+        return elf_arch(header).into_iter().collect();
+    }
+    if MACHO_MAGICS.iter().any(|m| header.starts_with(m)) {
+        return macho_arch(header).into_iter().collect();
+    }
+    if header.starts_with(MACHO_FAT_MAGIC) {
+        return macho_fat_arches(header);
+    }
+    if header.starts_with(b"MZ") {
+        return pe_arch(header).into_iter().collect();
+    }
+    vec![]
+}
+
+/// Returns true if an executable built for `arch` can run on `platform`. Besides an exact match,
+/// this allows 32-bit x86 executables on x86-64, and x86-64 executables on ARM64 macOS and
+/// Windows, which can run them through emulation.
+pub(crate) fn can_run(platform: &Platform, arch: Arch) -> bool {
+    if platform.target_arch == arch {
+        return true;
+    }
+    match (platform.target_arch, arch) {
+        (Arch::X86_64, Arch::X86) => true,
+        (Arch::AArch64, Arch::X86_64) => {
+            matches!(platform.target_os, OS::MacOS | OS::Windows)
+        }
+        (Arch::AArch64, Arch::X86) => platform.target_os == OS::Windows,
+        _ => false,
+    }
+}
+
+fn elf_arch(header: &[u8]) -> Option<Arch> {
+    let is_64_bit = *header.get(4)? == 2;
+    let machine = match header.get(5)? {
+        1 => u16::from_le_bytes(header.get(18..20)?.try_into().ok()?),
+        2 => u16::from_be_bytes(header.get(18..20)?.try_into().ok()?),
+        _ => return None,
+    };
+    Some(match machine {
+        2 | 18 => Arch::Sparc,
+        3 => Arch::X86,
+        8 if is_64_bit => Arch::Mips64,
+        8 => Arch::Mips,
+        20 => Arch::PowerPc,
+        21 => Arch::PowerPc64,
+        22 => Arch::S390X,
+        40 => Arch::Arm,
+        43 => Arch::Sparc64,
+        62 => Arch::X86_64,
+        183 => Arch::AArch64,
+        243 if is_64_bit => Arch::Riscv64,
+        243 => Arch::Riscv32,
+        258 => Arch::Loongarch64,
+        _ => return None,
+    })
+}
+
+fn macho_arch(header: &[u8]) -> Option<Arch> {
+    let cpu_type = header.get(4..8)?.try_into().ok()?;
+    // The magic is in the file's byte order, so if it reads as `feedface` in big-endian order,
+    // the rest of the header is big-endian too.
+    let cpu_type = if header[0] == 0xfe {
+        u32::from_be_bytes(cpu_type)
+    } else {
+        u32::from_le_bytes(cpu_type)
+    };
+    macho_cpu_type_arch(cpu_type)
+}
+
+// The header of a universal binary is always big-endian. It's followed by a 20 byte entry for each
+// architecture, which starts with the CPU type.
+fn macho_fat_arches(header: &[u8]) -> Vec<Arch> {
+    let Some(count) = read_be_u32(header, 4) else {
+        return vec![];
+    };
+    if count > MAX_FAT_ARCHES {
+        return vec![];
+    }
+    (0..count as usize)
+        .filter_map(|i| read_be_u32(header, 8 + i * 20))
+        .filter_map(macho_cpu_type_arch)
+        .collect()
+}
+
+fn macho_cpu_type_arch(cpu_type: u32) -> Option<Arch> {
+    Some(match cpu_type {
+        CPU_TYPE_X86 => Arch::X86,
+        CPU_TYPE_ARM => Arch::Arm,
+        CPU_TYPE_POWERPC => Arch::PowerPc,
+        t if t == CPU_TYPE_X86 | CPU_ARCH_ABI64 => Arch::X86_64,
+        t if t == CPU_TYPE_ARM | CPU_ARCH_ABI64 => Arch::AArch64,
+        t if t == CPU_TYPE_POWERPC | CPU_ARCH_ABI64 => Arch::PowerPc64,
+        _ => return None,
+    })
+}
+
+// The offset of the PE header is at 0x3c in the DOS header. The PE header is the `PE\0\0`
+// signature followed by the machine type.
+fn pe_arch(header: &[u8]) -> Option<Arch> {
+    let offset =
+        usize::try_from(u32::from_le_bytes(header.get(0x3c..0x40)?.try_into().ok()?)).ok()?;
+    if header.get(offset..offset + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = u16::from_le_bytes(header.get(offset + 4..offset + 6)?.try_into().ok()?);
+    Some(match machine {
+        0x14c => Arch::X86,
+        0x1c0 | 0x1c4 => Arch::Arm,
+        0x8664 => Arch::X86_64,
+        0xaa64 => Arch::AArch64,
+        _ => return None,
+    })
+}
+
+fn read_be_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn elf(class: u8, data: u8, machine: u16) -> Vec<u8> {
+        let mut header = vec![0; 64];
+        header[..4].copy_from_slice(ELF_MAGIC);
+        header[4] = class;
+        header[5] = data;
+        let machine = if data == 1 {
+            machine.to_le_bytes()
+        } else {
+            machine.to_be_bytes()
+        };
+        header[18..20].copy_from_slice(&machine);
+        header
+    }
+
+    fn pe(machine: u16) -> Vec<u8> {
+        let mut header = vec![0; 0x100];
+        header[..2].copy_from_slice(b"MZ");
+        header[0x3c..0x40].copy_from_slice(&0x80_u32.to_le_bytes());
+        header[0x80..0x84].copy_from_slice(b"PE\0\0");
+        header[0x84..0x86].copy_from_slice(&machine.to_le_bytes());
+        header
+    }
+
+    fn macho_fat(cpu_types: &[u32]) -> Vec<u8> {
+        let mut header = MACHO_FAT_MAGIC.to_vec();
+        header.extend(u32::try_from(cpu_types.len()).unwrap().to_be_bytes());
+        for t in cpu_types {
+            header.extend(t.to_be_bytes());
+            header.extend([0; 16]);
+        }
+        header
+    }
+
+    #[test_case(&elf(2, 1, 62), &[Arch::X86_64]; "ELF x86-64")]
+    #[test_case(&elf(2, 1, 183), &[Arch::AArch64]; "ELF aarch64")]
+    #[test_case(&elf(1, 1, 3), &[Arch::X86]; "ELF x86")]
+    #[test_case(&elf(2, 2, 22), &[Arch::S390X]; "ELF big-endian s390x")]
+    #[test_case(&elf(1, 2, 8), &[Arch::Mips]; "ELF big-endian mips")]
+    #[test_case(&elf(2, 1, 8), &[Arch::Mips64]; "ELF mips64")]
+    #[test_case(&elf(2, 1, 9999), &[]; "ELF unknown machine")]
+    #[test_case(b"\xcf\xfa\xed\xfe\x0c\x00\x00\x01", &[Arch::AArch64]; "Mach-O arm64")]
+    #[test_case(b"\xcf\xfa\xed\xfe\x07\x00\x00\x01", &[Arch::X86_64]; "Mach-O x86-64")]
+    #[test_case(b"\xfe\xed\xfa\xce\x00\x00\x00\x12", &[Arch::PowerPc]; "Mach-O big-endian ppc")]
+    #[test_case(&macho_fat(&[0x0100_0007, 0x0100_000c]), &[Arch::X86_64, Arch::AArch64]; "Mach-O universal")]
+    #[test_case(b"\xca\xfe\xba\xbe\x00\x00\x00\x34", &[]; "Java class file")]
+    #[test_case(&pe(0x8664), &[Arch::X86_64]; "PE x86-64")]
+    #[test_case(&pe(0xaa64), &[Arch::AArch64]; "PE arm64")]
+    #[test_case(&pe(0x14c), &[Arch::X86]; "PE x86")]
+    #[test_case(b"MZ", &[]; "truncated PE")]
+    #[test_case(b"#!/bin/sh\necho hello\n", &[]; "shell script")]
+    #[test_case(b"", &[]; "empty")]
+    fn arches_in_header(header: &[u8], expect: &[Arch]) {
+        assert_eq!(super::arches_in_header(header), expect);
+    }
+
+    #[test_case("x86_64-unknown-linux-gnu", Arch::X86_64, true)]
+    #[test_case("x86_64-unknown-linux-gnu", Arch::X86, true)]
+    #[test_case("x86_64-unknown-linux-gnu", Arch::AArch64, false)]
+    #[test_case("aarch64-unknown-linux-gnu", Arch::X86_64, false)]
+    #[test_case("aarch64-apple-darwin", Arch::X86_64, true)]
+    #[test_case("aarch64-pc-windows-msvc", Arch::X86, true)]
+    #[test_case("i686-unknown-linux-gnu", Arch::X86_64, false)]
+    fn can_run(triple: &str, arch: Arch, expect: bool) {
+        let platform = Platform::find(triple).unwrap();
+        assert_eq!(super::can_run(platform, arch), expect);
+    }
+}
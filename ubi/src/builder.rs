@@ -1,39 +1,98 @@
 /// The `builder` module contains the `UbiBuilder` struct which is used to create a `Ubi` instance.
 use crate::{
+    checksum::ChecksumAlgorithm,
+    completion::Shell,
+    extension::Extension,
+    filesystem::{FileSystem, RealFs},
     forge::{Forge, ForgeType},
     github::GitHub,
     gitlab::GitLab,
-    installer::{ArchiveInstaller, ExeInstaller, Installer},
+    installer::{
+        ArchiveInstaller, ExeInstaller, Installer, OnExisting, PathMapper, PostInstall, Progress,
+    },
+    minisign,
     picker::AssetPicker,
-    ubi::Ubi,
+    ubi::{AssetChecks, Ubi},
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use log::debug;
 use platforms::{Platform, PlatformReq, OS};
+use regex::Regex;
 use reqwest::{
     header::{HeaderMap, HeaderValue, USER_AGENT},
     Client,
 };
 use std::{
+    collections::HashMap,
     env,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
 };
 use url::Url;
 use which::which;
 
 /// `UbiBuilder` is used to create a [`Ubi`] instance.
 #[derive(Debug, Default)]
-#[allow(clippy::module_name_repetitions)]
+#[allow(clippy::module_name_repetitions, clippy::struct_excessive_bools)]
 pub struct UbiBuilder<'a> {
     project: Option<&'a str>,
     tag: Option<&'a str>,
     url: Option<&'a str>,
+    archive_file: Option<PathBuf>,
     install_dir: Option<PathBuf>,
     matching: Option<&'a str>,
     exe: Option<&'a str>,
+    alternative_exes: Vec<&'a str>,
+    non_executable_extensions: Vec<&'a str>,
     rename_exe_to: Option<&'a str>,
+    match_by_digest: Option<(ChecksumAlgorithm, &'a str)>,
+    exe_pattern: Option<&'a str>,
+    exe_dir_in_archive: Option<PathBuf>,
+    exe_path_in_archive: Option<PathBuf>,
+    keep_exe_name: bool,
+    reject_ambiguous_exe_matches: bool,
+    reject_ambiguous_partial_exe_matches: bool,
+    fall_back_to_sole_exe: bool,
+    verify_exe_runs: Option<Vec<&'a str>>,
+    post_install_callback: Option<PostInstall>,
+    post_install_command: Option<Vec<&'a str>>,
+    install_mode: Option<u32>,
+    install_dir_mode: Option<u32>,
+    skip_chmod: bool,
+    strict_arch_check: bool,
+    backup_existing_exe: bool,
+    remove_backup_after_install: bool,
+    preserve_mtimes: bool,
+    require_regular_file: bool,
+    completion_dirs: Vec<(Shell, PathBuf)>,
+    man_dir: Option<PathBuf>,
+    bin_dir: Option<PathBuf>,
+    path_mapper: Option<PathMapper>,
+    progress: Option<Progress>,
+    on_existing: OnExisting,
     extract_all: bool,
+    report_skipped_members: bool,
+    stage_extraction_in_temp_dir: bool,
+    temp_dir: Option<PathBuf>,
+    keep_archive: Option<PathBuf>,
+    replace_install_dir: bool,
+    flatten_executables: bool,
+    flatten_executable_names: Vec<&'a str>,
+    extract_appimage: bool,
+    parallel_zip_extraction: bool,
+    zip_password: Option<&'a str>,
+    custom_decompressors: HashMap<&'a str, Vec<&'a str>>,
+    strip_components: Option<usize>,
+    verify_archive_digest: Option<(ChecksumAlgorithm, &'a str)>,
+    checksums_file: Option<&'a str>,
+    checksums_algorithm: Option<ChecksumAlgorithm>,
+    minisign_public_key: Option<&'a str>,
+    #[cfg(feature = "gpg")]
+    gpg_key_file: Option<PathBuf>,
+    #[cfg(feature = "slsa")]
+    slsa_provenance: Option<(&'a str, &'a str)>,
+    filesystem: Option<Arc<dyn FileSystem>>,
     github_token: Option<&'a str>,
     gitlab_token: Option<&'a str>,
     platform: Option<&'a Platform>,
@@ -82,6 +141,19 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
+    /// Install from a release asset that is already on disk instead of downloading one. The file
+    /// name's extension determines how it is unpacked, just as it does for a downloaded asset. The
+    /// file is read in place and is not moved or deleted.
+    ///
+    /// You still need to set `project`, since its name is used to find the executable in the
+    /// archive, but nothing is fetched from the forge site. You cannot set this with `url`, `tag`,
+    /// or any of the options that verify the asset against a sibling asset in the release.
+    #[must_use]
+    pub fn archive_file<P: AsRef<Path>>(mut self, archive_file: P) -> Self {
+        self.archive_file = Some(archive_file.as_ref().to_path_buf());
+        self
+    }
+
     /// Set the directory to install the binary in. If not set, it will default to `./bin`.
     #[must_use]
     pub fn install_dir<P: AsRef<Path>>(mut self, install_dir: P) -> Self {
@@ -110,6 +182,31 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
+    /// Set other names that the executable might have in archive files, for projects that have
+    /// renamed their executable between releases, like `foo` to `foo2`. These are tried after the
+    /// name set with `exe`, or the project name, in the order given. An exact match for any of the
+    /// names is preferred over a partial match. The executable is installed under the name it
+    /// would have had without this, so it stays the same across releases.
+    ///
+    /// You cannot call `extract_all` or `exe_pattern` if you set this.
+    #[must_use]
+    pub fn alternative_exes(mut self, exes: &[&'a str]) -> Self {
+        self.alternative_exes = exes.to_vec();
+        self
+    }
+
+    /// Set extensions for files in archive files that are never installed as a partial match for
+    /// the executable name, like `dat` for a `project.dat` file. These are in addition to the
+    /// extensions that `ubi` already ignores, like `md`, `json`, and `txt`. This is useful when an
+    /// archive has data files that are named after the project and marked as executable.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn non_executable_extensions(mut self, extensions: &[&'a str]) -> Self {
+        self.non_executable_extensions = extensions.to_vec();
+        self
+    }
+
     /// The name to use when installing the executable. This is useful if the executable in the
     /// archive file has a name that includes a version number or platform information. If this is
     /// not set, then the executable will be installed with the name it has in the archive
@@ -123,6 +220,339 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
+    /// Select the executable in an archive file by its digest instead of by its name. The digest
+    /// should be given as a hex string. This is useful for archives where the file names don't
+    /// contain anything resembling the project name.
+    ///
+    /// Note that this reads and hashes every file in the archive until it finds a match, so it can
+    /// be much slower than matching by name for large archives. This has no effect if the release
+    /// asset is not an archive file.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn match_by_digest(mut self, algorithm: ChecksumAlgorithm, digest: &'a str) -> Self {
+        self.match_by_digest = Some((algorithm, digest));
+        self
+    }
+
+    /// Select the executable in an archive file with a regex instead of by its name. The regex must
+    /// match the entire file name of the archive member, not including its directory, so
+    /// `mytool-[0-9.]+` matches `bin/mytool-2.1.0` but not `bin/mytool-2.1.0.sha256`. If more than
+    /// one member matches, the first one in the archive is installed. The executable is still
+    /// installed under the name set by `exe` or `rename_exe_to`, or the project name.
+    ///
+    /// This has no effect if the release asset is not an archive file.
+    ///
+    /// You cannot call `extract_all` or `match_by_digest` if you set this.
+    #[must_use]
+    pub fn exe_pattern(mut self, pattern: &'a str) -> Self {
+        self.exe_pattern = Some(pattern);
+        self
+    }
+
+    /// Call this to install the executable with the name it has in the release, instead of
+    /// renaming it to the project name or the name set by `exe`. For an executable in an archive
+    /// file, this is the archive member's file name. For a compressed executable, this is the
+    /// release asset's name without the compression extension, and for a bare executable, it's
+    /// the release asset's name.
+    ///
+    /// You cannot call `rename_exe_to` or `extract_all` if you set this.
+    #[must_use]
+    pub fn keep_exe_name(mut self) -> Self {
+        self.keep_exe_name = true;
+        self
+    }
+
+    /// Call this to make it an error when an archive file has more than one member that is an exact
+    /// match for the executable name, or that matches `exe_pattern`, like `bin/mytool` and
+    /// `sbin/mytool`. By default, the first one in the archive is installed. Partial matches are
+    /// still picked as usual.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn reject_ambiguous_exe_matches(mut self) -> Self {
+        self.reject_ambiguous_exe_matches = true;
+        self
+    }
+
+    /// Call this to make it an error when an archive file has no exact match for the executable
+    /// name and more than one member that is a partial match for it, like `mytool-gnu` and
+    /// `mytool-musl`. The error lists all of the partial matches. By default, the one with the
+    /// shortest name is installed. This is useful in automated pipelines, where picking the wrong
+    /// executable is worse than failing.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn reject_ambiguous_partial_exe_matches(mut self) -> Self {
+        self.reject_ambiguous_partial_exe_matches = true;
+        self
+    }
+
+    /// Call this to install the only executable in a tarball or zip file when none of its members
+    /// match the executable name, instead of returning an error. This is useful when the
+    /// executable is named something unrelated to the project, like `rg` for `ripgrep`. If the
+    /// archive has more than one executable, `ubi` won't guess which one to install.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn fall_back_to_sole_exe(mut self) -> Self {
+        self.fall_back_to_sole_exe = true;
+        self
+    }
+
+    /// Call this to run the installed executable with the given arguments after it's installed,
+    /// and to return an error if it can't be run or doesn't exit successfully. If `args` is empty,
+    /// it is run with `--version`. This catches installing an executable for the wrong CPU
+    /// architecture or OS.
+    ///
+    /// This is skipped when the platform you set with `platform` can't run on this machine.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn verify_exe_runs(mut self, args: &[&'a str]) -> Self {
+        self.verify_exe_runs = Some(if args.is_empty() {
+            vec!["--version"]
+        } else {
+            args.to_vec()
+        });
+        self
+    }
+
+    /// Set a callback that is called with the paths of the installed executables after they have
+    /// been installed, had their modes set, and passed the `verify_exe_runs` check, if that's
+    /// set. If it returns an error, installing returns that error. This can be used to sign the
+    /// executable or give it extra capabilities. It is only called when the install succeeds.
+    ///
+    /// You cannot call `extract_all` or `post_install_command` if you set this.
+    #[must_use]
+    pub fn post_install_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&[PathBuf]) -> Result<()> + Send + Sync + 'static,
+    {
+        self.post_install_callback = Some(PostInstall::new(callback));
+        self
+    }
+
+    /// Set a command to run for each installed executable after it has been installed, like
+    /// `&["codesign", "--sign", "-", "{}"]`. The first element is the program to run. Each `{}` in
+    /// the arguments is replaced with the path of the installed executable, and if there is no
+    /// `{}`, the path is added as the last argument. Installing returns an error if the command
+    /// can't be run or doesn't exit successfully. Like `post_install_callback`, it is only run
+    /// when the install succeeds.
+    ///
+    /// You cannot call `extract_all` or `post_install_callback` if you set this.
+    #[must_use]
+    pub fn post_install_command(mut self, command: &[&'a str]) -> Self {
+        self.post_install_command = Some(command.to_vec());
+        self
+    }
+
+    /// Only look for the executable under this directory in an archive file, like `bin` or
+    /// `project-1.0.0/linux`. Archive members outside of this directory are ignored, which lets you
+    /// pick between executables with the same name in different directories. A leading `./` or `/`
+    /// is ignored, both here and on the archive members' paths.
+    ///
+    /// This has no effect if the release asset is not an archive file.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn exe_dir_in_archive<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.exe_dir_in_archive = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Install the archive member at exactly this path, like `dist/linux-amd64/mytool`, instead of
+    /// looking for the executable by name. This is useful for archives where the name-based
+    /// matching picks the wrong file, or none at all. It's an error if the archive doesn't have a
+    /// file at this path. A leading `./` or `/` is ignored, both here and on the archive members'
+    /// paths. The executable is still installed under the name set by `exe` or `rename_exe_to`, or
+    /// the project name.
+    ///
+    /// This has no effect if the release asset is not an archive file.
+    ///
+    /// You cannot call `extract_all`, `match_by_digest`, `exe_pattern`, or `alternative_exes` if
+    /// you set this.
+    #[must_use]
+    pub fn exe_path_in_archive<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.exe_path_in_archive = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the mode to give the installed executable, like `0o700`. By default, the executable is
+    /// given the mode recorded for it in a zip file, with the executable bits added, or `0o755`.
+    /// This is ignored on Windows.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn install_mode(mut self, mode: u32) -> Self {
+        self.install_mode = Some(mode);
+        self
+    }
+
+    /// Set the mode to give any directories that are created for the installed executable, like
+    /// `0o700` or `0o755`. Directories that already exist are left alone. By default, new
+    /// directories get the default mode for the process's umask. This is ignored on Windows.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn install_dir_mode(mut self, mode: u32) -> Self {
+        self.install_dir_mode = Some(mode);
+        self
+    }
+
+    /// Call this to tell `ubi` not to change the installed executable's permissions at all. This
+    /// is useful on filesystems where setting permissions fails, or where the executable bits are
+    /// inherited or managed some other way.
+    ///
+    /// You cannot call `install_mode` or `extract_all` if you set this.
+    #[must_use]
+    pub fn skip_chmod(mut self) -> Self {
+        self.skip_chmod = true;
+        self
+    }
+
+    /// Call this to make it an error when the installed executable was built for a CPU
+    /// architecture that can't run on the platform. By default, `ubi` reads the header of ELF,
+    /// Mach-O, and PE executables after installing them and logs a warning when the architecture
+    /// doesn't match, since that usually means that the wrong release asset was picked.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn strict_arch_check(mut self) -> Self {
+        self.strict_arch_check = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to copy an executable that already exists at the install path to the
+    /// same path plus `.bak`, like `mytool.bak`, before replacing it. If the install fails, the
+    /// backup is left in place so you can restore it. This gives you a way back if an upgrade
+    /// ships a broken build.
+    ///
+    /// You cannot call `extract_all` if you set this.
+    #[must_use]
+    pub fn backup_existing_exe(mut self) -> Self {
+        self.backup_existing_exe = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to remove the backup made by `backup_existing_exe` once the new
+    /// executable is installed. The backup is still kept if the install fails.
+    ///
+    /// You must call `backup_existing_exe` if you set this.
+    #[must_use]
+    pub fn remove_backup_after_install(mut self) -> Self {
+        self.remove_backup_after_install = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to give each file it extracts from a tarball or zip file the
+    /// modification time recorded for it in the archive. This applies to both the executable and,
+    /// with `extract_all`, every file in the archive. By default, files have the time they were
+    /// installed.
+    #[must_use]
+    pub fn preserve_mtimes(mut self) -> Self {
+        self.preserve_mtimes = true;
+        self
+    }
+
+    /// Call this to make it an error when the downloaded release asset, or the file given to
+    /// `archive_file`, is not a regular file, like a symlink or a FIFO. This is checked on the
+    /// opened file, without following symlinks where the platform allows it, every time the file
+    /// is opened. It guards against something swapping the file out between the download and the
+    /// install, which matters most when `ubi` can write to directories that other users can't.
+    #[must_use]
+    pub fn require_regular_file(mut self) -> Self {
+        self.require_regular_file = true;
+        self
+    }
+
+    /// Set a directory to copy shell completion files for the given shell into. When the archive
+    /// has a directory named `completions`, the files in it that are for this shell are copied
+    /// into this directory after the archive is extracted. Bash completions end in `.bash`, zsh
+    /// completions start with `_` or end in `.zsh`, and fish completions end in `.fish`. If the
+    /// archive has no completion files for the shell, nothing is copied. Call this once for each
+    /// shell you want completions for.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn completion_dir<P: AsRef<Path>>(mut self, shell: Shell, dir: P) -> Self {
+        self.completion_dirs.retain(|(s, _)| *s != shell);
+        self.completion_dirs
+            .push((shell, dir.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Set a directory to copy man pages into, like `~/.local/share/man`. When the archive has
+    /// files like `man/man1/project.1` or `man/man1/project.1.gz`, each one is copied into the
+    /// subdirectory of this directory for its section, like `man1`, after the archive is
+    /// extracted.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn man_dir<P: AsRef<Path>>(mut self, man_dir: P) -> Self {
+        self.man_dir = Some(man_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set a directory to put the archive's executables in, like `~/.local/bin`, while the rest
+    /// of the archive stays in the install directory. If the extracted archive has a top-level
+    /// `bin` directory, each entry in it is symlinked into this directory on Unix-like systems,
+    /// and copied into it on Windows.
+    ///
+    /// You must call `extract_all` if you set this. You cannot set this with
+    /// `flatten_executables`.
+    #[must_use]
+    pub fn bin_dir<P: AsRef<Path>>(mut self, bin_dir: P) -> Self {
+        self.bin_dir = Some(bin_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set a callback that decides where each archive member is extracted. It's called with the
+    /// member's path in the archive, after `strip_components` is applied. If it returns a path,
+    /// the member is extracted to that path relative to the install directory instead, so you can
+    /// send `docs/*` to one directory and `bin/*` to another. If it returns `None`, the member is
+    /// extracted to its usual path. Installing returns an error if a returned path is absolute or
+    /// would end up outside of the install directory. When this is set, the contents of a single
+    /// top-level directory in the archive are not moved up into the install directory.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn map_archive_member_paths<F>(mut self, mapper: F) -> Self
+    where
+        F: Fn(&Path) -> Option<PathBuf> + Send + Sync + 'static,
+    {
+        self.path_mapper = Some(PathMapper::new(mapper));
+        self
+    }
+
+    /// Set a callback to report progress as files are installed. This is called as each file is
+    /// written, with the number of bytes written to that file so far and the file's total size,
+    /// if that's known. The size is not known when the file is decompressed from a stream, like a
+    /// `.gz` or `.xz` file. This can be used to show a progress bar while a large file is installed.
+    #[must_use]
+    pub fn progress_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        self.progress = Some(Progress::new(callback));
+        self
+    }
+
+    /// Set what happens when the executable already exists at its install path. If you call
+    /// `extract_all`, this applies to the install directory instead, which only counts as existing
+    /// if it is not empty. By default, the existing executable or files are overwritten. With
+    /// [`OnExisting::Skip`], nothing is installed, and with [`OnExisting::Error`], installing
+    /// returns an error. A skipped executable is not included in the paths that
+    /// [`crate::Ubi::install_binary`] returns, and `verify_exe_runs` and the post-install callback
+    /// or command are not run for it.
+    ///
+    /// You cannot call `backup_existing_exe` unless this is [`OnExisting::Overwrite`].
+    #[must_use]
+    pub fn on_existing(mut self, on_existing: OnExisting) -> Self {
+        self.on_existing = on_existing;
+        self
+    }
+
     /// Call this to tell `ubi` to extract all files from the archive. By default `ubi` will look
     /// for an executable in an archive file. But if this is true, it will simply unpack the archive
     /// file in the specified directory.
@@ -134,6 +564,272 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
+    /// Call this to tell `ubi` to log a warning for each archive member that it does not extract
+    /// when `extract_all` is enabled, along with the reason that member was skipped. Members are
+    /// skipped when they are encrypted or when they would be written through a symlink in the
+    /// archive. By default, skipped members are only logged at the debug level. To get the skipped
+    /// members instead, call [`crate::Ubi::install_binary_reporting_skipped`].
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn report_skipped_members(mut self) -> Self {
+        self.report_skipped_members = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to extract the archive into a new directory under the system's temp
+    /// directory, and then move the completed tree into the install directory. This means an
+    /// interrupted extraction never leaves a partially extracted tree in the install directory.
+    /// This can also be faster when the install directory is on a slow filesystem, like a network
+    /// mount. If the temp directory and install directory are on different filesystems, the tree
+    /// is copied and the staged copy is deleted.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn stage_extraction_in_temp_dir(mut self) -> Self {
+        self.stage_extraction_in_temp_dir = true;
+        self
+    }
+
+    /// Set the directory that `ubi` creates its temp dirs in, instead of the system's temp
+    /// directory. This is where release assets are downloaded to, and where archives are staged
+    /// when they need to be extracted before installing. Putting this on the same filesystem as
+    /// the install directory lets files be moved into place with a rename instead of a copy, and
+    /// avoids filling up a small `/tmp`. The directory is created if it doesn't exist.
+    ///
+    /// With `extract_all`, this cannot be inside the install directory.
+    #[must_use]
+    pub fn temp_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.temp_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set a path to copy the downloaded release asset to, so you have a copy of exactly what was
+    /// installed, for auditing or for installing again later with `archive_file`. If this is an
+    /// existing directory, the asset is copied into it with its original name. Otherwise, it's
+    /// copied to this path. The asset is copied once all of its checksum and signature checks
+    /// pass, but before it's installed.
+    ///
+    /// This has no effect when you install from a local file with `archive_file`.
+    #[must_use]
+    pub fn keep_archive<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.keep_archive = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Call this to tell `ubi` to extract the archive into a new directory next to the install
+    /// directory, and then replace the install directory with it, removing anything that was in
+    /// the install directory before. The previous contents are only removed once the extraction
+    /// succeeds, so a failed upgrade leaves the previous install in place.
+    ///
+    /// You must call `extract_all` if you set this. You cannot set this with
+    /// `stage_extraction_in_temp_dir`.
+    #[must_use]
+    pub fn replace_install_dir(mut self) -> Self {
+        self.replace_install_dir = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to install every executable file in the archive directly into the
+    /// install directory, instead of keeping the archive's directory structure. Files that are
+    /// not executable are not installed. This is useful for archives that have several
+    /// executables spread across different directories.
+    ///
+    /// You must call `extract_all` if you set this. You cannot set this with `completion_dir` or
+    /// `man_dir`.
+    #[must_use]
+    pub fn flatten_executables(mut self) -> Self {
+        self.flatten_executables = true;
+        self
+    }
+
+    /// Set extra names of files to install when `flatten_executables` is enabled, even if they
+    /// aren't executable. This is useful for zip files made on Windows, which don't record which
+    /// files are executable. A name matches a file with that name, with or without an extension.
+    ///
+    /// You must call `flatten_executables` if you set this.
+    #[must_use]
+    pub fn flatten_executable_names(mut self, names: &[&'a str]) -> Self {
+        self.flatten_executable_names = names.to_vec();
+        self
+    }
+
+    /// Call this to tell `ubi` to unpack an `AppImage` into the install directory instead of
+    /// failing because it's not an archive. This is useful on systems without FUSE, where an
+    /// `AppImage` can't be run directly. The `AppImage` is unpacked by running it with the
+    /// `--appimage-extract` flag, so it must be built for the platform `ubi` is running on. For the
+    /// same reason, a dry run of installing an `AppImage` returns an error.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn extract_appimage(mut self) -> Self {
+        self.extract_appimage = true;
+        self
+    }
+
+    /// Call this to tell `ubi` to extract the files in a zip file across several threads. This
+    /// can be much faster for a large archive with many files, like a language SDK. It has no
+    /// effect on other kinds of archives.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn parallel_zip_extraction(mut self) -> Self {
+        self.parallel_zip_extraction = true;
+        self
+    }
+
+    /// Set the password for a release asset that is a password-protected zip file. Both the
+    /// traditional zip encryption and AES encryption are supported. Without this, installing an
+    /// executable that is encrypted is an error, and encrypted files are skipped when you call
+    /// `extract_all`.
+    #[must_use]
+    pub fn zip_password(mut self, zip_password: &'a str) -> Self {
+        self.zip_password = Some(zip_password);
+        self
+    }
+
+    /// Call this to give `ubi` a command that decompresses files with the given extension, for
+    /// compression formats that `ubi` doesn't support itself. The extension is given without a
+    /// leading dot, like `foo` or `tar.foo`, and it's only used for release assets whose extension
+    /// `ubi` doesn't already know. You can call this more than once to register commands for more
+    /// than one extension.
+    ///
+    /// The command is run with the downloaded file as its stdin, and must write the decompressed
+    /// data to its stdout. The decompressed file is named after the release asset without the
+    /// extension, so a command for `foo` turns `project.tar.foo` into `project.tar`, which `ubi`
+    /// then installs from like any other tarball.
+    #[must_use]
+    pub fn custom_decompressor(mut self, extension: &'a str, command: &[&'a str]) -> Self {
+        self.custom_decompressors
+            .insert(extension.trim_start_matches('.'), command.to_vec());
+        self
+    }
+
+    /// Set the number of leading path components to remove from each archive member when
+    /// extracting it, like `tar --strip-components`. Members that have no more than this many
+    /// components, like the top-level directory itself, are not extracted. When this is set,
+    /// `ubi` does not look for a single top-level directory to remove, so setting this to 0
+    /// extracts the archive exactly as it is.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn strip_components(mut self, strip_components: usize) -> Self {
+        self.strip_components = Some(strip_components);
+        self
+    }
+
+    /// Call this to tell `ubi` to check the digest of the downloaded archive against the given hex
+    /// digest. For tarballs, the digest is computed while the archive is being extracted, so the
+    /// file is only read once. Zip files are checked before they are extracted. If the digest does
+    /// not match, anything that was extracted is removed and `install_binary` returns an error.
+    ///
+    /// You must call `extract_all` if you set this.
+    #[must_use]
+    pub fn verify_archive_digest(mut self, algorithm: ChecksumAlgorithm, digest: &'a str) -> Self {
+        self.verify_archive_digest = Some((algorithm, digest));
+        self
+    }
+
+    /// Set the name of a checksums file release asset, like `SHA256SUMS` or `checksums.txt`. When
+    /// this is set, `ubi` downloads this file along with the release asset it picks, finds the line
+    /// for that asset, and checks that the asset's digest matches before installing anything.
+    /// Lines can be in either the `<digest>  <name>` or `<digest> *<name>` format that
+    /// `sha256sum`, `b3sum`, and similar tools produce, or the `SHA256 (<name>) = <digest>` format
+    /// that `shasum --tag` and BSD tools produce. Blank lines and `#` comments are ignored.
+    ///
+    /// For a line in the BSD format, the digest algorithm is the one the line names. Otherwise,
+    /// it's SHA-512 if the digest has 128 hex digits, BLAKE3 if the checksums file's name contains
+    /// `b3` or `blake3`, like `B3SUMS`, and SHA-256 if it doesn't. Call `checksums_algorithm` to
+    /// set it explicitly, which also picks that algorithm's digest when the file has digests from
+    /// more than one algorithm.
+    ///
+    /// If you set `url`, then the checksums file is downloaded from the same location as the
+    /// release asset.
+    #[must_use]
+    pub fn verify_checksums_file(mut self, name: &'a str) -> Self {
+        self.checksums_file = Some(name);
+        self
+    }
+
+    /// Set the digest algorithm used in the checksums file given to `verify_checksums_file`,
+    /// instead of guessing it.
+    ///
+    /// You must call `verify_checksums_file` if you set this.
+    #[must_use]
+    pub fn checksums_algorithm(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        self.checksums_algorithm = Some(algorithm);
+        self
+    }
+
+    /// Set a minisign public key to verify the downloaded release asset with. This is the base64
+    /// key on the second line of a `minisign.pub` file. When this is set, `ubi` downloads the
+    /// release asset with the same name plus `.minisig` and checks that it is a valid signature of
+    /// the downloaded asset before installing anything.
+    ///
+    /// If you set `url`, then the signature is downloaded from the same location as the release
+    /// asset.
+    #[must_use]
+    pub fn verify_minisign_signature(mut self, public_key: &'a str) -> Self {
+        self.minisign_public_key = Some(public_key);
+        self
+    }
+
+    /// Set a file containing the GPG public key(s) to verify the downloaded release asset with.
+    /// This can be a single armored key or a keyring. When this is set, `ubi` downloads the release
+    /// asset with the same name plus `.asc` or `.sig` and checks that it is a valid detached
+    /// signature of the downloaded asset, made by one of these keys, before installing anything.
+    ///
+    /// This runs the `gpg` executable, which must be installed and in your `PATH`. It is run with
+    /// a temporary home directory, so your own keyring is not used.
+    ///
+    /// If you set `url`, then the signature is downloaded from the same location as the release
+    /// asset.
+    #[cfg(feature = "gpg")]
+    #[must_use]
+    pub fn verify_gpg_signature<P: AsRef<Path>>(mut self, key_file: P) -> Self {
+        self.gpg_key_file = Some(key_file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the name of a release asset with SLSA provenance for the release, like
+    /// `project.intoto.jsonl`, and a builder ID. When this is set, `ubi` downloads the provenance
+    /// and checks that a provenance statement that names that builder lists the downloaded
+    /// release asset's digest as one of its subjects before installing anything. The provenance
+    /// can be a DSSE envelope, a Sigstore bundle, or a file with one envelope per line.
+    ///
+    /// The builder ID can leave off the `@<ref>` at the end of the ID of a builder that is a
+    /// reusable workflow, like
+    /// `https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_generic_slsa3.yml`.
+    ///
+    /// This is a consistency check, not provenance verification. The signatures on the provenance
+    /// are not checked, so anyone who can upload release assets can also upload provenance that
+    /// passes this check. It catches a release asset that doesn't match the provenance published
+    /// next to it, like a corrupted or replaced download. To verify who built a release asset, use
+    /// a tool that checks the Sigstore signatures, like `slsa-verifier`.
+    ///
+    /// If you set `url`, then the provenance is downloaded from the same location as the release
+    /// asset.
+    #[cfg(feature = "slsa")]
+    #[must_use]
+    pub fn check_slsa_provenance_subject(
+        mut self,
+        provenance_file: &'a str,
+        builder_id: &'a str,
+    ) -> Self {
+        self.slsa_provenance = Some((provenance_file, builder_id));
+        self
+    }
+
+    /// Set the filesystem that files are installed into. By default, `ubi` installs files into the
+    /// local filesystem. This lets you redirect installs into something else, like an overlay or a
+    /// remote filesystem, by implementing the [`FileSystem`] trait. The downloaded release asset is
+    /// always written to and read from the local filesystem.
+    #[must_use]
+    pub fn filesystem(mut self, filesystem: Arc<dyn FileSystem>) -> Self {
+        self.filesystem = Some(filesystem);
+        self
+    }
+
     /// Set a GitHub token to use for API requests. If this is not set then this will be taken from
     /// the `GITHUB_TOKEN` env var if it is set.
     #[must_use]
@@ -193,9 +889,10 @@ impl<'a> UbiBuilder<'a> {
     ///
     /// # Errors
     ///
-    /// If you have tried to set incompatible options (setting a `project` or `tag` with a `url`) or
-    /// you have not set required options (one of `project` or `url`), then this method will return
-    /// an error.
+    /// If you have tried to set incompatible options (setting a `project` or `tag` with a `url`),
+    /// you have not set required options (one of `project` or `url`), or an option has an invalid
+    /// value (like a malformed digest for `match_by_digest`), then this method will return an
+    /// error.
     pub fn build(self) -> Result<Ubi<'a>> {
         if self.project.is_none() && self.url.is_none() {
             return Err(anyhow!("You must set a project or url"));
@@ -203,13 +900,32 @@ impl<'a> UbiBuilder<'a> {
         if self.url.is_some() && (self.project.is_some() || self.tag.is_some()) {
             return Err(anyhow!("You cannot set a url with a project or tag"));
         }
-        if self.exe.is_some() && self.extract_all {
-            return Err(anyhow!("You cannot set exe and enable extract_all"));
+        if let Some(archive_file) = &self.archive_file {
+            if self.url.is_some() || self.tag.is_some() {
+                return Err(anyhow!("You cannot set archive_file with a url or tag"));
+            }
+            if !archive_file.is_file() {
+                return Err(anyhow!(
+                    "The archive file given for archive_file, {}, does not exist",
+                    archive_file.display(),
+                ));
+            }
+            self.check_archive_file_conflicts()?;
         }
-        if self.rename_exe_to.is_some() && self.extract_all {
-            return Err(anyhow!(
-                "You cannot set rename_exe_to and enable extract_all"
-            ));
+        self.check_option_conflicts()?;
+        self.check_custom_decompressors()?;
+
+        if let Some(public_key) = self.minisign_public_key {
+            minisign::parse_public_key(public_key)?;
+        }
+        #[cfg(feature = "gpg")]
+        if let Some(key_file) = &self.gpg_key_file {
+            if !key_file.is_file() {
+                return Err(anyhow!(
+                    "The GPG key file given for verify_gpg_signature, {}, does not exist",
+                    key_file.display(),
+                ));
+            }
         }
 
         let platform = self.determine_platform()?;
@@ -220,33 +936,444 @@ impl<'a> UbiBuilder<'a> {
         let (project_name, forge_type) =
             parse_project_name(self.project, asset_url.as_ref(), self.forge.clone())?;
         let installer = self.new_installer(&project_name, &platform)?;
+        installer.validate()?;
         let forge = self.new_forge(project_name, &forge_type)?;
         let is_musl = self.is_musl.unwrap_or_else(|| platform_is_musl(&platform));
+        let custom_decompressors = self
+            .custom_decompressors
+            .iter()
+            .map(|(ext, command)| {
+                (
+                    (*ext).to_string(),
+                    command.iter().map(ToString::to_string).collect(),
+                )
+            })
+            .collect::<HashMap<String, Vec<String>>>();
 
         Ok(Ubi::new(
             forge,
             asset_url,
-            AssetPicker::new(self.matching, platform, is_musl, self.extract_all),
+            self.archive_file,
+            AssetPicker::new(
+                self.matching,
+                platform,
+                is_musl,
+                self.extract_all,
+                custom_decompressors.keys().cloned().collect(),
+            ),
             installer,
+            AssetChecks {
+                checksums_file: self.checksums_file,
+                checksums_algorithm: self.checksums_algorithm,
+                minisign_public_key: self.minisign_public_key,
+                #[cfg(feature = "gpg")]
+                gpg_key_file: self.gpg_key_file.clone(),
+                #[cfg(feature = "slsa")]
+                slsa_provenance: self.slsa_provenance,
+            },
             reqwest_client()?,
-        ))
+        )
+        .custom_decompressors(custom_decompressors)
+        .temp_dir(self.temp_dir.clone())
+        .keep_archive(self.keep_archive.clone()))
+    }
+
+    fn check_custom_decompressors(&self) -> Result<()> {
+        for (ext, command) in &self.custom_decompressors {
+            if ext.is_empty() {
+                return Err(anyhow!(
+                    "You cannot set a custom decompressor for an empty extension"
+                ));
+            }
+            if command.is_empty() {
+                return Err(anyhow!(
+                    "The custom decompressor command for the `{ext}` extension is empty"
+                ));
+            }
+            if matches!(
+                Extension::from_path(Path::new(&format!("file.{ext}"))),
+                Ok(Some(_))
+            ) {
+                return Err(anyhow!(
+                    "You cannot set a custom decompressor for the `{ext}` extension because ubi \
+                     already supports it"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Checks for the verification options, which all need sibling assets from the release, so they
+    // can't be used when installing from a local file.
+    fn check_archive_file_conflicts(&self) -> Result<()> {
+        if self.archive_file.is_some() && self.checksums_file.is_some() {
+            return Err(anyhow!(
+                "You cannot set archive_file and verify_checksums_file"
+            ));
+        }
+        if self.archive_file.is_some() && self.minisign_public_key.is_some() {
+            return Err(anyhow!(
+                "You cannot set archive_file and verify_minisign_signature"
+            ));
+        }
+        #[cfg(feature = "gpg")]
+        if self.archive_file.is_some() && self.gpg_key_file.is_some() {
+            return Err(anyhow!(
+                "You cannot set archive_file and verify_gpg_signature"
+            ));
+        }
+        #[cfg(feature = "slsa")]
+        if self.archive_file.is_some() && self.slsa_provenance.is_some() {
+            return Err(anyhow!(
+                "You cannot set archive_file and check_slsa_provenance_subject"
+            ));
+        }
+        Ok(())
+    }
+
+    // Checks for options that can't be used together, or that require another option to be set.
+    fn check_option_conflicts(&self) -> Result<()> {
+        if self.exe.is_some() && self.extract_all {
+            return Err(anyhow!("You cannot set exe and enable extract_all"));
+        }
+        if self.rename_exe_to.is_some() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set rename_exe_to and enable extract_all"
+            ));
+        }
+        if self.match_by_digest.is_some() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set match_by_digest and enable extract_all"
+            ));
+        }
+        if self.exe_pattern.is_some() && self.extract_all {
+            return Err(anyhow!("You cannot set exe_pattern and enable extract_all"));
+        }
+        if !self.alternative_exes.is_empty() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set alternative_exes and enable extract_all"
+            ));
+        }
+        if !self.alternative_exes.is_empty() && self.exe_pattern.is_some() {
+            return Err(anyhow!("You cannot set alternative_exes and exe_pattern"));
+        }
+        if self.keep_exe_name && self.rename_exe_to.is_some() {
+            return Err(anyhow!("You cannot set keep_exe_name and rename_exe_to"));
+        }
+        if self.keep_exe_name && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set keep_exe_name and enable extract_all"
+            ));
+        }
+        if self.exe_dir_in_archive.is_some() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set exe_dir_in_archive and enable extract_all"
+            ));
+        }
+        if self.install_mode.is_some() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set install_mode and enable extract_all"
+            ));
+        }
+        if self.install_dir_mode.is_some() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set install_dir_mode and enable extract_all"
+            ));
+        }
+        if self.strict_arch_check && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set strict_arch_check and enable extract_all"
+            ));
+        }
+        if self.skip_chmod && self.extract_all {
+            return Err(anyhow!("You cannot set skip_chmod and enable extract_all"));
+        }
+        if self.skip_chmod && self.install_mode.is_some() {
+            return Err(anyhow!("You cannot set both install_mode and skip_chmod"));
+        }
+        if self.backup_existing_exe && self.on_existing != OnExisting::Overwrite {
+            return Err(anyhow!(
+                "You cannot set backup_existing_exe unless on_existing is Overwrite"
+            ));
+        }
+        if self.backup_existing_exe && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set backup_existing_exe and enable extract_all"
+            ));
+        }
+        if self.remove_backup_after_install && !self.backup_existing_exe {
+            return Err(anyhow!(
+                "You cannot set remove_backup_after_install without setting backup_existing_exe"
+            ));
+        }
+        if self.checksums_algorithm.is_some() && self.checksums_file.is_none() {
+            return Err(anyhow!(
+                "You cannot set checksums_algorithm without setting verify_checksums_file"
+            ));
+        }
+        self.check_exe_path_in_archive_conflicts()?;
+        self.check_exe_matching_conflicts()?;
+        self.check_after_install_conflicts()?;
+        self.check_extract_all_options()
+    }
+
+    // Checks for options that change which archive member matches the executable name, since
+    // nothing is matched when extracting everything.
+    fn check_exe_matching_conflicts(&self) -> Result<()> {
+        if !self.extract_all {
+            return Ok(());
+        }
+        if self.fall_back_to_sole_exe {
+            return Err(anyhow!(
+                "You cannot set fall_back_to_sole_exe and enable extract_all"
+            ));
+        }
+        if self.reject_ambiguous_exe_matches {
+            return Err(anyhow!(
+                "You cannot set reject_ambiguous_exe_matches and enable extract_all"
+            ));
+        }
+        if self.reject_ambiguous_partial_exe_matches {
+            return Err(anyhow!(
+                "You cannot set reject_ambiguous_partial_exe_matches and enable extract_all"
+            ));
+        }
+        if !self.non_executable_extensions.is_empty() {
+            return Err(anyhow!(
+                "You cannot set non_executable_extensions and enable extract_all"
+            ));
+        }
+        Ok(())
+    }
+
+    // Checks for options that run something with the installed executables.
+    fn check_after_install_conflicts(&self) -> Result<()> {
+        if self.verify_exe_runs.is_some() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set verify_exe_runs and enable extract_all"
+            ));
+        }
+        if self.post_install_callback.is_some() && self.post_install_command.is_some() {
+            return Err(anyhow!(
+                "You cannot set both post_install_callback and post_install_command"
+            ));
+        }
+        if self
+            .post_install_command
+            .as_ref()
+            .is_some_and(Vec::is_empty)
+        {
+            return Err(anyhow!("The post_install_command cannot be empty"));
+        }
+        if self.post_install_callback.is_some() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set post_install_callback and enable extract_all"
+            ));
+        }
+        if self.post_install_command.is_some() && self.extract_all {
+            return Err(anyhow!(
+                "You cannot set post_install_command and enable extract_all"
+            ));
+        }
+        Ok(())
+    }
+
+    // Checks for options that pick the executable in an archive some other way, since there's
+    // nothing left to pick once we have its exact path.
+    fn check_exe_path_in_archive_conflicts(&self) -> Result<()> {
+        if self.exe_path_in_archive.is_none() {
+            return Ok(());
+        }
+        if self.extract_all {
+            return Err(anyhow!(
+                "You cannot set exe_path_in_archive and enable extract_all"
+            ));
+        }
+        if self.match_by_digest.is_some() {
+            return Err(anyhow!(
+                "You cannot set exe_path_in_archive and match_by_digest"
+            ));
+        }
+        if self.exe_pattern.is_some() {
+            return Err(anyhow!(
+                "You cannot set exe_path_in_archive and exe_pattern"
+            ));
+        }
+        if !self.alternative_exes.is_empty() {
+            return Err(anyhow!(
+                "You cannot set exe_path_in_archive and alternative_exes"
+            ));
+        }
+        Ok(())
+    }
+
+    // Checks for options that only apply when extracting everything from an archive.
+    fn check_extract_all_options(&self) -> Result<()> {
+        if self.report_skipped_members && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set report_skipped_members without enabling extract_all"
+            ));
+        }
+        if self.stage_extraction_in_temp_dir && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set stage_extraction_in_temp_dir without enabling extract_all"
+            ));
+        }
+        if self.replace_install_dir && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set replace_install_dir without enabling extract_all"
+            ));
+        }
+        if self.flatten_executables && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set flatten_executables without enabling extract_all"
+            ));
+        }
+        if self.parallel_zip_extraction && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set parallel_zip_extraction without enabling extract_all"
+            ));
+        }
+        if self.extract_appimage && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set extract_appimage without enabling extract_all"
+            ));
+        }
+        if !self.flatten_executable_names.is_empty() && !self.flatten_executables {
+            return Err(anyhow!(
+                "You cannot set flatten_executable_names without setting flatten_executables"
+            ));
+        }
+        if self.strip_components.is_some() && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set strip_components without enabling extract_all"
+            ));
+        }
+        if !self.completion_dirs.is_empty() && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set completion_dir without enabling extract_all"
+            ));
+        }
+        if self.man_dir.is_some() && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set man_dir without enabling extract_all"
+            ));
+        }
+        if self.bin_dir.is_some() && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set bin_dir without enabling extract_all"
+            ));
+        }
+        if self.path_mapper.is_some() && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set map_archive_member_paths without enabling extract_all"
+            ));
+        }
+        if self.verify_archive_digest.is_some() && !self.extract_all {
+            return Err(anyhow!(
+                "You cannot set verify_archive_digest without enabling extract_all"
+            ));
+        }
+
+        Ok(())
     }
 
     fn new_installer(&self, project_name: &str, platform: &Platform) -> Result<Box<dyn Installer>> {
+        let filesystem = self.filesystem.clone().unwrap_or_else(|| Arc::new(RealFs));
         if self.extract_all {
             let install_path = install_path(self.install_dir.as_deref(), None)?;
-            Ok(Box::new(ArchiveInstaller::new(install_path)))
+            Ok(Box::new(
+                ArchiveInstaller::new(install_path)
+                    .report_skipped(self.report_skipped_members)
+                    .stage_in_temp_dir(self.stage_extraction_in_temp_dir)
+                    .temp_dir(self.temp_dir.clone())
+                    .replace_install_root(self.replace_install_dir)
+                    .flatten_executables(self.flatten_executables)
+                    .extract_appimage(self.extract_appimage)
+                    .parallel_zip_extraction(self.parallel_zip_extraction)
+                    .zip_password(self.zip_password.map(ToString::to_string))
+                    .flatten_executable_names(
+                        self.flatten_executable_names
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect(),
+                    )
+                    .strip_components(self.strip_components)
+                    .preserve_mtime(self.preserve_mtimes)
+                    .require_regular_file(self.require_regular_file)
+                    .completion_dirs(self.completion_dirs.clone())
+                    .man_dir(self.man_dir.clone())
+                    .bin_dir(self.bin_dir.clone())
+                    .path_mapper(self.path_mapper.clone())
+                    .progress(self.progress.clone())
+                    .on_existing(self.on_existing)
+                    .expected_digest(
+                        self.verify_archive_digest
+                            .map(|(algorithm, digest)| (algorithm, digest.to_string())),
+                    )
+                    .filesystem(filesystem),
+            ))
         } else {
             let expect_exe_stem_name = expect_exe_stem_name(self.exe, project_name);
-            let install_path = install_path(
-                self.install_dir.as_deref(),
-                self.rename_exe_to.or(Some(expect_exe_stem_name)),
-            )?;
-            Ok(Box::new(ExeInstaller::new(
-                install_path,
-                expect_exe_stem_name.to_string(),
-                platform.target_os == OS::Windows,
-            )))
+            let install_path = if self.keep_exe_name {
+                // The trailing separator tells the installer that this is a directory to install
+                // into.
+                let mut install_dir = install_path(self.install_dir.as_deref(), None)?;
+                install_dir
+                    .as_mut_os_string()
+                    .push(std::path::MAIN_SEPARATOR_STR);
+                install_dir
+            } else {
+                install_path(
+                    self.install_dir.as_deref(),
+                    self.rename_exe_to.or(Some(expect_exe_stem_name)),
+                )?
+            };
+            Ok(Box::new(
+                ExeInstaller::new(
+                    install_path,
+                    vec![expect_exe_stem_name.to_string()],
+                    platform.target_os == OS::Windows,
+                )
+                .match_by_digest(
+                    self.match_by_digest
+                        .map(|(algorithm, digest)| (algorithm, digest.to_string())),
+                )
+                .exe_pattern(self.exe_pattern.map(exe_pattern_regex).transpose()?)
+                .alternative_exe_names(
+                    self.alternative_exes
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect(),
+                )
+                .non_executable_extensions(
+                    self.non_executable_extensions
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect(),
+                )
+                .member_dir(self.exe_dir_in_archive.clone())
+                .member_path(self.exe_path_in_archive.clone())
+                .reject_ambiguous_matches(self.reject_ambiguous_exe_matches)
+                .reject_ambiguous_partial_matches(self.reject_ambiguous_partial_exe_matches)
+                .fall_back_to_sole_exe(self.fall_back_to_sole_exe)
+                .zip_password(self.zip_password.map(ToString::to_string))
+                .verify_runs(self.verify_runs_args(platform)?)
+                .post_install(self.post_install())
+                .mode(self.install_mode)
+                .dir_mode(self.install_dir_mode)
+                .skip_chmod(self.skip_chmod)
+                .temp_dir(self.temp_dir.clone())
+                .expected_platform(Some(platform.clone()))
+                .strict_arch_check(self.strict_arch_check)
+                .backup_existing(self.backup_existing_exe)
+                .remove_backup(self.remove_backup_after_install)
+                .preserve_mtime(self.preserve_mtimes)
+                .require_regular_file(self.require_regular_file)
+                .progress(self.progress.clone())
+                .on_existing(self.on_existing)
+                .filesystem(filesystem),
+            ))
         }
     }
 
@@ -288,6 +1415,36 @@ impl<'a> UbiBuilder<'a> {
         }
     }
 
+    // Returns the arguments to verify that the installed executable runs with, unless we're
+    // installing for a platform that can't run on this machine.
+    fn verify_runs_args(&self, platform: &Platform) -> Result<Option<Vec<String>>> {
+        let Some(args) = &self.verify_exe_runs else {
+            return Ok(None);
+        };
+        let req = PlatformReq::from_str(Self::TARGET)?;
+        let host_can_run = Platform::ALL.iter().filter(|p| req.matches(p)).any(|host| {
+            host.target_arch == platform.target_arch && host.target_os == platform.target_os
+        });
+        if !host_can_run {
+            debug!(
+                "not verifying that the executable runs, because this machine can't run {} executables",
+                platform.target_triple,
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(args.iter().map(ToString::to_string).collect()))
+    }
+
+    fn post_install(&self) -> Option<PostInstall> {
+        if let Some(command) = &self.post_install_command {
+            return Some(PostInstall::command(
+                command.iter().map(ToString::to_string).collect(),
+            ));
+        }
+        self.post_install_callback.clone()
+    }
+
     fn check_musl_setting(&self, platform: &Platform) -> Result<()> {
         if self.is_musl.unwrap_or_default() && platform.target_os != OS::Linux {
             return Err(anyhow!(
@@ -364,6 +1521,13 @@ fn expect_exe_stem_name<'a>(exe: Option<&'a str>, project_name: &'a str) -> &'a
     name
 }
 
+// The pattern has to match the whole file name, which is less surprising than matching any part of
+// it when the pattern is something like `mytool`.
+fn exe_pattern_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(&format!("^(?:{pattern})$"))
+        .with_context(|| format!("The exe_pattern, `{pattern}`, is not a valid regex"))
+}
+
 fn platform_is_musl(platform: &Platform) -> bool {
     if platform.target_os != OS::Linux {
         return false;
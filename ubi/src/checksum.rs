@@ -0,0 +1,410 @@
+use anyhow::{anyhow, Result};
+use lazy_regex::regex;
+use sha2::{digest::DynDigest, Digest, Sha256, Sha512};
+use std::{
+    cell::RefCell,
+    fmt::Write as _,
+    io::{self, Read},
+    path::Path,
+    rc::Rc,
+    str::FromStr,
+};
+// It'd be nice to use clap::ValueEnum here, but then we'd need to add clap as a dependency for the
+// library code, which would be annoying for downstream users who just want to use the library.
+use strum::{AsRefStr, EnumString, VariantNames};
+
+/// The digest algorithms that `ubi` knows how to compute.
+#[derive(AsRefStr, Clone, Copy, Debug, EnumString, PartialEq, Eq, VariantNames)]
+#[allow(clippy::module_name_repetitions)]
+pub enum ChecksumAlgorithm {
+    #[strum(serialize = "sha256")]
+    Sha256,
+    #[strum(serialize = "sha512")]
+    Sha512,
+    #[strum(serialize = "blake3")]
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    /// Guesses the algorithm used for a digest in the checksums file with the given name. A
+    /// SHA-512 digest is the only one with 128 hex digits. SHA-256 and BLAKE3 digests both have
+    /// 64, so we pick BLAKE3 when the file's name says so, like `B3SUMS`, `checksums.b3`, or
+    /// `checksums.blake3`, and SHA-256 otherwise. A name that just happens to contain `b3`, like
+    /// `web3-checksums.txt`, doesn't count.
+    pub(crate) fn for_checksums_file(file_name: &str, digest: &str) -> Self {
+        if digest.len() == ChecksumAlgorithm::Sha512.hex_len() {
+            return ChecksumAlgorithm::Sha512;
+        }
+        let file_name = file_name.to_lowercase();
+        if file_name.starts_with("b3sums")
+            || Path::new(&file_name).extension().is_some_and(|e| e == "b3")
+            || file_name.contains("blake3")
+        {
+            return ChecksumAlgorithm::Blake3;
+        }
+        ChecksumAlgorithm::Sha256
+    }
+
+    /// The length of this algorithm's digest as a hex string.
+    pub(crate) fn hex_len(self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sha256 | ChecksumAlgorithm::Blake3 => 64,
+            ChecksumAlgorithm::Sha512 => 128,
+        }
+    }
+
+    /// Returns true if the given string looks like a hex digest produced by this algorithm.
+    pub(crate) fn is_valid_hex_digest(self, digest: &str) -> bool {
+        digest.len() == self.hex_len() && digest.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    /// Reads everything from the given reader and returns its digest as a lowercase hex string.
+    pub(crate) fn hex_digest(self, reader: impl Read) -> Result<String> {
+        HashingReader::new(reader, self).finish()
+    }
+
+    fn new_hasher(self) -> Hasher {
+        match self {
+            ChecksumAlgorithm::Sha256 => Hasher::Sha(Box::new(Sha256::new())),
+            ChecksumAlgorithm::Sha512 => Hasher::Sha(Box::new(Sha512::new())),
+            ChecksumAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+}
+
+// The `blake3` crate's support for the `digest` traits depends on a different version of `digest`
+// than `sha2` does, so we can't treat every hasher as a `DynDigest`.
+enum Hasher {
+    Sha(Box<dyn DynDigest>),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finalize_reset(&mut self) -> Vec<u8> {
+        match self {
+            Hasher::Sha(hasher) => hasher.finalize_reset().into_vec(),
+            Hasher::Blake3(hasher) => {
+                let digest = hasher.finalize().as_bytes().to_vec();
+                hasher.reset();
+                digest
+            }
+        }
+    }
+}
+
+/// Finds the digest for the file with the given name in the contents of a checksums file, like a
+/// `SHA256SUMS` or `checksums.txt` release asset, along with the algorithm that made it. Lines can
+/// be in either of two formats:
+///
+/// - The GNU format that `sha256sum` and similar tools produce, which is a hex digest, whitespace,
+///   and a file name. This accepts both `<digest>  <name>` and `<digest> *<name>`, where the `*`
+///   marks a file hashed in binary mode.
+/// - The BSD format that `shasum --tag` and BSD's `sha256` produce, which is
+///   `<ALGORITHM> (<name>) = <digest>`.
+///
+/// In both formats, a leading `./` on the file name is ignored. Blank lines and lines starting
+/// with `#` are skipped.
+///
+/// A checksums file can have digests from more than one algorithm. When `algorithm` is given, this
+/// returns the file's digest for that algorithm, going by the algorithm a BSD line names, or by the
+/// length of the digest in a GNU line. Otherwise it returns the file's first digest. The algorithm
+/// for a GNU line is guessed with [`ChecksumAlgorithm::for_checksums_file`].
+pub(crate) fn digest_from_checksums_file<'a>(
+    contents: &'a str,
+    checksums_file_name: &str,
+    file_name: &str,
+    algorithm: Option<ChecksumAlgorithm>,
+) -> Option<(ChecksumAlgorithm, &'a str)> {
+    // If a GNU line for the file has a digest of the wrong length for the algorithm we were given,
+    // we return it when there's nothing better, so the caller can report that the digest is
+    // invalid, rather than that there isn't one.
+    let mut invalid = None;
+    for (line_algorithm, name, digest) in contents.lines().filter_map(parse_checksums_line) {
+        let name = name.strip_prefix("./").unwrap_or(name);
+        if name != file_name {
+            continue;
+        }
+        let line_algorithm = match (line_algorithm, algorithm) {
+            (LineAlgorithm::Named(a), _) => a,
+            (LineAlgorithm::Unsupported, _) => continue,
+            (LineAlgorithm::Unnamed, Some(a)) => {
+                if !a.is_valid_hex_digest(digest) {
+                    invalid.get_or_insert((a, digest));
+                    continue;
+                }
+                a
+            }
+            (LineAlgorithm::Unnamed, None) => {
+                ChecksumAlgorithm::for_checksums_file(checksums_file_name, digest)
+            }
+        };
+        if algorithm.is_none_or(|a| a == line_algorithm) {
+            return Some((line_algorithm, digest));
+        }
+    }
+
+    invalid
+}
+
+// The algorithm for a line in a checksums file. A BSD line names its algorithm, but a GNU line
+// doesn't.
+enum LineAlgorithm {
+    Named(ChecksumAlgorithm),
+    Unsupported,
+    Unnamed,
+}
+
+// Returns the algorithm, file name, and digest for a line, or `None` for a blank line, a comment,
+// or a line we can't parse.
+fn parse_checksums_line(line: &str) -> Option<(LineAlgorithm, &str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    if let Some(caps) = regex!(r"^([A-Za-z0-9-]+) ?\((.+)\) ?= ?([0-9A-Fa-f]+)$").captures(line) {
+        let algorithm = match caps[1].to_lowercase().as_str() {
+            "sha256" | "sha2-256" => LineAlgorithm::Named(ChecksumAlgorithm::Sha256),
+            "sha512" | "sha2-512" => LineAlgorithm::Named(ChecksumAlgorithm::Sha512),
+            "blake3" => LineAlgorithm::Named(ChecksumAlgorithm::Blake3),
+            _ => LineAlgorithm::Unsupported,
+        };
+        return Some((algorithm, caps.get(2)?.as_str(), caps.get(3)?.as_str()));
+    }
+
+    let (digest, name) = line.split_once(char::is_whitespace)?;
+    let name = name.trim_start();
+    let name = name.strip_prefix('*').unwrap_or(name);
+    Some((LineAlgorithm::Unnamed, name, digest))
+}
+
+/// Parses a digest in the `<algorithm>:<hex>` form that GitHub uses for release assets, like
+/// `sha256:9095bd...`.
+pub(crate) fn parse_prefixed_digest(digest: &str) -> Result<(ChecksumAlgorithm, String)> {
+    let (algorithm, hex) = digest
+        .split_once(':')
+        .ok_or_else(|| anyhow!("the digest {digest} is not in the form <algorithm>:<hex>"))?;
+    let algorithm = ChecksumAlgorithm::from_str(&algorithm.to_lowercase())
+        .map_err(|_| anyhow!("the digest {digest} uses an unsupported algorithm, {algorithm}"))?;
+    if !algorithm.is_valid_hex_digest(hex) {
+        return Err(anyhow!(
+            "the digest {digest} is not a valid {} hex digest",
+            algorithm.as_ref(),
+        ));
+    }
+
+    Ok((algorithm, hex.to_lowercase()))
+}
+
+/// A reader that computes a digest of everything that is read through it. Clones share the same
+/// underlying reader and digest state, so one clone can be handed to a decoder while another is
+/// kept around to get the digest once the decoder is done.
+pub(crate) struct HashingReader<R> {
+    state: Rc<RefCell<(R, Hasher)>>,
+}
+
+// We can't derive this, because that would require `R: Clone`.
+impl<R> Clone for HashingReader<R> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Rc::clone(&self.state),
+        }
+    }
+}
+
+impl<R: Read> HashingReader<R> {
+    pub(crate) fn new(reader: R, algorithm: ChecksumAlgorithm) -> Self {
+        Self {
+            state: Rc::new(RefCell::new((reader, algorithm.new_hasher()))),
+        }
+    }
+
+    /// Reads anything left in the underlying reader and returns the digest of everything that was
+    /// read as a lowercase hex string. Reading the rest of the input ensures that the digest covers
+    /// any bytes that a decoder stopped short of, like trailing data after a compressed stream.
+    pub(crate) fn finish(&self) -> Result<String> {
+        io::copy(&mut self.clone(), &mut io::sink())?;
+        let digest = self.state.borrow_mut().1.finalize_reset();
+        Ok(digest.iter().fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        }))
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.state.borrow_mut();
+        let n = state.0.read(buf)?;
+        state.1.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(
+        ChecksumAlgorithm::Sha256,
+        "9095bdb859308b62acf04036ffd4adfe366d7f737d276eb6c46ae434f3816c9b";
+        "sha256"
+    )]
+    #[test_case(
+        ChecksumAlgorithm::Sha512,
+        concat!(
+            "789f09c2868b1f6aa75bcdc4a2c761525d7a50617c76a8892307bc268bd0c4a6",
+            "e4c5359486e556f9f6233a32dc4b5b97e41a63d03a28d2da37d1aa7bf15f8ddb",
+        );
+        "sha512"
+    )]
+    #[test_case(
+        ChecksumAlgorithm::Blake3,
+        "688a43b46456beef9ceeee13d03f76e80566ef73bf11dd299dd900ce331a10f6";
+        "blake3"
+    )]
+    fn hex_digest(algorithm: ChecksumAlgorithm, expect: &str) -> Result<()> {
+        assert_eq!(algorithm.hex_digest("exe".as_bytes())?, expect);
+        Ok(())
+    }
+
+    #[test_case("SHA256SUMS", &"0".repeat(64), ChecksumAlgorithm::Sha256)]
+    #[test_case("checksums.txt", &"0".repeat(64), ChecksumAlgorithm::Sha256)]
+    #[test_case("SHA512SUMS", &"0".repeat(128), ChecksumAlgorithm::Sha512)]
+    #[test_case("checksums.txt", &"0".repeat(128), ChecksumAlgorithm::Sha512)]
+    #[test_case("B3SUMS", &"0".repeat(64), ChecksumAlgorithm::Blake3)]
+    #[test_case("project.blake3", &"0".repeat(64), ChecksumAlgorithm::Blake3)]
+    #[test_case("project.b3", &"0".repeat(64), ChecksumAlgorithm::Blake3)]
+    #[test_case("web3-checksums.txt", &"0".repeat(64), ChecksumAlgorithm::Sha256)]
+    #[test_case("sb3_SHA256SUMS", &"0".repeat(64), ChecksumAlgorithm::Sha256)]
+    fn for_checksums_file(file_name: &str, digest: &str, expect: ChecksumAlgorithm) {
+        assert_eq!(
+            ChecksumAlgorithm::for_checksums_file(file_name, digest),
+            expect,
+        );
+    }
+
+    const CHECKSUMS_FILE: &str = "
+0000000000000000000000000000000000000000000000000000000000000001  project-Linux-x86_64.tar.gz
+0000000000000000000000000000000000000000000000000000000000000002 *project-Windows-x86_64.zip
+0000000000000000000000000000000000000000000000000000000000000003  ./project-Darwin-arm64.tar.gz
+";
+
+    #[test_case(
+        "project-Linux-x86_64.tar.gz",
+        Some("0000000000000000000000000000000000000000000000000000000000000001");
+        "two spaces"
+    )]
+    #[test_case(
+        "project-Windows-x86_64.zip",
+        Some("0000000000000000000000000000000000000000000000000000000000000002");
+        "binary mode marker"
+    )]
+    #[test_case(
+        "project-Darwin-arm64.tar.gz",
+        Some("0000000000000000000000000000000000000000000000000000000000000003");
+        "leading dot slash"
+    )]
+    #[test_case("project-Linux-x86_64", None; "partial name")]
+    #[test_case("project-FreeBSD-x86_64.tar.gz", None; "missing")]
+    fn digest_from_checksums_file(file_name: &str, expect: Option<&str>) {
+        assert_eq!(
+            super::digest_from_checksums_file(CHECKSUMS_FILE, "SHA256SUMS", file_name, None),
+            expect.map(|d| (ChecksumAlgorithm::Sha256, d)),
+        );
+    }
+
+    const BSD_CHECKSUMS_FILE: &str = "
+# These were made with `shasum --tag`.
+
+MD5 (project-Linux-x86_64.tar.gz) = 00000000000000000000000000000000
+SHA512 (project-Linux-x86_64.tar.gz) = 00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001
+SHA256 (project-Linux-x86_64.tar.gz) = 0000000000000000000000000000000000000000000000000000000000000002
+SHA256 (./project-Darwin-arm64.tar.gz) = 0000000000000000000000000000000000000000000000000000000000000003
+0000000000000000000000000000000000000000000000000000000000000004  project-Windows-x86_64.zip
+";
+
+    #[test_case(
+        "project-Linux-x86_64.tar.gz",
+        None,
+        Some((ChecksumAlgorithm::Sha512, &format!("{}1", "0".repeat(127))));
+        "first supported algorithm"
+    )]
+    #[test_case(
+        "project-Linux-x86_64.tar.gz",
+        Some(ChecksumAlgorithm::Sha256),
+        Some((ChecksumAlgorithm::Sha256, &format!("{}2", "0".repeat(63))));
+        "chosen algorithm"
+    )]
+    #[test_case(
+        "project-Linux-x86_64.tar.gz",
+        Some(ChecksumAlgorithm::Blake3),
+        None;
+        "missing algorithm"
+    )]
+    #[test_case(
+        "project-Darwin-arm64.tar.gz",
+        None,
+        Some((ChecksumAlgorithm::Sha256, &format!("{}3", "0".repeat(63))));
+        "leading dot slash"
+    )]
+    #[test_case(
+        "project-Windows-x86_64.zip",
+        Some(ChecksumAlgorithm::Sha256),
+        Some((ChecksumAlgorithm::Sha256, &format!("{}4", "0".repeat(63))));
+        "gnu line"
+    )]
+    #[test_case(
+        "project-Windows-x86_64.zip",
+        Some(ChecksumAlgorithm::Sha512),
+        Some((ChecksumAlgorithm::Sha512, &format!("{}4", "0".repeat(63))));
+        "gnu line with the wrong length"
+    )]
+    fn digest_from_bsd_checksums_file(
+        file_name: &str,
+        algorithm: Option<ChecksumAlgorithm>,
+        expect: Option<(ChecksumAlgorithm, &str)>,
+    ) {
+        assert_eq!(
+            super::digest_from_checksums_file(
+                BSD_CHECKSUMS_FILE,
+                "checksums.txt",
+                file_name,
+                algorithm,
+            ),
+            expect,
+        );
+    }
+
+    #[test_case(&format!("sha256:{}", "A".repeat(64)), Ok(ChecksumAlgorithm::Sha256); "sha256")]
+    #[test_case(&format!("sha512:{}", "0".repeat(128)), Ok(ChecksumAlgorithm::Sha512); "sha512")]
+    #[test_case(
+        &"0".repeat(64),
+        Err("is not in the form <algorithm>:<hex>");
+        "no algorithm"
+    )]
+    #[test_case("md5:0000", Err("uses an unsupported algorithm, md5"); "unsupported algorithm")]
+    #[test_case("sha256:0000", Err("is not a valid sha256 hex digest"); "too short")]
+    fn parse_prefixed_digest(digest: &str, expect: Result<ChecksumAlgorithm, &str>) {
+        match (super::parse_prefixed_digest(digest), expect) {
+            (Ok((algorithm, hex)), Ok(expect)) => {
+                assert_eq!(algorithm, expect);
+                assert_eq!(hex, digest.split_once(':').unwrap().1.to_lowercase());
+            }
+            (Err(e), Err(expect)) => {
+                assert_eq!(e.to_string(), format!("the digest {digest} {expect}"));
+            }
+            (got, expect) => panic!("got {got:?} but expected {expect:?}"),
+        }
+    }
+}
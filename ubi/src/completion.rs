@@ -0,0 +1,61 @@
+use std::path::Path;
+use strum::{AsRefStr, EnumString, VariantNames};
+
+/// The shells that `ubi` can install completion files for.
+#[derive(AsRefStr, Clone, Copy, Debug, EnumString, PartialEq, Eq, Hash, VariantNames)]
+pub enum Shell {
+    #[strum(serialize = "bash")]
+    Bash,
+    #[strum(serialize = "zsh")]
+    Zsh,
+    #[strum(serialize = "fish")]
+    Fish,
+}
+
+impl Shell {
+    /// Returns the shell that the archive member at `path` is a completion file for, if any. Only
+    /// files inside a directory named `completions` are considered. Bash completions end in
+    /// `.bash`, zsh completions start with `_` or end in `.zsh`, and fish completions end in
+    /// `.fish`.
+    pub(crate) fn for_completion_file(path: &Path) -> Option<Self> {
+        let in_completions_dir = path
+            .parent()?
+            .components()
+            .any(|c| c.as_os_str() == "completions");
+        if !in_completions_dir {
+            return None;
+        }
+
+        let file_name = path.file_name()?.to_str()?;
+        let extension = Path::new(file_name).extension().and_then(|e| e.to_str());
+        match extension {
+            Some("bash") => Some(Shell::Bash),
+            Some("zsh") => Some(Shell::Zsh),
+            Some("fish") => Some(Shell::Fish),
+            None if file_name.len() > 1 && file_name.starts_with('_') => Some(Shell::Zsh),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("completions/project.bash", Some(Shell::Bash))]
+    #[test_case("project-1.0.0/completions/project.bash", Some(Shell::Bash))]
+    #[test_case("completions/bash/project.bash", Some(Shell::Bash))]
+    #[test_case("completions/_project", Some(Shell::Zsh))]
+    #[test_case("completions/project.zsh", Some(Shell::Zsh))]
+    #[test_case("completions/project.fish", Some(Shell::Fish))]
+    #[test_case("completions/README.md", None)]
+    #[test_case("completions/_", None)]
+    #[test_case("completions/_project.py", None)]
+    #[test_case("project.fish", None; "not in a completions dir")]
+    #[test_case("share/project.bash", None; "in another dir")]
+    #[test_case("completions", None; "the dir itself")]
+    fn for_completion_file(path: &str, expect: Option<Shell>) {
+        assert_eq!(Shell::for_completion_file(Path::new(path)), expect);
+    }
+}
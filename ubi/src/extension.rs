@@ -21,47 +21,83 @@ pub(crate) enum ExtensionError {
 
 #[derive(Debug, EnumIter, PartialEq, Eq)]
 pub(crate) enum Extension {
+    A,
     AppImage,
+    Ar,
     Bat,
+    Br,
     Bz,
     Bz2,
+    Cab,
+    Deb,
     Exe,
     Gz,
     Jar,
+    Lz,
+    Lz4,
+    Lzma,
     Pyz,
+    Rpm,
+    SevenZ,
     Tar,
+    TarBr,
     TarBz,
     TarBz2,
     TarGz,
+    TarLz,
+    TarLz4,
+    TarLzma,
     TarXz,
+    TarZ,
+    TarZst,
     Tbz,
     Tgz,
     Txz,
     Xz,
+    Z,
     Zip,
+    Zst,
 }
 
 impl Extension {
     pub(crate) fn extension(&self) -> &'static str {
         match self {
+            Extension::A => ".a",
             Extension::AppImage => ".AppImage",
+            Extension::Ar => ".ar",
             Extension::Bat => ".bat",
+            Extension::Br => ".br",
             Extension::Bz => ".bz",
             Extension::Bz2 => ".bz2",
+            Extension::Cab => ".cab",
+            Extension::Deb => ".deb",
             Extension::Exe => ".exe",
             Extension::Gz => ".gz",
+            Extension::Lz => ".lz",
+            Extension::Lz4 => ".lz4",
+            Extension::Lzma => ".lzma",
             Extension::Pyz => ".pyz",
+            Extension::Rpm => ".rpm",
             Extension::Jar => ".jar",
+            Extension::SevenZ => ".7z",
             Extension::Tar => ".tar",
+            Extension::TarBr => ".tar.br",
             Extension::TarBz => ".tar.bz",
             Extension::TarBz2 => ".tar.bz2",
             Extension::TarGz => ".tar.gz",
+            Extension::TarLz => ".tar.lz",
+            Extension::TarLz4 => ".tar.lz4",
+            Extension::TarLzma => ".tar.lzma",
             Extension::TarXz => ".tar.xz",
+            Extension::TarZ => ".tar.Z",
+            Extension::TarZst => ".tar.zst",
             Extension::Tbz => ".tbz",
             Extension::Tgz => ".tgz",
             Extension::Txz => ".txz",
             Extension::Xz => ".xz",
+            Extension::Z => ".Z",
             Extension::Zip => ".zip",
+            Extension::Zst => ".zst",
         }
     }
 
@@ -73,18 +109,36 @@ impl Extension {
         match self {
             Extension::AppImage
             | Extension::Bat
+            | Extension::Br
             | Extension::Bz
             | Extension::Bz2
             | Extension::Exe
             | Extension::Gz
             | Extension::Jar
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
             | Extension::Pyz
-            | Extension::Xz => false,
-            Extension::Tar
+            | Extension::Xz
+            | Extension::Z
+            | Extension::Zst => false,
+            Extension::A
+            | Extension::Ar
+            | Extension::Cab
+            | Extension::Deb
+            | Extension::Rpm
+            | Extension::SevenZ
+            | Extension::Tar
+            | Extension::TarBr
             | Extension::TarBz
             | Extension::TarBz2
             | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
             | Extension::TarXz
+            | Extension::TarZ
+            | Extension::TarZst
             | Extension::Tbz
             | Extension::Tgz
             | Extension::Txz
@@ -99,49 +153,120 @@ impl Extension {
             | Extension::Exe
             | Extension::Jar
             | Extension::Pyz => true,
-            Extension::Bz
+            Extension::A
+            | Extension::Ar
+            | Extension::Br
+            | Extension::Bz
             | Extension::Gz
             | Extension::Bz2
+            | Extension::Cab
+            | Extension::Deb
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
+            | Extension::Rpm
+            | Extension::SevenZ
             | Extension::Tar
+            | Extension::TarBr
             | Extension::TarBz
             | Extension::TarBz2
             | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
             | Extension::TarXz
+            | Extension::TarZ
+            | Extension::TarZst
             | Extension::Tbz
             | Extension::Tgz
             | Extension::Txz
             | Extension::Xz
-            | Extension::Zip => false,
+            | Extension::Z
+            | Extension::Zip
+            | Extension::Zst => false,
+        }
+    }
+
+    // Returns the byte sequences that a file with this extension can start with. This is empty for
+    // formats that don't have a fixed header, like brotli and LZMA, and for executables, since we
+    // don't check those, or a plain tarball, whose magic is not at the start of the file.
+    pub(crate) fn magic_bytes(&self) -> &'static [&'static [u8]] {
+        match self {
+            Extension::Bz
+            | Extension::Bz2
+            | Extension::TarBz
+            | Extension::TarBz2
+            | Extension::Tbz => &[b"BZh"],
+            Extension::Cab => &[b"MSCF"],
+            Extension::A | Extension::Ar | Extension::Deb => &[b"!<arch>\n"],
+            Extension::Gz | Extension::TarGz | Extension::Tgz => &[b"\x1f\x8b"],
+            Extension::Lz | Extension::TarLz => &[b"LZIP"],
+            // The second of these is the legacy LZ4 frame format.
+            Extension::Lz4 | Extension::TarLz4 => &[b"\x04\x22\x4d\x18", b"\x02\x21\x4c\x18"],
+            Extension::Rpm => &[b"\xed\xab\xee\xdb"],
+            Extension::SevenZ => &[b"7z\xbc\xaf\x27\x1c"],
+            Extension::Xz | Extension::TarXz | Extension::Txz => &[b"\xfd7zXZ\x00"],
+            Extension::Z | Extension::TarZ => &[b"\x1f\x9d"],
+            Extension::Zip => &[b"PK"],
+            Extension::Zst | Extension::TarZst => &[b"\x28\xb5\x2f\xfd"],
+            Extension::AppImage
+            | Extension::Bat
+            | Extension::Br
+            | Extension::Exe
+            | Extension::Jar
+            | Extension::Lzma
+            | Extension::Pyz
+            | Extension::Tar
+            | Extension::TarBr
+            | Extension::TarLzma => &[],
         }
     }
 
     pub(crate) fn matches_platform(&self, platform: &Platform) -> bool {
         match self {
-            Extension::AppImage => platform.target_os == OS::Linux,
+            Extension::AppImage | Extension::Deb | Extension::Rpm => {
+                platform.target_os == OS::Linux
+            }
             Extension::Bat | Extension::Exe => platform.target_os == OS::Windows,
             _ => true,
         }
     }
 
+    // Release assets in these formats are only picked when no asset in another format will do. See
+    // `AssetPicker::pick_asset`.
+    pub(crate) fn is_fallback_format(&self) -> bool {
+        matches!(
+            self,
+            Extension::Ar | Extension::Cab | Extension::Deb | Extension::Rpm | Extension::SevenZ
+        )
+    }
+
     pub(crate) fn is_windows_only(&self) -> bool {
         matches!(self, Extension::Bat | Extension::Exe)
     }
 
+    fn from_suffix(suffix: &str) -> Option<Extension> {
+        Extension::iter().find(|e| e.extension() == suffix)
+    }
+
     pub(crate) fn from_path(path: &Path) -> Result<Option<Extension>> {
         let Some(ext_str_from_path) = path.extension() else {
             return Ok(None);
         };
-        let path_str = path.to_string_lossy();
-
-        // We need to try the longest extensions first so that ".tar.gz" matches before ".gz" and so
-        // on for other compression formats.
-        if let Some(ext) = Extension::iter()
-            .sorted_by(|a, b| Ord::cmp(&a.extension().len(), &b.extension().len()))
-            .rev()
-            // This is intentionally using a string comparison instead of looking at
-            // path.extension(). That's because the `.extension()` method returns `"bz"` for paths
-            // like "foo.tar.bz", instead of "tar.bz".
-            .find(|e| path_str.ends_with(e.extension()))
+        let file_name = path
+            .file_name()
+            .map(OsStr::to_string_lossy)
+            .unwrap_or_default();
+
+        // We look at every suffix of the file name that starts with a dot, from the longest to the
+        // shortest, so that ".tar.gz" matches before ".gz" and so on for other compression formats.
+        // Because each suffix has to be an entire extension, dots earlier in the name, like those
+        // in "project.linux.tar.gz" or "project-v1.2.3.tar.gz", are skipped over. This is
+        // intentionally not using `path.extension()`, because that returns `"bz"` for paths like
+        // "foo.tar.bz", instead of "tar.bz".
+        if let Some(ext) = file_name
+            .match_indices('.')
+            .find_map(|(i, _)| Extension::from_suffix(&file_name[i..]))
         {
             return Ok(Some(ext));
         }
@@ -164,6 +289,23 @@ impl Extension {
     }
 }
 
+// Returns the longest of `extensions` that `file_name` ends with, where each extension is given
+// without its leading dot, like `foo` or `tar.foo`.
+pub(crate) fn matching_custom_extension<'e>(
+    file_name: &str,
+    extensions: impl IntoIterator<Item = &'e str>,
+) -> Option<&'e str> {
+    extensions
+        .into_iter()
+        .filter(|ext| {
+            file_name
+                .strip_suffix(ext)
+                .and_then(|rest| rest.strip_suffix('.'))
+                .is_some_and(|stem| !stem.is_empty())
+        })
+        .max_by_key(|ext| ext.len())
+}
+
 fn extension_is_part_of_version(path: &Path, ext_str: &OsStr) -> bool {
     let ext_str = ext_str.to_string_lossy().to_string();
 
@@ -208,18 +350,36 @@ mod test {
     use test_case::test_case;
     use test_log::test;
 
+    #[test_case("foo.a", Ok(Some(Extension::A)))]
     #[test_case("foo.AppImage", Ok(Some(Extension::AppImage)))]
+    #[test_case("foo.ar", Ok(Some(Extension::Ar)))]
+    #[test_case("foo.br", Ok(Some(Extension::Br)))]
     #[test_case("foo.bz", Ok(Some(Extension::Bz)))]
+    #[test_case("foo.cab", Ok(Some(Extension::Cab)))]
+    #[test_case("foo.deb", Ok(Some(Extension::Deb)))]
+    #[test_case("foo.rpm", Ok(Some(Extension::Rpm)))]
     #[test_case("foo.bz2", Ok(Some(Extension::Bz2)))]
     #[test_case("foo.exe", Ok(Some(Extension::Exe)))]
     #[test_case("foo.gz", Ok(Some(Extension::Gz)))]
+    #[test_case("foo.lz", Ok(Some(Extension::Lz)))]
+    #[test_case("foo.lz4", Ok(Some(Extension::Lz4)))]
+    #[test_case("foo.lzma", Ok(Some(Extension::Lzma)))]
+    #[test_case("foo.7z", Ok(Some(Extension::SevenZ)))]
     #[test_case("foo.tar", Ok(Some(Extension::Tar)))]
     #[test_case("foo.tar.bz", Ok(Some(Extension::TarBz)))]
     #[test_case("foo.tar.bz2", Ok(Some(Extension::TarBz2)))]
     #[test_case("foo.tar.gz", Ok(Some(Extension::TarGz)))]
+    #[test_case("foo.tar.lz", Ok(Some(Extension::TarLz)))]
+    #[test_case("foo.tar.br", Ok(Some(Extension::TarBr)))]
+    #[test_case("foo.tar.lz4", Ok(Some(Extension::TarLz4)))]
+    #[test_case("foo.tar.lzma", Ok(Some(Extension::TarLzma)))]
     #[test_case("foo.tar.xz", Ok(Some(Extension::TarXz)))]
+    #[test_case("foo.tar.Z", Ok(Some(Extension::TarZ)))]
+    #[test_case("foo.tar.zst", Ok(Some(Extension::TarZst)))]
     #[test_case("foo.xz", Ok(Some(Extension::Xz)))]
+    #[test_case("foo.Z", Ok(Some(Extension::Z)))]
     #[test_case("foo.zip", Ok(Some(Extension::Zip)))]
+    #[test_case("foo.zst", Ok(Some(Extension::Zst)))]
     #[test_case("foo", Ok(None))]
     #[test_case("foo_3.2.1_linux_amd64", Ok(None))]
     #[test_case("foo_3.9.1.linux.amd64", Ok(None))]
@@ -227,6 +387,18 @@ mod test {
     #[test_case("i386-linux-ghcup-0.1.30.0-linux_amd64", Ok(None))]
     #[test_case("foo.bar", Err(ExtensionError::UnknownExtension { path: PathBuf::from("foo.bar"), ext: "bar".to_string() }.into()))]
     #[test_case("pkl-lsp-0.2.0.jar", Ok(Some(Extension::Jar)))]
+    #[test_case("project.linux.tar.gz", Ok(Some(Extension::TarGz)))]
+    #[test_case("v1.2.3.tar.gz", Ok(Some(Extension::TarGz)))]
+    #[test_case("project-v1.2.3.tar.bz2", Ok(Some(Extension::TarBz2)))]
+    #[test_case("project_1.2.3_linux_amd64.tar.xz", Ok(Some(Extension::TarXz)))]
+    #[test_case("project-1.2.3.linux.amd64.tar.zst", Ok(Some(Extension::TarZst)))]
+    #[test_case("project-10.0.1.tar.lz4", Ok(Some(Extension::TarLz4)))]
+    #[test_case("project-10.0.1.tar.lz", Ok(Some(Extension::TarLz)))]
+    #[test_case("project-10.0.1.lzma", Ok(Some(Extension::Lzma)))]
+    #[test_case("project-1.2.3.gz", Ok(Some(Extension::Gz)))]
+    #[test_case("project-1.2.3.zip", Ok(Some(Extension::Zip)))]
+    #[test_case("project.tar.1.2.3.gz", Ok(Some(Extension::Gz)); "tar is not the last extension")]
+    #[test_case("releases/v1.2.3.tar.gz/project", Ok(None); "extension in a dir name")]
     fn from_path(path: &str, expect: Result<Option<Extension>>) {
         crate::test_case::init_logging();
 
@@ -242,6 +414,18 @@ mod test {
         }
     }
 
+    #[test_case("project.tar.foo", &["foo", "tar.foo"], Some("tar.foo"))]
+    #[test_case("project.foo", &["foo", "tar.foo"], Some("foo"))]
+    #[test_case("project.bar", &["foo"], None)]
+    #[test_case("project-foo", &["foo"], None; "not after a dot")]
+    #[test_case(".foo", &["foo"], None; "nothing before the extension")]
+    fn matching_custom_extension(file_name: &str, extensions: &[&str], expect: Option<&str>) {
+        assert_eq!(
+            super::matching_custom_extension(file_name, extensions.iter().copied()),
+            expect,
+        );
+    }
+
     #[test]
     fn matches_platform() -> Result<()> {
         let freebsd = Platform::find("x86_64-unknown-freebsd").unwrap().clone();
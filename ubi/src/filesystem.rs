@@ -0,0 +1,913 @@
+use filetime::FileTime;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Debug,
+    fs::{self, File},
+    io::{self, Cursor, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::PermissionsExt;
+
+/// The kind of thing that exists at a path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+/// The metadata `ubi` needs about an installed path. This describes the path itself, so symlinks
+/// are not followed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub kind: FileKind,
+    /// The Unix permission bits for the path. On platforms without Unix permissions, this is a
+    /// best-effort approximation.
+    pub mode: u32,
+    /// The time the path was last modified, if it's known.
+    pub modified: Option<SystemTime>,
+}
+
+/// The filesystem operations that `ubi` uses to install files. By default, `ubi` uses [`RealFs`],
+/// which just calls the matching functions in [`std::fs`]. You can provide your own
+/// implementation with [`crate::UbiBuilder::filesystem`] to install into something other than the
+/// local filesystem, like an overlay or a remote filesystem.
+///
+/// Note that the downloaded release asset is always read from the local filesystem. Only the
+/// installed files go through this trait.
+pub trait FileSystem: Debug + Send + Sync {
+    /// Creates a directory and all of its missing parents, like [`std::fs::create_dir_all`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Creates a file, truncating it if it already exists, like [`std::fs::File::create`]. The
+    /// parent directory must already exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created.
+    fn create_file(&self, path: &Path) -> io::Result<Box<dyn Write + '_>>;
+
+    /// Opens an existing file for reading, like [`std::fs::File::open`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or cannot be opened.
+    fn open_file(&self, path: &Path) -> io::Result<Box<dyn Read + '_>>;
+
+    /// Sets the Unix permission bits for a path. Implementations for platforms without Unix
+    /// permissions may ignore this.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or its permissions cannot be changed.
+    fn set_permissions(&self, path: &Path, mode: u32) -> io::Result<()>;
+
+    /// Sets the modification time of a file, like [`filetime::set_file_mtime`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or its modification time cannot be changed.
+    fn set_modified(&self, path: &Path, modified: SystemTime) -> io::Result<()>;
+
+    /// Renames a file or directory, like [`std::fs::rename`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path cannot be renamed, for example because `from` and `to` are on
+    /// different filesystems.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Removes a file or symlink, like [`std::fs::remove_file`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or is a directory.
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Removes an empty directory, like [`std::fs::remove_dir`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is not an empty directory.
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+
+    /// Removes a directory and everything in it, like [`std::fs::remove_dir_all`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is not a directory or anything in it cannot be removed.
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Returns the full path of each entry in a directory. The order of the entries is not
+    /// specified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is not a directory or cannot be read.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Returns the metadata for a path without following symlinks, like
+    /// [`std::fs::symlink_metadata`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with the kind [`ErrorKind::NotFound`] if nothing exists at the path.
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    /// Creates a symlink at `link` that points to `target`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the symlink cannot be created.
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()>;
+
+    /// Returns the target of a symlink, like [`std::fs::read_link`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is not a symlink.
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+}
+
+/// A [`FileSystem`] that uses the local filesystem via [`std::fs`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<Box<dyn Write + '_>> {
+        Ok(Box::new(File::create(path)?))
+    }
+
+    fn open_file(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    #[cfg(target_family = "unix")]
+    fn set_permissions(&self, path: &Path, mode: u32) -> io::Result<()> {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+    }
+
+    #[cfg(target_family = "windows")]
+    fn set_permissions(&self, _path: &Path, _mode: u32) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_modified(&self, path: &Path, modified: SystemTime) -> io::Result<()> {
+        filetime::set_file_mtime(path, FileTime::from_system_time(modified))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect()
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let meta = fs::symlink_metadata(path)?;
+        let kind = if meta.is_symlink() {
+            FileKind::Symlink
+        } else if meta.is_dir() {
+            FileKind::Dir
+        } else {
+            FileKind::File
+        };
+
+        #[cfg(target_family = "unix")]
+        let mode = meta.permissions().mode();
+        #[cfg(target_family = "windows")]
+        let mode = if meta.permissions().readonly() {
+            0o555
+        } else {
+            0o755
+        };
+
+        Ok(FileMetadata {
+            kind,
+            mode,
+            modified: meta.modified().ok(),
+        })
+    }
+
+    #[cfg(target_family = "unix")]
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(target, link)
+    }
+
+    #[cfg(target_family = "windows")]
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::read_link(path)
+    }
+}
+
+/// A [`FileSystem`] that keeps everything in memory. This is useful for testing code that
+/// installs files without touching the local filesystem.
+///
+/// A file only has a modification time once one is set with [`FileSystem::set_modified`].
+///
+/// Every path is treated as a plain key, so paths are not canonicalized and symlinks are never
+/// followed. The parent of a path must exist before a file, symlink, or directory (other than via
+/// [`FileSystem::create_dir_all`]) can be created in it. A path with no parent, like `/`, always
+/// exists as a directory.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+    discard_contents: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    File {
+        contents: Vec<u8>,
+        mode: u32,
+        modified: Option<SystemTime>,
+    },
+    Dir {
+        mode: u32,
+    },
+    Symlink(PathBuf),
+}
+
+impl MemoryFs {
+    /// Returns a new empty `MemoryFs`.
+    #[must_use]
+    pub fn new() -> Self {
+        MemoryFs::default()
+    }
+
+    /// Returns a new empty `MemoryFs` that throws away everything written to its files. This is
+    /// used for dry runs, where we only care about which paths would be created, so there's no
+    /// point in holding a whole release in memory.
+    pub(crate) fn without_contents() -> Self {
+        MemoryFs {
+            discard_contents: true,
+            ..MemoryFs::default()
+        }
+    }
+
+    fn nodes(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Node>> {
+        // A panic while the lock is held can't leave the map in an inconsistent state, since every
+        // operation is a single insert or remove, so it's fine to ignore poisoning.
+        self.nodes
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn check_parent_is_dir(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) if parent.parent().is_some() => match nodes.get(parent) {
+                Some(Node::Dir { .. }) => Ok(()),
+                Some(_) => Err(not_a_dir(parent)),
+                None => Err(not_found(parent)),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn has_children(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> bool {
+        nodes
+            .range(path.to_path_buf()..)
+            .skip(1)
+            .take_while(|(p, _)| p.starts_with(path))
+            .next()
+            .is_some()
+    }
+
+    fn take_tree(nodes: &mut BTreeMap<PathBuf, Node>, path: &Path) -> Vec<(PathBuf, Node)> {
+        let paths = nodes
+            .range(path.to_path_buf()..)
+            .map(|(p, _)| p)
+            .take_while(|p| p.starts_with(path))
+            .cloned()
+            .collect::<Vec<_>>();
+        paths
+            .into_iter()
+            .filter_map(|p| nodes.remove(&p).map(|n| (p, n)))
+            .collect()
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        let mut ancestors = path
+            .ancestors()
+            .filter(|a| a.parent().is_some())
+            .collect::<Vec<_>>();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            match nodes.get(ancestor) {
+                Some(Node::Dir { .. }) => (),
+                Some(_) => return Err(not_a_dir(ancestor)),
+                None => {
+                    nodes.insert(ancestor.to_path_buf(), Node::Dir { mode: 0o755 });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<Box<dyn Write + '_>> {
+        let mut nodes = self.nodes();
+        Self::check_parent_is_dir(&nodes, path)?;
+        let mode = match nodes.get(path) {
+            Some(Node::File { mode, .. }) => *mode,
+            Some(_) => return Err(already_exists(path)),
+            None => 0o644,
+        };
+        nodes.insert(
+            path.to_path_buf(),
+            Node::File {
+                contents: vec![],
+                mode,
+                modified: None,
+            },
+        );
+        Ok(Box::new(MemoryFile {
+            fs: self,
+            path: path.to_path_buf(),
+        }))
+    }
+
+    fn open_file(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        match self.nodes().get(path) {
+            Some(Node::File { contents, .. }) => Ok(Box::new(Cursor::new(contents.clone()))),
+            Some(_) => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a file", path.display()),
+            )),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn set_permissions(&self, path: &Path, new_mode: u32) -> io::Result<()> {
+        match self.nodes().get_mut(path) {
+            Some(Node::File { mode, .. } | Node::Dir { mode }) => {
+                *mode = new_mode;
+                Ok(())
+            }
+            // Like `std::fs::set_permissions`, we'd follow the symlink here, but we never follow
+            // symlinks, so we just ignore this.
+            Some(Node::Symlink(_)) => Ok(()),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn set_modified(&self, path: &Path, new_modified: SystemTime) -> io::Result<()> {
+        match self.nodes().get_mut(path) {
+            Some(Node::File { modified, .. }) => {
+                *modified = Some(new_modified);
+                Ok(())
+            }
+            Some(_) => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a file", path.display()),
+            )),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        if !nodes.contains_key(from) {
+            return Err(not_found(from));
+        }
+        Self::check_parent_is_dir(&nodes, to)?;
+        if to.starts_with(from) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "cannot move {} into itself at {}",
+                    from.display(),
+                    to.display()
+                ),
+            ));
+        }
+        match nodes.get(to) {
+            Some(Node::Dir { .. }) if Self::has_children(&nodes, to) => {
+                return Err(io::Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} is a directory that is not empty", to.display()),
+                ));
+            }
+            Some(_) => {
+                nodes.remove(to);
+            }
+            None => (),
+        }
+
+        for (path, node) in Self::take_tree(&mut nodes, from) {
+            let new_path = to.join(path.strip_prefix(from).expect("path is under from"));
+            nodes.insert(new_path, node);
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        match nodes.get(path) {
+            Some(Node::File { .. } | Node::Symlink(_)) => {
+                nodes.remove(path);
+                Ok(())
+            }
+            Some(Node::Dir { .. }) => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is a directory", path.display()),
+            )),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        match nodes.get(path) {
+            Some(Node::Dir { .. }) if Self::has_children(&nodes, path) => Err(io::Error::other(
+                format!("{} is a directory that is not empty", path.display()),
+            )),
+            Some(Node::Dir { .. }) => {
+                nodes.remove(path);
+                Ok(())
+            }
+            Some(_) => Err(not_a_dir(path)),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        match nodes.get(path) {
+            Some(Node::Dir { .. }) => {
+                Self::take_tree(&mut nodes, path);
+                Ok(())
+            }
+            Some(_) => Err(not_a_dir(path)),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let nodes = self.nodes();
+        if path.parent().is_some() {
+            match nodes.get(path) {
+                Some(Node::Dir { .. }) => (),
+                Some(_) => return Err(not_a_dir(path)),
+                None => return Err(not_found(path)),
+            }
+        }
+        Ok(nodes
+            .keys()
+            .filter(|p| p.parent() == Some(path))
+            .cloned()
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        match self.nodes().get(path) {
+            Some(Node::File { mode, modified, .. }) => Ok(FileMetadata {
+                kind: FileKind::File,
+                mode: *mode,
+                modified: *modified,
+            }),
+            Some(Node::Dir { mode }) => Ok(FileMetadata {
+                kind: FileKind::Dir,
+                mode: *mode,
+                modified: None,
+            }),
+            Some(Node::Symlink(_)) => Ok(FileMetadata {
+                kind: FileKind::Symlink,
+                mode: 0o777,
+                modified: None,
+            }),
+            None if path.parent().is_none() => Ok(FileMetadata {
+                kind: FileKind::Dir,
+                mode: 0o755,
+                modified: None,
+            }),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        Self::check_parent_is_dir(&nodes, link)?;
+        if nodes.contains_key(link) {
+            return Err(already_exists(link));
+        }
+        nodes.insert(link.to_path_buf(), Node::Symlink(target.to_path_buf()));
+        Ok(())
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.nodes().get(path) {
+            Some(Node::Symlink(target)) => Ok(target.clone()),
+            Some(_) => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a symlink", path.display()),
+            )),
+            None => Err(not_found(path)),
+        }
+    }
+}
+
+// Writes go straight into the file's node, so the contents are visible as soon as they're written,
+// just like with a real file.
+struct MemoryFile<'a> {
+    fs: &'a MemoryFs,
+    path: PathBuf,
+}
+
+impl Write for MemoryFile<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.fs.nodes().get_mut(&self.path) {
+            Some(Node::File { contents, .. }) => {
+                if !self.fs.discard_contents {
+                    contents.extend_from_slice(buf);
+                }
+                Ok(buf.len())
+            }
+            _ => Err(not_found(&self.path)),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A [`FileSystem`] for dry runs. It starts out looking like another filesystem, but everything
+/// written to it goes into a [`MemoryFs`] that throws away file contents, so the other filesystem
+/// is never changed. Paths are copied from the other filesystem the first time they're looked at,
+/// so a dry run sees the same existing files that a real install would.
+#[derive(Debug)]
+pub(crate) struct DryRunFs {
+    base: Arc<dyn FileSystem>,
+    memory: MemoryFs,
+    state: Mutex<DryRunState>,
+}
+
+#[derive(Debug, Default)]
+struct DryRunState {
+    // Every path we've looked up in the base filesystem, whether or not it existed there.
+    copied: HashSet<PathBuf>,
+    // Paths that were removed or renamed away. These hide everything under them in the base
+    // filesystem, even if something new is later created at the same path.
+    hidden: Vec<PathBuf>,
+    // The files and symlinks that have been created and are still there.
+    written: BTreeSet<PathBuf>,
+}
+
+impl DryRunFs {
+    pub(crate) fn new(base: Arc<dyn FileSystem>) -> Self {
+        DryRunFs {
+            base,
+            memory: MemoryFs::without_contents(),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Returns every file and symlink that was created at or under `path`, in sorted order.
+    pub(crate) fn written_under(&self, path: &Path) -> Vec<PathBuf> {
+        self.state()
+            .written
+            .iter()
+            .filter(|p| p.starts_with(path))
+            .cloned()
+            .collect()
+    }
+
+    fn state(&self) -> MutexGuard<'_, DryRunState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    // Copies `path` and its ancestors from the base filesystem, unless we've already done so.
+    fn copy_from_base(&self, state: &mut DryRunState, path: &Path) -> io::Result<()> {
+        let mut ancestors = path
+            .ancestors()
+            .filter(|a| a.parent().is_some())
+            .collect::<Vec<_>>();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            if !state.copied.insert(ancestor.to_path_buf())
+                || state.hidden.iter().any(|h| ancestor.starts_with(h))
+                || self.memory.nodes().contains_key(ancestor)
+            {
+                continue;
+            }
+            let meta = match self.base.metadata(ancestor) {
+                Ok(meta) => meta,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            let node = match meta.kind {
+                FileKind::Dir => Node::Dir { mode: meta.mode },
+                FileKind::File => Node::File {
+                    contents: vec![],
+                    mode: meta.mode,
+                    modified: meta.modified,
+                },
+                // The memory filesystem never follows symlinks, so a symlink to a directory that
+                // we need to go through, like an install dir that's a link, becomes a directory.
+                FileKind::Symlink if ancestor != path && self.base.read_dir(ancestor).is_ok() => {
+                    Node::Dir { mode: 0o755 }
+                }
+                FileKind::Symlink => Node::Symlink(self.base.read_link(ancestor)?),
+            };
+            self.memory.nodes().insert(ancestor.to_path_buf(), node);
+        }
+        Ok(())
+    }
+
+    fn copy_children_from_base(&self, state: &mut DryRunState, path: &Path) -> io::Result<()> {
+        self.copy_from_base(state, path)?;
+        if state.hidden.iter().any(|h| path.starts_with(h))
+            || !matches!(self.memory.nodes().get(path), Some(Node::Dir { .. }))
+        {
+            return Ok(());
+        }
+        match self.base.read_dir(path) {
+            Ok(children) => children
+                .iter()
+                .try_for_each(|child| self.copy_from_base(state, child)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn copy_tree_from_base(&self, state: &mut DryRunState, path: &Path) -> io::Result<()> {
+        self.copy_children_from_base(state, path)?;
+        if !matches!(self.memory.nodes().get(path), Some(Node::Dir { .. })) {
+            return Ok(());
+        }
+        self.memory
+            .read_dir(path)?
+            .iter()
+            .try_for_each(|child| self.copy_tree_from_base(state, child))
+    }
+}
+
+impl FileSystem for DryRunFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.copy_from_base(&mut self.state(), path)?;
+        self.memory.create_dir_all(path)
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<Box<dyn Write + '_>> {
+        let mut state = self.state();
+        self.copy_from_base(&mut state, path)?;
+        let file = self.memory.create_file(path)?;
+        state.written.insert(path.to_path_buf());
+        Ok(file)
+    }
+
+    fn open_file(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        self.copy_from_base(&mut self.state(), path)?;
+        self.memory.open_file(path)
+    }
+
+    fn set_permissions(&self, path: &Path, mode: u32) -> io::Result<()> {
+        self.copy_from_base(&mut self.state(), path)?;
+        self.memory.set_permissions(path, mode)
+    }
+
+    fn set_modified(&self, path: &Path, modified: SystemTime) -> io::Result<()> {
+        self.copy_from_base(&mut self.state(), path)?;
+        self.memory.set_modified(path, modified)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        self.copy_tree_from_base(&mut state, from)?;
+        self.copy_children_from_base(&mut state, to)?;
+        self.memory.rename(from, to)?;
+        state.hidden.extend([from.to_path_buf(), to.to_path_buf()]);
+        state.written.retain(|p| !p.starts_with(to));
+        let moved = state
+            .written
+            .iter()
+            .filter(|p| p.starts_with(from))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in moved {
+            state.written.remove(&path);
+            let new_path = to.join(path.strip_prefix(from).expect("path is under from"));
+            state.written.insert(new_path);
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        self.copy_from_base(&mut state, path)?;
+        self.memory.remove_file(path)?;
+        state.hidden.push(path.to_path_buf());
+        state.written.remove(path);
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        self.copy_children_from_base(&mut state, path)?;
+        self.memory.remove_dir(path)?;
+        state.hidden.push(path.to_path_buf());
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        self.copy_from_base(&mut state, path)?;
+        self.memory.remove_dir_all(path)?;
+        state.hidden.push(path.to_path_buf());
+        state.written.retain(|p| !p.starts_with(path));
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.copy_children_from_base(&mut self.state(), path)?;
+        self.memory.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        self.copy_from_base(&mut self.state(), path)?;
+        self.memory.metadata(path)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        self.copy_from_base(&mut state, link)?;
+        self.memory.symlink(target, link)?;
+        state.written.insert(link.to_path_buf());
+        Ok(())
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        self.copy_from_base(&mut self.state(), path)?;
+        self.memory.read_link(path)
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        ErrorKind::NotFound,
+        format!("{} does not exist", path.display()),
+    )
+}
+
+fn not_a_dir(path: &Path) -> io::Error {
+    io::Error::other(format!("{} is not a directory", path.display()))
+}
+
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn memory_fs_files_and_dirs() -> io::Result<()> {
+        let fs = MemoryFs::new();
+        let dir = Path::new("/install/bin");
+        fs.create_dir_all(dir)?;
+        assert_eq!(fs.metadata(Path::new("/install"))?.kind, FileKind::Dir);
+
+        let exe = dir.join("project");
+        fs.create_file(&exe)?.write_all(b"exe")?;
+        fs.set_permissions(&exe, 0o755)?;
+        assert_eq!(
+            fs.metadata(&exe)?,
+            FileMetadata {
+                kind: FileKind::File,
+                mode: 0o755,
+                modified: None,
+            },
+        );
+
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        fs.set_modified(&exe, modified)?;
+        assert_eq!(fs.metadata(&exe)?.modified, Some(modified));
+        let mut contents = String::new();
+        fs.open_file(&exe)?.read_to_string(&mut contents)?;
+        assert_eq!(contents, "exe");
+
+        assert!(fs.create_file(Path::new("/missing/file")).is_err());
+        assert!(fs.create_dir_all(&exe.join("sub")).is_err());
+        assert!(fs.remove_dir(dir).is_err());
+        assert_eq!(fs.read_dir(dir)?, vec![exe.clone()]);
+
+        fs.remove_file(&exe)?;
+        fs.remove_dir(dir)?;
+        assert_eq!(fs.metadata(dir).unwrap_err().kind(), ErrorKind::NotFound,);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_fs_reads_through_to_base() -> io::Result<()> {
+        let base = Arc::new(MemoryFs::new());
+        base.create_dir_all(Path::new("/install/bin"))?;
+        base.create_file(Path::new("/install/bin/old"))?
+            .write_all(b"old")?;
+        base.create_file(Path::new("/install/bin/gone"))?;
+
+        let fs = DryRunFs::new(base.clone());
+        assert_eq!(
+            fs.metadata(Path::new("/install/bin/old"))?.kind,
+            FileKind::File
+        );
+        fs.create_file(Path::new("/install/bin/new"))?
+            .write_all(b"new")?;
+        fs.remove_file(Path::new("/install/bin/gone"))?;
+        fs.rename(
+            Path::new("/install/bin/new"),
+            Path::new("/install/bin/renamed"),
+        )?;
+
+        let mut entries = fs.read_dir(Path::new("/install/bin"))?;
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                PathBuf::from("/install/bin/old"),
+                PathBuf::from("/install/bin/renamed"),
+            ],
+        );
+        assert_eq!(
+            fs.written_under(Path::new("/install")),
+            vec![PathBuf::from("/install/bin/renamed")],
+        );
+
+        // Nothing is written to the base filesystem.
+        assert!(base.metadata(Path::new("/install/bin/gone")).is_ok());
+        assert!(base.metadata(Path::new("/install/bin/renamed")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_fs_rename_moves_tree() -> io::Result<()> {
+        let fs = MemoryFs::new();
+        fs.create_dir_all(Path::new("/src/bin"))?;
+        fs.create_file(Path::new("/src/bin/project"))?;
+        fs.symlink(Path::new("bin/project"), Path::new("/src/project"))?;
+        // A directory that is not a prefix match for `/src`, but sorts after it.
+        fs.create_dir_all(Path::new("/src-other"))?;
+
+        fs.rename(Path::new("/src"), Path::new("/dst"))?;
+        assert!(fs.metadata(Path::new("/src")).is_err());
+        assert_eq!(
+            fs.metadata(Path::new("/dst/bin/project"))?.kind,
+            FileKind::File,
+        );
+        assert_eq!(
+            fs.read_link(Path::new("/dst/project"))?,
+            PathBuf::from("bin/project"),
+        );
+        assert_eq!(fs.metadata(Path::new("/src-other"))?.kind, FileKind::Dir);
+
+        fs.remove_dir_all(Path::new("/dst"))?;
+        assert_eq!(
+            fs.read_dir(Path::new("/"))?,
+            vec![PathBuf::from("/src-other")]
+        );
+
+        Ok(())
+    }
+}
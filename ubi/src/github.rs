@@ -132,6 +132,7 @@ mod tests {
         let assets = vec![Asset {
             name: "asset1".to_string(),
             url: Url::parse("https://api.github.com/repos/houseabsolute/ubi/releases/assets/1")?,
+            digest: None,
         }];
 
         let expect_path = if let Some(tag) = tag {
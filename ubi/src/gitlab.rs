@@ -135,6 +135,7 @@ mod tests {
         let assets = vec![Asset {
             name: "asset1".to_string(),
             url: Url::parse("https://gitlab.com/api/v4/projects/owner%2Frepo/releases/assets/1")?,
+            digest: None,
         }];
 
         let expect_path = if let Some(tag) = tag {
@@ -0,0 +1,147 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{
+    path::Path,
+    process::{Command, Output},
+};
+use tempfile::tempdir;
+
+/// Checks that the detached signature in the file at `signature` is a valid signature of the file
+/// at `path`, made with one of the keys in `key_file`. The key file can contain a single armored
+/// key or a whole keyring.
+///
+/// This runs the `gpg` executable with a temporary home directory, so the user's own keyring and
+/// trust settings are never used or changed. The `file_name` is only used in the error.
+pub(crate) fn verify_signature(
+    key_file: &Path,
+    path: &Path,
+    file_name: &str,
+    signature: &Path,
+) -> Result<()> {
+    let home = tempdir()?;
+
+    let import = run_gpg(home.path(), |cmd| {
+        cmd.arg("--import").arg(key_file);
+    })?;
+    if !import.status.success() {
+        return Err(anyhow!(
+            "could not import the GPG key(s) in {}: {}",
+            key_file.display(),
+            String::from_utf8_lossy(&import.stderr).trim(),
+        ));
+    }
+
+    let verify = run_gpg(home.path(), |cmd| {
+        cmd.args(["--status-fd", "1", "--verify"])
+            .arg(signature)
+            .arg(path);
+    })?;
+    let status = String::from_utf8_lossy(&verify.stdout);
+    let signer = signer(&status).unwrap_or("an unknown key");
+    // A zero exit status on its own isn't enough, since gpg can exit successfully without having
+    // checked any signature at all.
+    if verify.status.success() && status_lines(&status).any(|f| f.first() == Some(&"VALIDSIG")) {
+        debug!("the GPG signature for {file_name} was made by the key {signer}");
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "could not verify the GPG signature for {file_name} made by the key {signer}: {}",
+        String::from_utf8_lossy(&verify.stderr).trim(),
+    ))
+}
+
+fn run_gpg(home: &Path, add_args: impl FnOnce(&mut Command)) -> Result<Output> {
+    let mut cmd = Command::new("gpg");
+    cmd.arg("--batch").arg("--homedir").arg(home);
+    add_args(&mut cmd);
+    debug!("running {cmd:?}");
+    cmd.output()
+        .context("could not run gpg - is it installed and in your PATH?")
+}
+
+// Returns the fingerprint of the key that made the signature, based on gpg's `--status-fd`
+// output. If gpg doesn't tell us the fingerprint, this falls back to the key ID from the
+// signature.
+fn signer(status: &str) -> Option<&str> {
+    let mut key_id = None;
+    for fields in status_lines(status) {
+        match fields.as_slice() {
+            ["VALIDSIG" | "KEY_CONSIDERED", fingerprint, ..] => return Some(fingerprint),
+            // Versions of gpg since 2.2.7 add the fingerprint at the end of this line when they
+            // know it.
+            ["ERRSIG", _, _, _, _, _, _, fingerprint, ..] => return Some(fingerprint),
+            ["BADSIG" | "ERRSIG" | "NO_PUBKEY", id, ..] => key_id = key_id.or(Some(*id)),
+            _ => (),
+        }
+    }
+
+    key_id
+}
+
+fn status_lines(status: &str) -> impl Iterator<Item = Vec<&str>> {
+    status.lines().filter_map(|line| {
+        line.strip_prefix("[GNUPG:] ")
+            .map(|l| l.split_whitespace().collect())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    const FINGERPRINT: &str = "072F5DB2B0AB086884923BBD590720C0C15F97D3";
+
+    #[test_case("test-data/gpg-key.asc", "test-data/project.tar.gz", None; "valid")]
+    #[test_case(
+        "test-data/gpg-key.asc",
+        "test-data/project.zip",
+        Some("made by the key 072F5DB2B0AB086884923BBD590720C0C15F97D3");
+        "wrong file"
+    )]
+    #[test_case(
+        "test-data/gpg-other-key.asc",
+        "test-data/project.tar.gz",
+        Some("made by the key 072F5DB2B0AB086884923BBD590720C0C15F97D3");
+        "wrong key"
+    )]
+    fn verify_signature(key_file: &str, path: &str, expect_err: Option<&str>) -> Result<()> {
+        let res = super::verify_signature(
+            Path::new(key_file),
+            Path::new(path),
+            "project.tar.gz",
+            Path::new("test-data/project.tar.gz.asc"),
+        );
+        match expect_err {
+            None => res?,
+            Some(expect_err) => {
+                let err = res.expect_err("verification fails").to_string();
+                assert!(err.contains(expect_err), "{err} contains {expect_err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test_case(
+        concat!(
+            "[GNUPG:] GOODSIG 590720C0C15F97D3 test\n",
+            "[GNUPG:] VALIDSIG 072F5DB2B0AB086884923BBD590720C0C15F97D3 2026-10-16\n",
+        ),
+        Some(FINGERPRINT);
+        "valid"
+    )]
+    #[test_case(
+        concat!(
+            "[GNUPG:] ERRSIG 590720C0C15F97D3 22 8 00 1792148770 9\n",
+            "[GNUPG:] NO_PUBKEY 590720C0C15F97D3\n",
+        ),
+        Some("590720C0C15F97D3");
+        "old gpg without fingerprint"
+    )]
+    #[test_case("gpg: no signature found", None; "no status")]
+    fn signer(status: &str, expect: Option<&str>) {
+        assert_eq!(super::signer(status), expect);
+    }
+}
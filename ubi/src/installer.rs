@@ -1,45 +1,588 @@
-use crate::{extension::Extension, ubi::Download};
+use crate::{
+    appimage, binary_arch,
+    checksum::{ChecksumAlgorithm, HashingReader},
+    completion::Shell,
+    extension::{matching_custom_extension, Extension, ExtensionError},
+    filesystem::{DryRunFs, FileKind, FileSystem, MemoryFs, RealFs},
+    lzip::LzipDecoder,
+    lzw::LzwDecoder,
+    ubi::{new_temp_dir, Download},
+};
 use anyhow::{anyhow, Context, Result};
-use binstall_tar::Archive;
+use binstall_tar::{Archive, Entry, EntryType};
+use brotli::Decompressor as BrotliDecoder;
 use bzip2::read::BzDecoder;
-use flate2::read::GzDecoder;
-use log::{debug, info};
+use cab::Cabinet;
+use flate2::read::MultiGzDecoder;
+use log::{debug, info, warn};
+use lz4_flex::frame::FrameDecoder;
+use platforms::Platform;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sevenz_rust::{Archive as SevenZArchive, Password, SevenZArchiveEntry, SevenZReader};
 use std::{
-    collections::HashSet,
-    ffi::OsString,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
     fmt::Debug,
-    fs::{self, create_dir_all, File},
-    io::{Read, Write},
-    path::{Path, PathBuf},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    iter, mem,
+    num::NonZeroUsize,
+    path::{self, Component, Path, PathBuf},
+    process, str,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
 };
 use strum::IntoEnumIterator;
-use xz2::read::XzDecoder;
-use zip::{read::ZipFile, ZipArchive};
+use thiserror::Error;
+use xz2::{read::XzDecoder, stream::Stream};
+use zip::{read::ZipFile, result::ZipError, ExtraField, ZipArchive};
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+// The mode we give an installed executable when we don't have a better one to use.
+const EXECUTABLE_MODE: u32 = 0o755;
+
+// How many levels of archives inside archives we'll look through for the executable, like a
+// tarball inside a zip file.
+const MAX_NESTED_ARCHIVE_DEPTH: usize = 2;
+
+// On Windows, antivirus software often opens a file right after it's written, which makes other
+// operations on it fail for a moment. We retry those operations this many times, waiting twice as
+// long before each retry.
+const LOCKED_FILE_RETRIES: u32 = 4;
+const LOCKED_FILE_FIRST_DELAY: Duration = Duration::from_millis(100);
+
+// Archive members with these extensions are never partial matches for the executable name, even if
+// they're marked as executable. Release archives often have docs, configs, or checksums named
+// after the project, like `project.md` or `project.sha256`.
+const NON_EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "asc", "cfg", "conf", "csv", "html", "ini", "json", "md", "pdf", "pem", "rst", "sha256",
+    "sha512", "sig", "toml", "txt", "xml", "yaml", "yml",
+];
+
+/// The ways that installing a downloaded release asset can fail. The [`Installer`] methods return
+/// this, and the errors from [`crate::Ubi`]'s install methods can be downcast to it with
+/// [`anyhow::Error::downcast_ref`] when the failure happened while installing.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum InstallError {
+    /// No archive member matched the executable that was being looked for. The description says
+    /// what was being looked for, like `matching [project*]`.
+    #[error("could not find any files {looking_for} in the downloaded archive file")]
+    NoMatchingMember { looking_for: String },
+    /// The downloaded file is in a format that can't be installed from, at least not the way the
+    /// installer is configured.
+    #[error("{message}")]
+    UnsupportedFormat { path: PathBuf, message: String },
+    /// The downloaded file, or the data read from it, does not have the expected digest.
+    #[error(
+        "the {} digest of {} is {actual}, but we expected {expected}",
+        algorithm.as_ref(),
+        path.display(),
+    )]
+    ChecksumMismatch {
+        path: PathBuf,
+        algorithm: ChecksumAlgorithm,
+        expected: String,
+        actual: String,
+    },
+    /// An archive member has a path that would put it outside of the directory it's being
+    /// extracted into, either because it's absolute or because it has too many `..` components.
+    #[error(
+        "the archive member {} would be extracted outside of {}",
+        member.display(),
+        root.display(),
+    )]
+    PathTraversal { member: PathBuf, root: PathBuf },
+    /// Anything else that went wrong while reading the downloaded file or writing the installed
+    /// files.
+    #[error(transparent)]
+    ExtractionFailed(anyhow::Error),
+}
+
+// The installers use `anyhow` internally, so the more specific errors are wrapped in an
+// `anyhow::Error` until they get to an `Installer` method. This unwraps them again, and anything
+// that isn't one of them becomes `ExtractionFailed`.
+impl From<anyhow::Error> for InstallError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<InstallError>() {
+            Ok(e) => return e,
+            Err(err) => err,
+        };
+        match err.downcast::<ExtensionError>() {
+            Ok(e) => {
+                let message = e.to_string();
+                let ExtensionError::UnknownExtension { path, .. } = e;
+                InstallError::UnsupportedFormat { path, message }
+            }
+            Err(err) => InstallError::ExtractionFailed(err),
+        }
+    }
+}
+
+/// An installer takes a downloaded release asset and installs it. The [`ExeInstaller`] installs
+/// one or more executables from it, and the [`ArchiveInstaller`] extracts all of its contents.
+///
+/// This is only public when the `unstable-installer` feature is enabled, and it may change in any
+/// release.
+pub trait Installer: Debug {
+    /// Installs the downloaded release asset, returning the paths that were installed.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the asset cannot be uncompressed or extracted, if what should be
+    /// installed cannot be found in it, or if the installed files cannot be written.
+    fn install(&self, download: &Download) -> Result<Vec<PathBuf>, InstallError>;
+
+    /// Does everything that [`Installer::install`] does, including picking the archive member(s)
+    /// to install, but writes into memory instead of the installer's filesystem. This returns
+    /// every file and symlink path that `install` would have written, in sorted order.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as [`Installer::install`], other than those from writing the
+    /// installed files.
+    fn install_dry_run(&self, download: &Download) -> Result<Vec<PathBuf>, InstallError>;
+
+    /// Checks that the installer's options can be used together, returning an error that
+    /// describes the conflict if they cannot. This is called when a [`crate::Ubi`] is built, so
+    /// misconfiguration is caught before anything is downloaded.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error describing the first conflict it finds.
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Lists every file in the downloaded archive along with how it matches the executable name,
+    /// without extracting anything. This is for figuring out why the wrong archive member, or no
+    /// member, was picked.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the asset is not a tarball or zip file, if it cannot be read, or
+    /// if this installer extracts all of an archive's contents.
+    fn scan_archive(&self, download: &Download) -> Result<Vec<(String, MatchKind)>, InstallError> {
+        Err(anyhow!(
+            "cannot scan {} for executables when extracting all of its contents",
+            download.archive_path.display(),
+        )
+        .into())
+    }
+
+    /// Finds the executable in the downloaded file, just like [`Installer::install`] does, and
+    /// returns its contents instead of writing it to the install path.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as [`Installer::install_dry_run`]. It also returns an error if
+    /// more than one executable would be installed, or if this installer extracts all of an
+    /// archive's contents.
+    fn extract_to_bytes(&self, download: &Download) -> Result<Vec<u8>, InstallError> {
+        Err(anyhow!(
+            "cannot extract {} to memory when extracting all of its contents",
+            download.archive_path.display(),
+        )
+        .into())
+    }
 
-#[cfg(target_family = "unix")]
-use std::fs::{set_permissions, Permissions};
-#[cfg(target_family = "unix")]
-use std::os::unix::fs::PermissionsExt;
+    /// Does the same thing as [`Installer::install`], but returns a record of what was installed
+    /// where, instead of just the installed paths.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as [`Installer::install`]. It also returns an error if this
+    /// installer extracts all of an archive's contents.
+    fn install_with_manifest(&self, download: &Download) -> Result<InstallManifest, InstallError> {
+        Err(anyhow!(
+            "cannot make an install manifest for {} when extracting all of its contents",
+            download.archive_path.display(),
+        )
+        .into())
+    }
+
+    /// Does the same thing as [`Installer::install`], but also returns every archive member that
+    /// was not extracted, along with the reason it was skipped.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as [`Installer::install`]. It also returns an error unless this
+    /// installer extracts all of an archive's contents.
+    fn install_reporting_skipped(
+        &self,
+        download: &Download,
+    ) -> Result<(Vec<PathBuf>, Vec<SkippedMember>), InstallError> {
+        Err(anyhow!(
+            "cannot report the skipped members of {} unless extracting all of its contents",
+            download.archive_path.display(),
+        )
+        .into())
+    }
+
+    /// Does the same thing as [`Installer::install`], but returns a record of every file that was
+    /// extracted from the archive and where it ended up, along with every archive member that was
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as [`Installer::install`]. It also returns an error unless this
+    /// installer extracts all of an archive's contents.
+    fn install_with_extraction_report(
+        &self,
+        download: &Download,
+    ) -> Result<ExtractionReport, InstallError> {
+        Err(anyhow!(
+            "cannot make an extraction report for {} unless extracting all of its contents",
+            download.archive_path.display(),
+        )
+        .into())
+    }
 
-pub(crate) trait Installer: Debug {
-    fn install(&self, download: &Download) -> Result<()>;
+    /// Returns roughly how many bytes [`Installer::install`] would write, without writing
+    /// anything. This is for checking that there's enough free space before installing.
+    ///
+    /// For a tarball or zip file, this adds up the sizes that the archive records for the members
+    /// that would be installed. When one of those members is itself compressed or an archive, its
+    /// size in the archive is used, so the estimate may be low.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the asset cannot be read, or if what should be installed cannot be
+    /// found in it.
+    fn estimated_size(&self, download: &Download) -> Result<u64, InstallError> {
+        Err(anyhow!(
+            "cannot estimate the installed size of {}",
+            download.archive_path.display(),
+        )
+        .into())
+    }
 }
 
-#[derive(Debug)]
-pub(crate) struct ExeInstaller {
+/// An [`Installer`] that finds one or more executables in a release asset and installs them. The
+/// asset can be an archive file, a compressed executable, or a bare executable.
+#[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ExeInstaller {
     install_path: PathBuf,
-    exe_file_stem: String,
+    exe_file_stems: Vec<String>,
+    alternative_exe_names: Vec<String>,
     is_windows: bool,
     extensions: Vec<&'static str>,
+    non_executable_extensions: Vec<String>,
+    match_by_digest: Option<(ChecksumAlgorithm, String)>,
+    exe_pattern: Option<Regex>,
+    member_dir: Option<PathBuf>,
+    member_path: Option<PathBuf>,
+    reject_ambiguous_matches: bool,
+    reject_ambiguous_partial_matches: bool,
+    fall_back_to_sole_exe: bool,
+    zip_password: Option<String>,
+    verify_runs: Option<Vec<String>>,
+    post_install: Option<PostInstall>,
+    mode: Option<u32>,
+    dir_mode: Option<u32>,
+    skip_chmod: bool,
+    expected_platform: Option<Platform>,
+    strict_arch_check: bool,
+    backup_existing: bool,
+    remove_backup: bool,
+    preserve_mtime: bool,
+    require_regular_file: bool,
+    // How many archives we're nested inside of. See `extract_nested_archive`.
+    nested_archive_depth: usize,
+    progress: Option<Progress>,
+    on_existing: OnExisting,
+    // When this is set, every executable we write is recorded here. See `install_with_manifest`.
+    manifest: Option<Arc<Mutex<Vec<InstalledExecutable>>>>,
+    // When this is set, every install path that we skip because something is already there is
+    // recorded here. See `install`.
+    skipped_existing: Option<Arc<Mutex<Vec<PathBuf>>>>,
+    temp_dir: Option<PathBuf>,
+    fs: Arc<dyn FileSystem>,
 }
 
-#[derive(Debug)]
-pub(crate) struct ArchiveInstaller {
+/// An [`Installer`] that extracts all of the contents of an archive file into a directory.
+#[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ArchiveInstaller {
     install_root: PathBuf,
+    report_skipped: bool,
+    stage_in_temp_dir: bool,
+    replace_install_root: bool,
+    flatten_executables: bool,
+    flatten_executable_names: Vec<String>,
+    extract_appimage: bool,
+    parallel_zip_extraction: bool,
+    zip_password: Option<String>,
+    strip_components: Option<usize>,
+    expected_digest: Option<(ChecksumAlgorithm, String)>,
+    preserve_mtime: bool,
+    require_regular_file: bool,
+    completion_dirs: Vec<(Shell, PathBuf)>,
+    man_dir: Option<PathBuf>,
+    bin_dir: Option<PathBuf>,
+    path_mapper: Option<PathMapper>,
+    progress: Option<Progress>,
+    on_existing: OnExisting,
+    temp_dir: Option<PathBuf>,
+    // When this is set, every file we extract is recorded here, and updated as it's moved. See
+    // `install_with_extraction_report`.
+    record: Option<Arc<Mutex<ExtractionReport>>>,
+    fs: Arc<dyn FileSystem>,
+}
+
+/// How an archive member's name matches the executable that `ubi` is looking for. This is what
+/// [`crate::Ubi::scan_archive`] reports for each file in an archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchKind {
+    /// This is the member that would be installed. This is reported instead of `Exact` or
+    /// `Partial` for that member.
+    Selected,
+    /// The member's file name is an exact match for the executable name, or it matches the
+    /// executable pattern, if one was given.
+    Exact,
+    /// The member's file name starts with the executable name. On Unix-like systems, a partial
+    /// match is only installed if it's executable.
+    Partial,
+    /// The member's file name doesn't match the executable name.
+    NoMatch,
+}
+
+/// A record of what [`crate::Ubi::install_binary_with_manifest`] installed, which can be serialized
+/// as JSON for other tools to read.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct InstallManifest {
+    /// The file name of the release asset that was installed.
+    pub source: String,
+    /// The release asset's extension, like `.tar.gz`, if it has one.
+    pub extension: Option<String>,
+    /// Every executable that was installed, in the order they were written.
+    pub executables: Vec<InstalledExecutable>,
+}
+
+/// One executable in an [`InstallManifest`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct InstalledExecutable {
+    /// The path of the archive member that the executable was extracted from. This is not set
+    /// when the release asset is a compressed executable or a bare executable.
+    pub member: Option<String>,
+    /// The path that the executable was installed to.
+    pub path: PathBuf,
+    /// The mode that the executable was given.
+    pub mode: u32,
+    /// The size of the installed executable in bytes.
+    pub size: u64,
+}
+
+/// A record of what [`crate::Ubi::install_binary_with_extraction_report`] extracted from an archive
+/// file, and of what it did to the extracted files afterwards.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtractionReport {
+    /// The top-level directory, like `project-1.0.0`, whose contents were moved up into the install
+    /// directory because every archive member was in it. This directory is removed once it's
+    /// empty.
+    pub moved_up_dir: Option<PathBuf>,
+    /// Every file and symlink that was extracted, relative to the install directory and sorted by
+    /// path. Directories are not included.
+    pub extracted: Vec<ExtractedPath>,
+    /// Every archive member that was not extracted, along with the reason it was skipped.
+    pub skipped: Vec<SkippedMember>,
+}
+
+/// One file or symlink in an [`ExtractionReport`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtractedPath {
+    /// Where the file ended up, relative to the install directory.
+    pub path: PathBuf,
+    /// Where the file was extracted to before it was moved up out of
+    /// [`ExtractionReport::moved_up_dir`], relative to the install directory. This is only set
+    /// for files that were moved up.
+    pub moved_from: Option<PathBuf>,
+}
+
+/// A file in an archive that might be an executable, as returned by
+/// [`crate::executable_candidates`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutableCandidate {
+    /// The path of the member in the archive.
+    pub path: String,
+    /// The uncompressed size of the member in bytes.
+    pub size: u64,
+    /// Whether the member is marked as executable. This is true when the archive records a mode
+    /// with an executable bit set for it, or when its name ends in `.exe` or `.bat`.
+    pub is_executable: bool,
+}
+
+/// What an installer does when the path it would install to already exists. For an executable,
+/// this is the path of the executable. When extracting an entire archive, this is the install
+/// root, which only counts as existing if it is not empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnExisting {
+    /// Replace what's already there. This is the default.
+    #[default]
+    Overwrite,
+    /// Leave what's already there alone and don't install anything.
+    Skip,
+    /// Return an error.
+    Error,
+}
+
+/// A callback that is called as each file is written, with the number of bytes written to that
+/// file so far and the file's total size, if that's known. The size is not known when the file is
+/// being decompressed from a stream, like a `.gz` file.
+#[derive(Clone)]
+pub struct Progress(Arc<dyn Fn(u64, Option<u64>) + Send + Sync>);
+
+impl Progress {
+    /// Wraps `callback` so it can be given to an installer.
+    pub fn new(callback: impl Fn(u64, Option<u64>) + Send + Sync + 'static) -> Self {
+        Progress(Arc::new(callback))
+    }
+}
+
+impl Debug for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Progress(..)")
+    }
+}
+
+/// A callback that the [`ArchiveInstaller`] calls with the path of each archive member before it's
+/// extracted. Returning `Some(path)` extracts the member to that path, relative to the install
+/// root, instead. Returning `None` extracts it to its usual path.
+#[derive(Clone)]
+pub struct PathMapper(Arc<PathMapperFn>);
+
+type PathMapperFn = dyn Fn(&Path) -> Option<PathBuf> + Send + Sync;
+
+impl PathMapper {
+    /// Wraps `callback` so it can be given to an installer.
+    pub fn new(callback: impl Fn(&Path) -> Option<PathBuf> + Send + Sync + 'static) -> Self {
+        PathMapper(Arc::new(callback))
+    }
+}
+
+impl Debug for PathMapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PathMapper(..)")
+    }
+}
+
+/// A callback that the [`ExeInstaller`] calls with the paths of the installed executables, once
+/// they have all been written and had their modes set, and have passed any checks. Returning an
+/// error makes the install fail with that error. This is for things like code signing on macOS or
+/// `setcap` on Linux.
+#[derive(Clone)]
+pub struct PostInstall(Arc<PostInstallFn>);
+
+type PostInstallFn = dyn Fn(&[PathBuf]) -> Result<()> + Send + Sync;
+
+impl PostInstall {
+    /// Wraps `callback` so it can be given to an installer.
+    pub fn new(callback: impl Fn(&[PathBuf]) -> Result<()> + Send + Sync + 'static) -> Self {
+        PostInstall(Arc::new(callback))
+    }
+
+    /// Makes a callback that runs `command` once for each installed executable. The first element
+    /// of `command` is the program to run, and the rest are its arguments. Each `{}` in the
+    /// arguments is replaced with the executable's path. If none of the arguments contain `{}`,
+    /// the path is added as the last argument. The callback returns an error if the command
+    /// can't be run or doesn't exit successfully.
+    pub fn command(command: Vec<String>) -> Self {
+        PostInstall::new(move |paths| run_post_install_command(&command, paths))
+    }
+}
+
+impl Debug for PostInstall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PostInstall(..)")
+    }
+}
+
+/// The reason that an archive member was not extracted by the [`ArchiveInstaller`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum SkipReason {
+    /// The member would be written through a symlink that was extracted earlier, which could point
+    /// anywhere. Members whose own path resolves to a location outside of the install root are an
+    /// error rather than being skipped.
+    TraversalRejected,
+    /// The member is encrypted, and we cannot decrypt it.
+    Encrypted,
+    /// The member is a kind of file that we don't install, like a device file or a FIFO.
+    UnsupportedType,
+    /// The member is a symlink with an absolute target, or a target that resolves to a location
+    /// outside of the install root.
+    UnsafeSymlink,
+    /// The member is not an executable, and we're only installing executables.
+    NotExecutable,
+    /// The member has no path left to extract it to after
+    /// [`ArchiveInstaller::strip_components`] removed its leading components.
+    Filtered,
+    /// The member is metadata that macOS adds to archives, like the resource forks in a
+    /// `__MACOSX` directory or a `.DS_Store` file, which is never part of a release.
+    Junk,
+}
+
+// Where the contents of an entry in a plain `.tar` file are, along with what we need to install it.
+// See `ExeInstaller::extract_executable_from_plain_tarball`.
+struct TarEntryLocation {
+    path: PathBuf,
+    file_position: u64,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+// What we found while scanning the entries of a tarball or zip file for the executable. Each
+// match is stored with the entry's index. See `ExeInstaller::pick_archive_matches`.
+#[derive(Default)]
+struct ArchiveScan {
+    scanned: usize,
+    // The file name of every entry, when installing more than one executable.
+    file_names: Vec<(usize, String)>,
+    exact_matches: Vec<(usize, String)>,
+    // Each partial match is stored with whether it's executable, if the archive recorded its mode.
+    possible_matches: Vec<(usize, String, Option<bool>)>,
+    executables: Vec<(usize, String)>,
+}
+
+impl ArchiveScan {
+    fn name_of(&self, i: usize) -> Option<&str> {
+        self.exact_matches
+            .iter()
+            .chain(&self.executables)
+            .chain(&self.file_names)
+            .map(|(j, name)| (*j, name))
+            .chain(self.possible_matches.iter().map(|(j, name, _)| (*j, name)))
+            .find(|(j, _)| *j == i)
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+/// An archive member that was not extracted, along with the reason it was skipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedMember {
+    /// The path of the member in the archive.
+    pub path: PathBuf,
+    /// Why the member was not extracted.
+    pub reason: SkipReason,
 }
 
 impl ExeInstaller {
-    pub(crate) fn new(install_path: PathBuf, exe: String, is_windows: bool) -> Self {
+    /// Creates an installer that installs the executable with the given name to `install_path`.
+    /// When more than one name is given, this installs every archive member that is an exact
+    /// match for one of the names into the directory that contains `install_path`, keeping each
+    /// member's file name. All of the executables must be found in the archive.
+    ///
+    /// Set `is_windows` when installing on Windows, so that archive members ending in `.exe` or
+    /// `.bat` are matched.
+    #[must_use]
+    pub fn new(install_path: PathBuf, exes: Vec<String>, is_windows: bool) -> Self {
         let extensions = if is_windows {
             Extension::iter()
                 .filter(super::extension::Extension::is_windows_only)
@@ -51,37 +594,571 @@ impl ExeInstaller {
 
         ExeInstaller {
             install_path,
-            exe_file_stem: exe,
+            exe_file_stems: exes,
+            alternative_exe_names: vec![],
             is_windows,
             extensions,
+            non_executable_extensions: NON_EXECUTABLE_EXTENSIONS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            match_by_digest: None,
+            exe_pattern: None,
+            member_dir: None,
+            member_path: None,
+            reject_ambiguous_matches: false,
+            reject_ambiguous_partial_matches: false,
+            fall_back_to_sole_exe: false,
+            zip_password: None,
+            verify_runs: None,
+            post_install: None,
+            mode: None,
+            dir_mode: None,
+            skip_chmod: false,
+            expected_platform: None,
+            strict_arch_check: false,
+            backup_existing: false,
+            remove_backup: false,
+            preserve_mtime: false,
+            require_regular_file: false,
+            nested_archive_depth: 0,
+            progress: None,
+            on_existing: OnExisting::Overwrite,
+            manifest: None,
+            skipped_existing: None,
+            temp_dir: None,
+            fs: Arc::new(RealFs),
+        }
+    }
+
+    /// Sets the mode that the installed executable is given. When this isn't set, the executable
+    /// gets the mode recorded for it in a zip file, plus the executable bits, or `0o755`. This is
+    /// ignored on Windows.
+    #[must_use]
+    pub fn mode(mut self, mode: Option<u32>) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the mode that directories created for the executable are given, like `0o700`. This
+    /// only applies to directories that didn't exist before the install, so an existing directory
+    /// like `/usr/local/bin` is never changed. When this isn't set, new directories get the
+    /// default mode for the process's umask. This is ignored on Windows.
+    #[must_use]
+    pub fn dir_mode(mut self, dir_mode: Option<u32>) -> Self {
+        self.dir_mode = dir_mode;
+        self
+    }
+
+    /// When this is true, the installed executable's permissions are not changed at all, so it
+    /// keeps whatever mode the filesystem gives new files. This is for filesystems where setting
+    /// permissions fails, or where the mode is managed some other way. This cannot be used with
+    /// `mode`.
+    #[must_use]
+    pub fn skip_chmod(mut self, skip_chmod: bool) -> Self {
+        self.skip_chmod = skip_chmod;
+        self
+    }
+
+    /// Sets the directory that temp dirs are created in when something has to be extracted before
+    /// the executable can be found, like the data tarball in a `.deb` file or a nested archive.
+    /// By default, these are created in the system's temp directory.
+    #[must_use]
+    pub fn temp_dir(mut self, temp_dir: Option<PathBuf>) -> Self {
+        self.temp_dir = temp_dir;
+        self
+    }
+
+    /// When this is set, the header of each installed executable is checked to see if it was
+    /// built for a CPU architecture that can run on this platform, and a warning is logged if it
+    /// was not. This only checks ELF, Mach-O, and PE executables.
+    #[must_use]
+    pub fn expected_platform(mut self, expected_platform: Option<Platform>) -> Self {
+        self.expected_platform = expected_platform;
+        self
+    }
+
+    /// When this is true, an executable built for the wrong CPU architecture is an error instead
+    /// of a warning. This has no effect unless `expected_platform` is set.
+    #[must_use]
+    pub fn strict_arch_check(mut self, strict_arch_check: bool) -> Self {
+        self.strict_arch_check = strict_arch_check;
+        self
+    }
+
+    /// Sets the filesystem that the executable is installed into. By default, this is the local
+    /// filesystem.
+    #[must_use]
+    pub fn filesystem(mut self, fs: Arc<dyn FileSystem>) -> Self {
+        self.fs = fs;
+        self
+    }
+
+    /// When this is true, an executable that already exists at the install path is copied to the
+    /// same path plus `.bak` before it is replaced. If the install fails, the backup is left in
+    /// place so it can be restored.
+    #[must_use]
+    pub fn backup_existing(mut self, backup_existing: bool) -> Self {
+        self.backup_existing = backup_existing;
+        self
+    }
+
+    /// When this is true, the backups made because of `backup_existing` are removed once every
+    /// executable has been installed successfully.
+    #[must_use]
+    pub fn remove_backup(mut self, remove_backup: bool) -> Self {
+        self.remove_backup = remove_backup;
+        self
+    }
+
+    /// When this is true, an executable extracted from a tarball or zip file is given the
+    /// modification time recorded for it in the archive. By default, it has the time it was
+    /// installed.
+    #[must_use]
+    pub fn preserve_mtime(mut self, preserve_mtime: bool) -> Self {
+        self.preserve_mtime = preserve_mtime;
+        self
+    }
+
+    /// When this is true, installing is an error unless the downloaded file is a regular file. A
+    /// symlink, FIFO, device, or directory is rejected when it's opened, by checking the opened
+    /// file rather than the path. By default, the file is opened without checking what it is.
+    #[must_use]
+    pub fn require_regular_file(mut self, require_regular_file: bool) -> Self {
+        self.require_regular_file = require_regular_file;
+        self
+    }
+
+    /// When this is set, the callback is called as each executable is written. See [`Progress`]
+    /// for what it's called with.
+    #[must_use]
+    pub fn progress(mut self, progress: Option<Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets what happens when an executable already exists at the path it would be installed
+    /// to. By default, it is overwritten. An executable that is skipped is not included in the
+    /// paths that [`Installer::install`] returns, and nothing that runs after an install, like
+    /// `verify_runs` or `post_install`, is run for it.
+    #[must_use]
+    pub fn on_existing(mut self, on_existing: OnExisting) -> Self {
+        self.on_existing = on_existing;
+        self
+    }
+
+    /// Adds extensions for files that are never partial matches for the executable name, like
+    /// `dat` or `tar.sig`, to the ones that `ubi` already ignores, like `md`, `json`, and `txt`.
+    /// A leading `.` is ignored, and extensions are compared without regard to case. This doesn't
+    /// affect exact matches.
+    #[must_use]
+    pub fn non_executable_extensions(mut self, non_executable_extensions: Vec<String>) -> Self {
+        self.non_executable_extensions.extend(
+            non_executable_extensions
+                .into_iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase()),
+        );
+        self
+    }
+
+    /// When this is set, the file in an archive is selected by its digest instead of by its name.
+    /// Every file in the archive is read and hashed until one matches, so this is much slower
+    /// than matching by name for large archives. This has no effect when the download is not an
+    /// archive file.
+    #[must_use]
+    pub fn match_by_digest(mut self, match_by_digest: Option<(ChecksumAlgorithm, String)>) -> Self {
+        self.match_by_digest = match_by_digest;
+        self
+    }
+
+    /// When this is set, the file in an archive is selected by matching its file name against this
+    /// regex instead of by comparing it to the executable name. The first archive member that
+    /// matches is installed, and no partial matches are considered. The executable name is still
+    /// used to determine the install path.
+    #[must_use]
+    pub fn exe_pattern(mut self, exe_pattern: Option<Regex>) -> Self {
+        self.exe_pattern = exe_pattern;
+        self
+    }
+
+    /// When this is set, only archive members under this directory are considered when looking
+    /// for the executable by name, like `bin` or `project-1.0.0/linux`. This is useful when an
+    /// archive has executables with the same name in different directories.
+    #[must_use]
+    pub fn member_dir(mut self, member_dir: Option<PathBuf>) -> Self {
+        self.member_dir = member_dir;
+        self
+    }
+
+    /// When this is set, the file in an archive is selected by its full path in the archive, like
+    /// `dist/linux-amd64/project`, instead of by its name. None of the name-based matching is
+    /// done, and it's an error if the archive has no file at this path. A leading `./` or `/` is
+    /// ignored, both here and on the archive members' paths. The executable name is still used to
+    /// determine the install path.
+    #[must_use]
+    pub fn member_path(mut self, member_path: Option<PathBuf>) -> Self {
+        self.member_path = member_path;
+        self
+    }
+
+    /// Sets other names that the executable in an archive may have, for projects that have
+    /// renamed their executable between releases. The executable name comes first, followed by
+    /// these in the order given. An exact match for any of these names is preferred over a partial
+    /// match, and when there's more than one exact match, or more than one partial match, the one
+    /// for the earliest name wins. The executable is still installed under the executable name.
+    /// This cannot be used when installing more than one executable, or with `exe_pattern`.
+    #[must_use]
+    pub fn alternative_exe_names(mut self, alternative_exe_names: Vec<String>) -> Self {
+        self.alternative_exe_names = alternative_exe_names;
+        self
+    }
+
+    /// When this is set, finding more than one archive member that is an exact match for the
+    /// executable name, or that matches `exe_pattern`, is an error. By default, the first one in
+    /// the archive is installed. This doesn't affect partial matches.
+    #[must_use]
+    pub fn reject_ambiguous_matches(mut self, reject_ambiguous_matches: bool) -> Self {
+        self.reject_ambiguous_matches = reject_ambiguous_matches;
+        self
+    }
+
+    /// When this is set and there's no exact match for the executable name, finding more than one
+    /// archive member that is a partial match for it is an error that lists all of them. By
+    /// default, the partial match with the shortest file name is installed. Partial matches that
+    /// we'd ignore anyway, like a file that isn't executable in a tarball, don't count.
+    #[must_use]
+    pub fn reject_ambiguous_partial_matches(
+        mut self,
+        reject_ambiguous_partial_matches: bool,
+    ) -> Self {
+        self.reject_ambiguous_partial_matches = reject_ambiguous_partial_matches;
+        self
+    }
+
+    /// When this is true and no member of a tarball or zip file matches the executable name, the
+    /// archive's only executable file is installed instead, if it has exactly one. This is useful
+    /// when the executable's name has nothing to do with the project's name, like `rg` for
+    /// `ripgrep`. A file is only considered executable if the archive records its mode.
+    #[must_use]
+    pub fn fall_back_to_sole_exe(mut self, fall_back_to_sole_exe: bool) -> Self {
+        self.fall_back_to_sole_exe = fall_back_to_sole_exe;
+        self
+    }
+
+    /// Sets the password used to decrypt encrypted members of a zip file. Both the traditional zip
+    /// encryption and AES encryption are supported. Without a password, installing an encrypted
+    /// member is an error.
+    #[must_use]
+    pub fn zip_password(mut self, zip_password: Option<String>) -> Self {
+        self.zip_password = zip_password;
+        self
+    }
+
+    /// When this is set, each installed executable is run with these arguments once it's been
+    /// installed, and the install fails if it can't be run or if it doesn't exit successfully.
+    /// This catches installing an executable for the wrong CPU architecture or OS. The executable
+    /// is run from the local filesystem, so this only works with the default filesystem.
+    #[must_use]
+    pub fn verify_runs(mut self, verify_runs: Option<Vec<String>>) -> Self {
+        self.verify_runs = verify_runs;
+        self
+    }
+
+    /// When this is set, the callback is called after a successful install. See [`PostInstall`]
+    /// for what it's called with. It's not called for a dry run.
+    #[must_use]
+    pub fn post_install(mut self, post_install: Option<PostInstall>) -> Self {
+        self.post_install = post_install;
+        self
+    }
+
+    // Reads the header of each installed executable to see what CPU architectures it was built for,
+    // and warns if none of them can run on `expected_platform`. This is an error instead when
+    // `strict_arch_check` is set.
+    fn check_exe_arches(&self, paths: &[PathBuf]) -> Result<()> {
+        let Some(platform) = &self.expected_platform else {
+            return Ok(());
+        };
+        for exe in paths {
+            let mut header = vec![];
+            self.fs
+                .open_file(exe)?
+                .take(binary_arch::HEADER_LEN as u64)
+                .read_to_end(&mut header)?;
+            let arches = binary_arch::arches_in_header(&header);
+            if arches.is_empty() {
+                debug!(
+                    "could not tell what CPU architecture {} is for",
+                    exe.display(),
+                );
+                continue;
+            }
+            if arches.iter().any(|a| binary_arch::can_run(platform, *a)) {
+                continue;
+            }
+
+            let msg = format!(
+                "the installed executable at {} is for {}, which cannot run on {}",
+                exe.display(),
+                arches
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                platform.target_triple,
+            );
+            if self.strict_arch_check {
+                return Err(anyhow!(msg));
+            }
+            warn!("{msg}");
+        }
+
+        Ok(())
+    }
+
+    // Runs each installed executable with the `verify_runs` arguments, returning an error that
+    // includes its output if it can't be run or doesn't exit successfully.
+    fn verify_exes_run(&self, paths: &[PathBuf]) -> Result<()> {
+        let Some(args) = &self.verify_runs else {
+            return Ok(());
+        };
+        for exe in paths {
+            let mut cmd = process::Command::new(exe);
+            cmd.args(args).stdin(process::Stdio::null());
+            debug!("running {cmd:?} to check that the installed executable works");
+            let output = cmd.output().with_context(|| {
+                format!(
+                    "could not run the installed executable at {}",
+                    exe.display(),
+                )
+            })?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "running the installed executable at {} with `{}` failed ({})\n\
+                     stdout: {}\n\
+                     stderr: {}",
+                    exe.display(),
+                    args.join(" "),
+                    output.status,
+                    String::from_utf8_lossy(&output.stdout).trim(),
+                    String::from_utf8_lossy(&output.stderr).trim(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Returns the archive's only executable when nothing matched the executable name, given each
+    // executable and its path in the archive. We only collect these when `fall_back_to_sole_exe`
+    // is set.
+    fn sole_exe<T>(&self, mut executables: Vec<(T, String)>) -> Option<T> {
+        if executables.len() != 1 {
+            if self.fall_back_to_sole_exe {
+                debug!(
+                    "not falling back to the archive's only executable because it has {}",
+                    executables.len(),
+                );
+            }
+            return None;
+        }
+        let (m, path) = executables.pop()?;
+        debug!(
+            "no archive member matched the executable name {}, so using the only executable, {path}",
+            self.exe_file_stem(),
+        );
+        Some(m)
+    }
+
+    // Returns the only exact match, given each match and its path in the archive. If there's more
+    // than one, this returns an error that lists all of them. We only collect more than one match
+    // when `reject_ambiguous_matches` is set.
+    fn only_exact_match<T>(&self, mut exact_matches: Vec<(T, String)>) -> Result<Option<T>> {
+        // With alternative names, only the matches for the earliest name in `exe_names_by_priority`
+        // that has any are kept.
+        let rank = |path: &str| {
+            Path::new(path)
+                .file_name()
+                .and_then(OsStr::to_str)
+                .and_then(|file_name| self.exact_match_rank(file_name))
+        };
+        if let Some(best) = exact_matches
+            .iter()
+            .filter_map(|(_, path)| rank(path))
+            .min()
+        {
+            exact_matches.retain(|(_, path)| rank(path) == Some(best));
+        }
+        if exact_matches.len() > 1 {
+            return Err(anyhow!(
+                "found more than one archive member that matches the executable name {}: {}",
+                self.exe_file_stem(),
+                exact_matches
+                    .iter()
+                    .map(|(_, path)| path.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+        }
+        Ok(exact_matches.pop().map(|(m, _)| m))
+    }
+
+    // Returns true if `member_dir` isn't set, or if the archive member at `path` is under it. A
+    // leading `./` or `/` is ignored on both, so `bin` matches `./bin/project` in a tarball and
+    // `/bin/project` in an RPM file.
+    fn archive_member_is_in_member_dir(&self, path: &Path) -> bool {
+        let Some(member_dir) = &self.member_dir else {
+            return true;
+        };
+        relative_member_path(path).starts_with(relative_member_path(member_dir))
+    }
+
+    // Returns true if `member_path` is set and the archive member at `path` is the one it names.
+    fn archive_member_is_member_path(&self, path: &Path) -> bool {
+        self.member_path.as_ref().is_some_and(|member_path| {
+            relative_member_path(path) == relative_member_path(member_path)
+        })
+    }
+
+    // The name of the executable to look for when installing a single executable.
+    fn exe_file_stem(&self) -> &str {
+        &self.exe_file_stems[0]
+    }
+
+    // The executable name followed by any alternative names, in the order they're preferred.
+    fn exe_names_by_priority(&self) -> impl Iterator<Item = &str> {
+        iter::once(self.exe_file_stem())
+            .chain(self.alternative_exe_names.iter().map(String::as_str))
+    }
+
+    // When there are alternative names, we have to look at every exact match to find the one for
+    // the most preferred name, rather than taking the first one in the archive.
+    fn takes_first_exact_match(&self) -> bool {
+        !self.reject_ambiguous_matches && self.alternative_exe_names.is_empty()
+    }
+
+    // Returns the position of the first name in `exe_names_by_priority` that the file name is an
+    // exact match for.
+    fn exact_match_rank(&self, file_name: &str) -> Option<usize> {
+        self.exe_names_by_priority()
+            .position(|stem| self.archive_member_is_exact_match(stem, file_name))
+    }
+
+    // Returns the position of the first name in `exe_names_by_priority` that the file name is a
+    // partial match for.
+    fn partial_match_rank(&self, file_name: &str) -> Option<usize> {
+        self.exe_names_by_priority()
+            .position(|stem| self.archive_member_is_partial_match_for(stem, file_name))
+    }
+
+    // Picks the partial match for the most preferred name. If there's more than one of those, this
+    // picks the one with the shortest file name, which has the least extra stuff after the name,
+    // and the first one in the archive wins a tie. If `reject_ambiguous_partial_matches` is set,
+    // it returns an error that lists all of them instead.
+    fn closest_partial_match<T, N: AsRef<str>>(
+        &self,
+        possible_matches: impl IntoIterator<Item = (T, N)>,
+    ) -> Result<Option<T>> {
+        let rank = |file_name: &str| self.partial_match_rank(file_name).unwrap_or(usize::MAX);
+        let mut possible_matches = possible_matches.into_iter().collect::<Vec<_>>();
+        if self.reject_ambiguous_partial_matches {
+            if let Some(best) = possible_matches.iter().map(|(_, n)| rank(n.as_ref())).min() {
+                possible_matches.retain(|(_, n)| rank(n.as_ref()) == best);
+            }
+            if possible_matches.len() > 1 {
+                return Err(anyhow!(
+                    "found more than one archive member that is a partial match for the \
+                     executable name {}: {}. Use the full name of the executable or an \
+                     executable pattern to pick one of them.",
+                    self.exe_file_stem(),
+                    possible_matches
+                        .iter()
+                        .map(|(_, n)| n.as_ref())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ));
+            }
         }
+
+        Ok(possible_matches
+            .into_iter()
+            .min_by_key(|(_, file_name)| {
+                let file_name = file_name.as_ref();
+                (rank(file_name), file_name.len())
+            })
+            .map(|(m, _)| m))
+    }
+
+    fn installs_multiple_exes(&self) -> bool {
+        self.exe_file_stems.len() > 1
     }
 
-    fn extract_executable(&self, downloaded_file: &Path) -> Result<Option<PathBuf>> {
-        match Extension::from_path(downloaded_file)? {
+    // This returns the paths the executables were installed to.
+    fn extract_executable(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
+        let ext = Extension::from_path(downloaded_file)?;
+        if self.installs_multiple_exes() && !ext.as_ref().is_some_and(Extension::is_archive) {
+            return Err(anyhow!(
+                "cannot install more than one executable from {}, which is not an archive file",
+                downloaded_file.display(),
+            ));
+        }
+
+        match ext {
             Some(
                 Extension::Tar
+                | Extension::TarBr
                 | Extension::TarBz
                 | Extension::TarBz2
                 | Extension::TarGz
+                | Extension::TarLz
+                | Extension::TarLz4
+                | Extension::TarLzma
                 | Extension::TarXz
+                | Extension::TarZ
+                | Extension::TarZst
                 | Extension::Tbz
                 | Extension::Tgz
                 | Extension::Txz,
-            ) => Ok(Some(self.extract_executable_from_tarball(downloaded_file)?)),
+            ) => self.extract_executable_from_tarball(downloaded_file),
+            Some(Extension::Br) => {
+                self.unbrotli(downloaded_file)?;
+                Ok(vec![self.decompressed_install_path(downloaded_file)?])
+            }
             Some(Extension::Bz | Extension::Bz2) => {
                 self.unbzip(downloaded_file)?;
-                Ok(None)
+                Ok(vec![self.decompressed_install_path(downloaded_file)?])
+            }
+            Some(Extension::Gz) => Ok(vec![self.ungzip(downloaded_file)?]),
+            Some(Extension::Lz) => {
+                self.unlzip(downloaded_file)?;
+                Ok(vec![self.decompressed_install_path(downloaded_file)?])
+            }
+            Some(Extension::Lz4) => {
+                self.unlz4(downloaded_file)?;
+                Ok(vec![self.decompressed_install_path(downloaded_file)?])
             }
-            Some(Extension::Gz) => {
-                self.ungzip(downloaded_file)?;
-                Ok(None)
+            Some(Extension::Lzma) => {
+                self.unlzma(downloaded_file)?;
+                Ok(vec![self.decompressed_install_path(downloaded_file)?])
             }
             Some(Extension::Xz) => {
                 self.unxz(downloaded_file)?;
-                Ok(None)
+                Ok(vec![self.decompressed_install_path(downloaded_file)?])
             }
-            Some(Extension::Zip) => Ok(Some(self.extract_executable_from_zip(downloaded_file)?)),
+            Some(Extension::Z) => {
+                self.uncompress_z(downloaded_file)?;
+                Ok(vec![self.decompressed_install_path(downloaded_file)?])
+            }
+            Some(Extension::Zst) => {
+                self.unzstd(downloaded_file)?;
+                Ok(vec![self.decompressed_install_path(downloaded_file)?])
+            }
+            Some(Extension::Zip) => self.extract_executable_from_zip(downloaded_file),
+            Some(Extension::SevenZ) => self.extract_executable_from_7z(downloaded_file),
+            Some(Extension::Cab) => self.extract_executable_from_cab(downloaded_file),
+            Some(Extension::Deb) => self.extract_executable_from_deb(downloaded_file),
+            Some(Extension::A | Extension::Ar) => self.extract_executable_from_ar(downloaded_file),
+            Some(Extension::Rpm) => self.extract_executable_from_rpm(downloaded_file),
             Some(
                 Extension::AppImage
                 | Extension::Bat
@@ -89,11 +1166,11 @@ impl ExeInstaller {
                 | Extension::Pyz
                 | Extension::Jar,
             )
-            | None => Ok(Some(self.copy_executable(downloaded_file)?)),
+            | None => Ok(vec![self.copy_executable(downloaded_file)?]),
         }
     }
 
-    fn extract_executable_from_tarball(&self, downloaded_file: &Path) -> Result<PathBuf> {
+    fn extract_executable_from_tarball(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
         debug!(
             "extracting executable from tarball at {}",
             downloaded_file.display(),
@@ -106,650 +1183,7864 @@ impl ExeInstaller {
         // that handle back to the start of the file, but the readers provided by various decoders,
         // like `BzDecoder`, do not implement the `Seek` trait.
         //
-        // So the only viable solution is find the entry, then _re-open_ the file and go through the
-        // entries again until we find the one we want.
-        if let Some(idx) = self.best_match_from_tarball(downloaded_file)? {
-            let mut arch = tar_reader_for(downloaded_file)?;
-            for (i, entry) in arch.entries()?.enumerate() {
-                let mut entry = entry?;
-                if i != idx {
-                    continue;
-                }
-
-                let entry_path = entry.path()?;
-                let mut install_path = self.install_path.clone();
-                if let Some(ext) = Extension::from_path(entry_path.as_ref())? {
-                    if ext.should_preserve_extension_on_install() {
-                        debug!("preserving the {} extension on install", ext.extension());
-                        install_path.set_extension(ext.extension_without_dot());
-                    }
-                }
-
-                debug!(
-                    "extracting tarball entry named {} to {}",
-                    entry_path.display(),
-                    install_path.display(),
+        // So the only viable solution is find the entries, then _re-open_ the file and go through
+        // the entries again, extracting the ones we want. When installing more than one
+        // executable, we find all of them first, so we only need to go through the archive twice.
+        //
+        // A plain `.tar` file isn't compressed, so there we record where each entry's contents are
+        // while looking for matches, and then seek straight to them.
+        let mut locations = is_plain_tarball(downloaded_file).then(HashMap::new);
+        let matches = self.find_tarball_matches(downloaded_file, locations.as_mut())?;
+        if matches.is_empty() {
+            return self.could_not_find_archive_matches_error();
+        }
+        if let Some(locations) = locations {
+            if matches.iter().all(|i| locations.contains_key(i)) {
+                return self.extract_executable_from_plain_tarball(
+                    downloaded_file,
+                    &matches,
+                    &locations,
                 );
-                self.create_install_dir()?;
-                entry.unpack(&install_path).unwrap();
-
-                return Ok(install_path);
             }
         }
 
-        self.could_not_find_archive_matches_error()
-    }
-
-    fn best_match_from_tarball<'a>(&self, downloaded_file: &Path) -> Result<Option<usize>> {
-        let mut arch = tar_reader_for(downloaded_file)?;
-        let mut possible_matches: Vec<usize> = vec![];
+        let mut installed = vec![];
+        let mut extracted = 0;
+        let mut arch = tar_reader_for(downloaded_file, self.require_regular_file)?;
         for (i, entry) in arch.entries()?.enumerate() {
-            let entry = entry?;
-            if !entry.header().entry_type().is_file() {
+            let mut entry = entry?;
+            if !matches.contains(&i) {
                 continue;
             }
 
-            let path = entry.path()?;
-            debug!("found tarball entry with path {}", path.display());
-            if let Some(file_name) = path.file_name() {
-                if let Some(file_name) = file_name.to_str() {
-                    if self.archive_member_is_exact_match(file_name) {
-                        debug!("found tar file entry with exact match: {}", file_name);
-                        return Ok(Some(i));
-                    } else if self.archive_member_is_partial_match(file_name) {
-                        // This checks if the entry is marked as an executable, but a tarball
-                        // created on Windows may not have file modes set.
-                        if self.is_windows || entry.header().mode()? & 0o111 != 0 {
-                            debug!("found tar file entry with partial match: {}", file_name);
-                            possible_matches.push(i);
-                        }
-                    }
-                }
+            extracted += 1;
+            let entry_path = entry.path()?.into_owned();
+            let modified = self
+                .preserve_mtime
+                .then(|| tar_entry_mtime(&entry))
+                .transpose()?;
+            if entry.header().entry_type().is_hard_link() {
+                installed.extend(self.install_tarball_hard_link(
+                    downloaded_file,
+                    &entry_path,
+                    &entry,
+                    modified,
+                )?);
+            } else {
+                let size = entry.size();
+                installed.extend(self.install_tarball_entry(
+                    &entry_path,
+                    &mut entry,
+                    size,
+                    modified,
+                )?);
+            }
+            if extracted == matches.len() {
+                break;
             }
         }
 
-        Ok(possible_matches.into_iter().next())
+        Ok(installed)
     }
 
-    fn extract_executable_from_zip(&self, downloaded_file: &Path) -> Result<PathBuf> {
-        debug!(
-            "extracting executable from zip file at {}",
-            downloaded_file.display()
-        );
-
-        let mut zip = ZipArchive::new(open_file(downloaded_file)?)?;
-        if let Some(mut zf) = self.best_match_from_zip_archive(&mut zip)? {
-            let zf_path = Path::new(zf.name());
-            let mut install_path = self.install_path.clone();
-            if let Some(ext) = Extension::from_path(zf_path)? {
-                if ext.should_preserve_extension_on_install() {
-                    debug!("preserving the {} extension on install", ext.extension());
-                    install_path.set_extension(ext.extension_without_dot());
-                }
-            }
-
+    fn extract_executable_from_plain_tarball(
+        &self,
+        downloaded_file: &Path,
+        matches: &[usize],
+        locations: &HashMap<usize, TarEntryLocation>,
+    ) -> Result<Vec<PathBuf>> {
+        let mut file = open_file(downloaded_file, self.require_regular_file)?;
+        let mut installed = vec![];
+        for i in matches {
+            let location = &locations[i];
             debug!(
-                "extracting zip file entry named {} to {}",
-                zf.name(),
-                install_path.display(),
+                "reading tarball entry named {} from byte {} of the tarball",
+                location.path.display(),
+                location.file_position,
             );
-            let mut buffer: Vec<u8> = Vec::with_capacity(usize::try_from(zf.size())?);
-            zf.read_to_end(&mut buffer)?;
-            self.create_install_dir()?;
-
-            File::create(&install_path)?.write_all(&buffer)?;
-
-            return Ok(install_path);
+            file.seek(SeekFrom::Start(location.file_position))?;
+            installed.extend(self.install_tarball_entry(
+                &location.path,
+                &mut (&mut file).take(location.size),
+                location.size,
+                location.modified,
+            )?);
         }
 
-        self.could_not_find_archive_matches_error()
+        Ok(installed)
     }
 
-    fn best_match_from_zip_archive<'a>(
+    // A hard link entry has no contents of its own. It points to a file entry earlier in the
+    // tarball, so we go through the tarball again to find that entry, and install its contents
+    // under the name of the link.
+    fn install_tarball_hard_link<R: Read>(
         &self,
-        zip: &'a mut ZipArchive<File>,
-    ) -> Result<Option<ZipFile<'a>>> {
-        let mut possible_matches: Vec<usize> = vec![];
-        for i in 0..zip.len() {
-            let zf = zip.by_index(i)?;
-            if zf.is_file() {
-                let path = PathBuf::from(zf.name());
-                if let Some(file_name) = path.file_name() {
-                    if let Some(file_name) = file_name.to_str() {
-                        if self.archive_member_is_exact_match(file_name) {
-                            debug!("found zip file entry with exact match: {}", file_name);
-                            // It'd be nicer to immediately return `zf`, but that runs into lifetime
-                            // issues, because `zip.by_index` takes `&mut self`. Yeesh.
-                            possible_matches.clear();
-                            possible_matches.push(i);
-                            break;
-                        } else if self.archive_member_is_partial_match(file_name) {
-                            debug!("found zip file entry with partial match: {}", file_name);
-                            // Note that we don't test if the file is executable on Unix systems
-                            // because preserving the mode is not a standard Zip behavior, AFAICT.
-                            possible_matches.push(i);
+        downloaded_file: &Path,
+        link_path: &Path,
+        link: &Entry<'_, R>,
+        modified: Option<SystemTime>,
+    ) -> Result<Vec<PathBuf>> {
+        let Some(target) = link.link_name()? else {
+            return Err(anyhow!(
+                "the tarball entry named {} is a hard link without a target",
+                link_path.display(),
+            ));
+        };
+        let target = relative_member_path(&target);
+        debug!(
+            "tarball entry named {} is a hard link to {}",
+            link_path.display(),
+            target.display(),
+        );
+
+        let mut arch = tar_reader_for(downloaded_file, self.require_regular_file)?;
+        for entry in arch.entries()? {
+            let mut entry = entry?;
+            if !tar_entry_is_file(entry.header().entry_type())
+                || relative_member_path(&entry.path()?) != target
+            {
+                continue;
+            }
+            let size = entry.size();
+            return self.install_tarball_entry(link_path, &mut entry, size, modified);
+        }
+
+        Err(anyhow!(
+            "could not find {}, which the tarball entry named {} is a hard link to",
+            target.display(),
+            link_path.display(),
+        ))
+    }
+
+    // This returns the paths the tarball entry was installed to. There's more than one when the
+    // entry is itself an archive.
+    fn install_tarball_entry(
+        &self,
+        entry_path: &Path,
+        reader: &mut dyn Read,
+        size: u64,
+        modified: Option<SystemTime>,
+    ) -> Result<Vec<PathBuf>> {
+        if is_archive(entry_path) {
+            return self.extract_nested_archive(entry_path, reader);
+        }
+
+        let install_path = self.resolve_install_path(entry_path)?;
+        debug!(
+            "extracting tarball entry named {} to {}",
+            entry_path.display(),
+            install_path.display(),
+        );
+        self.write_executable(
+            &install_path,
+            Some(entry_path),
+            reader,
+            EXECUTABLE_MODE,
+            modified,
+            Some(size),
+        )
+        .with_context(|| {
+            format!(
+                "could not extract tarball entry named {} to {}",
+                entry_path.display(),
+                install_path.display(),
+            )
+        })?;
+
+        Ok(vec![install_path])
+    }
+
+    // This returns the indexes of the entries to extract, in archive order. When installing a
+    // single executable, there is at most one.
+    fn best_match_from_tarball(&self, downloaded_file: &Path) -> Result<Vec<usize>> {
+        self.find_tarball_matches(downloaded_file, None)
+    }
+
+    // This is `best_match_from_tarball`, but when `locations` is given, it also records where the
+    // contents of each file entry are in the tarball, keyed by the entry's index. This is only
+    // useful for a plain `.tar` file, since we can't seek in a decompressed stream.
+    fn find_tarball_matches(
+        &self,
+        downloaded_file: &Path,
+        mut locations: Option<&mut HashMap<usize, TarEntryLocation>>,
+    ) -> Result<Vec<usize>> {
+        if let Some((algorithm, digest)) = &self.match_by_digest {
+            return Ok(self
+                .digest_match_from_tarball(downloaded_file, *algorithm, digest)?
+                .into_iter()
+                .collect());
+        }
+
+        let mut arch = tar_reader_for(downloaded_file, self.require_regular_file)?;
+        let mut scan = ArchiveScan::default();
+        // The entries iterator never returns GNU long name entries or PAX extended headers.
+        // Instead, it applies them to the entry that follows, so `entry.path()` returns the full
+        // path for a file with a long path, and the indexes match those in
+        // `extract_executable_from_tarball`.
+        for (i, entry) in arch.entries()?.enumerate() {
+            let entry = entry?;
+            scan.scanned = i + 1;
+            let entry_type = entry.header().entry_type();
+            // A hard link can be the only entry with the executable's name, with the file it links
+            // to under some other name.
+            if !tar_entry_is_file(entry_type) && !entry_type.is_hard_link() {
+                continue;
+            }
+
+            let path = entry.path()?;
+            debug!("found tarball entry with path {}", path.display());
+            if !self.archive_member_is_in_member_dir(&path) {
+                continue;
+            }
+            // The contents of a sparse entry are stored without its holes, and a hard link entry
+            // has no contents, so neither can be read from its position in the tarball. Leaving
+            // them out makes us go through the tarball entries to extract them instead.
+            if let Some(locations) = locations
+                .as_deref_mut()
+                .filter(|_| !entry_type.is_gnu_sparse() && !entry_type.is_hard_link())
+            {
+                locations.insert(
+                    i,
+                    TarEntryLocation {
+                        path: path.to_path_buf(),
+                        file_position: entry.raw_file_position(),
+                        size: entry.size(),
+                        modified: self
+                            .preserve_mtime
+                            .then(|| tar_entry_mtime(&entry))
+                            .transpose()?,
+                    },
+                );
+            }
+            if self.member_path.is_some() {
+                if self.archive_member_is_member_path(&path) {
+                    debug!(
+                        "found tar file entry at the member path: {}",
+                        path.display()
+                    );
+                    scan.exact_matches.push((i, path.display().to_string()));
+                    self.log_match_decision("tarball", &scan, &[i], "it is at the member path");
+                    return Ok(vec![i]);
+                }
+                continue;
+            }
+            if self.fall_back_to_sole_exe && entry.header().mode()? & 0o111 != 0 {
+                scan.executables.push((i, path.display().to_string()));
+            }
+            if let Some(file_name) = path.file_name() {
+                if let Some(file_name) = file_name.to_str() {
+                    if self.installs_multiple_exes() {
+                        scan.file_names.push((i, file_name.to_string()));
+                    } else if self.archive_member_is_exe(file_name) {
+                        debug!("found tar file entry with exact match: {}", file_name);
+                        scan.exact_matches.push((i, path.display().to_string()));
+                        if self.takes_first_exact_match() {
+                            self.log_match_decision(
+                                "tarball",
+                                &scan,
+                                &[i],
+                                "it is the first exact match for the executable name",
+                            );
+                            return Ok(vec![i]);
+                        }
+                    } else if self.archive_member_is_partial_match(file_name) {
+                        // This checks if the entry is marked as an executable, but a tarball
+                        // created on Windows may not have file modes set. An archive in the
+                        // tarball won't be executable, but it might contain the executable.
+                        if self.is_windows
+                            || entry.header().mode()? & 0o111 != 0
+                            || is_archive(&path)
+                        {
+                            debug!("found tar file entry with partial match: {}", file_name);
+                            scan.possible_matches
+                                .push((i, file_name.to_string(), Some(true)));
                         }
                     }
                 }
             }
         }
 
-        if let Some(i) = possible_matches.first() {
-            return Ok(Some(zip.by_index(*i)?));
+        self.pick_archive_matches("tarball", &scan)
+    }
+
+    fn digest_match_from_tarball(
+        &self,
+        downloaded_file: &Path,
+        algorithm: ChecksumAlgorithm,
+        digest: &str,
+    ) -> Result<Option<usize>> {
+        let mut arch = tar_reader_for(downloaded_file, self.require_regular_file)?;
+        for (i, entry) in arch.entries()?.enumerate() {
+            let entry = entry?;
+            if !tar_entry_is_file(entry.header().entry_type()) {
+                continue;
+            }
+
+            let path = entry.path()?.into_owned();
+            if algorithm.hex_digest(entry)?.eq_ignore_ascii_case(digest) {
+                debug!(
+                    "found tar file entry with matching {} digest: {}",
+                    algorithm.as_ref(),
+                    path.display(),
+                );
+                return Ok(Some(i));
+            }
         }
 
         Ok(None)
     }
 
-    fn archive_member_is_exact_match(&self, file_name: &str) -> bool {
-        if self.extensions.is_empty() {
-            return file_name == self.exe_file_stem;
+    fn extract_executable_from_zip(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
+        debug!(
+            "extracting executable from zip file at {}",
+            downloaded_file.display()
+        );
+
+        let mut zip = open_zip(downloaded_file, self.require_regular_file)?;
+        let matches = self.best_match_from_zip_archive(&mut zip)?;
+        if matches.is_empty() {
+            return self.could_not_find_archive_matches_error();
         }
 
-        self.extensions
-            .iter()
-            .map(|&ext| format!("{}{}", self.exe_file_stem.to_lowercase(), ext))
-            .any(|n| n == file_name)
+        let mut installed = vec![];
+        for i in matches {
+            let mut zf = zip_entry(&mut zip, i, self.zip_password.as_deref())?;
+            let name = zip_entry_path(zf.name());
+            if is_archive(&name) {
+                installed.extend(self.extract_nested_archive(&name, &mut zf)?);
+            } else {
+                installed.push(self.extract_zip_file(&mut zf)?);
+            }
+        }
+
+        Ok(installed)
     }
 
-    fn archive_member_is_partial_match(&self, file_name: &str) -> bool {
-        if !file_name.starts_with(&self.exe_file_stem) {
-            return false;
+    // This returns the path the zip file entry was installed to. When the entry is a compressed
+    // file, like `project.gz`, it's decompressed as it's written, and it's installed as if its
+    // name didn't have the compression extension.
+    fn extract_zip_file(&self, zf: &mut ZipFile) -> Result<PathBuf> {
+        let name = zip_entry_path(zf.name());
+        let compression = compressed_file_extension(&name);
+        let install_path = if compression.is_some() {
+            self.resolve_install_path(&name.with_extension(""))?
+        } else {
+            self.resolve_install_path(&name)?
+        };
+        debug!(
+            "extracting zip file entry named {} to {}",
+            name.display(),
+            install_path.display(),
+        );
+
+        // Zip files made on Unix usually record the file's mode, so we keep that. But we always
+        // add the executable bits, since the file is useless to us if it can't be run.
+        let mode = zf
+            .unix_mode()
+            .map_or(EXECUTABLE_MODE, |mode| (mode & 0o7777) | 0o111);
+        let modified = self.preserve_mtime.then(|| zip_file_mtime(zf)).flatten();
+        let result = if let Some(ext) = compression {
+            debug!(
+                "uncompressing zip file entry named {} as a {} file",
+                name.display(),
+                ext.extension_without_dot(),
+            );
+            let mut reader = compressed_file_decoder(&ext, &mut *zf)?;
+            self.write_executable(
+                &install_path,
+                Some(&name),
+                &mut reader,
+                mode,
+                modified,
+                None,
+            )
+        } else {
+            let size = zf.size();
+            self.write_executable(&install_path, Some(&name), zf, mode, modified, Some(size))
+        };
+        result.with_context(|| {
+            format!(
+                "could not extract zip file entry named {} to {}",
+                name.display(),
+                install_path.display(),
+            )
+        })?;
+
+        Ok(install_path)
+    }
+
+    // Like `best_match_from_tarball`, this returns the indexes of the entries to extract.
+    fn best_match_from_zip_archive(&self, zip: &mut ZipArchive<File>) -> Result<Vec<usize>> {
+        if let Some((algorithm, digest)) = &self.match_by_digest {
+            return Ok(self
+                .digest_match_from_zip_archive(zip, *algorithm, digest)?
+                .into_iter()
+                .collect());
         }
-        if self.extensions.is_empty() {
-            return true;
+
+        let mut scan = ArchiveScan::default();
+        for i in 0..zip.len() {
+            // We only need each entry's metadata here, and `by_index` returns an error for an
+            // encrypted entry.
+            let zf = zip.by_index_raw(i)?;
+            scan.scanned = i + 1;
+            if zf.is_file() {
+                let path = zip_entry_path(zf.name());
+                if !self.archive_member_is_in_member_dir(&path) {
+                    continue;
+                }
+                if self.member_path.is_some() {
+                    if self.archive_member_is_member_path(&path) {
+                        debug!(
+                            "found zip file entry at the member path: {}",
+                            path.display()
+                        );
+                        scan.exact_matches.push((i, path.display().to_string()));
+                        self.log_match_decision(
+                            "zip file",
+                            &scan,
+                            &[i],
+                            "it is at the member path",
+                        );
+                        return Ok(vec![i]);
+                    }
+                    continue;
+                }
+                if self.fall_back_to_sole_exe && zf.unix_mode().is_some_and(|m| m & 0o111 != 0) {
+                    scan.executables.push((i, path.display().to_string()));
+                }
+                if let Some(file_name) = path.file_name() {
+                    if let Some(file_name) = file_name.to_str() {
+                        if self.installs_multiple_exes() {
+                            scan.file_names.push((i, file_name.to_string()));
+                        } else if self.archive_member_is_exe(file_name) {
+                            debug!("found zip file entry with exact match: {}", file_name);
+                            scan.exact_matches.push((i, path.display().to_string()));
+                            if self.takes_first_exact_match() {
+                                self.log_match_decision(
+                                    "zip file",
+                                    &scan,
+                                    &[i],
+                                    "it is the first exact match for the executable name",
+                                );
+                                return Ok(vec![i]);
+                            }
+                        } else if self.archive_member_is_partial_match(file_name) {
+                            debug!("found zip file entry with partial match: {}", file_name);
+                            // An archive or compressed file in the zip file won't be executable,
+                            // but it might contain the executable.
+                            let is_executable = if is_archive(&path)
+                                || compressed_file_extension(&path).is_some()
+                            {
+                                Some(true)
+                            } else {
+                                zf.unix_mode().map(|mode| mode & 0o111 != 0)
+                            };
+                            scan.possible_matches
+                                .push((i, file_name.to_string(), is_executable));
+                        }
+                    }
+                }
+            }
         }
-        self.extensions
-            .iter()
-            .any(|&ext| file_name.to_lowercase().ends_with(ext))
+
+        // Not every zip file records each file's mode, and a zip file created on Windows won't
+        // mark the executable as executable. But when a partial match is marked as executable, we
+        // ignore any that are marked as not executable, like a data file with the same prefix as
+        // the executable.
+        if !self.is_windows
+            && scan
+                .possible_matches
+                .iter()
+                .any(|(_, _, e)| *e == Some(true))
+        {
+            scan.possible_matches.retain(|(_, file_name, e)| {
+                if *e == Some(false) {
+                    debug!(
+                        "ignoring non-executable zip file entry with partial match: {file_name}"
+                    );
+                }
+                *e != Some(false)
+            });
+        }
+
+        self.pick_archive_matches("zip file", &scan)
     }
 
-    fn could_not_find_archive_matches_error(&self) -> Result<PathBuf> {
-        let expect_names = if self.extensions.is_empty() {
-            format!("{}*", self.exe_file_stem)
-        } else {
-            self.extensions
+    // Picks the entries to install from what we found while scanning a tarball or zip file. An
+    // exact match wins over a partial match, which wins over falling back to the archive's only
+    // executable.
+    fn pick_archive_matches(&self, archive_kind: &str, scan: &ArchiveScan) -> Result<Vec<usize>> {
+        let (matches, reason) = if self.installs_multiple_exes() {
+            (
+                self.exact_match_for_each_exe(
+                    scan.file_names.iter().map(|(i, n)| (*i, n.as_str())),
+                )?,
+                "they are the exact matches for the executable names",
+            )
+        } else if let Some(i) = self.only_exact_match(scan.exact_matches.clone())? {
+            (
+                vec![i],
+                "it is the best exact match for the executable name",
+            )
+        } else if let Some(i) = self.closest_partial_match(
+            scan.possible_matches
                 .iter()
-                .map(|ext| format!("{}*{}", self.exe_file_stem, ext))
-                .collect::<Vec<_>>()
-                .join(" ")
+                .map(|(i, n, _)| (*i, n.as_str())),
+        )? {
+            (
+                vec![i],
+                "it is the closest partial match for the executable name",
+            )
+        } else if let Some(i) = self.sole_exe(scan.executables.clone()) {
+            (vec![i], "it is the only executable in the archive")
+        } else {
+            (vec![], "no entry matched the executable name")
         };
+        self.log_match_decision(archive_kind, scan, &matches, reason);
 
-        debug!("could not find any entries matching [{}]", expect_names);
-        Err(anyhow!(
-            "could not find any files matching [{}] in the downloaded archive file",
-            expect_names,
-        ))
+        Ok(matches)
     }
 
-    fn unbzip(&self, downloaded_file: &Path) -> Result<()> {
-        debug!("uncompressing executable from bzip file");
-        let reader = BzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
+    // The debug logs for each entry are spread out over the whole scan, so this logs a summary of
+    // the decision, saying what we found, which entries we picked, and why.
+    fn log_match_decision(
+        &self,
+        archive_kind: &str,
+        scan: &ArchiveScan,
+        selected: &[usize],
+        reason: &str,
+    ) {
+        let exact_matches = if self.installs_multiple_exes() {
+            selected.len()
+        } else {
+            scan.exact_matches.len()
+        };
+        let selected = if selected.is_empty() {
+            "nothing".to_string()
+        } else {
+            selected
+                .iter()
+                .map(|i| match scan.name_of(*i) {
+                    Some(name) => format!("entry {i} ({name})"),
+                    None => format!("entry {i}"),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        debug!(
+            "{archive_kind} match summary: scanned {} entries, found {exact_matches} exact and {} \
+             partial matches, selected {selected} because {reason}",
+            scan.scanned,
+            scan.possible_matches.len(),
+        );
     }
 
-    fn ungzip(&self, downloaded_file: &Path) -> Result<()> {
-        debug!("uncompressing executable from gzip file");
-        let reader = GzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
+    fn digest_match_from_zip_archive(
+        &self,
+        zip: &mut ZipArchive<File>,
+        algorithm: ChecksumAlgorithm,
+        digest: &str,
+    ) -> Result<Option<usize>> {
+        for i in 0..zip.len() {
+            {
+                let zf = zip.by_index_raw(i)?;
+                if !zf.is_file() {
+                    continue;
+                }
+                if zf.encrypted() && self.zip_password.is_none() {
+                    debug!(
+                        "not checking the digest of encrypted zip file entry {}",
+                        zf.name(),
+                    );
+                    continue;
+                }
+            }
+
+            let zf = zip_entry(zip, i, self.zip_password.as_deref())?;
+
+            let name = zf.name().to_string();
+            if algorithm.hex_digest(zf)?.eq_ignore_ascii_case(digest) {
+                debug!(
+                    "found zip file entry with matching {} digest: {}",
+                    algorithm.as_ref(),
+                    name,
+                );
+                return Ok(Some(i));
+            }
+        }
+
+        Ok(None)
     }
 
-    fn unxz(&self, downloaded_file: &Path) -> Result<()> {
-        debug!("uncompressing executable from xz file");
-        let reader = XzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
+    fn scan_tarball(&self, downloaded_file: &Path) -> Result<Vec<(String, MatchKind)>> {
+        let selected = self.best_match_from_tarball(downloaded_file)?;
+        let mut report = vec![];
+        let mut arch = tar_reader_for(downloaded_file, self.require_regular_file)?;
+        for (i, entry) in arch.entries()?.enumerate() {
+            let entry = entry?;
+            let entry_type = entry.header().entry_type();
+            if !tar_entry_is_file(entry_type) && !entry_type.is_hard_link() {
+                continue;
+            }
+            let path = entry.path()?;
+            let kind = if selected.contains(&i) {
+                MatchKind::Selected
+            } else {
+                self.match_kind(&path)
+            };
+            report.push((path.to_string_lossy().into_owned(), kind));
+        }
+
+        Ok(report)
     }
 
-    fn write_to_install_path(&self, mut reader: impl Read) -> Result<()> {
-        self.create_install_dir()?;
-        let mut writer = File::create(&self.install_path)
-            .with_context(|| format!("Cannot write to {}", self.install_path.display()))?;
-        std::io::copy(&mut reader, &mut writer)?;
-        Ok(())
+    fn scan_zip_archive(&self, downloaded_file: &Path) -> Result<Vec<(String, MatchKind)>> {
+        let mut zip = open_zip(downloaded_file, self.require_regular_file)?;
+        let selected = self.best_match_from_zip_archive(&mut zip)?;
+        let mut report = vec![];
+        for i in 0..zip.len() {
+            let zf = zip.by_index_raw(i)?;
+            if !zf.is_file() {
+                continue;
+            }
+            let path = zip_entry_path(zf.name());
+            let kind = if selected.contains(&i) {
+                MatchKind::Selected
+            } else {
+                self.match_kind(&path)
+            };
+            report.push((path.to_string_lossy().into_owned(), kind));
+        }
+
+        Ok(report)
     }
 
-    fn copy_executable(&self, exe_file: &Path) -> Result<PathBuf> {
-        debug!("copying executable to final location");
-        self.create_install_dir()?;
+    fn estimated_size_of_tarball(&self, downloaded_file: &Path) -> Result<u64> {
+        let matches = self.best_match_from_tarball(downloaded_file)?;
+        if matches.is_empty() {
+            return self.could_not_find_archive_matches_error();
+        }
 
-        let mut install_path = self.install_path.clone();
-        if let Some(ext) = Extension::from_path(exe_file)? {
-            if ext.should_preserve_extension_on_install() {
-                debug!("preserving the {} extension on install", ext.extension());
-                install_path.set_extension(ext.extension_without_dot());
+        let mut size = 0;
+        let mut found = 0;
+        // A hard link's target comes before it in the tarball, so we keep track of the size of
+        // each file we've seen.
+        let mut file_sizes: HashMap<PathBuf, u64> = HashMap::new();
+        let mut arch = tar_reader_for(downloaded_file, self.require_regular_file)?;
+        for (i, entry) in arch.entries()?.enumerate() {
+            let entry = entry?;
+            let entry_type = entry.header().entry_type();
+            if tar_entry_is_file(entry_type) {
+                file_sizes.insert(relative_member_path(&entry.path()?), entry.size());
+            }
+            if !matches.contains(&i) {
+                continue;
+            }
+            if entry_type.is_hard_link() {
+                if let Some(target) = entry.link_name()? {
+                    size += file_sizes
+                        .get(&relative_member_path(&target))
+                        .copied()
+                        .unwrap_or_default();
+                }
+            } else {
+                // For a GNU sparse file, this is the size of the file with its holes filled in.
+                size += entry.size();
+            }
+            found += 1;
+            if found == matches.len() {
+                break;
             }
         }
-        std::fs::copy(exe_file, &install_path).context(format!(
-            "error copying file from {} to {}",
-            exe_file.display(),
-            install_path.display()
-        ))?;
 
-        Ok(install_path)
+        Ok(size)
     }
 
-    fn create_install_dir(&self) -> Result<()> {
-        let Some(path) = self.install_path.parent() else {
-            return Err(anyhow!(
-                "install path at {} has no parent",
-                self.install_path.display()
-            ));
-        };
+    fn estimated_size_of_zip(&self, downloaded_file: &Path) -> Result<u64> {
+        let mut zip = open_zip(downloaded_file, self.require_regular_file)?;
+        let matches = self.best_match_from_zip_archive(&mut zip)?;
+        if matches.is_empty() {
+            return self.could_not_find_archive_matches_error();
+        }
 
-        debug!("creating directory at {}", path.display());
-        create_dir_all(path)
-            .with_context(|| format!("could not create a directory at {}", path.display()))
+        let mut size = 0;
+        for i in matches {
+            size += zip.by_index_raw(i)?.size();
+        }
+
+        Ok(size)
     }
 
-    #[cfg(target_family = "windows")]
-    fn chmod_executable(_exe: &Path) -> Result<()> {
-        Ok(())
+    // Other formats don't tell us the size of a member, or of a compressed file, without
+    // decompressing it, so we do the install into memory and add up what was written.
+    fn estimated_size_in_memory(&self, downloaded_file: &Path) -> Result<u64> {
+        let mut installer = self.clone();
+        if self.install_path_is_dir() {
+            installer
+                .install_path
+                .as_mut_os_string()
+                .push(std::path::MAIN_SEPARATOR_STR);
+        }
+        let fs = Arc::new(MemoryFs::new());
+        let installer = installer
+            .backup_existing(false)
+            .on_existing(OnExisting::Overwrite)
+            .filesystem(fs.clone());
+
+        let mut size = 0;
+        for path in installer.extract_executable(downloaded_file)? {
+            size += io::copy(&mut fs.open_file(&path)?, &mut io::sink())?;
+        }
+
+        Ok(size)
     }
 
-    #[cfg(target_family = "unix")]
-    fn chmod_executable(exe: &Path) -> Result<()> {
-        match set_permissions(exe, Permissions::from_mode(0o755)) {
-            Ok(()) => Ok(()),
-            Err(e) => Err(anyhow::Error::new(e)),
+    fn match_kind(&self, path: &Path) -> MatchKind {
+        if !self.archive_member_is_in_member_dir(path) {
+            return MatchKind::NoMatch;
+        }
+        if self.member_path.is_some() {
+            return if self.archive_member_is_member_path(path) {
+                MatchKind::Exact
+            } else {
+                MatchKind::NoMatch
+            };
+        }
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            return MatchKind::NoMatch;
+        };
+        if self.installs_multiple_exes() {
+            if self
+                .exe_file_stems
+                .iter()
+                .any(|stem| self.archive_member_is_exact_match(stem, file_name))
+            {
+                return MatchKind::Exact;
+            }
+        } else if self.archive_member_is_exe(file_name) {
+            return MatchKind::Exact;
+        } else if self.archive_member_is_partial_match(file_name) {
+            return MatchKind::Partial;
         }
+        MatchKind::NoMatch
     }
-}
 
-impl Installer for ExeInstaller {
-    fn install(&self, download: &Download) -> Result<()> {
-        let exe = self.extract_executable(&download.archive_path)?;
-        let real_exe = exe.as_deref().unwrap_or(&self.install_path);
-        Self::chmod_executable(real_exe)?;
-        info!("Installed executable into {}", real_exe.display());
+    fn extract_executable_from_7z(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
+        debug!(
+            "extracting executable from 7z file at {}",
+            downloaded_file.display()
+        );
 
-        Ok(())
-    }
-}
+        let mut sz = open_7z(downloaded_file, self.require_regular_file)?;
+        let names = if let Some((algorithm, digest)) = &self.match_by_digest {
+            // The entries share a single decompression stream, so once we've hashed our way to the
+            // matching entry, we have to start over from the beginning to extract it.
+            let names = Self::digest_match_from_7z_archive(&mut sz, *algorithm, digest)?
+                .into_iter()
+                .collect::<Vec<_>>();
+            sz = open_7z(downloaded_file, self.require_regular_file)?;
+            names
+        } else {
+            self.best_match_from_7z_archive(sz.archive())?
+        };
+        if names.is_empty() {
+            return self.could_not_find_archive_matches_error();
+        }
 
-impl ArchiveInstaller {
-    pub(crate) fn new(install_path: PathBuf) -> Self {
-        ArchiveInstaller {
-            install_root: install_path,
+        let mut installed = vec![];
+        let mut found = 0;
+        for_each_7z_entry(&mut sz, |entry, reader| {
+            if entry.is_directory() || !names.iter().any(|n| n == entry.name()) {
+                return Ok(true);
+            }
+            installed.extend(self.install_archive_entry(
+                "7z",
+                Path::new(entry.name()),
+                reader,
+                entry.size(),
+            )?);
+            found += 1;
+            // We keep going until we have every entry we're looking for.
+            Ok(found < names.len())
+        })?;
+
+        if found == 0 {
+            return self.could_not_find_archive_matches_error();
         }
+
+        Ok(installed)
     }
 
-    fn extract_entire_archive(&self, downloaded_file: &Path) -> Result<()> {
-        match Extension::from_path(downloaded_file)? {
-            Some(
-                Extension::Tar
-                | Extension::TarBz
-                | Extension::TarBz2
-                | Extension::TarGz
-                | Extension::TarXz
-                | Extension::Tbz
-                | Extension::Tgz
-                | Extension::Txz,
-            ) => self.extract_entire_tarball(downloaded_file)?,
-            Some(Extension::Zip) => self.extract_entire_zip(downloaded_file)?,
-            _ => {
-                return Err(anyhow!(
-                    concat!(
-                        "the downloaded release asset, {}, does not appear to be an",
-                        " archive file so we cannopt extract all of its contents",
-                    ),
-                    downloaded_file.display(),
-                ))
+    // Returns the name of the first 7z file entry with the given digest. Each entry is hashed as
+    // it's read, so we never hold an entry in memory.
+    fn digest_match_from_7z_archive(
+        sz: &mut SevenZReader<File>,
+        algorithm: ChecksumAlgorithm,
+        digest: &str,
+    ) -> Result<Option<String>> {
+        let mut found = None;
+        for_each_7z_entry(sz, |entry, reader| {
+            if entry.is_directory() {
+                return Ok(true);
+            }
+            if !algorithm.hex_digest(reader)?.eq_ignore_ascii_case(digest) {
+                return Ok(true);
+            }
+            debug!(
+                "found 7z file entry with matching {} digest: {}",
+                algorithm.as_ref(),
+                entry.name(),
+            );
+            found = Some(entry.name().to_string());
+            Ok(false)
+        })?;
+
+        Ok(found)
+    }
+
+    // Installs the archive entry at `name`, which is either an executable or a nested archive to
+    // extract executables from. The `archive_kind` is only used in log messages.
+    fn install_archive_entry(
+        &self,
+        archive_kind: &str,
+        name: &Path,
+        reader: &mut dyn Read,
+        size: u64,
+    ) -> Result<Vec<PathBuf>> {
+        if is_archive(name) {
+            return self.extract_nested_archive(name, reader);
+        }
+
+        let install_path = self.resolve_install_path(name)?;
+        debug!(
+            "extracting {archive_kind} file entry named {} to {}",
+            name.display(),
+            install_path.display(),
+        );
+        self.write_executable(
+            &install_path,
+            Some(name),
+            reader,
+            EXECUTABLE_MODE,
+            None,
+            Some(size),
+        )?;
+        Ok(vec![install_path])
+    }
+
+    // This returns the names of the entries to extract. When installing a single executable, there
+    // is at most one.
+    fn best_match_from_7z_archive(&self, archive: &SevenZArchive) -> Result<Vec<String>> {
+        let files = archive
+            .files
+            .iter()
+            .filter(|e| {
+                !e.is_directory() && self.archive_member_is_in_member_dir(Path::new(e.name()))
+            })
+            .collect::<Vec<_>>();
+        if self.member_path.is_some() {
+            return Ok(files
+                .iter()
+                .find(|e| self.archive_member_is_member_path(Path::new(e.name())))
+                .map(|e| e.name().to_string())
+                .into_iter()
+                .collect());
+        }
+        if self.installs_multiple_exes() {
+            let file_names = files.iter().enumerate().filter_map(|(i, entry)| {
+                Path::new(entry.name())
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .map(|f| (i, f))
+            });
+            return Ok(self
+                .exact_match_for_each_exe(file_names)?
+                .into_iter()
+                .map(|i| files[i].name().to_string())
+                .collect());
+        }
+
+        let mut exact_matches: Vec<(&str, String)> = vec![];
+        let mut possible_matches: Vec<(&str, &str)> = vec![];
+        for entry in files {
+            let path = Path::new(entry.name());
+            if let Some(file_name) = path.file_name().and_then(|f| f.to_str()) {
+                if self.archive_member_is_exe(file_name) {
+                    debug!("found 7z file entry with exact match: {}", file_name);
+                    if self.takes_first_exact_match() {
+                        return Ok(vec![entry.name().to_string()]);
+                    }
+                    exact_matches.push((entry.name(), entry.name().to_string()));
+                } else if self.archive_member_is_partial_match(file_name) {
+                    debug!("found 7z file entry with partial match: {}", file_name);
+                    // Like zip files, 7z files don't reliably record whether a file is executable,
+                    // so we don't check for that here.
+                    possible_matches.push((entry.name(), file_name));
+                }
+            }
+        }
+
+        if let Some(name) = self.only_exact_match(exact_matches)? {
+            return Ok(vec![name.to_string()]);
+        }
+
+        Ok(self
+            .closest_partial_match(possible_matches)?
+            .into_iter()
+            .map(str::to_string)
+            .collect())
+    }
+
+    // A `.deb` file is an `ar` archive, and the files that the package installs are in its
+    // `data.tar.*` member. We extract that tarball to a temp dir and look for the executable in it.
+    // Its paths are like `./usr/bin/project`, but only the file name matters when we look for the
+    // executable.
+    fn extract_executable_from_deb(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
+        debug!(
+            "extracting executable from deb file at {}",
+            downloaded_file.display()
+        );
+
+        // The temp dir has to outlive the loop, since we extract from the data tarball after it.
+        let mut data = None;
+        for_each_ar_entry(downloaded_file, self.require_regular_file, |name, entry| {
+            if !name.starts_with("data.tar") || name.contains('/') {
+                return Ok(true);
+            }
+
+            let td = new_temp_dir(self.temp_dir.as_deref())?;
+            let data_path = td.path().join(name);
+            debug!(
+                "extracting {name} from the deb file to {}",
+                data_path.display()
+            );
+            io::copy(entry, &mut File::create(&data_path)?)
+                .with_context(|| format!("could not extract {name} from the deb file"))?;
+            data = Some((td, data_path));
+            Ok(false)
+        })?;
+
+        let Some((_td, data_path)) = data else {
+            return Err(anyhow!(
+                "the deb file at {} does not have a data.tar member",
+                downloaded_file.display(),
+            ));
+        };
+        self.extract_executable_from_tarball(&data_path)
+    }
+
+    // An ar archive doesn't have directories. We read the member headers first to pick the members
+    // to extract, and then read the archive again to stream just those members to disk.
+    fn extract_executable_from_ar(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
+        debug!(
+            "extracting executable from ar file at {}",
+            downloaded_file.display()
+        );
+
+        let mut headers: Vec<(PathBuf, u32)> = vec![];
+        let mut digest_match = None;
+        for_each_ar_entry(downloaded_file, self.require_regular_file, |name, entry| {
+            headers.push((PathBuf::from(name), entry.header().mode() & 0o7777));
+            if self.match_by_digest.is_some()
+                && self.matches_digest("ar", Path::new(name), entry)?
+            {
+                digest_match = Some(headers.len() - 1);
+                return Ok(false);
+            }
+            Ok(true)
+        })?;
+
+        let matches = if self.match_by_digest.is_some() {
+            digest_match.into_iter().collect()
+        } else {
+            let headers = headers
+                .iter()
+                .map(|(path, mode)| (path.as_path(), *mode))
+                .collect::<Vec<_>>();
+            self.best_match_from_files("ar", &headers)?
+        };
+        if matches.is_empty() {
+            return self.could_not_find_archive_matches_error();
+        }
+
+        let mut installed = vec![];
+        let mut i = 0;
+        let mut found = 0;
+        for_each_ar_entry(downloaded_file, self.require_regular_file, |name, entry| {
+            if matches.contains(&i) {
+                let size = entry.header().size();
+                installed.extend(self.install_archive_entry("ar", Path::new(name), entry, size)?);
+                found += 1;
+            }
+            i += 1;
+            Ok(found < matches.len())
+        })?;
+
+        Ok(installed)
+    }
+
+    // The payload of an RPM file is a compressed cpio archive of the files that the package
+    // installs, with paths like `/usr/bin/project`. Like a `.deb` file, only the file name matters
+    // when we look for the executable. The `rpm` crate decompresses the whole payload into memory,
+    // so we keep the contents of the files we read from it.
+    fn extract_executable_from_rpm(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
+        debug!(
+            "extracting executable from rpm file at {}",
+            downloaded_file.display()
+        );
+
+        let package = rpm::Package::open(downloaded_file)
+            .with_context(|| format!("could not read rpm file at {}", downloaded_file.display()))?;
+        let mut files: Vec<(PathBuf, u32, Vec<u8>)> = vec![];
+        for file in package.files()? {
+            let file = file?;
+            let mode = file.metadata.mode();
+            if mode.file_type() != rpm::FileType::Regular {
+                continue;
+            }
+            let path = file.metadata.path();
+            if let Some(content) = file.into_content() {
+                files.push((path, u32::from(mode.permissions()), content));
+            }
+        }
+
+        self.install_files_from_memory("rpm", &files)
+    }
+
+    // Installs the best match from `files`, which are the path, mode, and contents of each file in
+    // an archive that we read into memory. The `archive_kind` is only used in log messages.
+    fn install_files_from_memory(
+        &self,
+        archive_kind: &str,
+        files: &[(PathBuf, u32, Vec<u8>)],
+    ) -> Result<Vec<PathBuf>> {
+        let matches = if self.match_by_digest.is_some() {
+            let mut matches = vec![];
+            for (i, (path, _, content)) in files.iter().enumerate() {
+                if self.matches_digest(archive_kind, path, content.as_slice())? {
+                    matches.push(i);
+                    break;
+                }
+            }
+            matches
+        } else {
+            let headers = files
+                .iter()
+                .map(|(path, mode, _)| (path.as_path(), *mode))
+                .collect::<Vec<_>>();
+            self.best_match_from_files(archive_kind, &headers)?
+        };
+        if matches.is_empty() {
+            return self.could_not_find_archive_matches_error();
+        }
+
+        let mut installed = vec![];
+        for i in matches {
+            let (path, _, content) = &files[i];
+            installed.extend(self.install_archive_entry(
+                archive_kind,
+                path,
+                &mut content.as_slice(),
+                content.len() as u64,
+            )?);
+        }
+
+        Ok(installed)
+    }
+
+    // Returns true if what we read from `reader` has the digest given for `match_by_digest`.
+    fn matches_digest(&self, archive_kind: &str, path: &Path, reader: impl Read) -> Result<bool> {
+        let Some((algorithm, digest)) = &self.match_by_digest else {
+            return Ok(false);
+        };
+        if !algorithm.hex_digest(reader)?.eq_ignore_ascii_case(digest) {
+            return Ok(false);
+        }
+        debug!(
+            "found {archive_kind} file entry with matching {} digest: {}",
+            algorithm.as_ref(),
+            path.display(),
+        );
+        Ok(true)
+    }
+
+    // This returns the indexes of the files to extract, given the path and mode of each file. When
+    // installing a single executable, there is at most one.
+    fn best_match_from_files(
+        &self,
+        archive_kind: &str,
+        files: &[(&Path, u32)],
+    ) -> Result<Vec<usize>> {
+        if self.member_path.is_some() {
+            return Ok(files
+                .iter()
+                .position(|(path, _)| self.archive_member_is_member_path(path))
+                .into_iter()
+                .collect());
+        }
+
+        let file_names = files
+            .iter()
+            .enumerate()
+            .filter(|(_, (path, _))| self.archive_member_is_in_member_dir(path))
+            .filter_map(|(i, (path, _))| path.file_name().and_then(|f| f.to_str()).map(|f| (i, f)));
+        if self.installs_multiple_exes() {
+            return self.exact_match_for_each_exe(file_names);
+        }
+
+        let mut exact_matches: Vec<(usize, String)> = vec![];
+        let mut possible_matches: Vec<(usize, &str)> = vec![];
+        for (i, file_name) in file_names {
+            if self.archive_member_is_exe(file_name) {
+                debug!(
+                    "found {archive_kind} file entry with exact match: {}",
+                    file_name
+                );
+                if self.takes_first_exact_match() {
+                    return Ok(vec![i]);
+                }
+                exact_matches.push((i, files[i].0.display().to_string()));
+            } else if self.archive_member_is_partial_match(file_name) {
+                // Like tarballs, rpm and ar files record each file's mode, so we can skip files
+                // that aren't executable.
+                let (path, mode) = files[i];
+                if self.is_windows || mode & 0o111 != 0 || is_archive(path) {
+                    debug!(
+                        "found {archive_kind} file entry with partial match: {}",
+                        file_name
+                    );
+                    possible_matches.push((i, file_name));
+                }
+            }
+        }
+        if let Some(i) = self.only_exact_match(exact_matches)? {
+            return Ok(vec![i]);
+        }
+
+        Ok(self
+            .closest_partial_match(possible_matches)?
+            .into_iter()
+            .collect())
+    }
+
+    fn extract_executable_from_cab(&self, downloaded_file: &Path) -> Result<Vec<PathBuf>> {
+        debug!(
+            "extracting executable from cab file at {}",
+            downloaded_file.display()
+        );
+
+        let mut cabinet = open_cab(downloaded_file, self.require_regular_file)?;
+        let names = self.best_match_from_cab(&mut cabinet)?;
+        if names.is_empty() {
+            return self.could_not_find_archive_matches_error();
+        }
+
+        let mut installed = vec![];
+        for name in names {
+            let member_path = cab_member_path(&name);
+            let size = cabinet
+                .get_file_entry(&name)
+                .map(|e| u64::from(e.uncompressed_size()));
+            let mut reader = cabinet
+                .read_file(&name)
+                .with_context(|| format!("could not read {name} from the cab file"))?;
+            if is_archive(&member_path) {
+                installed.extend(self.extract_nested_archive(&member_path, &mut reader)?);
+                continue;
+            }
+
+            let install_path = self.resolve_install_path(&member_path)?;
+            debug!(
+                "extracting cab file entry named {} to {}",
+                name,
+                install_path.display(),
+            );
+            // Cab files don't record Unix modes, so this always makes the file executable.
+            self.write_executable(
+                &install_path,
+                Some(&member_path),
+                &mut reader,
+                EXECUTABLE_MODE,
+                None,
+                size,
+            )?;
+            installed.push(install_path);
+        }
+
+        Ok(installed)
+    }
+
+    // This returns the names of the entries to extract. When installing a single executable, there
+    // is at most one.
+    fn best_match_from_cab(&self, cabinet: &mut Cabinet<File>) -> Result<Vec<String>> {
+        let names = cabinet
+            .folder_entries()
+            .flat_map(|folder| folder.file_entries())
+            .map(|entry| entry.name().to_string())
+            .collect::<Vec<_>>();
+
+        if let Some((algorithm, digest)) = &self.match_by_digest {
+            for name in names {
+                let got = algorithm.hex_digest(cabinet.read_file(&name)?)?;
+                if got.eq_ignore_ascii_case(digest) {
+                    debug!(
+                        "found cab file entry with matching {} digest: {}",
+                        algorithm.as_ref(),
+                        name,
+                    );
+                    return Ok(vec![name]);
+                }
+            }
+            return Ok(vec![]);
+        }
+
+        let paths = names.iter().map(|n| cab_member_path(n)).collect::<Vec<_>>();
+        if self.member_path.is_some() {
+            return Ok(paths
+                .iter()
+                .position(|path| self.archive_member_is_member_path(path))
+                .map(|i| names[i].clone())
+                .into_iter()
+                .collect());
+        }
+        let file_names = paths
+            .iter()
+            .enumerate()
+            .filter(|(_, path)| self.archive_member_is_in_member_dir(path))
+            .filter_map(|(i, path)| path.file_name().and_then(|f| f.to_str()).map(|f| (i, f)));
+        if self.installs_multiple_exes() {
+            return Ok(self
+                .exact_match_for_each_exe(file_names)?
+                .into_iter()
+                .map(|i| names[i].clone())
+                .collect());
+        }
+
+        let mut exact_matches: Vec<(usize, String)> = vec![];
+        let mut possible_matches: Vec<(usize, &str)> = vec![];
+        for (i, file_name) in file_names {
+            if self.archive_member_is_exe(file_name) {
+                debug!("found cab file entry with exact match: {}", file_name);
+                if self.takes_first_exact_match() {
+                    return Ok(vec![names[i].clone()]);
+                }
+                exact_matches.push((i, names[i].clone()));
+            } else if self.archive_member_is_partial_match(file_name) {
+                debug!("found cab file entry with partial match: {}", file_name);
+                possible_matches.push((i, file_name));
+            }
+        }
+        if let Some(i) = self.only_exact_match(exact_matches)? {
+            return Ok(vec![names[i].clone()]);
+        }
+
+        Ok(self
+            .closest_partial_match(possible_matches)?
+            .into_iter()
+            .map(|i| names[i].clone())
+            .collect())
+    }
+
+    // When installing more than one executable, this finds the first archive member that is an
+    // exact match for each executable name, given the index and file name of each member. It
+    // returns the matching indexes in archive order, or an error naming every executable that
+    // could not be found.
+    fn exact_match_for_each_exe<'n>(
+        &self,
+        file_names: impl IntoIterator<Item = (usize, &'n str)>,
+    ) -> Result<Vec<usize>> {
+        let mut found: Vec<Option<usize>> = vec![None; self.exe_file_stems.len()];
+        for (i, file_name) in file_names {
+            for (stem, found) in self.exe_file_stems.iter().zip(found.iter_mut()) {
+                if found.is_none() && self.archive_member_is_exact_match(stem, file_name) {
+                    debug!("found archive entry with exact match for {stem}: {file_name}");
+                    *found = Some(i);
+                }
+            }
+        }
+
+        let missing = self
+            .exe_file_stems
+            .iter()
+            .zip(&found)
+            .filter(|(_, found)| found.is_none())
+            .map(|(stem, _)| self.exact_match_names(stem))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let expect_names = missing.join(" ");
+            debug!("could not find any entries matching [{}]", expect_names);
+            return Err(InstallError::NoMatchingMember {
+                looking_for: format!("matching [{expect_names}]"),
+            }
+            .into());
+        }
+
+        let mut matches = found.into_iter().flatten().collect::<Vec<_>>();
+        matches.sort_unstable();
+        matches.dedup();
+        Ok(matches)
+    }
+
+    // When installing a single executable, this checks whether the archive member is an exact
+    // match for it, or whether its file name matches `exe_pattern` if that's set.
+    fn archive_member_is_exe(&self, file_name: &str) -> bool {
+        if let Some(pattern) = &self.exe_pattern {
+            return pattern.is_match(file_name);
+        }
+        self.exact_match_rank(file_name).is_some()
+    }
+
+    fn archive_member_is_exact_match(&self, stem: &str, file_name: &str) -> bool {
+        // Like partial matches, Windows file names are case-insensitive, so `Project.EXE` is an
+        // exact match for `project`.
+        let (stem, file_name) = if self.is_windows {
+            (
+                Cow::Owned(stem.to_lowercase()),
+                Cow::Owned(file_name.to_lowercase()),
+            )
+        } else {
+            (Cow::Borrowed(stem), Cow::Borrowed(file_name))
+        };
+        if self.extensions.is_empty() {
+            return file_name == stem;
+        }
+
+        self.extensions
+            .iter()
+            .any(|&ext| file_name.strip_suffix(ext) == Some(&*stem))
+    }
+
+    // The file names that are an exact match for the given stem, separated by spaces.
+    fn exact_match_names(&self, stem: &str) -> String {
+        if self.extensions.is_empty() {
+            return stem.to_string();
+        }
+
+        self.extensions
+            .iter()
+            .map(|ext| format!("{}{}", stem.to_lowercase(), ext))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn archive_member_is_partial_match(&self, file_name: &str) -> bool {
+        if self.exe_pattern.is_some() || self.has_non_executable_extension(file_name) {
+            return false;
+        }
+        self.partial_match_rank(file_name).is_some()
+    }
+
+    fn has_non_executable_extension(&self, file_name: &str) -> bool {
+        matching_custom_extension(
+            &file_name.to_lowercase(),
+            self.non_executable_extensions.iter().map(String::as_str),
+        )
+        .is_some()
+    }
+
+    fn archive_member_is_partial_match_for(&self, stem: &str, file_name: &str) -> bool {
+        // Windows file names are case-insensitive, so `Project.exe` is a match for `project`.
+        let starts_with_stem = if self.is_windows {
+            file_name.to_lowercase().starts_with(&stem.to_lowercase())
+        } else {
+            file_name.starts_with(stem)
+        };
+        if !starts_with_stem {
+            return false;
+        }
+        if self.extensions.is_empty() {
+            return true;
+        }
+        self.extensions
+            .iter()
+            .any(|&ext| file_name.to_lowercase().ends_with(ext))
+    }
+
+    fn could_not_find_archive_matches_error<T>(&self) -> Result<T> {
+        if let Some((algorithm, digest)) = &self.match_by_digest {
+            debug!(
+                "could not find any entries with the {} digest {}",
+                algorithm.as_ref(),
+                digest,
+            );
+            return Err(InstallError::NoMatchingMember {
+                looking_for: format!("with the {} digest {digest}", algorithm.as_ref()),
+            }
+            .into());
+        }
+
+        if let Some(member_path) = &self.member_path {
+            debug!("could not find an entry at {}", member_path.display());
+            return Err(InstallError::NoMatchingMember {
+                looking_for: format!("at the path {}", member_path.display()),
+            }
+            .into());
+        }
+
+        if let Some(pattern) = &self.exe_pattern {
+            debug!("could not find any entries matching {}", pattern.as_str());
+            return Err(InstallError::NoMatchingMember {
+                looking_for: format!("matching the pattern `{}`", pattern.as_str()),
+            }
+            .into());
+        }
+
+        let expect_names = self
+            .exe_names_by_priority()
+            .map(|stem| {
+                if self.extensions.is_empty() {
+                    format!("{stem}*")
+                } else {
+                    self.extensions
+                        .iter()
+                        .map(|ext| format!("{stem}*{ext}"))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        debug!("could not find any entries matching [{}]", expect_names);
+        Err(InstallError::NoMatchingMember {
+            looking_for: format!("matching [{expect_names}]"),
+        }
+        .into())
+    }
+
+    fn unbzip(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from bzip file");
+        let reader = BzDecoder::new(open_file(downloaded_file, self.require_regular_file)?);
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unbrotli(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from brotli file");
+        // The second argument is the size of the buffer used for the compressed input.
+        let reader =
+            BrotliDecoder::new(open_file(downloaded_file, self.require_regular_file)?, 4096);
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    // A gzip file may record the name of the file that was compressed. If it does, and that name
+    // has an extension we preserve, like `.pyz`, we add it to the install path, just like we do for
+    // an archive member. This returns the path the executable was installed to.
+    fn ungzip(&self, downloaded_file: &Path) -> Result<PathBuf> {
+        debug!("uncompressing executable from gzip file");
+        let mut reader =
+            MultiGzDecoder::new(open_file(downloaded_file, self.require_regular_file)?);
+        let mut install_path = self.decompressed_install_path(downloaded_file)?;
+        let stored_name = reader
+            .header()
+            .and_then(|h| h.filename())
+            .map(|n| PathBuf::from(String::from_utf8_lossy(n).as_ref()));
+        if let Some(stored_name) = stored_name {
+            debug!("the gzip file says it contains {}", stored_name.display());
+            // Since we didn't pick this name, an unknown extension isn't an error.
+            if let Ok(Some(ext)) = preserved_extension(&stored_name) {
+                install_path = with_preserved_extension(install_path, &ext);
             }
         }
+        self.write_executable(
+            &install_path,
+            None,
+            &mut reader,
+            EXECUTABLE_MODE,
+            None,
+            None,
+        )?;
+
+        Ok(install_path)
+    }
+
+    fn unlzip(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from lzip file");
+        let reader = LzipDecoder::new(open_file(downloaded_file, self.require_regular_file)?);
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unlz4(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from lz4 file");
+        let reader = FrameDecoder::new(open_file(downloaded_file, self.require_regular_file)?);
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unlzma(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from lzma file");
+        let reader = lzma_decoder(open_file(downloaded_file, self.require_regular_file)?)?;
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unxz(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from xz file");
+        // Some xz files are made by concatenating several xz streams, and `XzDecoder::new` stops
+        // at the end of the first one.
+        let reader =
+            XzDecoder::new_multi_decoder(open_file(downloaded_file, self.require_regular_file)?);
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn uncompress_z(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from compress (.Z) file");
+        let reader = LzwDecoder::new(open_file(downloaded_file, self.require_regular_file)?);
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unzstd(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from zstd file");
+        let reader = ZstdDecoder::new(open_file(downloaded_file, self.require_regular_file)?)?;
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn write_to_install_path(&self, downloaded_file: &Path, mut reader: impl Read) -> Result<()> {
+        let install_path = self.decompressed_install_path(downloaded_file)?;
+        self.write_executable(
+            &install_path,
+            None,
+            &mut reader,
+            EXECUTABLE_MODE,
+            None,
+            None,
+        )
+    }
+
+    // When the install path is a directory, a compressed executable keeps the name it has without
+    // the compression extension, so `project-linux.gz` is installed as `project-linux`.
+    fn decompressed_install_path(&self, downloaded_file: &Path) -> Result<PathBuf> {
+        if !self.install_path_is_dir() {
+            return Ok(self.install_path.clone());
+        }
+        let Some(file_stem) = downloaded_file.file_stem() else {
+            return Err(anyhow!("{} has no file name", downloaded_file.display()));
+        };
+        Ok(self.install_path.join(file_stem))
+    }
+
+    // The install path is treated as a directory when it ends with a path separator or when it's
+    // an existing directory. In that case, the executable is installed into it with the name it
+    // has in the release, instead of being renamed.
+    fn install_path_is_dir(&self) -> bool {
+        self.install_path
+            .to_string_lossy()
+            .ends_with(std::path::is_separator)
+            || self
+                .fs
+                .metadata(&self.install_path)
+                .is_ok_and(|m| m.kind == FileKind::Dir)
+    }
+
+    // Writes the executable to `install_path` and gives it `mode`, unless the installer has its
+    // own mode set, and `modified` as its modification time if that's set. The `size` is passed to
+    // the progress callback, if there is one. This writes to a temp file next to `install_path`
+    // and then renames it into place, so an interrupted install never leaves a partly written
+    // executable behind.
+    fn write_executable(
+        &self,
+        install_path: &Path,
+        member: Option<&Path>,
+        reader: &mut dyn Read,
+        mode: u32,
+        modified: Option<SystemTime>,
+        size: Option<u64>,
+    ) -> Result<()> {
+        if self.skip_existing_exe(install_path)? {
+            return Ok(());
+        }
+        self.create_install_dir()?;
+        if self.backup_existing {
+            self.back_up_existing_exe(install_path)?;
+        }
+        let temp_path = sibling_path(install_path, &format!("ubi-{}.tmp", process::id()))?;
+        debug!("writing executable to temp file at {}", temp_path.display());
+        let mode = self.mode.unwrap_or(mode);
+        let res = self
+            .write_temp_file(&temp_path, reader, mode, modified, size)
+            .and_then(|written| {
+                self.rename_into_place(&temp_path, install_path)?;
+                Ok(written)
+            });
+        match res {
+            Ok(written) => {
+                if let Some(manifest) = &self.manifest {
+                    manifest.lock().unwrap().push(InstalledExecutable {
+                        member: member.map(|m| m.to_string_lossy().into_owned()),
+                        path: install_path.to_path_buf(),
+                        mode,
+                        size: written,
+                    });
+                }
+                Ok(())
+            }
+            Err(e) => {
+                if let Err(e) = self.fs.remove_file(&temp_path) {
+                    debug!("could not remove temp file at {}: {e}", temp_path.display());
+                }
+                Err(e)
+            }
+        }
+    }
+
+    // We copy the existing executable rather than renaming it, so there's still an executable at
+    // the install path if we're interrupted before the new one is renamed into place.
+    fn back_up_existing_exe(&self, install_path: &Path) -> Result<()> {
+        if !self
+            .fs
+            .metadata(install_path)
+            .is_ok_and(|m| m.kind == FileKind::File)
+        {
+            return Ok(());
+        }
+
+        let backup_path = backup_path(install_path);
+        debug!(
+            "backing up the existing executable at {} to {}",
+            install_path.display(),
+            backup_path.display(),
+        );
+        copy_file(self.fs.as_ref(), install_path, &backup_path)
+    }
+
+    fn remove_backups(&self, paths: &[PathBuf]) -> Result<()> {
+        for path in paths {
+            let backup_path = backup_path(path);
+            if self.fs.metadata(&backup_path).is_ok() {
+                debug!("removing the backup at {}", backup_path.display());
+                self.fs.remove_file(&backup_path).with_context(|| {
+                    format!("could not remove the backup at {}", backup_path.display())
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_temp_file(
+        &self,
+        temp_path: &Path,
+        reader: &mut dyn Read,
+        mode: u32,
+        modified: Option<SystemTime>,
+        size: Option<u64>,
+    ) -> Result<u64> {
+        let written = {
+            let mut file = retry_if_locked(|| self.fs.create_file(temp_path))
+                .with_context(|| format!("Cannot write to {}", temp_path.display()))?;
+            let written = copy_with_progress(reader, &mut file, self.progress.as_ref(), size)?;
+            file.flush()?;
+            written
+        };
+        // The modification time is kept when the file is renamed into place.
+        if let Some(modified) = modified {
+            self.fs.set_modified(temp_path, modified)?;
+        }
+        self.chmod_executable(temp_path, mode)?;
+
+        Ok(written)
+    }
+
+    // Renaming over an existing file replaces it, including on Windows. But Windows won't let us
+    // replace an executable that is running, though it will let us rename it. So if the rename
+    // fails when something is already installed, we move that out of the way and try again.
+    fn rename_into_place(&self, temp_path: &Path, install_path: &Path) -> Result<()> {
+        // When there's already an executable at the install path, a rename that fails usually means
+        // that it's running, and waiting for it won't help, so we go straight to moving it out of
+        // the way instead of retrying.
+        let replaces_file = self
+            .fs
+            .metadata(install_path)
+            .is_ok_and(|m| m.kind == FileKind::File);
+        let res = if replaces_file {
+            self.fs.rename(temp_path, install_path)
+        } else {
+            retry_if_locked(|| self.fs.rename(temp_path, install_path))
+        };
+        let Err(e) = res else {
+            return Ok(());
+        };
+        if !replaces_file {
+            return Err(anyhow::Error::new(e).context(format!(
+                "could not move {} to {}",
+                temp_path.display(),
+                install_path.display(),
+            )));
+        }
+
+        debug!(
+            "could not replace {}, so moving it out of the way first: {e}",
+            install_path.display(),
+        );
+        let old_path = sibling_path(install_path, &format!("ubi-{}.old", process::id()))?;
+        retry_if_locked(|| self.fs.rename(install_path, &old_path))?;
+        if let Err(e) = retry_if_locked(|| self.fs.rename(temp_path, install_path)) {
+            retry_if_locked(|| self.fs.rename(&old_path, install_path))?;
+            return Err(anyhow::Error::new(e).context(format!(
+                "could not move {} to {}",
+                temp_path.display(),
+                install_path.display(),
+            )));
+        }
+        // This fails if the old executable is still running, in which case we have to leave it.
+        if let Err(e) = self.fs.remove_file(&old_path) {
+            warn!(
+                "could not remove the previously installed executable at {}: {e}",
+                old_path.display(),
+            );
+        }
+
+        Ok(())
+    }
+
+    // When the archive member we picked is itself an archive, like a tarball in a zip file, we
+    // write it to a temp dir and look for the executable in that instead. This stops after
+    // `MAX_NESTED_ARCHIVE_DEPTH` levels, so an archive can't make us recurse forever.
+    fn extract_nested_archive(
+        &self,
+        member_path: &Path,
+        reader: &mut dyn Read,
+    ) -> Result<Vec<PathBuf>> {
+        if self.nested_archive_depth >= MAX_NESTED_ARCHIVE_DEPTH {
+            return Err(anyhow!(
+                "the archive member {} is an archive nested more than {} levels deep",
+                member_path.display(),
+                MAX_NESTED_ARCHIVE_DEPTH,
+            ));
+        }
+        let Some(file_name) = member_path.file_name() else {
+            return Err(anyhow!(
+                "the archive member {} has no file name",
+                member_path.display(),
+            ));
+        };
+
+        let td = new_temp_dir(self.temp_dir.as_deref())?;
+        let nested_path = td.path().join(file_name);
+        debug!(
+            "extracting nested archive named {} to {}",
+            member_path.display(),
+            nested_path.display(),
+        );
+        io::copy(reader, &mut File::create(&nested_path)?).with_context(|| {
+            format!(
+                "could not extract the nested archive named {} to {}",
+                member_path.display(),
+                nested_path.display(),
+            )
+        })?;
+
+        // The member dir and member path are for the outer archive, so we look everywhere in the
+        // nested one.
+        let mut nested = self.clone().member_dir(None).member_path(None);
+        nested.nested_archive_depth += 1;
+        nested.extract_executable(&nested_path)
+    }
+
+    // Returns the path to install the executable at `member_path` to, which is either an archive
+    // member or a downloaded file that is the executable. When installing a single executable,
+    // this is the install path, plus the member's extension if it's one we preserve and the
+    // install path doesn't already have an extension. When installing more than one, or when the
+    // install path is a directory, each member keeps its file name in the install directory. Every
+    // code path that installs an executable that it found by name uses this, so they all name the
+    // installed executable the same way.
+    fn resolve_install_path(&self, member_path: &Path) -> Result<PathBuf> {
+        if self.installs_multiple_exes() || self.install_path_is_dir() {
+            let Some(file_name) = member_path.file_name() else {
+                return Err(anyhow!("{} has no file name", member_path.display()));
+            };
+            return Ok(self.install_dir()?.join(file_name));
+        }
+
+        match preserved_extension(member_path)? {
+            Some(ext) => Ok(with_preserved_extension(self.install_path.clone(), &ext)),
+            None => Ok(self.install_path.clone()),
+        }
+    }
+
+    fn copy_executable(&self, exe_file: &Path) -> Result<PathBuf> {
+        debug!("copying executable to final location");
+
+        let install_path = self.resolve_install_path(exe_file)?;
+        let size = exe_file.metadata().ok().map(|m| m.len());
+        self.write_executable(
+            &install_path,
+            None,
+            &mut open_file(exe_file, self.require_regular_file)?,
+            EXECUTABLE_MODE,
+            None,
+            size,
+        )
+        .context(format!(
+            "error copying file from {} to {}",
+            exe_file.display(),
+            install_path.display()
+        ))?;
+
+        Ok(install_path)
+    }
+
+    fn install_dir(&self) -> Result<&Path> {
+        if self.install_path_is_dir() {
+            return Ok(&self.install_path);
+        }
+        self.install_path.parent().ok_or_else(|| {
+            anyhow!(
+                "install path at {} has no parent",
+                self.install_path.display()
+            )
+        })
+    }
+
+    // Returns true if there's already something at `install_path` and we've been told to skip
+    // installing when there is.
+    fn skip_existing_exe(&self, install_path: &Path) -> Result<bool> {
+        if self.on_existing == OnExisting::Overwrite || self.fs.metadata(install_path).is_err() {
+            return Ok(false);
+        }
+        match self.on_existing {
+            OnExisting::Skip => {
+                info!(
+                    "Skipped installing {} because it already exists",
+                    install_path.display(),
+                );
+                if let Some(skipped) = &self.skipped_existing {
+                    skipped.lock().unwrap().push(install_path.to_path_buf());
+                }
+                Ok(true)
+            }
+            OnExisting::Error => Err(anyhow!(
+                "cannot install {} because it already exists",
+                install_path.display(),
+            )),
+            OnExisting::Overwrite => unreachable!(),
+        }
+    }
+
+    fn create_install_dir(&self) -> Result<()> {
+        let path = self.install_dir()?;
+        // We need to know which directories we're about to create, so we only change the mode of
+        // those.
+        let new_dirs = if self.dir_mode.is_some() {
+            path.ancestors()
+                .filter(|a| !a.as_os_str().is_empty())
+                .take_while(|a| self.fs.metadata(a).is_err())
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>()
+        } else {
+            vec![]
+        };
+
+        debug!("creating directory at {}", path.display());
+        self.fs
+            .create_dir_all(path)
+            .with_context(|| format!("could not create a directory at {}", path.display()))?;
+
+        if let Some(dir_mode) = self.dir_mode {
+            // These go from the bottom up, so a mode without the owner's execute bit doesn't stop
+            // us from reaching the directories below the first one we change.
+            for dir in &new_dirs {
+                debug!("setting the mode of {} to {dir_mode:#o}", dir.display());
+                self.fs
+                    .set_permissions(dir, dir_mode)
+                    .with_context(|| format!("could not set permissions for {}", dir.display()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // The `RealFs` implementation ignores this on Windows, since it doesn't have Unix permissions.
+    fn chmod_executable(&self, exe: &Path, mode: u32) -> Result<()> {
+        if self.skip_chmod {
+            debug!("not setting the permissions of {}", exe.display());
+            return Ok(());
+        }
+        match retry_if_locked(|| self.fs.set_permissions(exe, mode)) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(anyhow::Error::new(e)),
+        }
+    }
+}
+
+impl Installer for ExeInstaller {
+    // This returns the paths of the installed executables, which may have an extension that the
+    // configured install path does not, like `.exe`. Executables that were skipped because of
+    // `on_existing` are left out, since we didn't touch them.
+    fn install(&self, download: &Download) -> Result<Vec<PathBuf>, InstallError> {
+        check_download(download, self.require_regular_file)?;
+        let skipped = Arc::new(Mutex::new(vec![]));
+        let mut installer = self.clone();
+        installer.skipped_existing = Some(skipped.clone());
+        let mut paths = installer.extract_executable(&download.archive_path)?;
+        let skipped = mem::take(&mut *skipped.lock().unwrap());
+        paths.retain(|p| !skipped.contains(p));
+        if paths.is_empty() {
+            return Ok(paths);
+        }
+        paths.sort();
+        for exe in &paths {
+            info!("Installed executable into {}", exe.display());
+        }
+        self.check_exe_arches(&paths)?;
+        // If either of these fail, we leave any backups in place so the previous executables can
+        // be put back.
+        self.verify_exes_run(&paths)?;
+        if let Some(post_install) = &self.post_install {
+            debug!("calling the post-install callback");
+            (post_install.0)(&paths)?;
+        }
+        if self.backup_existing && self.remove_backup {
+            self.remove_backups(&paths)?;
+        }
+
+        Ok(paths)
+    }
+
+    fn install_dry_run(&self, download: &Download) -> Result<Vec<PathBuf>, InstallError> {
+        check_download(download, self.require_regular_file)?;
+        let skipped = Arc::new(Mutex::new(vec![]));
+        let mut installer = self.clone();
+        installer.skipped_existing = Some(skipped.clone());
+        // The install dir may not exist yet, so we mark it as a directory with a trailing
+        // separator.
+        if self.install_path_is_dir() {
+            installer
+                .install_path
+                .as_mut_os_string()
+                .push(std::path::MAIN_SEPARATOR_STR);
+        }
+        // Existing paths come from our real filesystem, so skipping or refusing to replace an
+        // existing executable works just like it does for a real install.
+        let installer = installer.filesystem(Arc::new(DryRunFs::new(self.fs.clone())));
+        let mut paths = installer.extract_executable(&download.archive_path)?;
+        let skipped = mem::take(&mut *skipped.lock().unwrap());
+        paths.retain(|p| !skipped.contains(p));
+        paths.sort();
+        for exe in &paths {
+            info!("Would install executable into {}", exe.display());
+        }
+
+        Ok(paths)
+    }
+
+    fn install_with_manifest(&self, download: &Download) -> Result<InstallManifest, InstallError> {
+        let executables = Arc::new(Mutex::new(vec![]));
+        let mut installer = self.clone();
+        installer.manifest = Some(executables.clone());
+        installer.install(download)?;
+
+        let source = download.archive_path.file_name().map_or_else(
+            || download.archive_path.to_string_lossy(),
+            OsStr::to_string_lossy,
+        );
+        let extension = Extension::from_path(&download.archive_path)?;
+        let executables = mem::take(&mut *executables.lock().unwrap());
+        Ok(InstallManifest {
+            source: source.into_owned(),
+            extension: extension.map(|e| e.extension().to_string()),
+            executables,
+        })
+    }
+
+    // This extracts the executable into a `MemoryFs`, so it goes through all the same matching and
+    // decompression as a real install.
+    fn extract_to_bytes(&self, download: &Download) -> Result<Vec<u8>, InstallError> {
+        if self.installs_multiple_exes() {
+            return Err(anyhow!("cannot extract more than one executable to memory").into());
+        }
+        check_download(download, self.require_regular_file)?;
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = self
+            .clone()
+            .backup_existing(false)
+            .on_existing(OnExisting::Overwrite)
+            .filesystem(fs.clone());
+        let paths = installer.extract_executable(&download.archive_path)?;
+        let [path] = paths.as_slice() else {
+            return Err(anyhow!(
+                "expected to extract one executable from {} but got {}",
+                download.archive_path.display(),
+                paths.len(),
+            )
+            .into());
+        };
+
+        let mut contents = vec![];
+        fs.open_file(path)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .map_err(anyhow::Error::from)?;
+        Ok(contents)
+    }
+
+    // Only tarballs and zip files can be scanned.
+    fn scan_archive(&self, download: &Download) -> Result<Vec<(String, MatchKind)>, InstallError> {
+        let path = &download.archive_path;
+        match Extension::from_path(path)? {
+            Some(
+                Extension::Tar
+                | Extension::TarBr
+                | Extension::TarBz
+                | Extension::TarBz2
+                | Extension::TarGz
+                | Extension::TarLz
+                | Extension::TarLz4
+                | Extension::TarLzma
+                | Extension::TarXz
+                | Extension::TarZ
+                | Extension::TarZst
+                | Extension::Tbz
+                | Extension::Tgz
+                | Extension::Txz,
+            ) => Ok(self.scan_tarball(path)?),
+            Some(Extension::Zip) => Ok(self.scan_zip_archive(path)?),
+            _ => Err(InstallError::UnsupportedFormat {
+                path: path.clone(),
+                message: format!(
+                    "cannot scan {} for executables because it is not a tarball or zip file",
+                    path.display(),
+                ),
+            }),
+        }
+    }
+
+    fn estimated_size(&self, download: &Download) -> Result<u64, InstallError> {
+        let path = &download.archive_path;
+        check_file_format(path, open_file(path, self.require_regular_file)?)?;
+        let size = match Extension::from_path(path)? {
+            Some(
+                Extension::Tar
+                | Extension::TarBr
+                | Extension::TarBz
+                | Extension::TarBz2
+                | Extension::TarGz
+                | Extension::TarLz
+                | Extension::TarLz4
+                | Extension::TarLzma
+                | Extension::TarXz
+                | Extension::TarZ
+                | Extension::TarZst
+                | Extension::Tbz
+                | Extension::Tgz
+                | Extension::Txz,
+            ) => self.estimated_size_of_tarball(path)?,
+            Some(Extension::Zip) => self.estimated_size_of_zip(path)?,
+            _ => self.estimated_size_in_memory(path)?,
+        };
+        debug!(
+            "estimated installed size of {} is {size} bytes",
+            path.display()
+        );
+
+        Ok(size)
+    }
+
+    // When `match_by_digest` is set, it takes precedence over the name-based matching of archive
+    // members. The executable name is still used to determine the install path.
+    fn validate(&self) -> Result<()> {
+        if self.exe_file_stems.is_empty() {
+            return Err(anyhow!("the installer was not given any executable names"));
+        }
+        if self.installs_multiple_exes() && self.match_by_digest.is_some() {
+            return Err(anyhow!(
+                "match_by_digest cannot be used when installing more than one executable",
+            ));
+        }
+        if self.installs_multiple_exes() && self.exe_pattern.is_some() {
+            return Err(anyhow!(
+                "exe_pattern cannot be used when installing more than one executable",
+            ));
+        }
+        if self.installs_multiple_exes() && !self.alternative_exe_names.is_empty() {
+            return Err(anyhow!(
+                "alternative executable names cannot be used when installing more than one executable",
+            ));
+        }
+        if self.exe_pattern.is_some() && !self.alternative_exe_names.is_empty() {
+            return Err(anyhow!(
+                "alternative executable names cannot be used with exe_pattern"
+            ));
+        }
+        if self.match_by_digest.is_some() && self.exe_pattern.is_some() {
+            return Err(anyhow!(
+                "match_by_digest and exe_pattern cannot be used together"
+            ));
+        }
+        if self.member_path.is_some() {
+            if self.installs_multiple_exes() {
+                return Err(anyhow!(
+                    "member_path cannot be used when installing more than one executable",
+                ));
+            }
+            if self.match_by_digest.is_some() || self.exe_pattern.is_some() {
+                return Err(anyhow!(
+                    "member_path cannot be used with match_by_digest or exe_pattern"
+                ));
+            }
+            if !self.alternative_exe_names.is_empty() {
+                return Err(anyhow!(
+                    "alternative executable names cannot be used with member_path"
+                ));
+            }
+        }
+
+        if self.mode.is_some() && self.skip_chmod {
+            return Err(anyhow!("mode and skip_chmod cannot be used together"));
+        }
+        if let Some(mode) = self.mode {
+            if mode > 0o7777 {
+                return Err(anyhow!(
+                    "the install mode, {mode:#o}, has bits set other than the permission bits",
+                ));
+            }
+        }
+        if let Some(dir_mode) = self.dir_mode {
+            if dir_mode > 0o7777 {
+                return Err(anyhow!(
+                    "the install directory mode, {dir_mode:#o}, has bits set other than the \
+                     permission bits",
+                ));
+            }
+        }
+
+        if let Some((algorithm, digest)) = &self.match_by_digest {
+            if !algorithm.is_valid_hex_digest(digest) {
+                return Err(anyhow!(
+                    "the digest given for match_by_digest, `{}`, is not a valid {} hex digest",
+                    digest,
+                    algorithm.as_ref(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ArchiveInstaller {
+    /// Creates an installer that extracts an archive file into `install_path`. The directory is
+    /// created if it doesn't exist.
+    #[must_use]
+    pub fn new(install_path: PathBuf) -> Self {
+        ArchiveInstaller {
+            install_root: install_path,
+            report_skipped: false,
+            stage_in_temp_dir: false,
+            replace_install_root: false,
+            flatten_executables: false,
+            flatten_executable_names: vec![],
+            extract_appimage: false,
+            parallel_zip_extraction: false,
+            zip_password: None,
+            strip_components: None,
+            expected_digest: None,
+            preserve_mtime: false,
+            require_regular_file: false,
+            completion_dirs: vec![],
+            man_dir: None,
+            bin_dir: None,
+            path_mapper: None,
+            progress: None,
+            on_existing: OnExisting::Overwrite,
+            temp_dir: None,
+            record: None,
+            fs: Arc::new(RealFs),
+        }
+    }
+
+    /// Sets the filesystem that the archive is extracted into. By default, this is the local
+    /// filesystem.
+    #[must_use]
+    pub fn filesystem(mut self, fs: Arc<dyn FileSystem>) -> Self {
+        self.fs = fs;
+        self
+    }
+
+    /// When this is set, the digest of the downloaded archive is checked against the given hex
+    /// digest. If they don't match, everything that was extracted is removed and an error is
+    /// returned. For tarballs, the digest is computed while the archive is extracted, so the file
+    /// is only read once. Zip files must be read before they are extracted, so the digest is
+    /// checked before extraction instead.
+    #[must_use]
+    pub fn expected_digest(mut self, expected_digest: Option<(ChecksumAlgorithm, String)>) -> Self {
+        self.expected_digest = expected_digest;
+        self
+    }
+
+    /// When this is true, the archive is extracted into a new directory under the system's temp
+    /// directory, and the completed tree is then moved into the install root. The staging
+    /// directory is created with the installer's filesystem, just like the install root. This
+    /// means that an interrupted extraction never leaves a partial tree in the install root, and it
+    /// can be much faster when the install root is on a slow filesystem.
+    #[must_use]
+    pub fn stage_in_temp_dir(mut self, stage_in_temp_dir: bool) -> Self {
+        self.stage_in_temp_dir = stage_in_temp_dir;
+        self
+    }
+
+    /// Sets the directory that staging directories are created in, for `stage_in_temp_dir` and
+    /// `flatten_executables`. By default, these are created in the system's temp directory. When
+    /// this is on the same filesystem as the install root, the extracted files are moved into
+    /// place with a rename instead of being copied. This must not be inside the install root.
+    #[must_use]
+    pub fn temp_dir(mut self, temp_dir: Option<PathBuf>) -> Self {
+        self.temp_dir = temp_dir;
+        self
+    }
+
+    /// When this is true, the archive is extracted into a new directory next to the install root,
+    /// and that directory then replaces the install root, along with everything that was in it.
+    /// The previous install root is only removed once the extraction has succeeded, so a failed
+    /// upgrade leaves the previous install in place. Since the new directory is next to the
+    /// install root, it's moved into place with a rename, rather than by copying.
+    ///
+    /// This cannot be combined with [`ArchiveInstaller::stage_in_temp_dir`].
+    #[must_use]
+    pub fn replace_install_root(mut self, replace_install_root: bool) -> Self {
+        self.replace_install_root = replace_install_root;
+        self
+    }
+
+    /// When this is true, the archive's directory structure is not kept. Instead, every executable
+    /// file in the archive, wherever it is, is written directly into the install root with its
+    /// file name. Directories and everything that isn't an executable are not installed. A file is
+    /// an executable if any of its execute bits are set, or if its name is one of the names given
+    /// to [`ArchiveInstaller::flatten_executable_names`]. It is an error for two executables in
+    /// the archive to have the same file name.
+    ///
+    /// This cannot be combined with installing shell completion files or man pages.
+    #[must_use]
+    pub fn flatten_executables(mut self, flatten_executables: bool) -> Self {
+        self.flatten_executables = flatten_executables;
+        self
+    }
+
+    /// Sets extra names of files that are treated as executables when
+    /// [`ArchiveInstaller::flatten_executables`] is enabled, even if they don't have an execute
+    /// bit set. This is useful for zip files made on Windows, which don't record file modes. A
+    /// name matches a file if it's the same as the file's name with or without its extension, so
+    /// `tool` matches `tool.exe`.
+    #[must_use]
+    pub fn flatten_executable_names(mut self, names: Vec<String>) -> Self {
+        self.flatten_executable_names = names;
+        self
+    }
+
+    /// When this is true, an `AppImage` is unpacked into the install root, so its contents can be
+    /// used on systems without FUSE. This runs the `AppImage` with its `--appimage-extract` flag,
+    /// so it only works when the `AppImage` can run on this platform. Since a dry run can't run
+    /// it, a dry run of installing an `AppImage` returns an error. By default, an `AppImage`
+    /// cannot be installed by this installer.
+    #[must_use]
+    pub fn extract_appimage(mut self, extract_appimage: bool) -> Self {
+        self.extract_appimage = extract_appimage;
+        self
+    }
+
+    /// When this is true, the files in a zip file are extracted across several threads, one for
+    /// each CPU. This can make extracting a large archive with many files, like a language SDK,
+    /// much faster. Directories and symlinks are still created in archive order before any files
+    /// are written. This has no effect on other kinds of archives, since they can only be read
+    /// from start to finish.
+    #[must_use]
+    pub fn parallel_zip_extraction(mut self, parallel_zip_extraction: bool) -> Self {
+        self.parallel_zip_extraction = parallel_zip_extraction;
+        self
+    }
+
+    /// Sets the password used to decrypt encrypted members of a zip file. Both the traditional zip
+    /// encryption and AES encryption are supported. Without a password, encrypted members are
+    /// skipped.
+    #[must_use]
+    pub fn zip_password(mut self, zip_password: Option<String>) -> Self {
+        self.zip_password = zip_password;
+        self
+    }
+
+    /// When this is set, this many leading components are removed from the path of each archive
+    /// member before it's extracted, like `tar --strip-components`. Members whose path has no
+    /// more components than this are not extracted. This takes precedence over moving the
+    /// contents of a single top-level directory up into the install root, which is not done when
+    /// this is set.
+    #[must_use]
+    pub fn strip_components(mut self, strip_components: Option<usize>) -> Self {
+        self.strip_components = strip_components;
+        self
+    }
+
+    /// When this is true, each archive member that is not extracted is logged as a warning. By
+    /// default, these are only logged at the debug level.
+    #[must_use]
+    pub fn report_skipped(mut self, report_skipped: bool) -> Self {
+        self.report_skipped = report_skipped;
+        self
+    }
+
+    /// When this is true, each file extracted from a tarball or zip file is given the
+    /// modification time recorded for it in the archive. By default, files have the time they
+    /// were extracted.
+    #[must_use]
+    pub fn preserve_mtime(mut self, preserve_mtime: bool) -> Self {
+        self.preserve_mtime = preserve_mtime;
+        self
+    }
+
+    /// When this is true, installing is an error unless the downloaded file is a regular file. A
+    /// symlink, FIFO, device, or directory is rejected when it's opened, by checking the opened
+    /// file rather than the path. By default, the file is opened without checking what it is.
+    #[must_use]
+    pub fn require_regular_file(mut self, require_regular_file: bool) -> Self {
+        self.require_regular_file = require_regular_file;
+        self
+    }
+
+    /// When this is set, shell completion files in a `completions` directory in the archive are
+    /// also copied into the directory given for their shell. See [`Shell::for_completion_file`]
+    /// for how these files are recognized. Completion files for shells without a directory are
+    /// left where they were extracted, and it's not an error for an archive to have none.
+    #[must_use]
+    pub fn completion_dirs(mut self, completion_dirs: Vec<(Shell, PathBuf)>) -> Self {
+        self.completion_dirs = completion_dirs;
+        self
+    }
+
+    /// When this is set, man pages in the archive are also copied into this directory, keeping
+    /// their section subdirectory, so `man/man1/project.1` is copied to `<man_dir>/man1/project.1`.
+    /// A man page is a file named like `*.N` or `*.N.gz` in a `man/manN` directory.
+    #[must_use]
+    pub fn man_dir(mut self, man_dir: Option<PathBuf>) -> Self {
+        self.man_dir = man_dir;
+        self
+    }
+
+    /// When this is set and the archive has a `bin` directory at the top of the install root,
+    /// each entry in that directory is linked into this directory, so the executables can be put
+    /// on the `PATH` while the rest of the archive stays in the install root. On Unix-like
+    /// systems these are symlinks to the entries in the install root. On Windows they are copies,
+    /// since creating a symlink there usually needs extra privileges. Anything already in this
+    /// directory with the same name is replaced.
+    #[must_use]
+    pub fn bin_dir(mut self, bin_dir: Option<PathBuf>) -> Self {
+        self.bin_dir = bin_dir;
+        self
+    }
+
+    /// When this is set, the callback decides where each archive member is extracted. It's called
+    /// with the member's path in the archive, after any leading components are removed by
+    /// [`ArchiveInstaller::strip_components`]. See [`PathMapper`] for what it returns. A returned
+    /// path that is absolute or that would end up outside of the install root is an error. The
+    /// contents of a single top-level directory are not moved up into the install root when this
+    /// is set, since the returned paths are already relative to the install root.
+    #[must_use]
+    pub fn path_mapper(mut self, path_mapper: Option<PathMapper>) -> Self {
+        self.path_mapper = path_mapper;
+        self
+    }
+
+    /// When this is set, the callback is called as each file in the archive is written. See
+    /// [`Progress`] for what it's called with.
+    #[must_use]
+    pub fn progress(mut self, progress: Option<Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets what happens when the install root already exists and is not empty. By default, the
+    /// archive is extracted into it, overwriting any files with the same paths.
+    #[must_use]
+    pub fn on_existing(mut self, on_existing: OnExisting) -> Self {
+        self.on_existing = on_existing;
+        self
+    }
+
+    // Returns true if the install root has things in it and we've been told to skip installing
+    // when it does.
+    fn skip_existing_install_root(&self) -> Result<bool> {
+        if self.on_existing == OnExisting::Overwrite {
+            return Ok(false);
+        }
+        let is_empty = match self.fs.read_dir(&self.install_root) {
+            Ok(entries) => entries.is_empty(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => true,
+            Err(e) => {
+                return Err(anyhow::Error::new(e))
+                    .with_context(|| format!("could not read {}", self.install_root.display()))
+            }
+        };
+        if is_empty {
+            return Ok(false);
+        }
+        if self.on_existing == OnExisting::Error {
+            return Err(anyhow!(
+                "cannot install into {} because it already exists and is not empty",
+                self.install_root.display(),
+            ));
+        }
+
+        Ok(true)
+    }
+
+    // Checks the download and extracts it into the install root, unless we've been told to skip
+    // an install root that isn't empty. This returns the archive members that were skipped.
+    fn install_into_root(&self, download: &Download) -> Result<Vec<SkippedMember>> {
+        check_download(download, self.require_regular_file)?;
+        if self.skip_existing_install_root()? {
+            info!(
+                "Skipped installing into {} because it is not empty",
+                self.install_root.display(),
+            );
+            return Ok(vec![]);
+        }
+
+        let skipped = self.extract_reporting_skipped(download)?;
+        info!(
+            "Installed contents of archive file into {}",
+            self.install_root.display()
+        );
+
+        Ok(skipped)
+    }
+
+    // Extracts the archive into the install root, and then installs completions, man pages, and
+    // bin dir links. Unlike `install`, this doesn't check the download or look at `on_existing`
+    // first. This returns every archive member that was not extracted, along with the reason it
+    // was skipped.
+    fn extract_reporting_skipped(&self, download: &Download) -> Result<Vec<SkippedMember>> {
+        let skipped = if self.flatten_executables {
+            self.extract_flattened(&download.archive_path)?
+        } else if self.replace_install_root {
+            self.extract_and_replace_install_root(&download.archive_path)?
+        } else if self.stage_in_temp_dir {
+            self.extract_via_staging_dir(&download.archive_path)?
+        } else {
+            self.extract_entire_archive(&download.archive_path, &self.install_root)?
+        };
+        for path in self.install_completions()? {
+            info!("Installed shell completion file at {}", path.display());
+        }
+        for path in self.install_man_pages()? {
+            info!("Installed man page at {}", path.display());
+        }
+        for path in self.link_bin_dir()? {
+            info!("Linked executable at {}", path.display());
+        }
+        for s in &skipped {
+            if self.report_skipped {
+                warn!("skipped archive member {} ({})", s.path.display(), s.reason);
+            } else {
+                debug!("skipped archive member {} ({})", s.path.display(), s.reason);
+            }
+        }
+
+        Ok(skipped)
+    }
+
+    // Copies the completion files in the install root into the directory for their shell,
+    // returning the paths of the copies.
+    fn install_completions(&self) -> Result<Vec<PathBuf>> {
+        if self.completion_dirs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut installed = vec![];
+        for (file, relative) in self.files_in_install_root()? {
+            let Some(shell) = Shell::for_completion_file(&relative) else {
+                continue;
+            };
+            let Some((_, dir)) = self.completion_dirs.iter().find(|(s, _)| *s == shell) else {
+                debug!(
+                    "not installing the {} completion file at {} because no directory was set for {}",
+                    shell.as_ref(),
+                    file.display(),
+                    shell.as_ref(),
+                );
+                continue;
+            };
+
+            // We checked that the path has a file name in `for_completion_file`.
+            let dest = dir.join(file.file_name().unwrap());
+            self.create_dir_all(dir)?;
+            copy_file(self.fs.as_ref(), &file, &dest)?;
+            installed.push(dest);
+        }
+
+        Ok(installed)
+    }
+
+    // Copies the man pages in the install root into the section's directory under the man dir,
+    // returning the paths of the copies.
+    fn install_man_pages(&self) -> Result<Vec<PathBuf>> {
+        let Some(man_dir) = &self.man_dir else {
+            return Ok(vec![]);
+        };
+
+        let mut installed = vec![];
+        for (file, relative) in self.files_in_install_root()? {
+            let Some(section) = man_page_section(&relative) else {
+                continue;
+            };
+
+            let section_dir = man_dir.join(format!("man{section}"));
+            // We checked that the path has a file name in `man_page_section`.
+            let dest = section_dir.join(file.file_name().unwrap());
+            self.create_dir_all(&section_dir)?;
+            copy_file(self.fs.as_ref(), &file, &dest)?;
+            installed.push(dest);
+        }
+
+        Ok(installed)
+    }
+
+    // Links each entry in the install root's `bin` dir into the bin dir, returning the paths of the
+    // links.
+    fn link_bin_dir(&self) -> Result<Vec<PathBuf>> {
+        let Some(bin_dir) = &self.bin_dir else {
+            return Ok(vec![]);
+        };
+        let archive_bin_dir = self.install_root.join("bin");
+        if !self
+            .fs
+            .metadata(&archive_bin_dir)
+            .is_ok_and(|m| m.kind == FileKind::Dir)
+        {
+            debug!(
+                "not linking any executables into {} because there is no directory at {}",
+                bin_dir.display(),
+                archive_bin_dir.display(),
+            );
+            return Ok(vec![]);
+        }
+
+        let mut entries = self.fs.read_dir(&archive_bin_dir)?;
+        entries.sort();
+        self.create_dir_all(bin_dir)?;
+        let mut linked = vec![];
+        for entry in entries {
+            if self.fs.metadata(&entry)?.kind == FileKind::Dir {
+                continue;
+            }
+            // Entries from `read_dir` always have a file name.
+            let dest = bin_dir.join(entry.file_name().unwrap());
+            remove_existing(self.fs.as_ref(), &dest)?;
+            if cfg!(target_family = "windows") {
+                copy_file(self.fs.as_ref(), &entry, &dest)?;
+            } else {
+                // A relative target would be resolved relative to the bin dir, not to where we
+                // are now.
+                self.create_symlink(&path::absolute(&entry)?, &dest)?;
+            }
+            linked.push(dest);
+        }
+
+        Ok(linked)
+    }
+
+    // Returns every regular file in the install root in sorted order, along with its path
+    // relative to the install root.
+    fn files_in_install_root(&self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut paths = vec![];
+        list_files(self.fs.as_ref(), &self.install_root, &mut paths)?;
+        paths.sort();
+
+        let mut files = vec![];
+        for path in paths {
+            if self.fs.metadata(&path)?.kind != FileKind::File {
+                continue;
+            }
+            let relative = path
+                .strip_prefix(&self.install_root)
+                .unwrap_or(&path)
+                .to_path_buf();
+            files.push((path, relative));
+        }
+
+        Ok(files)
+    }
+
+    fn extract_via_staging_dir(&self, downloaded_file: &Path) -> Result<Vec<SkippedMember>> {
+        let staging_dir = new_temp_dir(self.temp_dir.as_deref())?;
+        let staging_root = staging_dir.path().join("root");
+        debug!(
+            "extracting archive into staging directory at {}",
+            staging_root.display(),
+        );
+
+        let skipped = self.extract_entire_archive(downloaded_file, &staging_root)?;
+        move_tree(self.fs.as_ref(), &staging_root, &self.install_root)?;
+        self.record_moved(&staging_root, &self.install_root);
+
+        Ok(skipped)
+    }
+
+    // Extracts the archive into a directory next to the install root, and then swaps that in for
+    // the install root. If anything fails, the previous install root is left as it was.
+    fn extract_and_replace_install_root(
+        &self,
+        downloaded_file: &Path,
+    ) -> Result<Vec<SkippedMember>> {
+        let staging_root = sibling_path(&self.install_root, &format!("ubi-{}.tmp", process::id()))?;
+        debug!(
+            "extracting archive into {} before replacing {}",
+            staging_root.display(),
+            self.install_root.display(),
+        );
+        let skipped = match self.extract_entire_archive(downloaded_file, &staging_root) {
+            Ok(skipped) => skipped,
+            Err(e) => {
+                self.remove_staging_root(&staging_root);
+                return Err(e);
+            }
+        };
+
+        let old_root = sibling_path(&self.install_root, &format!("ubi-{}.old", process::id()))?;
+        let has_old_root = self.fs.metadata(&self.install_root).is_ok();
+        if has_old_root {
+            if let Err(e) = self.fs.rename(&self.install_root, &old_root) {
+                self.remove_staging_root(&staging_root);
+                return Err(anyhow::Error::new(e).context(format!(
+                    "could not move {} out of the way",
+                    self.install_root.display(),
+                )));
+            }
+        }
+        if let Err(e) = self.fs.rename(&staging_root, &self.install_root) {
+            if has_old_root {
+                self.fs.rename(&old_root, &self.install_root)?;
+            }
+            self.remove_staging_root(&staging_root);
+            return Err(anyhow::Error::new(e).context(format!(
+                "could not move {} to {}",
+                staging_root.display(),
+                self.install_root.display(),
+            )));
+        }
+        self.record_moved(&staging_root, &self.install_root);
+        if has_old_root {
+            debug!("removing the previous install at {}", old_root.display());
+            if let Err(e) = self.fs.remove_dir_all(&old_root) {
+                warn!(
+                    "could not remove the previous install at {}: {e}",
+                    old_root.display(),
+                );
+            }
+        }
+
+        Ok(skipped)
+    }
+
+    // This is best effort, since we're already on our way to returning an error.
+    fn remove_staging_root(&self, staging_root: &Path) {
+        if self.fs.metadata(staging_root).is_err() {
+            return;
+        }
+        if let Err(e) = self.fs.remove_dir_all(staging_root) {
+            debug!(
+                "could not remove the staging directory at {}: {e}",
+                staging_root.display(),
+            );
+        }
+    }
+
+    // Extracts the archive into a staging directory and then moves each executable in it into the
+    // install root. Everything else is reported as skipped.
+    fn extract_flattened(&self, downloaded_file: &Path) -> Result<Vec<SkippedMember>> {
+        let staging_dir = new_temp_dir(self.temp_dir.as_deref())?;
+        let staging_root = staging_dir.path().join("root");
+        debug!(
+            "extracting archive into staging directory at {} to find its executables",
+            staging_root.display(),
+        );
+
+        let mut skipped = self.extract_entire_archive(downloaded_file, &staging_root)?;
+        let mut paths = vec![];
+        list_files(self.fs.as_ref(), &staging_root, &mut paths)?;
+        paths.sort();
+
+        let mut flattened: HashMap<OsString, PathBuf> = HashMap::new();
+        for path in paths {
+            let relative = path
+                .strip_prefix(&staging_root)
+                .unwrap_or(&path)
+                .to_path_buf();
+            let Some(file_name) = path.file_name() else {
+                continue;
+            };
+            if !self.is_flattened_executable(&path)? {
+                skipped.push(SkippedMember {
+                    path: relative,
+                    reason: SkipReason::NotExecutable,
+                });
+                continue;
+            }
+            if let Some(other) = flattened.insert(file_name.to_os_string(), relative.clone()) {
+                return Err(anyhow!(
+                    "cannot flatten the executables {} and {} into {} because they have the same \
+                     file name",
+                    other.display(),
+                    relative.display(),
+                    self.install_root.display(),
+                ));
+            }
+
+            let dest = self.install_root.join(file_name);
+            debug!(
+                "moving executable {} to {}",
+                relative.display(),
+                dest.display()
+            );
+            self.create_dir_all(&self.install_root)?;
+            move_tree(self.fs.as_ref(), &path, &dest)?;
+            self.record_moved(&path, &dest);
+        }
+        // Everything that's left in the staging directory was skipped.
+        self.record_removed(&staging_root);
+
+        Ok(skipped)
+    }
+
+    fn is_flattened_executable(&self, path: &Path) -> Result<bool> {
+        let meta = self.fs.metadata(path)?;
+        if meta.kind != FileKind::File {
+            return Ok(false);
+        }
+        if meta.mode & 0o111 != 0 {
+            return Ok(true);
+        }
+        Ok([path.file_name(), path.file_stem()]
+            .into_iter()
+            .flatten()
+            .any(|name| {
+                self.flatten_executable_names
+                    .iter()
+                    .any(|n| OsStr::new(n) == name)
+            }))
+    }
+
+    fn extract_entire_appimage(
+        &self,
+        downloaded_file: &Path,
+        root: &Path,
+    ) -> Result<Vec<SkippedMember>> {
+        debug!("extracting entire AppImage");
+        let (_td, extracted) = appimage::extract(downloaded_file, self.temp_dir.as_deref())?;
+        self.create_dir_all(root)?;
+        for entry in RealFs.read_dir(&extracted)? {
+            let file_name = entry
+                .file_name()
+                .ok_or_else(|| anyhow!("{} has no file name", entry.display()))?;
+            let dest = root.join(file_name);
+            copy_tree(&RealFs, self.fs.as_ref(), &entry, &dest)?;
+            if self.record.is_some() {
+                let mut paths = vec![];
+                list_files(self.fs.as_ref(), &dest, &mut paths)?;
+                for path in &paths {
+                    self.record_extracted(path);
+                }
+            }
+        }
+
+        Ok(vec![])
+    }
+
+    fn extract_entire_archive(
+        &self,
+        downloaded_file: &Path,
+        root: &Path,
+    ) -> Result<Vec<SkippedMember>> {
+        let mut skipped = match Extension::from_path(downloaded_file)? {
+            Some(
+                Extension::Tar
+                | Extension::TarBr
+                | Extension::TarBz
+                | Extension::TarBz2
+                | Extension::TarGz
+                | Extension::TarLz
+                | Extension::TarLz4
+                | Extension::TarLzma
+                | Extension::TarXz
+                | Extension::TarZ
+                | Extension::TarZst
+                | Extension::Tbz
+                | Extension::Tgz
+                | Extension::Txz,
+            ) => self.extract_entire_tarball(downloaded_file, root)?,
+            Some(Extension::Zip) => {
+                self.verify_digest_before_extracting(downloaded_file)?;
+                self.extract_entire_zip(downloaded_file, root)?
+            }
+            Some(Extension::SevenZ) => {
+                self.verify_digest_before_extracting(downloaded_file)?;
+                self.extract_entire_7z(downloaded_file, root)?
+            }
+            Some(Extension::AppImage) if self.extract_appimage => {
+                self.verify_digest_before_extracting(downloaded_file)?;
+                self.extract_entire_appimage(downloaded_file, root)?
+            }
+            Some(
+                ext @ (Extension::A
+                | Extension::Ar
+                | Extension::Cab
+                | Extension::Deb
+                | Extension::Rpm),
+            ) => {
+                return Err(InstallError::UnsupportedFormat {
+                    path: downloaded_file.to_path_buf(),
+                    message: format!(
+                        "the downloaded release asset, {}, is a {} file, and we cannot extract all \
+                         of the contents of {} files",
+                        downloaded_file.display(),
+                        ext.extension_without_dot(),
+                        ext.extension_without_dot(),
+                    ),
+                }
+                .into())
+            }
+            _ => {
+                return Err(InstallError::UnsupportedFormat {
+                    path: downloaded_file.to_path_buf(),
+                    message: format!(
+                        "the downloaded release asset, {}, does not appear to be an archive file \
+                         so we cannopt extract all of its contents",
+                        downloaded_file.display(),
+                    ),
+                }
+                .into())
+            }
+        };
+
+        if let Some(n) = self.strip_components {
+            debug!("stripped {n} leading path components from each archive member");
+        } else if self.path_mapper.is_some() {
+            debug!("not looking for a common top-level directory because a path mapper was given");
+        } else if self.should_move_up_one_dir(root)? {
+            skipped.extend(self.move_contents_up_one_dir(root)?);
+        } else {
+            debug!("extracted archive did not contain a common top-level directory");
+        }
+
+        Ok(skipped)
+    }
+
+    fn extract_entire_tarball(
+        &self,
+        downloaded_file: &Path,
+        root: &Path,
+    ) -> Result<Vec<SkippedMember>> {
+        debug!("extracting entire tarball at {}", downloaded_file.display(),);
+
+        self.create_dir_all(root)?;
+
+        // When we have a digest to check, we hash the file as the decoder reads it, so that we
+        // don't have to read the whole archive a second time.
+        let (mut arch, hashing_reader) = match &self.expected_digest {
+            Some((algorithm, _)) => {
+                let reader = HashingReader::new(
+                    open_file(downloaded_file, self.require_regular_file)?,
+                    *algorithm,
+                );
+                (
+                    tar_archive_for(downloaded_file, Box::new(reader.clone()))?,
+                    Some(reader),
+                )
+            }
+            None => (
+                tar_reader_for(downloaded_file, self.require_regular_file)?,
+                None,
+            ),
+        };
+
+        let mut extracted: Vec<PathBuf> = vec![];
+        let mut skipped: Vec<SkippedMember> = vec![];
+        // Like `Archive::unpack`, we create directories after everything else, so that a directory
+        // with restrictive permissions doesn't stop us from extracting its contents.
+        let mut directories = vec![];
+        for entry in arch.entries()? {
+            let mut entry = entry?;
+            if entry.header().entry_type().is_dir() {
+                directories.push(entry);
+                continue;
+            }
+            self.unpack_tarball_entry(&mut entry, root, &mut extracted, &mut skipped)?;
+        }
+
+        directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+        for mut dir in directories {
+            self.unpack_tarball_entry(&mut dir, root, &mut extracted, &mut skipped)?;
+        }
+
+        if let (Some(reader), Some((algorithm, expect))) = (hashing_reader, &self.expected_digest) {
+            let got = reader.finish()?;
+            if !got.eq_ignore_ascii_case(expect) {
+                remove_extracted(self.fs.as_ref(), root, &extracted);
+                return Err(
+                    digest_mismatch_error(downloaded_file, *algorithm, expect, &got).into(),
+                );
+            }
+            debug!("the {} digest of the tarball matches", algorithm.as_ref());
+        }
+
+        Ok(skipped)
+    }
+
+    // We can't use `Entry::unpack_in` here, because it always writes to the local filesystem. So
+    // this does the parts of what it does that matter for release archives, using `self.fs`. This
+    // doesn't preserve mtimes or extended attributes, and hard links are installed as copies.
+    fn unpack_tarball_entry<R: Read>(
+        &self,
+        entry: &mut Entry<'_, R>,
+        root: &Path,
+        extracted: &mut Vec<PathBuf>,
+        skipped: &mut Vec<SkippedMember>,
+    ) -> Result<()> {
+        let path = entry.path()?.into_owned();
+        let stripped = match self.member_out_rel_path(&path) {
+            Ok(stripped) => stripped,
+            Err(reason) => {
+                if !entry.header().entry_type().is_dir() {
+                    skipped.push(SkippedMember { path, reason });
+                }
+                return Ok(());
+            }
+        };
+        let Some(out_path) = self.enclosed_out_path(root, &stripped)? else {
+            skipped.push(SkippedMember {
+                path,
+                reason: SkipReason::TraversalRejected,
+            });
+            return Ok(());
+        };
+        // This is an entry for the root itself, like `./`.
+        if out_path == root {
+            return Ok(());
+        }
+
+        let entry_type = entry.header().entry_type();
+        let mode = entry.header().mode()? & 0o777;
+        if entry_type.is_dir() {
+            self.create_dir_all(&out_path)?;
+        } else if entry_type.is_symlink() {
+            let Some(target) = entry.link_name()? else {
+                return Err(anyhow!("symlink entry {} has no target", path.display()));
+            };
+            if !symlink_is_enclosed(root, &out_path, &target) {
+                skipped.push(SkippedMember {
+                    path,
+                    reason: SkipReason::UnsafeSymlink,
+                });
+                return Ok(());
+            }
+            self.create_parent_dir(&out_path)?;
+            self.create_symlink(&target, &out_path)?;
+            self.record_extracted(&out_path);
+            extracted.push(out_path);
+            return Ok(());
+        } else if entry_type.is_hard_link() {
+            let Some(link_name) = entry.link_name()? else {
+                return Err(anyhow!("hard link entry {} has no target", path.display()));
+            };
+            let Some(link_name) = self.member_rel_path(&link_name) else {
+                return Err(anyhow!(
+                    "hard link entry {} has a target, {}, with too few path components to strip",
+                    path.display(),
+                    link_name.display(),
+                ));
+            };
+            let Some(link_path) = self.enclosed_out_path(root, &link_name)? else {
+                skipped.push(SkippedMember {
+                    path,
+                    reason: SkipReason::TraversalRejected,
+                });
+                return Ok(());
+            };
+            self.create_parent_dir(&out_path)?;
+            copy_file(self.fs.as_ref(), &link_path, &out_path)?;
+            self.record_extracted(&out_path);
+        } else if tar_entry_is_file(entry_type) {
+            self.create_parent_dir(&out_path)?;
+            let size = entry.size();
+            self.write_file(&out_path, entry, Some(size))?;
+        } else {
+            skipped.push(SkippedMember {
+                path,
+                reason: SkipReason::UnsupportedType,
+            });
+            return Ok(());
+        }
+
+        self.fs
+            .set_permissions(&out_path, mode)
+            .with_context(|| format!("could not set permissions for {}", out_path.display()))?;
+        // We don't set this for directories, since extracting anything else into a directory
+        // changes its modification time anyway.
+        if self.preserve_mtime && !entry_type.is_dir() {
+            self.set_modified(&out_path, tar_entry_mtime(entry)?)?;
+        }
+        extracted.push(out_path);
+
+        Ok(())
+    }
+
+    // Returns the path relative to the install root that an archive member should be extracted to,
+    // or `None` if it shouldn't be extracted. This strips leading components and then applies the
+    // path mapper, if there is one. The path still needs to be checked with `enclosed_out_path`.
+    fn member_rel_path<'p>(&self, path: &'p Path) -> Option<Cow<'p, Path>> {
+        let stripped = self.strip_leading_components(path)?;
+        let Some(mapper) = &self.path_mapper else {
+            return Some(stripped);
+        };
+        match (mapper.0)(&stripped) {
+            Some(new_path) => {
+                debug!(
+                    "the path mapper relocated archive member {} to {}",
+                    path.display(),
+                    new_path.display(),
+                );
+                Some(Cow::Owned(new_path))
+            }
+            None => Some(stripped),
+        }
+    }
+
+    // Like `member_rel_path`, but this also skips junk, and returns the reason a member isn't
+    // extracted. Directories that are skipped aren't reported by the callers, since anything in
+    // them is skipped for the same reason and reported on its own.
+    fn member_out_rel_path<'p>(&self, path: &'p Path) -> Result<Cow<'p, Path>, SkipReason> {
+        if is_junk_member(path) {
+            debug!(
+                "not extracting archive member {} because it is macOS metadata",
+                path.display()
+            );
+            return Err(SkipReason::Junk);
+        }
+        self.member_rel_path(path).ok_or(SkipReason::Filtered)
+    }
+
+    // Removes `strip_components` leading components from an archive member's path. A leading `./`
+    // isn't counted. This returns `None` when the path doesn't have more components than that, so
+    // there's nothing left to extract. Absolute paths are returned as is, so that
+    // `enclosed_out_path` rejects them.
+    fn strip_leading_components<'p>(&self, path: &'p Path) -> Option<Cow<'p, Path>> {
+        let Some(n) = self.strip_components else {
+            return Some(Cow::Borrowed(path));
+        };
+        if path.has_root() {
+            return Some(Cow::Borrowed(path));
+        }
+
+        let stripped = path
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .skip(n)
+            .collect::<PathBuf>();
+        if stripped.as_os_str().is_empty() {
+            debug!(
+                "not extracting archive member {} because it has {n} or fewer path components",
+                path.display(),
+            );
+            return None;
+        }
+
+        Some(Cow::Owned(stripped))
+    }
+
+    // Returns the path under `root` that an archive member with the given path should be extracted
+    // to. This returns an error if the member's path is absolute or if, after resolving any `..`
+    // components, it would end up outside of `root`. It returns `None` if the member would be
+    // written through a symlink, since a symlink extracted earlier could point anywhere.
+    fn enclosed_out_path(&self, root: &Path, path: &Path) -> Result<Option<PathBuf>> {
+        let mut out_path = root.to_path_buf();
+        let mut depth = 0;
+        for component in path.components() {
+            match component {
+                Component::Normal(c) => {
+                    if self
+                        .fs
+                        .metadata(&out_path)
+                        .is_ok_and(|m| m.kind == FileKind::Symlink)
+                    {
+                        return Ok(None);
+                    }
+                    out_path.push(c);
+                    depth += 1;
+                }
+                Component::ParentDir => {
+                    if depth == 0 {
+                        return Err(InstallError::PathTraversal {
+                            member: path.to_path_buf(),
+                            root: root.to_path_buf(),
+                        }
+                        .into());
+                    }
+                    out_path.pop();
+                    depth -= 1;
+                }
+                Component::Prefix(_) | Component::RootDir => {
+                    return Err(InstallError::PathTraversal {
+                        member: path.to_path_buf(),
+                        root: root.to_path_buf(),
+                    }
+                    .into());
+                }
+                Component::CurDir => (),
+            }
+        }
+
+        Ok(Some(out_path))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.fs
+            .create_dir_all(path)
+            .with_context(|| format!("could not create a directory at {}", path.display()))
+    }
+
+    fn create_parent_dir(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            self.create_dir_all(parent)?;
+        }
+        Ok(())
+    }
+
+    fn create_file(&self, path: &Path) -> Result<Box<dyn Write + '_>> {
+        self.fs
+            .create_file(path)
+            .with_context(|| format!("Cannot write to {}", path.display()))
+    }
+
+    fn write_file(&self, path: &Path, reader: &mut dyn Read, size: Option<u64>) -> Result<()> {
+        let mut file = self.create_file(path)?;
+        copy_with_progress(reader, &mut file, self.progress.as_ref(), size)
+            .with_context(|| format!("could not write to {}", path.display()))?;
+        self.record_extracted(path);
+        Ok(())
+    }
+
+    fn record_extracted(&self, path: &Path) {
+        if let Some(record) = &self.record {
+            record.lock().unwrap().extracted.push(ExtractedPath {
+                path: path.to_path_buf(),
+                moved_from: None,
+            });
+        }
+    }
+
+    // Updates the recorded paths at or under `from` to be under `to` instead, since they've been
+    // moved there.
+    fn record_moved(&self, from: &Path, to: &Path) {
+        let Some(record) = &self.record else {
+            return;
+        };
+        for e in &mut record.lock().unwrap().extracted {
+            if let Ok(rest) = e.path.strip_prefix(from) {
+                e.path = if rest.as_os_str().is_empty() {
+                    to.to_path_buf()
+                } else {
+                    to.join(rest)
+                };
+            }
+        }
+    }
+
+    // Forgets the recorded paths at or under `path`, since they've been removed.
+    fn record_removed(&self, path: &Path) {
+        if let Some(record) = &self.record {
+            record
+                .lock()
+                .unwrap()
+                .extracted
+                .retain(|e| !e.path.starts_with(path));
+        }
+    }
+
+    fn set_modified(&self, path: &Path, modified: SystemTime) -> Result<()> {
+        self.fs
+            .set_modified(path, modified)
+            .with_context(|| format!("could not set the modification time for {}", path.display()))
+    }
+
+    fn create_symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        create_symlink(self.fs.as_ref(), target, link)
+    }
+
+    fn verify_digest_before_extracting(&self, downloaded_file: &Path) -> Result<()> {
+        let Some((algorithm, expect)) = &self.expected_digest else {
+            return Ok(());
+        };
+
+        let got = algorithm.hex_digest(open_file(downloaded_file, self.require_regular_file)?)?;
+        if !got.eq_ignore_ascii_case(expect) {
+            return Err(digest_mismatch_error(downloaded_file, *algorithm, expect, &got).into());
+        }
+        debug!("the {} digest of the archive matches", algorithm.as_ref());
+
+        Ok(())
+    }
+
+    // We do this because some projects use a top-level dir like `project-x86-64-Linux`, which is
+    // pretty annoying to work with. In this case, it's a lot easier to install this into
+    // `~/bin/project` so the directory tree ends up with the same structure on all platforms.
+    fn should_move_up_one_dir(&self, root: &Path) -> Result<bool> {
+        let mut prefixes: HashSet<OsString> = HashSet::new();
+        for full_path in self.fs.read_dir(root).with_context(|| {
+            format!(
+                "could not read {} after unpacking the tarball into this directory",
+                root.display(),
+            )
+        })? {
+            // If the entry is a file in the top-level of the install dir, then there's no common
+            // directory prefix.
+            if self
+                .fs
+                .metadata(&full_path)
+                .with_context(|| format!("could not get metadata for {}", full_path.display()))?
+                .kind
+                == FileKind::File
+                && full_path
+                    .parent()
+                    .expect("path of entry in install root somehow has no parent")
+                    == root
+            {
+                return Ok(false);
+            }
+
+            let path = if let Ok(path) = full_path.strip_prefix(root) {
+                path
+            } else {
+                &full_path
+            };
+
+            // Tarballs made with something like `tar -C dir .` prefix every member with `./`, and
+            // `.` shouldn't count as a common top-level directory.
+            if let Some(prefix) = path.components().find(|c| *c != Component::CurDir) {
+                prefixes.insert(prefix.as_os_str().to_os_string());
+            } else {
+                return Err(anyhow!("directory entry has no path components"));
+            }
+        }
+
+        // If all the entries
+        Ok(prefixes.len() == 1)
+    }
+
+    // Returns any symlinks that were removed because they would point outside of `path` once
+    // they've been moved up.
+    fn move_contents_up_one_dir(&self, path: &Path) -> Result<Vec<SkippedMember>> {
+        let entries = self.fs.read_dir(path)?;
+        let Some(top_level_path) = entries.into_iter().next() else {
+            return Err(anyhow!("no directory found in path"));
+        };
+
+        // A symlink like `project/bin/tool -> ../../other` stays inside the root when it's
+        // extracted, but it would point outside of it after we remove the `project` directory.
+        let mut skipped = vec![];
+        let mut links = vec![];
+        find_symlinks(self.fs.as_ref(), &top_level_path, &mut links)?;
+        for link in links {
+            if symlink_is_enclosed(&top_level_path, &link, &self.fs.read_link(&link)?) {
+                continue;
+            }
+            self.fs.remove_file(&link)?;
+            self.record_removed(&link);
+            skipped.push(SkippedMember {
+                path: link.strip_prefix(path).unwrap_or(&link).to_path_buf(),
+                reason: SkipReason::UnsafeSymlink,
+            });
+        }
+
+        debug!(
+            "moving extracted archive contents up one directory from {} to {}",
+            top_level_path.display(),
+            path.display(),
+        );
+
+        // When the directory has an entry with its own name, like `project/project`, that entry
+        // can't be moved up until the directory is out of its way.
+        let top_level_name = top_level_path
+            .file_name()
+            .ok_or_else(|| anyhow!("{} has no file name", top_level_path.display()))?;
+        if let Some(record) = &self.record {
+            let mut record = record.lock().unwrap();
+            record.moved_up_dir = Some(PathBuf::from(top_level_name));
+            for e in &mut record.extracted {
+                if e.path.starts_with(&top_level_path) {
+                    e.moved_from = e.path.strip_prefix(path).ok().map(Path::to_path_buf);
+                }
+            }
+        }
+        let source_dir = if self
+            .fs
+            .metadata(&top_level_path.join(top_level_name))
+            .is_ok()
+        {
+            let renamed = unused_path(self.fs.as_ref(), &top_level_path, "ubi-move");
+            debug!(
+                "renaming {} to {} so that its contents can be moved up",
+                top_level_path.display(),
+                renamed.display(),
+            );
+            self.fs.rename(&top_level_path, &renamed)?;
+            self.record_moved(&top_level_path, &renamed);
+            renamed
+        } else {
+            top_level_path
+        };
+
+        for entry in self.fs.read_dir(&source_dir)? {
+            let target = path.join(
+                entry
+                    .file_name()
+                    .ok_or_else(|| anyhow!("{} has no file name", entry.display()))?,
+            );
+            // On Unix, renaming a file over another file replaces it, but renaming onto an
+            // existing directory fails unless it's empty, and on Windows renaming onto most
+            // existing paths fails. Removing whatever is there first behaves the same everywhere.
+            remove_existing(self.fs.as_ref(), &target)?;
+            self.fs.rename(&entry, &target).with_context(|| {
+                format!("could not move {} to {}", entry.display(), target.display())
+            })?;
+            self.record_moved(&entry, &target);
+        }
+
+        self.fs.remove_dir(&source_dir)?;
+
+        Ok(skipped)
+    }
+
+    fn extract_entire_zip(
+        &self,
+        downloaded_file: &Path,
+        root: &Path,
+    ) -> Result<Vec<SkippedMember>> {
+        debug!(
+            "extracting entire zip file at {}",
+            downloaded_file.display(),
+        );
+
+        let mut zip = open_zip(downloaded_file, self.require_regular_file)?;
+        let mut skipped: Vec<SkippedMember> = vec![];
+        // When extracting in parallel, this holds the index and output path of each file to write.
+        let mut files: Vec<(usize, PathBuf)> = vec![];
+        for i in 0..zip.len() {
+            // We look at the raw entry first because `by_index` returns an error for encrypted
+            // entries.
+            let (name, encrypted, is_dir) = {
+                let zf = zip.by_index_raw(i)?;
+                (zip_entry_path(zf.name()), zf.encrypted(), zf.is_dir())
+            };
+            let stripped = match self.member_out_rel_path(&name) {
+                Ok(stripped) => stripped,
+                Err(reason) => {
+                    if !is_dir {
+                        skipped.push(SkippedMember { path: name, reason });
+                    }
+                    continue;
+                }
+            };
+            let out_path = self.enclosed_out_path(root, &stripped)?;
+            if encrypted && self.zip_password.is_none() {
+                skipped.push(SkippedMember {
+                    path: name,
+                    reason: SkipReason::Encrypted,
+                });
+                continue;
+            }
+            let Some(out_path) = out_path else {
+                skipped.push(SkippedMember {
+                    path: name,
+                    reason: SkipReason::TraversalRejected,
+                });
+                continue;
+            };
+
+            let mut zf = zip_entry(&mut zip, i, self.zip_password.as_deref())?;
+            if zf.is_dir() {
+                self.create_dir_all(&out_path)?;
+                continue;
+            }
+            self.create_parent_dir(&out_path)?;
+
+            if zf.is_symlink() {
+                let mut target = String::new();
+                zf.read_to_string(&mut target)?;
+                if !symlink_is_enclosed(root, &out_path, Path::new(&target)) {
+                    skipped.push(SkippedMember {
+                        path: name,
+                        reason: SkipReason::UnsafeSymlink,
+                    });
+                    continue;
+                }
+                self.create_symlink(Path::new(&target), &out_path)?;
+                self.record_extracted(&out_path);
+                continue;
+            }
+
+            if self.parallel_zip_extraction {
+                files.push((i, out_path));
+                continue;
+            }
+            self.write_zip_entry(&mut zf, &out_path)?;
+        }
+
+        if !files.is_empty() {
+            self.write_zip_entries_in_parallel(downloaded_file, &files)?;
+        }
+
+        Ok(skipped)
+    }
+
+    fn write_zip_entry(&self, zf: &mut ZipFile, out_path: &Path) -> Result<()> {
+        let size = zf.size();
+        self.write_file(out_path, zf, Some(size))?;
+        if let Some(mode) = zf.unix_mode() {
+            self.fs.set_permissions(out_path, mode)?;
+        }
+        if let Some(modified) = self.preserve_mtime.then(|| zip_file_mtime(zf)).flatten() {
+            self.set_modified(out_path, modified)?;
+        }
+        Ok(())
+    }
+
+    // A `ZipArchive` can only read one entry at a time, so each thread opens the zip file itself.
+    // The threads take the next file to write from a shared counter, so one big file doesn't hold
+    // up the files after it.
+    fn write_zip_entries_in_parallel(
+        &self,
+        downloaded_file: &Path,
+        files: &[(usize, PathBuf)],
+    ) -> Result<()> {
+        let threads = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(files.len());
+        debug!(
+            "extracting {} zip file entries with {threads} threads",
+            files.len(),
+        );
+
+        let next = AtomicUsize::new(0);
+        thread::scope(|s| {
+            let handles = (0..threads)
+                .map(|_| {
+                    s.spawn(|| -> Result<()> {
+                        let mut zip = open_zip(downloaded_file, self.require_regular_file)?;
+                        while let Some((i, out_path)) =
+                            files.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            self.write_zip_entry(
+                                &mut zip_entry(&mut zip, *i, self.zip_password.as_deref())?,
+                                out_path,
+                            )?;
+                        }
+                        Ok(())
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles.into_iter().try_for_each(|h| {
+                h.join()
+                    .map_err(|_| anyhow!("a thread extracting the zip file panicked"))?
+            })
+        })
+    }
+
+    fn extract_entire_7z(&self, downloaded_file: &Path, root: &Path) -> Result<Vec<SkippedMember>> {
+        debug!("extracting entire 7z file at {}", downloaded_file.display(),);
+
+        self.create_dir_all(root)?;
+
+        let mut sz = open_7z(downloaded_file, self.require_regular_file)?;
+        let mut skipped: Vec<SkippedMember> = vec![];
+        for_each_7z_entry(&mut sz, |entry, reader| {
+            // Anti-items mark files that were deleted in an update to an archive, so there's
+            // nothing to extract.
+            if entry.is_anti_item() {
+                return Ok(true);
+            }
+
+            let name = PathBuf::from(entry.name());
+            let stripped = match self.member_out_rel_path(&name) {
+                Ok(stripped) => stripped,
+                Err(reason) => {
+                    if !entry.is_directory() {
+                        skipped.push(SkippedMember { path: name, reason });
+                    }
+                    return Ok(true);
+                }
+            };
+            let Some(out_path) = self.enclosed_out_path(root, &stripped)? else {
+                skipped.push(SkippedMember {
+                    path: name,
+                    reason: SkipReason::TraversalRejected,
+                });
+                return Ok(true);
+            };
+
+            if entry.is_directory() {
+                self.create_dir_all(&out_path)?;
+                return Ok(true);
+            }
+
+            self.create_parent_dir(&out_path)?;
+            self.write_file(&out_path, reader, Some(entry.size()))?;
+            // Archivers that run on Unix, like p7zip, store the file's mode in the high 16 bits of
+            // the attributes and set this flag to say they've done so.
+            if entry.has_windows_attributes && entry.windows_attributes() & 0x8000 != 0 {
+                self.fs
+                    .set_permissions(&out_path, (entry.windows_attributes() >> 16) & 0o777)?;
+            }
+
+            Ok(true)
+        })?;
+
+        Ok(skipped)
+    }
+}
+
+impl Installer for ArchiveInstaller {
+    // This returns the top-level entries in the install root once the archive has been extracted.
+    // If the install root already had other things in it, those are included too.
+    fn install(&self, download: &Download) -> Result<Vec<PathBuf>, InstallError> {
+        Ok(self.install_reporting_skipped(download)?.0)
+    }
+
+    fn install_reporting_skipped(
+        &self,
+        download: &Download,
+    ) -> Result<(Vec<PathBuf>, Vec<SkippedMember>), InstallError> {
+        let skipped = self.install_into_root(download)?;
+
+        let mut entries = self.fs.read_dir(&self.install_root).with_context(|| {
+            format!(
+                "could not read {} after installing into it",
+                self.install_root.display(),
+            )
+        })?;
+        entries.sort();
+
+        Ok((entries, skipped))
+    }
+
+    // Nothing is extracted when the install root is skipped because it's not empty, so the report
+    // is empty too.
+    fn install_with_extraction_report(
+        &self,
+        download: &Download,
+    ) -> Result<ExtractionReport, InstallError> {
+        let record = Arc::new(Mutex::new(ExtractionReport::default()));
+        let mut installer = self.clone();
+        installer.record = Some(record.clone());
+        let skipped = installer.install_into_root(download)?;
+
+        let mut report = mem::take(&mut *record.lock().unwrap());
+        for e in &mut report.extracted {
+            if let Ok(path) = e.path.strip_prefix(&self.install_root) {
+                e.path = path.to_path_buf();
+            }
+        }
+        report.extracted.sort_by(|a, b| a.path.cmp(&b.path));
+        report.skipped = skipped;
+
+        Ok(report)
+    }
+
+    fn install_dry_run(&self, download: &Download) -> Result<Vec<PathBuf>, InstallError> {
+        check_download(download, self.require_regular_file)?;
+        // The only way to extract an AppImage is to run it, and a dry run shouldn't run anything
+        // that was downloaded.
+        if self.extract_appimage
+            && matches!(
+                Extension::from_path(&download.archive_path),
+                Ok(Some(Extension::AppImage))
+            )
+        {
+            return Err(anyhow!(
+                "cannot do a dry run of extracting {} because extracting an AppImage requires \
+                 running it",
+                download.archive_path.display(),
+            )
+            .into());
+        }
+        if self.skip_existing_install_root()? {
+            info!(
+                "Would skip installing into {} because it is not empty",
+                self.install_root.display(),
+            );
+            return Ok(vec![]);
+        }
+
+        // Existing paths come from our real filesystem, but only the paths this install writes
+        // are reported.
+        let fs = Arc::new(DryRunFs::new(self.fs.clone()));
+        let installer = self.clone().filesystem(fs.clone());
+        installer.extract_reporting_skipped(download)?;
+
+        let mut paths = fs.written_under(&self.install_root);
+        let extra_dirs = self.completion_dirs.iter().map(|(_, dir)| dir);
+        for dir in extra_dirs.chain(&self.man_dir).chain(&self.bin_dir) {
+            if !dir.starts_with(&self.install_root) {
+                paths.extend(fs.written_under(dir));
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        for path in &paths {
+            info!("Would install {}", path.display());
+        }
+
+        Ok(paths)
+    }
+
+    // This counts every file in the archive, including any that `strip_components` or a path
+    // mapper would skip.
+    fn estimated_size(&self, download: &Download) -> Result<u64, InstallError> {
+        let path = &download.archive_path;
+        check_file_format(path, open_file(path, self.require_regular_file)?)?;
+        let size = match Extension::from_path(path)? {
+            Some(
+                Extension::Tar
+                | Extension::TarBr
+                | Extension::TarBz
+                | Extension::TarBz2
+                | Extension::TarGz
+                | Extension::TarLz
+                | Extension::TarLz4
+                | Extension::TarLzma
+                | Extension::TarXz
+                | Extension::TarZ
+                | Extension::TarZst
+                | Extension::Tbz
+                | Extension::Tgz
+                | Extension::Txz,
+            ) => tarball_files_size(path, self.require_regular_file)?,
+            Some(Extension::Zip) => zip_files_size(path, self.require_regular_file)?,
+            Some(Extension::SevenZ) => sevenz_files_size(path, self.require_regular_file)?,
+            _ => {
+                return Err(InstallError::UnsupportedFormat {
+                    path: path.clone(),
+                    message: format!(
+                        "cannot estimate the installed size of {} because it is not a tarball, \
+                         zip file, or 7z file",
+                        path.display(),
+                    ),
+                })
+            }
+        };
+        debug!(
+            "estimated installed size of {} is {size} bytes",
+            path.display()
+        );
+
+        Ok(size)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(temp_dir) = &self.temp_dir {
+            if temp_dir.starts_with(&self.install_root) {
+                return Err(anyhow!(
+                    "the temp dir, {}, cannot be inside the install root, {}",
+                    temp_dir.display(),
+                    self.install_root.display(),
+                ));
+            }
+        }
+        if self.replace_install_root && self.stage_in_temp_dir {
+            return Err(anyhow!(
+                "cannot stage the extraction in a temp dir when replacing the install root"
+            ));
+        }
+        if self.flatten_executables && !self.completion_dirs.is_empty() {
+            return Err(anyhow!(
+                "cannot install shell completion files when flattening executables"
+            ));
+        }
+        if self.flatten_executables && self.man_dir.is_some() {
+            return Err(anyhow!(
+                "cannot install man pages when flattening executables"
+            ));
+        }
+        if self.flatten_executables && self.bin_dir.is_some() {
+            return Err(anyhow!(
+                "cannot link executables into a bin dir when flattening executables"
+            ));
+        }
+        if let Some((algorithm, digest)) = &self.expected_digest {
+            if !algorithm.is_valid_hex_digest(digest) {
+                return Err(anyhow!(
+                    "the expected archive digest, `{}`, is not a valid {} hex digest",
+                    digest,
+                    algorithm.as_ref(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists every file in the archive at `archive_path` that might be an executable, in archive
+/// order. A file is included if it's marked as executable, or if it doesn't have an extension that
+/// `ubi` knows is not an executable's, like `.md` or `.tar.gz`. Unlike [`crate::Ubi::scan_archive`],
+/// this doesn't look at the names of the files at all, so it can be used to offer a choice of
+/// executables to install.
+///
+/// # Errors
+///
+/// This returns an error if the archive is not a tarball or zip file, or if it cannot be read.
+pub fn executable_candidates(archive_path: &Path) -> Result<Vec<ExecutableCandidate>> {
+    match Extension::from_path(archive_path)? {
+        Some(
+            Extension::Tar
+            | Extension::TarBr
+            | Extension::TarBz
+            | Extension::TarBz2
+            | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
+            | Extension::TarXz
+            | Extension::TarZ
+            | Extension::TarZst
+            | Extension::Tbz
+            | Extension::Tgz
+            | Extension::Txz,
+        ) => tarball_executable_candidates(archive_path),
+        Some(Extension::Zip) => zip_executable_candidates(archive_path),
+        _ => Err(anyhow!(
+            "cannot list the executables in {} because it is not a tarball or zip file",
+            archive_path.display(),
+        )),
+    }
+}
+
+fn tarball_executable_candidates(archive_path: &Path) -> Result<Vec<ExecutableCandidate>> {
+    let mut arch = tar_reader_for(archive_path, false)?;
+    let mut candidates = vec![];
+    for entry in arch.entries()? {
+        let entry = entry?;
+        if !tar_entry_is_file(entry.header().entry_type()) {
+            continue;
+        }
+        let path = entry.path()?;
+        candidates.extend(executable_candidate(
+            &path,
+            entry.header().mode().ok(),
+            entry.size(),
+        ));
+    }
+
+    Ok(candidates)
+}
+
+fn zip_executable_candidates(archive_path: &Path) -> Result<Vec<ExecutableCandidate>> {
+    let mut zip = open_zip(archive_path, false)?;
+    let mut candidates = vec![];
+    for i in 0..zip.len() {
+        let zf = zip.by_index_raw(i)?;
+        if !zf.is_file() {
+            continue;
+        }
+        candidates.extend(executable_candidate(
+            &zip_entry_path(zf.name()),
+            zf.unix_mode(),
+            zf.size(),
+        ));
+    }
+
+    Ok(candidates)
+}
+
+// Returns the total size of the files in a tarball, as recorded in their headers.
+fn tarball_files_size(archive_path: &Path, require_regular_file: bool) -> Result<u64> {
+    let mut arch = tar_reader_for(archive_path, require_regular_file)?;
+    let mut size = 0;
+    for entry in arch.entries()? {
+        let entry = entry?;
+        if tar_entry_is_file(entry.header().entry_type()) {
+            size += entry.size();
+        }
+    }
+
+    Ok(size)
+}
+
+fn zip_files_size(archive_path: &Path, require_regular_file: bool) -> Result<u64> {
+    let mut zip = open_zip(archive_path, require_regular_file)?;
+    let mut size = 0;
+    for i in 0..zip.len() {
+        let zf = zip.by_index_raw(i)?;
+        if zf.is_file() {
+            size += zf.size();
+        }
+    }
+
+    Ok(size)
+}
+
+fn sevenz_files_size(archive_path: &Path, require_regular_file: bool) -> Result<u64> {
+    let sz = open_7z(archive_path, require_regular_file)?;
+    Ok(sz
+        .archive()
+        .files
+        .iter()
+        .filter(|e| !e.is_directory() && !e.is_anti_item())
+        .map(SevenZArchiveEntry::size)
+        .sum())
+}
+
+fn executable_candidate(path: &Path, mode: Option<u32>, size: u64) -> Option<ExecutableCandidate> {
+    let is_executable = mode.is_some_and(|m| m & 0o111 != 0)
+        || path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("exe") || e.eq_ignore_ascii_case("bat"));
+    // A file without an extension might be an executable, as might a file with an extension that
+    // we install as is, like `.AppImage` or `.jar`.
+    let might_be_executable = match Extension::from_path(path) {
+        Ok(None) => true,
+        Ok(Some(ext)) => ext.should_preserve_extension_on_install(),
+        Err(_) => false,
+    };
+    if !is_executable && !might_be_executable {
+        return None;
+    }
+
+    Some(ExecutableCandidate {
+        path: path.to_string_lossy().into_owned(),
+        size,
+        is_executable,
+    })
+}
+
+fn is_plain_tarball(downloaded_file: &Path) -> bool {
+    matches!(
+        Extension::from_path(downloaded_file),
+        Ok(Some(Extension::Tar))
+    )
+}
+
+fn tar_reader_for(
+    downloaded_file: &Path,
+    require_regular_file: bool,
+) -> Result<Archive<Box<dyn Read>>> {
+    tar_archive_for(
+        downloaded_file,
+        Box::new(open_file(downloaded_file, require_regular_file)?),
+    )
+}
+
+// Opens the download once and runs every check that has to pass before anything is extracted from
+// it, reading from that one handle: that it's a regular file, when we require one, that it matches
+// its digest and minisign signature, and that it looks like the format its extension says it is.
+fn check_download(download: &Download, require_regular_file: bool) -> Result<()> {
+    // There's no point in opening a file with an extension we can't install from.
+    Extension::from_path(&download.archive_path)?;
+    let mut file = open_file(&download.archive_path, require_regular_file)?;
+    download.verify_digest(&mut file)?;
+    file.rewind()?;
+    download.verify_minisign_signature(&mut file)?;
+    file.rewind()?;
+    check_file_format(&download.archive_path, file)
+}
+
+// Checks that the downloaded file starts with the magic bytes for the format its extension says it
+// is. When a release URL returns an error page, that page gets saved as if it were the release
+// asset, and without this check the error would come from deep inside a decoder.
+fn check_file_format(path: &Path, file: impl Read) -> Result<()> {
+    let Ok(Some(ext)) = Extension::from_path(path) else {
+        return Ok(());
+    };
+    let magic = ext.magic_bytes();
+    if magic.is_empty() {
+        return Ok(());
+    }
+
+    let mut start = Vec::with_capacity(512);
+    file.take(512).read_to_end(&mut start)?;
+    if magic.iter().any(|m| start.starts_with(m)) {
+        return Ok(());
+    }
+
+    let starts_with = first_line_of_text(&start)
+        .map(|line| format!(", it starts with `{line}`"))
+        .unwrap_or_default();
+    Err(anyhow!(
+        "the downloaded file at {} does not look like a {} file{starts_with} - this can happen when \
+         the download URL returns an error page instead of the release asset",
+        path.display(),
+        ext.extension(),
+    ))
+}
+
+// Returns the first non-empty line of `bytes`, shortened to 100 characters, if the bytes look like
+// text.
+fn first_line_of_text(bytes: &[u8]) -> Option<String> {
+    // We only read the start of the file, so the last character may be cut off.
+    let text = match str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return None;
+    }
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+    if line.chars().count() > 100 {
+        return Some(format!("{}...", line.chars().take(100).collect::<String>()));
+    }
+    Some(line.to_string())
+}
+
+// This picks the decoder for the tarball based on the extension of `downloaded_file`, and uses it to
+// read from `file`.
+fn tar_archive_for(downloaded_file: &Path, file: Box<dyn Read>) -> Result<Archive<Box<dyn Read>>> {
+    let ext = downloaded_file.extension();
+    match ext {
+        Some(ext) => match ext.to_str() {
+            Some("tar") => Ok(Archive::new(file)),
+            Some("br") => Ok(Archive::new(Box::new(BrotliDecoder::new(file, 4096)))),
+            Some("bz" | "tbz" | "bz2" | "tbz2") => Ok(Archive::new(Box::new(BzDecoder::new(file)))),
+            // Some gzip files, like those made by `pigz`, are several concatenated gzip members,
+            // and `GzDecoder` stops at the end of the first one.
+            Some("gz" | "tgz") => Ok(Archive::new(Box::new(MultiGzDecoder::new(file)))),
+            Some("lz") => Ok(Archive::new(Box::new(LzipDecoder::new(file)))),
+            Some("lz4") => Ok(Archive::new(Box::new(FrameDecoder::new(file)))),
+            Some("lzma") => Ok(Archive::new(Box::new(lzma_decoder(file)?))),
+            Some("xz" | "txz") => Ok(Archive::new(Box::new(XzDecoder::new_multi_decoder(file)))),
+            Some("Z") => Ok(Archive::new(Box::new(LzwDecoder::new(file)))),
+            Some("zst") => Ok(Archive::new(Box::new(ZstdDecoder::new(file)?))),
+            Some(e) => Err(InstallError::UnsupportedFormat {
+                path: downloaded_file.to_path_buf(),
+                message: format!("don't know how to uncompress a tarball with extension = {e}"),
+            }
+            .into()),
+            None => Err(anyhow!(
+                "tarball {:?} has a non-UTF-8 extension",
+                downloaded_file,
+            )),
+        },
+        None => Ok(Archive::new(file)),
+    }
+}
+
+// This decodes the legacy `.lzma` format, which `XzDecoder::new` doesn't handle, since it only
+// decodes `.xz` files.
+fn lzma_decoder<R: Read>(reader: R) -> Result<XzDecoder<R>> {
+    Ok(XzDecoder::new_stream(
+        reader,
+        Stream::new_lzma_decoder(u64::MAX)?,
+    ))
+}
+
+pub(crate) fn digest_mismatch_error(
+    downloaded_file: &Path,
+    algorithm: ChecksumAlgorithm,
+    expect: &str,
+    got: &str,
+) -> InstallError {
+    InstallError::ChecksumMismatch {
+        path: downloaded_file.to_path_buf(),
+        algorithm,
+        expected: expect.to_string(),
+        actual: got.to_string(),
+    }
+}
+
+// Removes the files we extracted, along with any directories that are left empty afterwards. This
+// is best effort, since we're already on our way to returning an error.
+fn remove_extracted(fs: &dyn FileSystem, root: &Path, extracted: &[PathBuf]) {
+    debug!(
+        "removing {} paths extracted into {}",
+        extracted.len(),
+        root.display(),
+    );
+
+    let mut dirs: HashSet<&Path> = HashSet::new();
+    for path in extracted {
+        match fs.metadata(path) {
+            Ok(meta) if meta.kind == FileKind::Dir => {
+                dirs.insert(path);
+            }
+            Ok(_) => {
+                if let Err(e) = fs.remove_file(path) {
+                    debug!("could not remove {}: {e}", path.display());
+                }
+            }
+            Err(_) => (),
+        }
+        dirs.extend(
+            path.ancestors()
+                .skip(1)
+                .take_while(|a| *a != root && a.starts_with(root)),
+        );
+    }
+
+    let mut dirs = dirs.into_iter().collect::<Vec<_>>();
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+    for dir in dirs {
+        // This fails for any directory that still has something in it, which is what we want.
+        let _ = fs.remove_dir(dir);
+    }
+}
+
+// Moves the `src` tree to `dst`. If `dst` already exists, the contents of `src` are merged into it.
+// We try to use `FileSystem::rename` first, but this fails when `src` and `dst` are on different
+// filesystems, in which case we fall back to copying the tree and then deleting the original.
+fn move_tree(fs: &dyn FileSystem, src: &Path, dst: &Path) -> Result<()> {
+    if fs.metadata(dst).is_ok_and(|m| m.kind == FileKind::Dir)
+        && fs.metadata(src)?.kind != FileKind::File
+    {
+        for entry in fs.read_dir(src)? {
+            let file_name = entry
+                .file_name()
+                .ok_or_else(|| anyhow!("{} has no file name", entry.display()))?;
+            move_tree(fs, &entry, &dst.join(file_name))?;
+        }
+        fs.remove_dir(src)?;
+        return Ok(());
+    }
+
+    if let Some(parent) = dst.parent() {
+        fs.create_dir_all(parent)
+            .with_context(|| format!("could not create a directory at {}", parent.display()))?;
+    }
+
+    if let Err(e) = fs.rename(src, dst) {
+        debug!(
+            "could not rename {} to {} ({e}), falling back to copying",
+            src.display(),
+            dst.display(),
+        );
+        copy_tree_then_remove(fs, src, dst)?;
+    }
+
+    Ok(())
+}
+
+// Returns a path next to `path` that doesn't exist yet, made by adding `suffix` and, if needed, a
+// number to its file name.
+fn unused_path(fs: &dyn FileSystem, path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{suffix}"));
+    let mut candidate = PathBuf::from(&name);
+    let mut n = 1;
+    while fs.metadata(&candidate).is_ok() {
+        let mut numbered = name.clone();
+        numbered.push(format!("-{n}"));
+        candidate = PathBuf::from(numbered);
+        n += 1;
+    }
+    candidate
+}
+
+// Removes whatever is at `path`, if anything.
+fn remove_existing(fs: &dyn FileSystem, path: &Path) -> Result<()> {
+    let Ok(meta) = fs.metadata(path) else {
+        return Ok(());
+    };
+    debug!("removing existing {} before replacing it", path.display());
+    if meta.kind == FileKind::Dir {
+        fs.remove_dir_all(path)
+    } else {
+        fs.remove_file(path)
+    }
+    .with_context(|| format!("could not remove the existing {}", path.display()))
+}
+
+fn copy_tree_then_remove(fs: &dyn FileSystem, src: &Path, dst: &Path) -> Result<()> {
+    copy_tree(fs, fs, src, dst)?;
+    if fs.metadata(src)?.kind == FileKind::Dir {
+        fs.remove_dir_all(src)
+    } else {
+        fs.remove_file(src)
+    }
+    .with_context(|| format!("could not remove {} after copying it", src.display()))
+}
+
+// Copies the tree at `src` in `src_fs` to `dst` in `dst_fs`. These can be the same filesystem.
+fn copy_tree(
+    src_fs: &dyn FileSystem,
+    dst_fs: &dyn FileSystem,
+    src: &Path,
+    dst: &Path,
+) -> Result<()> {
+    match src_fs.metadata(src)?.kind {
+        FileKind::Symlink => create_symlink(dst_fs, &src_fs.read_link(src)?, dst)?,
+        FileKind::Dir => {
+            dst_fs
+                .create_dir_all(dst)
+                .with_context(|| format!("could not create a directory at {}", dst.display()))?;
+            for entry in src_fs.read_dir(src)? {
+                let file_name = entry
+                    .file_name()
+                    .ok_or_else(|| anyhow!("{} has no file name", entry.display()))?;
+                copy_tree(src_fs, dst_fs, &entry, &dst.join(file_name))?;
+            }
+        }
+        FileKind::File => copy_file_between(src_fs, dst_fs, src, dst)?,
+    }
+
+    Ok(())
+}
+
+// Returns the section of the man page at `path`, if it is one. A man page is a file in a `manN`
+// directory inside a `man` directory, whose name ends in `.N` or `.N.gz`, where `N` is the section
+// number from 1 to 9.
+fn man_page_section(path: &Path) -> Option<char> {
+    let file_name = path.file_name()?.to_str()?;
+    let section_dir = path.parent()?;
+    let section = section_dir
+        .file_name()?
+        .to_str()?
+        .strip_prefix("man")
+        .filter(|s| s.len() == 1)?
+        .chars()
+        .next()
+        .filter(|c| ('1'..='9').contains(c))?;
+    if section_dir.parent()?.file_name()? != "man" {
+        return None;
+    }
+
+    let stem = file_name.strip_suffix(".gz").unwrap_or(file_name);
+    let suffix = format!(".{section}");
+    if stem.len() > suffix.len() && stem.ends_with(&suffix) {
+        Some(section)
+    } else {
+        None
+    }
+}
+
+// Returns true for the metadata that the macOS Finder adds to the archives it makes: the resource
+// forks in a top-level `__MACOSX` directory, and `.DS_Store` files.
+fn is_junk_member(path: &Path) -> bool {
+    path.components()
+        .find(|c| *c != Component::CurDir)
+        .is_some_and(|c| c.as_os_str() == "__MACOSX")
+        || path.file_name().is_some_and(|n| n == ".DS_Store")
+}
+
+// Returns true if `target` is a relative path that, when resolved from the directory containing
+// `link`, stays inside of `root`. Since we never write through symlinks, it's enough to check this
+// lexically.
+fn symlink_is_enclosed(root: &Path, link: &Path, target: &Path) -> bool {
+    let Some(link_dir) = link.parent().and_then(|p| p.strip_prefix(root).ok()) else {
+        return false;
+    };
+    let mut depth = link_dir.components().count();
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::ParentDir => {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            }
+            Component::CurDir => (),
+            Component::Prefix(_) | Component::RootDir => return false,
+        }
+    }
+
+    true
+}
+
+// Adds every symlink under `path` to `links`.
+fn find_symlinks(fs: &dyn FileSystem, path: &Path, links: &mut Vec<PathBuf>) -> Result<()> {
+    match fs.metadata(path)?.kind {
+        FileKind::Symlink => links.push(path.to_path_buf()),
+        FileKind::Dir => {
+            for entry in fs.read_dir(path)? {
+                find_symlinks(fs, &entry, links)?;
+            }
+        }
+        FileKind::File => (),
+    }
+
+    Ok(())
+}
+
+// Adds every file and symlink under `path` to `paths`.
+fn list_files(fs: &dyn FileSystem, path: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    if fs.metadata(path)?.kind != FileKind::Dir {
+        paths.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in fs.read_dir(path)? {
+        list_files(fs, &entry, paths)?;
+    }
+
+    Ok(())
+}
+
+// Copies the contents and permissions of the file at `src` to `dst`.
+fn copy_file(fs: &dyn FileSystem, src: &Path, dst: &Path) -> Result<()> {
+    copy_file_between(fs, fs, src, dst)
+}
+
+fn copy_file_between(
+    src_fs: &dyn FileSystem,
+    dst_fs: &dyn FileSystem,
+    src: &Path,
+    dst: &Path,
+) -> Result<()> {
+    let context = || {
+        format!(
+            "error copying file from {} to {}",
+            src.display(),
+            dst.display()
+        )
+    };
+    let mut reader = src_fs.open_file(src).with_context(context)?;
+    io::copy(
+        &mut reader,
+        &mut dst_fs.create_file(dst).with_context(context)?,
+    )
+    .with_context(context)?;
+    let mode = src_fs.metadata(src).with_context(context)?.mode;
+    dst_fs.set_permissions(dst, mode).with_context(context)
+}
+
+// This is like `io::copy`, but it calls the progress callback, if there is one, before anything is
+// copied and after each chunk is written.
+fn copy_with_progress(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    progress: Option<&Progress>,
+    size: Option<u64>,
+) -> io::Result<u64> {
+    let Some(Progress(callback)) = progress else {
+        return io::copy(reader, writer);
+    };
+
+    let mut buf = vec![0; 64 * 1024];
+    let mut written = 0;
+    callback(written, size);
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(written),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        written += n as u64;
+        callback(written, size);
+    }
+}
+
+fn create_symlink(fs: &dyn FileSystem, target: &Path, link: &Path) -> Result<()> {
+    fs.symlink(target, link)
+        .with_context(|| format!("could not create a symlink at {}", link.display()))
+}
+
+// Calls `f` with the name and contents of each member of the ar archive at `path`, until `f`
+// returns `false`. A deb file is an ar archive, so this is used for those too. The `ar` crate skips
+// the symbol table and long name table that some archivers add.
+fn for_each_ar_entry(
+    path: &Path,
+    require_regular_file: bool,
+    mut f: impl FnMut(&str, &mut ar::Entry<'_, File>) -> Result<bool>,
+) -> Result<()> {
+    let mut archive = ar::Archive::new(open_file(path, require_regular_file)?);
+    while let Some(entry) = archive.next_entry() {
+        let mut entry =
+            entry.with_context(|| format!("could not read ar archive at {}", path.display()))?;
+        let identifier = String::from_utf8_lossy(entry.header().identifier()).into_owned();
+        // GNU ar puts a `/` at the end of each member's name.
+        let name = identifier.trim_end_matches('/');
+        if !f(name, &mut entry)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn run_post_install_command(command: &[String], paths: &[PathBuf]) -> Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        return Err(anyhow!("the post-install command is empty"));
+    };
+    for path in paths {
+        let mut cmd = process::Command::new(program);
+        let mut has_path = false;
+        for arg in args {
+            if arg.contains("{}") {
+                cmd.arg(arg.replace("{}", &path.to_string_lossy()));
+                has_path = true;
+            } else {
+                cmd.arg(arg);
+            }
+        }
+        if !has_path {
+            cmd.arg(path);
+        }
+        cmd.stdin(process::Stdio::null());
+        debug!("running {cmd:?} after installing {}", path.display());
+        let output = cmd.output().with_context(|| {
+            format!(
+                "could not run the post-install command `{}` for {}",
+                command.join(" "),
+                path.display(),
+            )
+        })?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "the post-install command `{}` for {} failed ({})\n\
+                 stdout: {}\n\
+                 stderr: {}",
+                command.join(" "),
+                path.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stdout).trim(),
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn open_cab(downloaded_file: &Path, require_regular_file: bool) -> Result<Cabinet<File>> {
+    Cabinet::new(open_file(downloaded_file, require_regular_file)?)
+        .with_context(|| format!("could not read cab file at {}", downloaded_file.display()))
+}
+
+// Cab files use `\` to separate the parts of a member's path, even when they're made on Unix.
+fn cab_member_path(name: &str) -> PathBuf {
+    name.split('\\').collect()
+}
+
+fn open_7z(downloaded_file: &Path, require_regular_file: bool) -> Result<SevenZReader<File>> {
+    let file = open_file(downloaded_file, require_regular_file)?;
+    let len = file.metadata()?.len();
+    SevenZReader::new(file, len, Password::empty())
+        .with_context(|| format!("could not read 7z file at {}", downloaded_file.display()))
+}
+
+// This calls `each` for every entry in the 7z file, stopping when it returns `false`. The reader
+// passed to `each` must be read to the end unless it returns `false`, because the entries share a
+// single decompression stream. The 7z reader wants its closure to return its own error type, so we
+// stash any error from `each` and stop iterating instead.
+fn for_each_7z_entry(
+    sz: &mut SevenZReader<File>,
+    mut each: impl FnMut(&SevenZArchiveEntry, &mut dyn Read) -> Result<bool>,
+) -> Result<()> {
+    let mut error = None;
+    sz.for_each_entries(|entry, reader| match each(entry, reader) {
+        Ok(true) => {
+            // Make sure the next entry starts at the right place in the stream.
+            io::copy(reader, &mut io::sink())?;
+            Ok(true)
+        }
+        Ok(false) => Ok(false),
+        Err(e) => {
+            error = Some(e);
+            Ok(false)
+        }
+    })?;
+
+    error.map_or(Ok(()), Err)
+}
+
+fn tar_entry_mtime<R: Read>(entry: &Entry<'_, R>) -> Result<SystemTime> {
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(entry.header().mtime()?))
+}
+
+// A GNU sparse entry is a regular file with holes in it. Reading the entry fills in the holes, and
+// its size is the size of the whole file, so we can treat it like any other file.
+fn tar_entry_is_file(entry_type: EntryType) -> bool {
+    entry_type.is_file() || entry_type.is_contiguous() || entry_type.is_gnu_sparse()
+}
+
+// Zip files made on Unix usually have an extended timestamp field with the modification time as a
+// Unix timestamp, and those made on Windows may have an NTFS field with a Windows file time. We
+// use one of those when it's there. Otherwise, we use the MS-DOS date and time that every zip file
+// member has. This has no time zone, so we treat it as UTC.
+fn zip_file_mtime(zf: &ZipFile) -> Option<SystemTime> {
+    let secs = zf
+        .extra_data_fields()
+        .find_map(|field| match field {
+            ExtraField::ExtendedTimestamp(ts) => ts.mod_time().map(u64::from),
+            // A Windows file time counts 100 nanosecond intervals since 1601.
+            ExtraField::Ntfs(ntfs) => (ntfs.mtime() / 10_000_000).checked_sub(11_644_473_600),
+        })
+        .or_else(|| {
+            let dt = zf.last_modified()?;
+            let days = days_since_unix_epoch(dt.year(), dt.month(), dt.day());
+            let secs =
+                u64::from(dt.hour()) * 3600 + u64::from(dt.minute()) * 60 + u64::from(dt.second());
+            // MS-DOS dates start at 1980, so this is never before the Unix epoch.
+            Some(u64::try_from(days).ok()? * 86_400 + secs)
+        })?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+// This is the `days_from_civil` algorithm from
+// https://howardhinnant.github.io/date_algorithms.html#days_from_civil.
+fn days_since_unix_epoch(year: u16, month: u8, day: u8) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Some Windows tools write zip entry names with backslashes as the separator, even though the zip
+// spec says to use forward slashes. A `Path` on Unix treats a name like `bin\project.exe` as a
+// single file name, so we switch the separators before making a path from the name.
+fn zip_entry_path(name: &str) -> PathBuf {
+    PathBuf::from(name.replace('\\', "/"))
+}
+
+// Returns an archive member's path without a leading `./` or `/`, so that `bin/project`,
+// `./bin/project`, and `/bin/project` are all the same path.
+fn relative_member_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| matches!(c, Component::Normal(_) | Component::ParentDir))
+        .collect()
+}
+
+// Returns the zip file entry at index `i`, decrypting it with `password` if it's encrypted. The
+// `zip` crate's own errors for an encrypted entry, or for an entry that uses a feature it doesn't
+// support, like an unknown compression method, don't say which entry it was.
+fn zip_entry<'z>(
+    zip: &'z mut ZipArchive<File>,
+    i: usize,
+    password: Option<&str>,
+) -> Result<ZipFile<'z>> {
+    let (name, encrypted) = {
+        let zf = zip.by_index_raw(i)?;
+        (zf.name().to_string(), zf.encrypted())
+    };
+    let unsupported = |e| match e {
+        ZipError::UnsupportedArchive(msg) => anyhow!(
+            "the archive member {name} uses a zip file feature that ubi does not support: {msg}"
+        ),
+        e => e.into(),
+    };
+    if !encrypted {
+        return zip.by_index(i).map_err(unsupported);
+    }
+
+    let Some(password) = password else {
+        return Err(anyhow!(
+            "the archive member {name} is encrypted, and ubi cannot extract password-protected \
+             zip file members without the password"
+        ));
+    };
+    debug!("decrypting zip file entry {name}");
+    zip.by_index_decrypt(i, password.as_bytes())
+        .map_err(|e| match e {
+            ZipError::InvalidPassword => {
+                anyhow!("the zip file password is not correct for the archive member {name}")
+            }
+            e => unsupported(e),
+        })
+}
+
+// A path with an extension we don't recognize, like `project.1`, is not an archive.
+fn is_archive(path: &Path) -> bool {
+    matches!(Extension::from_path(path), Ok(Some(ext)) if ext.is_archive())
+}
+
+// Returns the extension of `path` if it's a compression format for a single file that we can
+// decompress while extracting an archive member, like `.gz`.
+fn compressed_file_extension(path: &Path) -> Option<Extension> {
+    match Extension::from_path(path) {
+        Ok(Some(
+            ext @ (Extension::Bz | Extension::Bz2 | Extension::Gz | Extension::Xz | Extension::Zst),
+        )) => Some(ext),
+        _ => None,
+    }
+}
+
+// Wraps `reader` in the same decoder we use for a downloaded file with the extension `ext`, which
+// must be one that `compressed_file_extension` returns.
+fn compressed_file_decoder<'r>(
+    ext: &Extension,
+    reader: impl Read + 'r,
+) -> Result<Box<dyn Read + 'r>> {
+    Ok(match ext {
+        Extension::Bz | Extension::Bz2 => Box::new(BzDecoder::new(reader)),
+        Extension::Gz => Box::new(MultiGzDecoder::new(reader)),
+        Extension::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+        Extension::Zst => Box::new(ZstdDecoder::new(reader)?),
+        _ => {
+            return Err(anyhow!(
+                "cannot decompress an archive member with the extension {}",
+                ext.extension(),
+            ))
+        }
+    })
+}
+
+// Returns `path` plus `.bak`, so `project.exe` is backed up to `project.exe.bak`.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(".bak");
+    PathBuf::from(backup_path)
+}
+
+// Returns the extension of `path` if it's one that we keep on the installed executable, like
+// `.exe` or `.pyz`.
+fn preserved_extension(path: &Path) -> Result<Option<Extension>> {
+    let Some(ext) = Extension::from_path(path)? else {
+        return Ok(None);
+    };
+    if !ext.should_preserve_extension_on_install() {
+        return Ok(None);
+    }
+    debug!("preserving the {} extension on install", ext.extension());
+    Ok(Some(ext))
+}
+
+// Adds `ext`, an extension we preserve from the file being installed, to `install_path`. If the
+// install path already has an extension, then whoever picked the install path put it there, so we
+// keep it, even when it's not the same as `ext`. Calling `set_extension` would replace it instead,
+// turning an install path like `tool.v2` into `tool.pyz`.
+fn with_preserved_extension(mut install_path: PathBuf, ext: &Extension) -> PathBuf {
+    match install_path.extension() {
+        None => {
+            install_path.set_extension(ext.extension_without_dot());
+        }
+        Some(existing) if existing.eq_ignore_ascii_case(ext.extension_without_dot()) => (),
+        Some(existing) => info!(
+            "installing to {} as given, without replacing its {} extension with the {} extension \
+             of the file being installed",
+            install_path.display(),
+            existing.to_string_lossy(),
+            ext.extension_without_dot(),
+        ),
+    }
+    install_path
+}
+
+// Runs `op`, running it again after a short wait if it fails because something else has the file
+// open. This only happens on Windows, so on other platforms `op` is only run once.
+fn retry_if_locked<T>(mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut delay = LOCKED_FILE_FIRST_DELAY;
+    for _ in 0..LOCKED_FILE_RETRIES {
+        match op() {
+            Err(e) if is_locked_file_error(&e) => {
+                debug!(
+                    "retrying in {}ms because the file is locked: {e}",
+                    delay.as_millis()
+                );
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            res => return res,
+        }
+    }
+    op()
+}
+
+#[cfg(target_family = "windows")]
+fn is_locked_file_error(e: &io::Error) -> bool {
+    const ERROR_ACCESS_DENIED: i32 = 5;
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    matches!(
+        e.raw_os_error(),
+        Some(ERROR_ACCESS_DENIED | ERROR_SHARING_VIOLATION),
+    )
+}
+
+#[cfg(not(target_family = "windows"))]
+fn is_locked_file_error(_e: &io::Error) -> bool {
+    false
+}
+
+// Returns a hidden path in the same directory as `path`, like `.project.ubi-123.tmp` for
+// `project`. Since it's in the same directory, it can be renamed to `path` without copying.
+fn sibling_path(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let Some(file_name) = path.file_name() else {
+        return Err(anyhow!("the path {} has no file name", path.display()));
+    };
+    let mut sibling_name = OsString::from(".");
+    sibling_name.push(file_name);
+    sibling_name.push(".");
+    sibling_name.push(suffix);
+    Ok(path.with_file_name(sibling_name))
+}
+
+// When `require_regular_file` is true, this refuses to open anything but a regular file. The check
+// is done on the opened handle rather than the path, and on Unix the file is opened without
+// following a symlink, so the file can't be swapped out between the check and the read.
+pub(crate) fn open_file(path: &Path, require_regular_file: bool) -> Result<File> {
+    if !require_regular_file {
+        return File::open(path)
+            .with_context(|| format!("Failed to open file at {}", path.display()));
+    }
+
+    let mut options = OpenOptions::new();
+    options.read(true);
+    // Opening a FIFO blocks until something writes to it unless we ask for a non-blocking open.
+    // This makes no difference for reading a regular file.
+    #[cfg(target_family = "unix")]
+    std::os::unix::fs::OpenOptionsExt::custom_flags(
+        &mut options,
+        libc::O_NOFOLLOW | libc::O_NONBLOCK,
+    );
+    let file_type = match options.open(path) {
+        Ok(file) => {
+            let file_type = file
+                .metadata()
+                .with_context(|| format!("could not read {}", path.display()))?
+                .file_type();
+            if file_type.is_file() {
+                return Ok(file);
+            }
+            file_type
+        }
+        // This is how we find out that the path is a symlink, or something else we can't open,
+        // like a socket.
+        Err(e) => match fs::symlink_metadata(path) {
+            Ok(meta) if !meta.is_file() => meta.file_type(),
+            _ => {
+                return Err(anyhow::Error::new(e)
+                    .context(format!("Failed to open file at {}", path.display())))
+            }
+        },
+    };
+    Err(anyhow!(
+        "refusing to install from {} because it is {}, not a regular file",
+        path.display(),
+        describe_file_type(file_type),
+    ))
+}
+
+// Describes a file type that isn't a regular file, for `open_file`.
+fn describe_file_type(file_type: fs::FileType) -> &'static str {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        if file_type.is_fifo() {
+            return "a FIFO";
+        }
+        if file_type.is_socket() {
+            return "a socket";
+        }
+        if file_type.is_block_device() || file_type.is_char_device() {
+            return "a device";
+        }
+    }
+    if file_type.is_symlink() {
+        "a symlink"
+    } else if file_type.is_dir() {
+        "a directory"
+    } else {
+        "something else"
+    }
+}
+
+// Zip64 archives, which are needed for members and archives over 4 GiB, are handled by the `zip`
+// crate. But it doesn't support everything in the zip spec, like archives split across multiple
+// disks, and its error for these doesn't say which file it was reading.
+fn open_zip(path: &Path, require_regular_file: bool) -> Result<ZipArchive<File>> {
+    ZipArchive::new(open_file(path, require_regular_file)?).map_err(|e| match e {
+        ZipError::UnsupportedArchive(msg) => anyhow!(
+            "the zip file at {} uses a feature that ubi does not support: {msg}",
+            path.display(),
+        ),
+        e => e.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFs;
+    use std::{
+        fs::{self, create_dir_all},
+        sync::Mutex,
+    };
+    use tempfile::tempdir;
+    use test_case::test_case;
+    use test_log::test;
+
+    #[test_case("test-data/project.7z", None)]
+    #[test_case("test-data/project.a", None)]
+    #[test_case("test-data/project.ar", None)]
+    #[test_case("test-data/project.AppImage", Some("AppImage"))]
+    #[test_case("test-data/project.bat", Some("bat"))]
+    #[test_case("test-data/project.br", None)]
+    #[test_case("test-data/project.bz", None)]
+    #[test_case("test-data/project.bz2", None)]
+    #[test_case("test-data/project.cab", None)]
+    #[test_case("test-data/project.deb", None)]
+    #[test_case("test-data/project.rpm", None)]
+    #[test_case("test-data/project.exe", Some("exe"))]
+    #[test_case("test-data/project.gz", None)]
+    #[test_case("test-data/project.lz", None)]
+    #[test_case("test-data/project.lz4", None)]
+    #[test_case("test-data/project.pyz", Some("pyz"))]
+    #[test_case("test-data/project.tar", None)]
+    #[test_case("test-data/project.tar.bz", None)]
+    #[test_case("test-data/project.tar.bz2", None)]
+    #[test_case("test-data/project.tar.gz", None)]
+    #[test_case("test-data/project.tar.lz", None)]
+    #[test_case("test-data/project.tar.br", None)]
+    #[test_case("test-data/project.tar.lz4", None)]
+    #[test_case("test-data/project.tar.lzma", None)]
+    #[test_case("test-data/project.tar.xz", None)]
+    #[test_case("test-data/project.tar.Z", None)]
+    #[test_case("test-data/project.tar.zst", None)]
+    #[test_case("test-data/project.lzma", None)]
+    #[test_case("test-data/project.xz", None)]
+    #[test_case("test-data/project.Z", None)]
+    #[test_case("test-data/project.zip", None)]
+    // This zip file's members use zip64 sizes, like a zip file written by a streaming tool.
+    #[test_case("test-data/project-zip64.zip", None)]
+    #[test_case("test-data/project.zst", None)]
+    #[test_case("test-data/project", None)]
+    // This tests a bug where zip files with partial matches before an exact match would pick the wrong file.
+    #[test_case("test-data/project-with-partial-before-exact.zip", None)]
+    // These are archive files that just contain a partial match for the expected executable.
+    #[test_case("test-data/project-with-partial-match.tar.gz", None)]
+    #[test_case("test-data/project-with-partial-match.tar.zst", None)]
+    #[test_case("test-data/project-with-partial-match.zip", None)]
+    #[test_case("test-data/project-with-partial-match.7z", None)]
+    // The partial match has a long name, and there's a partial match that isn't executable before
+    // it.
+    #[test_case("test-data/project-with-partial-match.ar", None)]
+    // These contain two partial matches, and the longer one comes first.
+    #[test_case("test-data/project-with-debug-partial-match.tar.gz", None)]
+    #[test_case("test-data/project-with-debug-partial-match.zip", None)]
+    #[test_case("test-data/project-with-debug-partial-match.7z", None)]
+    // These contain the executable in an archive inside the archive.
+    #[test_case("test-data/project-with-nested-tarball.zip", None)]
+    // The executable in these is compressed inside of the zip file.
+    #[test_case("test-data/project-with-gzipped-exe.zip", None)]
+    #[test_case("test-data/project-with-xz-exe.zip", None)]
+    #[test_case("test-data/project-with-nested-zip.tar.gz", None)]
+    // These store the executable's path, which is more than 100 characters long, in a GNU long
+    // name entry and a PAX extended header.
+    #[test_case("test-data/project-with-gnu-long-name.tar.gz", None)]
+    #[test_case("test-data/project-with-pax-long-name.tar.gz", None)]
+    // These are made of two concatenated xz streams.
+    #[test_case("test-data/project-multi-stream.tar.xz", None)]
+    #[test_case("test-data/project-multi-stream.xz", None)]
+    // And these are made of two concatenated gzip members.
+    #[test_case("test-data/project-multi-member.tar.gz", None)]
+    #[test_case("test-data/project-multi-member.gz", None)]
+    fn exe_installer(archive_path: &str, installed_extension: Option<&str>) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+
+        let td = tempdir()?;
+        let path_without_subdir = td.path().to_path_buf();
+        test_installer(
+            archive_path,
+            installed_extension,
+            path_without_subdir,
+            false,
+            &real_fs,
+        )?;
+
+        let td = tempdir()?;
+        let mut path_with_subdir = td.path().to_path_buf();
+        path_with_subdir.push("subdir");
+        test_installer(
+            archive_path,
+            installed_extension,
+            path_with_subdir.clone(),
+            false,
+            &real_fs,
+        )?;
+
+        test_installer(
+            archive_path,
+            installed_extension,
+            path_with_subdir,
+            false,
+            &memory_fs,
+        )
+    }
+
+    // These tests check that we look for project.bat and project.exe in archive files when running
+    // on Windows.
+    #[test_case("test-data/windows-project-bat.tar.gz", "bat")]
+    #[test_case("test-data/windows-project-exe.tar.gz", "exe")]
+    #[test_case("test-data/windows-project-bat.zip", "bat")]
+    #[test_case("test-data/windows-project-exe.zip", "exe")]
+    #[test_case("test-data/windows-project-exe-with-backslashes.zip", "exe")]
+    #[test_case("test-data/windows-project-exe.7z", "exe")]
+    #[test_case("test-data/windows-project-exe.cab", "exe")]
+    // And these check that we match project-with-stuff.exe.
+    #[test_case("test-data/windows-project-exe-with-partial-match.tar.gz", "exe")]
+    #[test_case("test-data/windows-project-exe-with-partial-match.zip", "exe")]
+    // And this checks that the match ignores case, so we match Project-With-Stuff.exe.
+    #[test_case(
+        "test-data/windows-project-exe-with-mixed-case-partial-match.zip",
+        "exe"
+    )]
+    fn exe_installer_on_windows(archive_path: &str, extension: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        test_installer(
+            archive_path,
+            Some(extension),
+            PathBuf::from("/install"),
+            true,
+            &memory_fs,
+        )
+    }
+
+    #[test_case("project.exe", true, true; "windows lowercase")]
+    #[test_case("Project.EXE", true, true; "windows mixed case")]
+    #[test_case("project.bat", true, true; "windows bat")]
+    #[test_case("project", true, false; "windows without extension")]
+    #[test_case("project", false, true; "unix")]
+    #[test_case("Project", false, false; "unix mixed case")]
+    fn exe_installer_exact_match(file_name: &str, is_windows: bool, expect: bool) {
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            is_windows,
+        );
+        assert_eq!(
+            installer.archive_member_is_exact_match("project", file_name),
+            expect,
+        );
+    }
+
+    #[test_case("test-data/project.tar.gz", false, "project")]
+    #[test_case("test-data/project.zip", false, "project")]
+    #[test_case("test-data/windows-project-exe.zip", true, "project.exe")]
+    #[test_case("test-data/windows-project-bat.tar.gz", true, "project.bat")]
+    fn exe_installer_dry_run(archive_path: &str, is_windows: bool, expect: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            is_windows,
+        )
+        .filesystem(fs.clone());
+        let paths = installer.install_dry_run(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(paths, vec![Path::new("/install").join(expect)]);
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install")), None);
+
+        Ok(())
+    }
+
+    fn test_installer(
+        archive_path: &str,
+        installed_extension: Option<&str>,
+        install_dir: PathBuf,
+        is_windows: bool,
+        fs: &Arc<dyn FileSystem>,
+    ) -> Result<()> {
+        let exe_file_stem = "project";
+
+        let mut install_path = install_dir;
+        install_path.push("project");
+
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            vec![exe_file_stem.to_string()],
+            is_windows,
+        )
+        .filesystem(fs.clone());
+        let paths = installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        let mut expect_install_path = install_path.clone();
+        if let Some(installed_extension) = installed_extension {
+            let path = PathBuf::from(format!("foo.{installed_extension}"));
+            let ext = Extension::from_path(&path).unwrap().unwrap();
+            if ext.should_preserve_extension_on_install() {
+                expect_install_path.set_extension(ext.extension_without_dot());
+            }
+        }
+
+        assert_eq!(paths, vec![expect_install_path.clone()]);
+
+        let meta = fs.metadata(&expect_install_path)?;
+        assert_eq!(
+            meta.kind,
+            FileKind::File,
+            "{} is a file",
+            expect_install_path.display()
+        );
+        // Testing the installed file's length is a shortcut to make sure we install the file we
+        // expected to install.
+        let expect_len = if expect_install_path.extension().unwrap_or_default() == "pyz" {
+            fs::metadata(archive_path)?.len()
+        } else {
+            3
+        };
+        assert_eq!(
+            u64::try_from(read_file(fs.as_ref(), &expect_install_path)?.len())?,
+            expect_len
+        );
+        #[cfg(target_family = "unix")]
+        assert!(meta.mode & 0o111 != 0);
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", 0o700)]
+    #[test_case("test-data/project-with-mode.zip", 0o4750)]
+    fn exe_installer_with_mode(archive_path: &str, mode: u32) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .mode(Some(mode))
+            .filesystem(fs.clone());
+        installer.validate()?;
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(fs.metadata(&install_path)?.mode, mode);
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn require_regular_file() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let symlink = td.path().join("project.tar.gz");
+        std::os::unix::fs::symlink(fs::canonicalize("test-data/project.tar.gz")?, &symlink)?;
+        let download = Download::new(symlink.clone());
+        let expect_err = format!(
+            "refusing to install from {} because it is a symlink, not a regular file",
+            symlink.display(),
+        );
+
+        let fs = Arc::new(MemoryFs::new());
+        let exe_installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(fs.clone());
+        exe_installer.install(&download)?;
+        let err = exe_installer
+            .require_regular_file(true)
+            .install(&download)
+            .unwrap_err();
+        assert_eq!(err.to_string(), expect_err);
+
+        let archive_installer =
+            ArchiveInstaller::new(PathBuf::from("/install/archive")).filesystem(fs.clone());
+        archive_installer.install(&download)?;
+        let err = archive_installer
+            .require_regular_file(true)
+            .install(&download)
+            .unwrap_err();
+        assert_eq!(err.to_string(), expect_err);
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn open_file_requires_regular_file() -> Result<()> {
+        let td = tempdir()?;
+        let file = td.path().join("project.tar.gz");
+        fs::copy("test-data/project.tar.gz", &file)?;
+        let symlink = td.path().join("symlink.tar.gz");
+        std::os::unix::fs::symlink(&file, &symlink)?;
+        let socket = td.path().join("socket.tar.gz");
+        let _listener = std::os::unix::net::UnixListener::bind(&socket)?;
+        let fifo = td.path().join("fifo.tar.gz");
+        let c_fifo = std::ffi::CString::new(fifo.as_os_str().as_encoded_bytes())?;
+        // SAFETY: `c_fifo` is a valid NUL-terminated path.
+        assert_eq!(unsafe { libc::mkfifo(c_fifo.as_ptr(), 0o644) }, 0);
+
+        open_file(&file, true)?;
+        open_file(&symlink, false)?;
+        // Nothing ever writes to the FIFO, so this would hang if we didn't open it without
+        // blocking.
+        for (path, kind) in [
+            (symlink, "a symlink"),
+            (socket, "a socket"),
+            (fifo, "a FIFO"),
+            (td.path().to_path_buf(), "a directory"),
+        ] {
+            let err = open_file(&path, true).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "refusing to install from {} because it is {kind}, not a regular file",
+                    path.display(),
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn exe_installer_with_dir_mode() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        fs.create_dir_all(Path::new("/install"))?;
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/bin/project/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .dir_mode(Some(0o700))
+        .filesystem(fs.clone());
+        installer.validate()?;
+        installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")))?;
+
+        assert_eq!(fs.metadata(Path::new("/install"))?.mode, 0o755);
+        assert_eq!(fs.metadata(Path::new("/install/bin"))?.mode, 0o700);
+        assert_eq!(fs.metadata(Path::new("/install/bin/project"))?.mode, 0o700);
+        assert_eq!(
+            fs.metadata(Path::new("/install/bin/project/project"))?.mode,
+            0o755,
+        );
+
+        assert!(installer.dir_mode(Some(0o40_755)).validate().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn exe_installer_with_invalid_mode() {
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .mode(Some(0o100_755));
+        assert!(installer.validate().is_err());
+    }
+
+    // The mode recorded in a zip file is kept, but the executable bits are always set.
+    #[test_case("test-data/project-with-mode.zip", 0o751)]
+    #[test_case("test-data/project.zip", 0o775)]
+    #[test_case("test-data/project.tar.gz", 0o755)]
+    #[test_case("test-data/project.gz", 0o755)]
+    fn exe_installer_mode(archive_path: &str, expect_mode: u32) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(fs.metadata(&install_path)?.mode, expect_mode);
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz")]
+    #[test_case("test-data/project.zip")]
+    #[test_case("test-data/project.7z")]
+    #[test_case("test-data/project.gz")]
+    #[test_case("test-data/project")]
+    fn exe_installer_replaces_existing_exe(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        fs.create_dir_all(Path::new("/install"))?;
+        fs.create_file(&install_path)?.write_all(b"old exe")?;
+        fs.set_permissions(&install_path, 0o644)?;
+
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, b"exe");
+        assert_ne!(fs.metadata(&install_path)?.mode & 0o111, 0);
+        assert_eq!(fs.read_dir(Path::new("/install"))?, vec![install_path]);
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz")]
+    #[test_case("test-data/project.zip")]
+    #[test_case("test-data/project")]
+    fn exe_installer_skip_chmod(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .skip_chmod(true)
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, b"exe");
+        // This is the mode that `MemoryFs` gives new files.
+        assert_eq!(fs.metadata(&install_path)?.mode, 0o644);
+
+        Ok(())
+    }
+
+    #[test_case("x86_64-unknown-linux-gnu", false, true; "matching arch")]
+    #[test_case("aarch64-unknown-linux-gnu", false, true; "mismatch warns")]
+    #[test_case("aarch64-unknown-linux-gnu", true, false; "mismatch is an error when strict")]
+    fn exe_installer_checks_arch(triple: &str, strict: bool, expect_ok: bool) -> Result<()> {
+        crate::test_case::init_logging();
+
+        // This is just enough of an x86-64 ELF header for the check.
+        let mut elf = vec![0; 64];
+        elf[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        elf[18] = 62;
+        let td = tempdir()?;
+        let exe = td.path().join("project");
+        fs::write(&exe, &elf)?;
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .expected_platform(Some(Platform::find(triple).unwrap().clone()))
+        .strict_arch_check(strict)
+        .filesystem(fs);
+        let res = installer.install(&Download::new(exe));
+        if expect_ok {
+            res?;
+        } else {
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                "the installed executable at /install/project is for x86_64, which cannot run on \
+                 aarch64-unknown-linux-gnu",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test_case(false, true; "keep backup")]
+    #[test_case(true, false; "remove backup")]
+    fn exe_installer_backs_up_existing_exe(remove_backup: bool, expect_backup: bool) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        fs.create_dir_all(Path::new("/install"))?;
+        fs.create_file(&install_path)?.write_all(b"old exe")?;
+        fs.set_permissions(&install_path, 0o700)?;
+
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .backup_existing(true)
+            .remove_backup(remove_backup)
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")))?;
+
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, b"exe");
+        let backup_path = PathBuf::from("/install/project.bak");
+        if expect_backup {
+            assert_eq!(read_file(fs.as_ref(), &backup_path)?, b"old exe");
+            assert_eq!(fs.metadata(&backup_path)?.mode, 0o700);
+        } else {
+            assert_eq!(kind_of(fs.as_ref(), &backup_path), None);
+        }
+
+        Ok(())
+    }
+
+    // A failed write leaves the existing executable alone, along with its backup, and cleans up
+    // the temp file.
+    #[test]
+    fn exe_installer_write_error_keeps_existing_exe() -> Result<()> {
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("the download was interrupted"))
+            }
+        }
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        fs.create_dir_all(Path::new("/install"))?;
+        fs.create_file(&install_path)?.write_all(b"old exe")?;
+
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .backup_existing(true)
+            .remove_backup(true)
+            .filesystem(fs.clone());
+        let err = installer
+            .write_executable(
+                &install_path,
+                None,
+                &mut FailingReader,
+                EXECUTABLE_MODE,
+                None,
+                None,
+            )
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "the download was interrupted");
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, b"old exe");
+        let backup_path = PathBuf::from("/install/project.bak");
+        assert_eq!(read_file(fs.as_ref(), &backup_path)?, b"old exe");
+        let mut paths = fs.read_dir(Path::new("/install"))?;
+        paths.sort();
+        assert_eq!(paths, vec![install_path, backup_path]);
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", true, Some(1_737_238_421))]
+    #[test_case("test-data/project.zip", true, Some(1_737_238_772))]
+    #[test_case("test-data/project.tar.gz", false, None)]
+    #[test_case("test-data/project.zip", false, None)]
+    // We don't know the modification time of a file that isn't in an archive.
+    #[test_case("test-data/project.gz", true, None)]
+    fn exe_installer_preserve_mtime(
+        archive_path: &str,
+        preserve_mtime: bool,
+        expect_secs: Option<u64>,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .preserve_mtime(preserve_mtime)
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(
+            fs.metadata(&install_path)?.modified,
+            expect_secs.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn exe_installer_prefers_executable_zip_partial_match() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(
+            "test-data/project-with-data-partial-match.zip",
+        )))?;
+
+        let mut contents = String::new();
+        fs.open_file(&install_path)?.read_to_string(&mut contents)?;
+        assert_eq!(contents, "exe");
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-with-data-files.tar.gz", &[], Err("project/project.dat has unknown extension dat"))]
+    #[test_case("test-data/project-with-data-files.zip", &[], Err("project/project.dat has unknown extension dat"))]
+    #[test_case("test-data/project-with-data-files.tar.gz", &["dat"], Ok(b"exe"))]
+    #[test_case("test-data/project-with-data-files.zip", &["dat"], Ok(b"exe"))]
+    #[test_case("test-data/project-with-data-files.tar.gz", &[".DAT"], Ok(b"exe"); "leading dot and uppercase")]
+    fn exe_installer_non_executable_extensions(
+        archive_path: &str,
+        non_executable_extensions: &[&str],
+        expect: Result<&[u8], &str>,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .non_executable_extensions(
+                non_executable_extensions
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            )
+            .filesystem(fs.clone());
+        // The archive's `project.json` file is always skipped, since `json` is one of the default
+        // non-executable extensions. Without `dat`, the `project.dat` file is picked, since it's
+        // the shortest partial match.
+        let res = installer.install(&Download::new(PathBuf::from(archive_path)));
+        match expect {
+            Ok(contents) => {
+                res?;
+                assert_eq!(read_file(fs.as_ref(), &install_path)?, contents);
+            }
+            Err(msg) => assert_eq!(res.unwrap_err().to_string(), msg),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn exe_installer_nested_archives_too_deep() {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(fs.clone());
+        let err = installer
+            .install(&Download::new(PathBuf::from(
+                "test-data/project-with-nested-archives-too-deep.zip",
+            )))
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "the archive member project.tar.gz is an archive nested more than 2 levels deep",
+        );
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install/project")), None);
+    }
+
+    #[test_case("test-data/project-with-helper.tar.gz")]
+    #[test_case("test-data/project-with-helper.tar"; "plain tarball")]
+    #[test_case("test-data/project-with-helper.zip")]
+    #[test_case("test-data/project-with-helper.7z")]
+    fn exe_installer_multiple_exes(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string(), "project-helper".to_string()],
+            false,
+        )
+        .filesystem(fs.clone());
+        installer.validate()?;
+        let paths = installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/install/project"),
+                PathBuf::from("/install/project-helper"),
+            ],
+        );
+        assert_eq!(
+            read_file(fs.as_ref(), Path::new("/install/project"))?,
+            b"exe"
+        );
+        assert_eq!(
+            read_file(fs.as_ref(), Path::new("/install/project-helper"))?,
+            b"helper",
+        );
+        assert_eq!(
+            fs.metadata(Path::new("/install/project-helper"))?.mode,
+            0o755
+        );
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install/README.md")), None);
+
+        Ok(())
+    }
+
+    #[test_case(
+        "test-data/project-with-helper.tar.gz",
+        "could not find any files matching [project-other] in the downloaded archive file";
+        "tarball"
+    )]
+    #[test_case(
+        "test-data/project-with-helper.zip",
+        "could not find any files matching [project-other] in the downloaded archive file";
+        "zip"
+    )]
+    #[test_case(
+        "test-data/project-with-helper.7z",
+        "could not find any files matching [project-other] in the downloaded archive file";
+        "7z"
+    )]
+    #[test_case(
+        "test-data/project.gz",
+        "cannot install more than one executable from test-data/project.gz, which is not an archive file";
+        "not an archive"
+    )]
+    fn exe_installer_multiple_exes_errors(archive_path: &str, expect_err: &str) {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string(), "project-other".to_string()],
+            false,
+        )
+        .filesystem(fs.clone());
+        let res = installer.install(&Download::new(PathBuf::from(archive_path)));
+        assert_eq!(res.unwrap_err().to_string(), expect_err);
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install/project")), None);
+    }
+
+    #[test]
+    fn exe_installer_multiple_exes_with_match_by_digest() {
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string(), "project-helper".to_string()],
+            false,
+        )
+        .match_by_digest(Some((ChecksumAlgorithm::Sha256, "0".repeat(64))));
+        assert!(installer.validate().is_err());
+    }
+
+    #[test_case(
+        "test-data/project-with-debug-partial-match.tar.gz",
+        "project-linux-debug",
+        b"debug"
+    )]
+    #[test_case(
+        "test-data/project-with-debug-partial-match.zip",
+        "project-linux-debug",
+        b"debug"
+    )]
+    #[test_case(
+        "test-data/project-with-debug-partial-match.7z",
+        "project-linux-debug",
+        b"debug"
+    )]
+    #[test_case(
+        "test-data/project-with-debug-partial-match.tar.gz",
+        "project-[a-z]+",
+        b"exe"
+    )]
+    #[test_case(
+        "test-data/project-with-debug-partial-match.zip",
+        "project-[a-z]+",
+        b"exe"
+    )]
+    #[test_case(
+        "test-data/project-with-debug-partial-match.7z",
+        "project-[a-z]+",
+        b"exe"
+    )]
+    fn exe_installer_exe_pattern(archive_path: &str, pattern: &str, expect: &[u8]) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .exe_pattern(Some(Regex::new(&format!("^(?:{pattern})$"))?))
+            .filesystem(fs.clone());
+        installer.validate()?;
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, expect);
+
+        Ok(())
+    }
+
+    // With a pattern, partial matches of the executable name are not considered.
+    #[test_case("test-data/project-with-partial-match.tar.gz")]
+    #[test_case("test-data/project-with-partial-match.zip")]
+    #[test_case("test-data/project-with-partial-match.7z")]
+    fn exe_installer_exe_pattern_no_match(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .exe_pattern(Some(Regex::new("^project$")?))
+        .filesystem(Arc::new(MemoryFs::new()));
+        let res = installer.install(&Download::new(PathBuf::from(archive_path)));
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "could not find any files matching the pattern `^project$` in the downloaded archive file",
+        );
+
+        Ok(())
+    }
+
+    #[test_case("test-data/inscrutable-names.tar.gz")]
+    #[test_case("test-data/inscrutable-names.zip")]
+    #[test_case("test-data/inscrutable-names.7z")]
+    fn exe_installer_match_by_digest(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .filesystem(fs.clone())
+            .match_by_digest(Some((
+                ChecksumAlgorithm::Sha256,
+                // This is the SHA-256 digest of "exe".
+                "9095bdb859308b62acf04036ffd4adfe366d7f737d276eb6c46ae434f3816c9b".to_string(),
+            )));
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, b"exe");
+        #[cfg(target_family = "unix")]
+        assert!(fs.metadata(&install_path)?.mode & 0o111 != 0);
+
+        let installer = ExeInstaller::new(install_path, vec!["project".to_string()], false)
+            .filesystem(fs)
+            .match_by_digest(Some((ChecksumAlgorithm::Sha256, "0".repeat(64))));
+        let res = installer.install(&Download::new(PathBuf::from(archive_path)));
+        assert!(res.is_err());
+
+        Ok(())
+    }
+
+    // The members of an ar file are hashed while we read their headers, and the one that matches
+    // is extracted on a second pass over the file.
+    #[test]
+    fn exe_installer_match_by_digest_in_ar_file() -> Result<()> {
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["other".to_string()], false)
+            .filesystem(fs.clone())
+            .match_by_digest(Some((
+                ChecksumAlgorithm::Sha256,
+                // This is the SHA-256 digest of "foo".
+                "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae".to_string(),
+            )));
+        installer.install(&Download::new(PathBuf::from("test-data/project.ar")))?;
+
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, b"foo");
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", None; "valid")]
+    #[test_case(
+        "test-data/project.zip",
+        Some("could not verify the minisign signature for project.zip");
+        "wrong file"
+    )]
+    fn exe_installer_verifies_minisign_signature(
+        archive_path: &str,
+        expect_err: Option<&str>,
+    ) -> Result<()> {
+        let download = Download::new(PathBuf::from(archive_path)).minisign_signature(
+            "RWQBI0VniavN7+pKbGPinFIKvvVQexMuxfmVR3auvr57kkIe6mkURtIs",
+            &fs::read_to_string("test-data/project.tar.gz.minisig")?,
+        );
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .filesystem(fs.clone());
+        let res = installer.install(&download);
+        match expect_err {
+            None => {
+                res?;
+                assert_eq!(kind_of(fs.as_ref(), &install_path), Some(FileKind::File));
+            }
+            Some(expect_err) => {
+                let err = res.expect_err("verification fails").to_string();
+                assert!(err.contains(expect_err), "{err} contains {expect_err}");
+                assert_eq!(kind_of(fs.as_ref(), &install_path), None);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test_case(ChecksumAlgorithm::Sha256, "abc123"; "too short")]
+    #[test_case(ChecksumAlgorithm::Sha512, &"a".repeat(64); "sha256 length for sha512")]
+    #[test_case(ChecksumAlgorithm::Sha256, &"z".repeat(64); "not hex")]
+    fn exe_installer_validate_rejects_bad_digest(algorithm: ChecksumAlgorithm, digest: &str) {
+        let installer =
+            ExeInstaller::new(PathBuf::from("project"), vec!["project".to_string()], false)
+                .match_by_digest(Some((algorithm, digest.to_string())));
+        assert!(installer.validate().is_err());
+    }
+
+    #[test]
+    fn exe_installer_validate_accepts_good_digest() -> Result<()> {
+        let installer =
+            ExeInstaller::new(PathBuf::from("project"), vec!["project".to_string()], false)
+                .match_by_digest(Some((ChecksumAlgorithm::Sha256, "A".repeat(64))));
+        installer.validate()
+    }
+
+    #[test_case("test-data/project.7z")]
+    #[test_case("test-data/project.tar")]
+    #[test_case("test-data/project.tar.bz")]
+    #[test_case("test-data/project.tar.bz2")]
+    #[test_case("test-data/project.tar.gz")]
+    #[test_case("test-data/project.tar.lz")]
+    #[test_case("test-data/project.tar.br")]
+    #[test_case("test-data/project.tar.lz4")]
+    #[test_case("test-data/project.tar.lzma")]
+    #[test_case("test-data/project.tar.xz")]
+    #[test_case("test-data/project.tar.Z")]
+    #[test_case("test-data/project.tar.zst")]
+    #[test_case("test-data/project.zip")]
+    #[test_case("test-data/project-zip64.zip")]
+    #[test_case("test-data/project-multi-stream.tar.xz")]
+    #[test_case("test-data/project-multi-member.tar.gz")]
+    fn archive_installer(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let mut path_without_subdir = td.path().to_path_buf();
+        path_without_subdir.push("project");
+        let mut path_with_subdir = td.path().to_path_buf();
+        path_with_subdir.extend(&["subdir", "project"]);
+
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        for fs in [real_fs, memory_fs] {
+            for install_root in [&path_without_subdir, &path_with_subdir] {
+                let installer = ArchiveInstaller::new(install_root.clone()).filesystem(fs.clone());
+                installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+                assert_eq!(kind_of(fs.as_ref(), install_root), Some(FileKind::Dir));
+
+                let bin_dir = install_root.join("bin");
+                assert_eq!(kind_of(fs.as_ref(), &bin_dir), Some(FileKind::Dir));
+
+                let exe = bin_dir.join("project");
+                assert_eq!(kind_of(fs.as_ref(), &exe), Some(FileKind::File));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-in-same-name-dir.tar.gz")]
+    #[test_case("test-data/project-in-same-name-dir.zip")]
+    fn archive_installer_top_level_dir_has_entry_with_same_name(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        for fs in [real_fs, memory_fs] {
+            let installer = ArchiveInstaller::new(install_root.clone()).filesystem(fs.clone());
+            installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+            let mut entries = fs.read_dir(&install_root)?;
+            entries.sort();
+            assert_eq!(
+                entries,
+                vec![install_root.join("project"), install_root.join("share")],
+            );
+            assert_eq!(
+                kind_of(fs.as_ref(), &install_root.join("project")),
+                Some(FileKind::File),
+            );
+            assert_eq!(
+                kind_of(fs.as_ref(), &install_root.join("share").join("README.md")),
+                Some(FileKind::File),
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test_case("test-data/toolkit-with-scattered-exes.tar.gz", &[], &["tool-a", "tool-b"])]
+    #[test_case("test-data/toolkit-with-scattered-exes.zip", &[], &["tool-a", "tool-b"])]
+    #[test_case(
+        "test-data/toolkit-with-scattered-exes.tar.gz",
+        &["helper"],
+        &["helper.sh", "tool-a", "tool-b"];
+        "with extra names"
+    )]
+    fn archive_installer_flatten_executables(
+        archive_path: &str,
+        names: &[&str],
+        expect: &[&str],
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("bin");
+
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        for fs in [real_fs, memory_fs] {
+            let installer = ArchiveInstaller::new(install_root.clone())
+                .flatten_executables(true)
+                .flatten_executable_names(names.iter().map(ToString::to_string).collect())
+                .filesystem(fs.clone());
+            let skipped =
+                installer.extract_reporting_skipped(&Download::new(PathBuf::from(archive_path)))?;
+
+            let mut entries = fs.read_dir(&install_root)?;
+            entries.sort();
+            assert_eq!(
+                entries,
+                expect
+                    .iter()
+                    .map(|name| install_root.join(name))
+                    .collect::<Vec<_>>(),
+            );
+            assert!(
+                skipped
+                    .iter()
+                    .any(|s| s.path == Path::new("README.md")
+                        && s.reason == SkipReason::NotExecutable)
+            );
+            fs.remove_dir_all(&install_root)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_flatten_executables_with_duplicate_names() -> Result<()> {
+        let td = tempdir()?;
+        let install_root = td.path().join("bin");
+        let installer = ArchiveInstaller::new(install_root.clone()).flatten_executables(true);
+        let err = installer
+            .install(&Download::new(PathBuf::from(
+                "test-data/toolkit-with-duplicate-exe-names.tar.gz",
+            )))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "cannot flatten the executables {} and {} into {} because they have the same file \
+                 name",
+                Path::new("bin").join("tool").display(),
+                Path::new("libexec").join("tool").display(),
+                install_root.display(),
+            ),
+        );
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn archive_installer_extract_appimage() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+        let download = Download::new(PathBuf::from("test-data/project-self-extracting.AppImage"));
+
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        for fs in [real_fs, memory_fs] {
+            let installer = ArchiveInstaller::new(install_root.clone()).filesystem(fs.clone());
+            assert!(installer.install(&download).is_err());
+
+            let installer = installer.extract_appimage(true);
+            installer.install(&download)?;
+
+            assert_eq!(
+                fs.read_link(&install_root.join("AppRun"))?,
+                Path::new("usr/bin/project"),
+            );
+            let exe = install_root.join("usr").join("bin").join("project");
+            assert_eq!(fs.metadata(&exe)?.mode & 0o777, 0o755);
+            assert_eq!(
+                kind_of(fs.as_ref(), &install_root.join("project.desktop")),
+                Some(FileKind::File),
+            );
+            fs.remove_dir_all(&install_root)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_extract_appimage_dry_run() {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ArchiveInstaller::new(PathBuf::from("/install/project"))
+            .extract_appimage(true)
+            .filesystem(fs.clone());
+        let err = installer
+            .install_dry_run(&Download::new(PathBuf::from(
+                "test-data/project-self-extracting.AppImage",
+            )))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot do a dry run of extracting test-data/project-self-extracting.AppImage because \
+             extracting an AppImage requires running it",
+        );
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install/project")), None);
+    }
+
+    #[test_case("/install/project", &["project"], "bin/project", "/install/project")]
+    #[test_case("/install/project", &["project"], "bin/project.exe", "/install/project.exe")]
+    #[test_case("/install/project", &["project"], "project.pyz", "/install/project.pyz")]
+    #[test_case("/install/project.pyz", &["project.pyz"], "project.pyz", "/install/project.pyz"; "same extension")]
+    #[test_case("/install/project.PYZ", &["project.PYZ"], "project.pyz", "/install/project.PYZ"; "same extension in another case")]
+    #[test_case("/install/project.v2", &["project.v2"], "project.v2.pyz", "/install/project.v2"; "different extension")]
+    #[test_case("/install/project", &["project"], "project.tar.gz", "/install/project"; "archive")]
+    #[test_case("/install/", &["project"], "bin/project-linux.exe", "/install/project-linux.exe")]
+    #[test_case("/install/project", &["project", "other"], "bin/other", "/install/other")]
+    fn resolve_install_path(
+        install_path: &str,
+        exes: &[&str],
+        member_path: &str,
+        expect: &str,
+    ) -> Result<()> {
+        let installer = ExeInstaller::new(
+            PathBuf::from(install_path),
+            exes.iter().map(ToString::to_string).collect(),
+            false,
+        )
+        .filesystem(Arc::new(MemoryFs::new()));
+        assert_eq!(
+            installer.resolve_install_path(Path::new(member_path))?,
+            PathBuf::from(expect),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn retry_if_locked_does_not_retry_other_errors() {
+        let mut calls = 0;
+        let res: io::Result<()> = super::retry_if_locked(|| {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(res.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[cfg(target_family = "windows")]
+    #[test]
+    fn retry_if_locked_retries_sharing_violations() -> Result<()> {
+        let mut calls = 0;
+        super::retry_if_locked(|| {
+            calls += 1;
+            if calls < 3 {
+                // This is ERROR_SHARING_VIOLATION.
+                return Err(io::Error::from_raw_os_error(32));
+            }
+            Ok(())
+        })?;
+        assert_eq!(calls, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unused_path() -> Result<()> {
+        let fs = MemoryFs::new();
+        let root = PathBuf::from("/root");
+        fs.create_dir_all(&root.join("project.ubi-move"))?;
+        fs.create_dir_all(&root.join("project.ubi-move-1"))?;
+
+        assert_eq!(
+            super::unused_path(&fs, &root.join("project"), "ubi-move"),
+            root.join("project.ubi-move-2"),
+        );
+        assert_eq!(
+            super::unused_path(&fs, &root.join("other"), "ubi-move"),
+            root.join("other.ubi-move"),
+        );
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz")]
+    #[test_case("test-data/project.zip")]
+    fn archive_installer_staged_in_temp_dir(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("subdir").join("project");
+        let installer = ArchiveInstaller::new(install_root.clone()).stage_in_temp_dir(true);
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        let exe = install_root.join("bin").join("project");
+        assert!(exe.exists());
+        assert!(exe.is_file());
+
+        Ok(())
+    }
+
+    #[test_case(
+        "test-data/project.tar.gz",
+        "539d9c66d338e329584755476f5a1e57a89f35b22f3d0979da14e61d8fa20447";
+        "tar.gz"
+    )]
+    #[test_case(
+        "test-data/project.zip",
+        "2288E7DB202A6DE50D6AFC3D82AF640155E928C1D469EBA107F23BE8298BC68E";
+        "zip with uppercase digest"
+    )]
+    fn archive_installer_verify_digest(archive_path: &str, digest: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .filesystem(fs.clone())
+            .expected_digest(Some((ChecksumAlgorithm::Sha256, digest.to_string())));
+        installer.validate()?;
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        let exe = install_root.join("bin").join("project");
+        assert_eq!(kind_of(fs.as_ref(), &exe), Some(FileKind::File));
+
+        Ok(())
+    }
+
+    #[test_case(
+        "test-data/project.tar.gz",
+        "0000000000000000000000000000000000000000000000000000000000000000";
+        "tar.gz with wrong digest"
+    )]
+    // The digest must cover the whole file, not just the parts the decoder needed to read.
+    #[test_case(
+        "test-data/project-with-trailing-bytes.tar.gz",
+        "539d9c66d338e329584755476f5a1e57a89f35b22f3d0979da14e61d8fa20447";
+        "tar.gz with trailing bytes"
+    )]
+    #[test_case(
+        "test-data/project.zip",
+        "0000000000000000000000000000000000000000000000000000000000000000";
+        "zip with wrong digest"
+    )]
+    fn archive_installer_verify_digest_mismatch(archive_path: &str, digest: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .filesystem(fs.clone())
+            .expected_digest(Some((ChecksumAlgorithm::Sha256, digest.to_string())));
+        let res = installer.install(&Download::new(PathBuf::from(archive_path)));
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains(&format!("but we expected {digest}")));
+        // Everything that was extracted before the mismatch was found must be gone.
+        assert_eq!(kind_of(fs.as_ref(), &install_root.join("project")), None);
+        assert_eq!(kind_of(fs.as_ref(), &install_root.join("bin")), None);
+        assert!(
+            kind_of(fs.as_ref(), &install_root).is_none() || fs.read_dir(&install_root)?.is_empty()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_zip_with_backslashes() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        for fs in [real_fs, memory_fs] {
+            ArchiveInstaller::new(install_root.clone())
+                .filesystem(fs.clone())
+                .install(&Download::new(PathBuf::from(
+                    "test-data/windows-project-exe-with-backslashes.zip",
+                )))?;
+            assert_eq!(
+                kind_of(fs.as_ref(), &install_root.join("bin").join("project.exe")),
+                Some(FileKind::File),
+            );
+            fs.remove_dir_all(&install_root)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_parallel_zip_extraction() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let serial_root = td.path().join("serial");
+        let parallel_root = td.path().join("parallel");
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        for fs in [real_fs, memory_fs] {
+            for (root, parallel) in [(&serial_root, false), (&parallel_root, true)] {
+                ArchiveInstaller::new(root.clone())
+                    .parallel_zip_extraction(parallel)
+                    .filesystem(fs.clone())
+                    .install(&Download::new(PathBuf::from(
+                        "test-data/toolkit-with-scattered-exes.zip",
+                    )))?;
+            }
+
+            for file in [
+                "README.md",
+                "bin/tool-a",
+                "libexec/sub/tool-b",
+                "share/helper.sh",
+            ] {
+                let read = |root: &Path| -> Result<(Vec<u8>, u32)> {
+                    let path = root.join(file);
+                    let mut contents = vec![];
+                    fs.open_file(&path)?.read_to_end(&mut contents)?;
+                    Ok((contents, fs.metadata(&path)?.mode & 0o777))
+                };
+                assert_eq!(read(&parallel_root)?, read(&serial_root)?, "{file}");
+            }
+
+            fs.remove_dir_all(&serial_root)?;
+            fs.remove_dir_all(&parallel_root)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_entry_path() {
+        assert_eq!(
+            super::zip_entry_path("project\\bin\\project.exe"),
+            Path::new("project/bin/project.exe"),
+        );
+        assert_eq!(
+            super::zip_entry_path("project/bin/project"),
+            Path::new("project/bin/project"),
+        );
+    }
+
+    #[test_case("test-data/toolkit-with-scattered-exes.tar.gz")]
+    #[test_case("test-data/toolkit-with-scattered-exes.zip")]
+    fn executable_candidates_from_modes(archive_path: &str) -> Result<()> {
+        assert_eq!(
+            executable_candidates(Path::new(archive_path))?,
+            vec![
+                ExecutableCandidate {
+                    path: "toolkit/libexec/sub/tool-b".to_string(),
+                    size: 3,
+                    is_executable: true,
+                },
+                ExecutableCandidate {
+                    path: "toolkit/bin/tool-a".to_string(),
+                    size: 3,
+                    is_executable: true,
+                },
+            ],
+        );
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", "./project/bin/project", false)]
+    #[test_case("test-data/project.zip", "project/bin/project", false)]
+    #[test_case("test-data/windows-project-exe.zip", "project/bin/project.exe", true)]
+    #[test_case("test-data/windows-project-bat.zip", "project/bin/project.bat", true)]
+    fn executable_candidates_from_names(
+        archive_path: &str,
+        expect_path: &str,
+        expect_executable: bool,
+    ) -> Result<()> {
+        assert_eq!(
+            executable_candidates(Path::new(archive_path))?,
+            vec![ExecutableCandidate {
+                path: expect_path.to_string(),
+                size: 3,
+                is_executable: expect_executable,
+            }],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn executable_candidates_not_an_archive() {
+        let err = executable_candidates(Path::new("test-data/project.gz")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot list the executables in test-data/project.gz because it is not a tarball or \
+             zip file",
+        );
+    }
+
+    #[test_case("test-data/project.tar.gz")]
+    #[test_case("test-data/project.zip")]
+    fn archive_installer_replace_install_root(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        for fs in [real_fs, memory_fs] {
+            fs.create_dir_all(&install_root)?;
+            fs.create_file(&install_root.join("old-file"))?
+                .write_all(b"old")?;
+
+            // A failed extraction leaves the previous install alone.
+            let installer = ArchiveInstaller::new(install_root.clone())
+                .replace_install_root(true)
+                .filesystem(fs.clone());
+            let res = installer
+                .clone()
+                .expected_digest(Some((ChecksumAlgorithm::Sha256, "0".repeat(64))))
+                .install(&Download::new(PathBuf::from(archive_path)));
+            assert!(res.is_err());
+            assert_eq!(
+                fs.read_dir(&install_root)?,
+                vec![install_root.join("old-file")],
+            );
+
+            let entries = installer.install(&Download::new(PathBuf::from(archive_path)))?;
+            assert_eq!(entries, vec![install_root.join("bin")]);
+            assert_eq!(
+                kind_of(fs.as_ref(), &install_root.join("bin").join("project")),
+                Some(FileKind::File),
+            );
+            // Nothing is left next to the install root.
+            assert_eq!(fs.read_dir(td.path())?, vec![install_root.clone()]);
+            fs.remove_dir_all(&install_root)?;
+        }
+
+        Ok(())
+    }
+
+    #[test_case("abc"; "too short")]
+    #[test_case(
+        "zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz";
+        "not hex"
+    )]
+    fn archive_installer_validate_rejects_bad_digest(digest: &str) {
+        let installer = ArchiveInstaller::new(PathBuf::from("project"))
+            .expected_digest(Some((ChecksumAlgorithm::Sha256, digest.to_string())));
+        assert!(installer.validate().is_err());
+    }
+
+    // We can't count on the tests running with two filesystems available, so this tests the
+    // fallback used when renaming across filesystems fails directly.
+    #[test]
+    fn copy_tree_then_remove_moves_everything() -> Result<()> {
+        let td = tempdir()?;
+        let src = td.path().join("src");
+        create_dir_all(src.join("bin"))?;
+        fs::write(src.join("bin").join("project"), "exe")?;
+        fs::write(src.join("README.md"), "readme")?;
+        #[cfg(target_family = "unix")]
+        std::os::unix::fs::symlink("bin/project", src.join("project"))?;
+
+        let dst = td.path().join("dst");
+        copy_tree_then_remove(&RealFs, &src, &dst)?;
+
+        assert!(!src.exists());
+        assert_eq!(fs::read_to_string(dst.join("bin").join("project"))?, "exe");
+        assert_eq!(fs::read_to_string(dst.join("README.md"))?, "readme");
+        #[cfg(target_family = "unix")]
+        assert_eq!(
+            fs::read_link(dst.join("project"))?,
+            PathBuf::from("bin/project")
+        );
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", 0, "project/bin/project")]
+    #[test_case("test-data/project.tar.gz", 1, "bin/project")]
+    #[test_case("test-data/project.tar.gz", 2, "project")]
+    #[test_case("test-data/project.zip", 0, "project/bin/project")]
+    #[test_case("test-data/project.zip", 2, "project")]
+    #[test_case("test-data/project.7z", 0, "project/bin/project")]
+    #[test_case("test-data/project.7z", 2, "project")]
+    fn archive_installer_strip_components(
+        archive_path: &str,
+        strip_components: usize,
+        expect: &str,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .strip_components(Some(strip_components))
+            .filesystem(fs.clone());
+        let paths = installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        let expect = install_root.join(expect);
+        let top_level = expect
+            .ancestors()
+            .find(|p| p.parent() == Some(&install_root))
+            .unwrap();
+        assert_eq!(paths, vec![top_level.to_path_buf()]);
+        assert_eq!(read_file(fs.as_ref(), &expect)?, b"exe");
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", 1_737_238_421)]
+    #[test_case("test-data/project.zip", 1_737_238_772; "extended timestamp")]
+    #[test_case("test-data/project-with-mode.zip", 1_735_689_600; "MS-DOS timestamp")]
+    fn archive_installer_preserve_mtime(archive_path: &str, expect_secs: u64) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+        let installer = ArchiveInstaller::new(install_root.clone()).preserve_mtime(true);
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(
+            fs::metadata(install_root.join("bin/project"))?.modified()?,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(expect_secs),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_completion_dirs() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/project");
+        let bash_dir = PathBuf::from("/completions/bash");
+        let fish_dir = PathBuf::from("/completions/fish");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .completion_dirs(vec![
+                (Shell::Bash, bash_dir.clone()),
+                (Shell::Fish, fish_dir.clone()),
+            ])
+            .filesystem(fs.clone());
+        let download = Download::new(PathBuf::from("test-data/project-with-completions.tar.gz"));
+        installer.install(&download)?;
+
+        for path in [
+            bash_dir.join("project.bash"),
+            fish_dir.join("project.fish"),
+            install_root.join("completions/project.bash"),
+            install_root.join("completions/_project"),
+            install_root.join("bin/project"),
+        ] {
+            assert_eq!(
+                kind_of(fs.as_ref(), &path),
+                Some(FileKind::File),
+                "{}",
+                path.display(),
+            );
+        }
+        assert_eq!(fs.read_dir(&bash_dir)?, vec![bash_dir.join("project.bash")]);
+        assert_eq!(fs.read_dir(&fish_dir)?, vec![fish_dir.join("project.fish")]);
+
+        let paths = ArchiveInstaller::new(install_root.clone())
+            .completion_dirs(vec![(Shell::Bash, bash_dir.clone())])
+            .install_dry_run(&download)?;
+        assert!(paths.contains(&bash_dir.join("project.bash")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_completion_dirs_without_completions() -> Result<()> {
+        let fs = Arc::new(MemoryFs::new());
+        let bash_dir = PathBuf::from("/completions/bash");
+        ArchiveInstaller::new(PathBuf::from("/project"))
+            .completion_dirs(vec![(Shell::Bash, bash_dir.clone())])
+            .filesystem(fs.clone())
+            .install(&Download::new(PathBuf::from("test-data/project.tar.gz")))?;
+
+        assert_eq!(kind_of(fs.as_ref(), &bash_dir), None);
+
+        Ok(())
+    }
+
+    #[test_case(
+        b"<!DOCTYPE html>\n<html><head><title>404 Not Found</title>",
+        Some("<!DOCTYPE html>");
+        "html page"
+    )]
+    #[test_case(b"\n\n  Not Found  \n", Some("Not Found"); "text with leading blank lines")]
+    #[test_case(b"\x89PNG\r\n\x1a\n\x00\x00", None; "binary")]
+    #[test_case(b"caf\xc3", Some("caf"); "text cut off in a character")]
+    #[test_case(b"", None; "empty")]
+    fn first_line_of_text(bytes: &[u8], expect: Option<&str>) {
+        assert_eq!(super::first_line_of_text(bytes).as_deref(), expect);
+    }
+
+    #[test]
+    fn installers_reject_error_pages() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let page = td.path().join("project.tar.gz");
+        fs::write(&page, "<html>\n<body>Not Found</body>\n</html>\n")?;
+        let download = Download::new(page.clone());
+
+        let exe_installer = ExeInstaller::new(
+            td.path().join("bin").join("project"),
+            vec!["project".to_string()],
+            false,
+        );
+        let archive_installer = ArchiveInstaller::new(td.path().join("project"));
+        for res in [
+            exe_installer.install(&download),
+            archive_installer.install(&download),
+        ] {
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                format!(
+                    "the downloaded file at {} does not look like a .tar.gz file, it starts with \
+                     `<html>` - this can happen when the download URL returns an error page \
+                     instead of the release asset",
+                    page.display(),
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_temp_dir() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+        let temp_dir = td.path().join("tmp");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .stage_in_temp_dir(true)
+            .temp_dir(Some(temp_dir.clone()));
+        installer.validate()?;
+        installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")))?;
+
+        assert_eq!(
+            kind_of(&RealFs, &install_root.join("bin").join("project")),
+            Some(FileKind::File),
+        );
+        // The staging dir is created in the temp dir and removed once it's been moved into place.
+        assert_eq!(RealFs.read_dir(&temp_dir)?, Vec::<PathBuf>::new());
+
+        let err = ArchiveInstaller::new(install_root.clone())
+            .temp_dir(Some(install_root.join("tmp")))
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "the temp dir, {}, cannot be inside the install root, {}",
+                install_root.join("tmp").display(),
+                install_root.display(),
+            ),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_bin_dir() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+        let bin_dir = td.path().join("bin");
+        let real_fs: Arc<dyn FileSystem> = Arc::new(RealFs);
+        let memory_fs: Arc<dyn FileSystem> = Arc::new(MemoryFs::new());
+        for fs in [real_fs, memory_fs] {
+            let installer = ArchiveInstaller::new(install_root.clone())
+                .bin_dir(Some(bin_dir.clone()))
+                .filesystem(fs.clone());
+            // Installing a second time replaces the links from the first install.
+            for _ in 0..2 {
+                installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")))?;
+            }
+
+            let link = bin_dir.join("project");
+            if cfg!(target_family = "windows") {
+                assert_eq!(kind_of(fs.as_ref(), &link), Some(FileKind::File));
+            } else {
+                assert_eq!(
+                    fs.read_link(&link)?,
+                    install_root.join("bin").join("project")
+                );
+            }
+            assert_eq!(
+                kind_of(fs.as_ref(), &install_root.join("bin").join("project")),
+                Some(FileKind::File),
+            );
+
+            fs.remove_dir_all(&install_root)?;
+            fs.remove_dir_all(&bin_dir)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_path_mapper() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .strip_components(Some(1))
+            .path_mapper(Some(PathMapper::new(|path| {
+                path.strip_prefix("man")
+                    .ok()
+                    .map(|p| Path::new("share").join("man").join(p))
+            })))
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(
+            "test-data/project-with-man-pages.tar.gz",
+        )))?;
+
+        let mut paths = vec![];
+        list_files(fs.as_ref(), &install_root, &mut paths)?;
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "bin/project",
+                "share/man/README.md",
+                "share/man/man1/project.1",
+                "share/man/man5/project.conf.5.gz",
+            ]
+            .iter()
+            .map(|p| install_root.join(p))
+            .collect::<Vec<_>>(),
+        );
+
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .path_mapper(Some(PathMapper::new(|_| Some(PathBuf::from("../escape")))))
+            .filesystem(fs.clone());
+        let err = installer
+            .install(&Download::new(PathBuf::from("test-data/project.zip")))
+            .unwrap_err();
+        assert!(
+            matches!(&err, InstallError::PathTraversal { member, .. } if member == Path::new("../escape")),
+            "{err:?}",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_man_dir() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/project");
+        let man_dir = PathBuf::from("/share/man");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .man_dir(Some(man_dir.clone()))
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(
+            "test-data/project-with-man-pages.tar.gz",
+        )))?;
+
+        let mut man_pages = vec![];
+        list_files(fs.as_ref(), &man_dir, &mut man_pages)?;
+        man_pages.sort();
+        assert_eq!(
+            man_pages,
+            vec![
+                man_dir.join("man1/project.1"),
+                man_dir.join("man5/project.conf.5.gz"),
+            ],
+        );
+        assert_eq!(
+            kind_of(fs.as_ref(), &install_root.join("man/man1/project.1")),
+            Some(FileKind::File),
+        );
+
+        Ok(())
+    }
+
+    #[test_case("man/man1/project.1", Some('1'))]
+    #[test_case("project-1.0.0/man/man1/project.1", Some('1'))]
+    #[test_case("man/man5/project.conf.5.gz", Some('5'))]
+    #[test_case("man/man1/project.5", None; "wrong section")]
+    #[test_case("man/man1/project", None; "no section")]
+    #[test_case("man/man1/.1", None; "only a section")]
+    #[test_case("man/man10/project.10", None; "section out of range")]
+    #[test_case("share/man1/project.1", None; "not in a man dir")]
+    #[test_case("man/project.1", None; "not in a section dir")]
+    fn man_page_section(path: &str, expect: Option<char>) {
+        assert_eq!(super::man_page_section(Path::new(path)), expect);
+    }
+
+    #[test_case("test-data/project.gz", None)]
+    #[test_case("test-data/project.tar.gz", Some(3))]
+    #[test_case("test-data/project.zip", Some(3))]
+    #[test_case("test-data/project.7z", Some(3))]
+    #[test_case("test-data/project.exe", Some(3))]
+    fn exe_installer_progress(archive_path: &str, expect_size: Option<u64>) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let calls = Arc::new(Mutex::new(vec![]));
+        let progress = {
+            let calls = calls.clone();
+            Progress::new(move |written, size| calls.lock().unwrap().push((written, size)))
+        };
+        let installer = ExeInstaller::new(
+            PathBuf::from("/bin/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .progress(Some(progress))
+        .filesystem(Arc::new(MemoryFs::new()));
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![(0, expect_size), (3, expect_size)],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_progress() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let calls = Arc::new(Mutex::new(vec![]));
+        let progress = {
+            let calls = calls.clone();
+            Progress::new(move |written, size| calls.lock().unwrap().push((written, size)))
+        };
+        let installer = ArchiveInstaller::new(PathBuf::from("/project"))
+            .progress(Some(progress))
+            .filesystem(Arc::new(MemoryFs::new()));
+        installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")))?;
+
+        let calls = calls.lock().unwrap();
+        assert!(calls.contains(&(3, Some(3))), "{calls:?}");
+        assert!(calls.iter().all(|(written, size)| Some(*written) <= *size));
+
+        Ok(())
+    }
+
+    #[test_case(1970, 1, 1, 0)]
+    #[test_case(1980, 1, 1, 3652)]
+    #[test_case(2000, 3, 1, 11017)]
+    #[test_case(2024, 2, 29, 19782)]
+    fn days_since_unix_epoch(year: u16, month: u8, day: u8, expect: i64) {
+        assert_eq!(super::days_since_unix_epoch(year, month, day), expect);
+    }
+
+    #[test]
+    fn archive_installer_strip_components_too_many() -> Result<()> {
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .strip_components(Some(3))
+            .filesystem(fs.clone());
+        let paths = installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")))?;
+        assert!(paths.is_empty());
+
+        Ok(())
+    }
+
+    // This tests a bug in the initial implementation where a tarball that just contained files
+    // caused us to try to move its contents up to a directory that didn't exist.
+    #[test]
+    fn archive_installer_one_file_in_archive_root() -> Result<()> {
+        let fs = Arc::new(MemoryFs::new());
+        for install_root in [
+            PathBuf::from("/install/project"),
+            PathBuf::from("/install/subdir/project"),
+        ] {
+            let installer = ArchiveInstaller::new(install_root.clone()).filesystem(fs.clone());
+            let paths = installer.install(&Download::new(PathBuf::from(
+                "test-data/project-with-one-file.tar.gz",
+            )))?;
+            assert_eq!(paths, vec![install_root.join("project")]);
+
+            assert_eq!(kind_of(fs.as_ref(), &install_root), Some(FileKind::Dir));
+
+            let exe = install_root.join("project");
+            assert_eq!(kind_of(fs.as_ref(), &exe), Some(FileKind::File));
+        }
+
+        Ok(())
+    }
+
+    #[test_case(false)]
+    #[test_case(true)]
+    fn archive_installer_dot_prefixed_entries(stage_in_temp_dir: bool) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .stage_in_temp_dir(stage_in_temp_dir)
+            .filesystem(fs.clone());
+        let paths = installer.install(&Download::new(PathBuf::from(
+            "test-data/project-with-dot-prefixed-entries.tar.gz",
+        )))?;
+        assert_eq!(
+            paths,
+            vec![install_root.join("README.md"), install_root.join("bin")],
+        );
+
+        assert_eq!(
+            kind_of(fs.as_ref(), &install_root.join("bin").join("project")),
+            Some(FileKind::File),
+        );
+        assert_eq!(
+            kind_of(fs.as_ref(), &install_root.join("project-x86_64-linux")),
+            None,
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_no_root_path() -> Result<()> {
+        let fs = Arc::new(MemoryFs::new());
+        for install_root in [
+            PathBuf::from("/install/project"),
+            PathBuf::from("/install/subdir/project"),
+        ] {
+            let installer = ArchiveInstaller::new(install_root.clone()).filesystem(fs.clone());
+            let paths = installer.install(&Download::new(PathBuf::from(
+                "test-data/no-shared-root.tar.gz",
+            )))?;
+            assert_eq!(
+                paths,
+                vec![install_root.join("README.md"), install_root.join("bin")],
+            );
+
+            assert_eq!(kind_of(fs.as_ref(), &install_root), Some(FileKind::Dir));
+
+            let bin_dir = install_root.join("bin");
+            assert_eq!(kind_of(fs.as_ref(), &bin_dir), Some(FileKind::Dir));
+
+            let exe = bin_dir.join("project");
+            assert_eq!(kind_of(fs.as_ref(), &exe), Some(FileKind::File));
+
+            let readme = install_root.join("README.md");
+            assert_eq!(kind_of(fs.as_ref(), &readme), Some(FileKind::File));
+        }
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", false)]
+    #[test_case("test-data/project.tar.gz", true)]
+    #[test_case("test-data/project.zip", false)]
+    #[test_case("test-data/project-with-links.tar.gz", false)]
+    fn archive_installer_dry_run(archive_path: &str, stage_in_temp_dir: bool) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .filesystem(fs.clone())
+            .stage_in_temp_dir(stage_in_temp_dir);
+        let download = Download::new(PathBuf::from(archive_path));
+        let paths = installer.install_dry_run(&download)?;
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install")), None);
+
+        // The dry run should report exactly the files that a real install writes.
+        installer.install(&download)?;
+        let mut written = vec![];
+        list_files(fs.as_ref(), &install_root, &mut written)?;
+        written.sort();
+        assert_eq!(paths, written);
+        assert!(paths.contains(&install_root.join("bin").join("project")));
+
+        Ok(())
+    }
+
+    #[test_case(
+        "test-data/project-with-encrypted-member.zip",
+        "project/secret",
+        SkipReason::Encrypted
+    )]
+    #[test_case(
+        "test-data/project-with-links.tar.gz",
+        "project/escape",
+        SkipReason::UnsafeSymlink
+    )]
+    #[test_case(
+        "test-data/project-with-macos-junk.zip",
+        "__MACOSX/project/bin/._project",
+        SkipReason::Junk
+    )]
+    fn archive_installer_reports_skipped_members(
+        archive_path: &str,
+        skipped_path: &str,
+        reason: SkipReason,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .filesystem(fs.clone())
+            .report_skipped(true);
+        let (paths, skipped) =
+            installer.install_reporting_skipped(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(
+            skipped,
+            vec![SkippedMember {
+                path: PathBuf::from(skipped_path),
+                reason,
+            }],
+        );
+        assert!(paths.contains(&install_root.join("bin")), "{paths:?}");
+
+        let exe = install_root.join("bin").join("project");
+        assert_eq!(kind_of(fs.as_ref(), &exe), Some(FileKind::File));
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install/evil")), None);
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/tmp/evil")), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_reports_filtered_members() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .filesystem(fs.clone())
+            .strip_components(Some(1));
+        let (_, skipped) = installer.install_reporting_skipped(&Download::new(PathBuf::from(
+            "test-data/no-shared-root.tar.gz",
+        )))?;
+
+        // The `./` and `./bin/` directories are stripped too, but only files are reported.
+        assert_eq!(
+            skipped,
+            vec![SkippedMember {
+                path: PathBuf::from("./README.md"),
+                reason: SkipReason::Filtered,
+            }],
+        );
+        assert_eq!(
+            kind_of(fs.as_ref(), &install_root.join("project")),
+            Some(FileKind::File),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn install_error_kinds() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let exe_installer = |exe: &str| {
+            ExeInstaller::new(td.path().join(exe), vec![exe.to_string()], false)
+                .filesystem(Arc::new(MemoryFs::new()))
+        };
+        let archive_installer =
+            ArchiveInstaller::new(td.path().join("project")).filesystem(Arc::new(MemoryFs::new()));
+        let download = |path: &str| Download::new(PathBuf::from(path));
+
+        let err = exe_installer("other")
+            .install(&download("test-data/project.tar.gz"))
+            .unwrap_err();
+        assert!(
+            matches!(&err, InstallError::NoMatchingMember { looking_for } if looking_for == "matching [other*]"),
+            "{err:?}",
+        );
+
+        let err = archive_installer
+            .install(&download("test-data/project.cab"))
+            .unwrap_err();
+        assert!(
+            matches!(&err, InstallError::UnsupportedFormat { path, .. } if path == Path::new("test-data/project.cab")),
+            "{err:?}",
+        );
+
+        let err = exe_installer("project")
+            .install(&download("test-data/project.bar"))
+            .unwrap_err();
+        assert!(
+            matches!(&err, InstallError::UnsupportedFormat { path, .. } if path == Path::new("test-data/project.bar")),
+            "{err:?}",
+        );
+
+        let err = archive_installer
+            .install(&download("test-data/project-with-traversal.tar.gz"))
+            .unwrap_err();
+        assert!(
+            matches!(&err, InstallError::PathTraversal { member, .. } if member == Path::new("../evil")),
+            "{err:?}",
+        );
+
+        let mut mismatched = download("test-data/project.tar.gz");
+        mismatched.expected_digest = Some((ChecksumAlgorithm::Sha256, "0".repeat(64)));
+        let err = exe_installer("project").install(&mismatched).unwrap_err();
+        assert!(
+            matches!(&err, InstallError::ChecksumMismatch { expected, .. } if *expected == "0".repeat(64)),
+            "{err:?}",
+        );
+
+        let err = exe_installer("project")
+            .install(&download("test-data/does-not-exist.tar.gz"))
+            .unwrap_err();
+        assert!(matches!(&err, InstallError::ExtractionFailed(_)), "{err:?}",);
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-with-traversal.tar.gz", "../evil", false)]
+    #[test_case("test-data/project-with-traversal.tar.gz", "../evil", true)]
+    #[test_case("test-data/project-with-traversal.zip", "../evil", false)]
+    #[test_case("test-data/project-with-traversal.zip", "../evil", true)]
+    #[test_case("test-data/project-with-absolute-path.tar.gz", "/tmp/evil", false)]
+    #[test_case("test-data/project-with-absolute-path.zip", "/tmp/evil", false)]
+    fn archive_installer_rejects_traversal(
+        archive_path: &str,
+        member: &str,
+        stage_in_temp_dir: bool,
+    ) {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .filesystem(fs.clone())
+            .stage_in_temp_dir(stage_in_temp_dir);
+        let err = installer
+            .install(&Download::new(PathBuf::from(archive_path)))
+            .expect_err("extracting an archive with a member outside the root fails");
+
+        assert!(
+            err.to_string()
+                .contains(&format!("the archive member {member} ")),
+            "error names the offending member: {err}",
+        );
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install/evil")), None);
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/tmp/evil")), None);
+    }
+
+    #[test_case("test-data/project-with-unsafe-symlinks.tar.gz")]
+    #[test_case("test-data/project-with-unsafe-symlinks.zip")]
+    fn archive_installer_unsafe_symlinks(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone()).filesystem(fs.clone());
+        let skipped =
+            installer.extract_reporting_skipped(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(
+            skipped,
+            ["project/absolute", "project/outside", "project/sibling"]
+                .into_iter()
+                .map(|path| SkippedMember {
+                    path: PathBuf::from(path),
+                    reason: SkipReason::UnsafeSymlink,
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        assert_eq!(
+            fs.read_link(&install_root.join("lib").join("libfoo.so"))?,
+            PathBuf::from("libfoo.so.1"),
+        );
+        assert_eq!(
+            fs.read_link(&install_root.join("bin").join("libfoo.so"))?,
+            PathBuf::from("../lib/./libfoo.so"),
+        );
+        for unsafe_link in ["absolute", "outside", "sibling"] {
+            assert_eq!(kind_of(fs.as_ref(), &install_root.join(unsafe_link)), None);
+        }
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-in-same-name-dir.tar.gz", false, false)]
+    #[test_case("test-data/project-in-same-name-dir.zip", false, false)]
+    #[test_case("test-data/project-in-same-name-dir.tar.gz", true, false)]
+    #[test_case("test-data/project-in-same-name-dir.tar.gz", false, true)]
+    fn archive_installer_extraction_report(
+        archive_path: &str,
+        stage_in_temp_dir: bool,
+        replace_install_root: bool,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ArchiveInstaller::new(PathBuf::from("/install/project"))
+            .stage_in_temp_dir(stage_in_temp_dir)
+            .replace_install_root(replace_install_root)
+            .filesystem(fs.clone());
+        let report = installer
+            .install_with_extraction_report(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(
+            report,
+            ExtractionReport {
+                moved_up_dir: Some(PathBuf::from("project")),
+                extracted: vec![
+                    ExtractedPath {
+                        path: PathBuf::from("project"),
+                        moved_from: Some(PathBuf::from("project/project")),
+                    },
+                    ExtractedPath {
+                        path: PathBuf::from("share/README.md"),
+                        moved_from: Some(PathBuf::from("project/share/README.md")),
+                    },
+                ],
+                skipped: vec![],
+            },
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_installer_extraction_report_without_top_level_dir() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ArchiveInstaller::new(PathBuf::from("/install/project"))
+            .strip_components(Some(2))
+            .filesystem(fs.clone());
+        let report = installer.install_with_extraction_report(&Download::new(PathBuf::from(
+            "test-data/project-with-unsafe-symlinks.tar.gz",
+        )))?;
+
+        assert_eq!(report.moved_up_dir, None);
+        assert_eq!(
+            report
+                .extracted
+                .iter()
+                .map(|e| (e.path.to_string_lossy().into_owned(), e.moved_from.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("libfoo.so".to_string(), None),
+                ("libfoo.so.1".to_string(), None),
+                ("project".to_string(), None),
+            ],
+        );
+
+        Ok(())
+    }
+
+    // The `sibling` symlink is extracted, and then removed when the contents of the top-level
+    // directory are moved up, since it would point outside of the install root.
+    #[test]
+    fn archive_installer_extraction_report_unsafe_symlinks() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer =
+            ArchiveInstaller::new(PathBuf::from("/install/project")).filesystem(fs.clone());
+        let report = installer.install_with_extraction_report(&Download::new(PathBuf::from(
+            "test-data/project-with-unsafe-symlinks.tar.gz",
+        )))?;
+
+        assert_eq!(
+            report
+                .extracted
+                .iter()
+                .map(|e| e.path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>(),
+            [
+                "bin/libfoo.so",
+                "bin/project",
+                "lib/libfoo.so",
+                "lib/libfoo.so.1",
+            ],
+        );
+        assert_eq!(
+            report
+                .skipped
+                .iter()
+                .map(|s| s.path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>(),
+            ["project/absolute", "project/outside", "project/sibling"],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn exe_installer_extraction_report() {
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(Arc::new(MemoryFs::new()));
+        let err = installer
+            .install_with_extraction_report(&Download::new(PathBuf::from(
+                "test-data/project.tar.gz",
+            )))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot make an extraction report for test-data/project.tar.gz unless extracting all \
+             of its contents",
+        );
+    }
+
+    #[test]
+    fn archive_installer_links() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/install/project");
+        let installer = ArchiveInstaller::new(install_root.clone()).filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(
+            "test-data/project-with-links.tar.gz",
+        )))?;
+
+        let bin_dir = install_root.join("bin");
+        assert_eq!(
+            fs.read_link(&bin_dir.join("alias"))?,
+            PathBuf::from("project")
+        );
+        assert_eq!(read_file(fs.as_ref(), &bin_dir.join("hardlink"))?, b"exe");
+        assert_eq!(fs.metadata(&bin_dir.join("hardlink"))?.mode, 0o755);
+
+        Ok(())
+    }
+
+    #[test_case(OnExisting::Overwrite, Some((b"exe", true)))]
+    #[test_case(OnExisting::Skip, Some((b"old exe", false)))]
+    #[test_case(OnExisting::Error, None)]
+    fn exe_installer_on_existing(
+        on_existing: OnExisting,
+        expect: Option<(&[u8], bool)>,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        fs.create_dir_all(Path::new("/install"))?;
+        fs.create_file(&install_path)?.write_all(b"old exe")?;
+
+        let post_install_calls = Arc::new(Mutex::new(0));
+        let calls = post_install_calls.clone();
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .on_existing(on_existing)
+            .post_install(Some(PostInstall::new(move |_| {
+                *calls.lock().unwrap() += 1;
+                Ok(())
+            })))
+            .filesystem(fs.clone());
+        let res = installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")));
+
+        if let Some((contents, installed)) = expect {
+            // A skipped executable isn't returned, and the post-install callback isn't called for
+            // it.
+            let expect_paths = if installed {
+                vec![install_path.clone()]
+            } else {
+                vec![]
+            };
+            assert_eq!(res?, expect_paths);
+            assert_eq!(read_file(fs.as_ref(), &install_path)?, contents);
+            assert_eq!(*post_install_calls.lock().unwrap(), usize::from(installed));
+        } else {
+            assert!(res.is_err());
+            assert_eq!(read_file(fs.as_ref(), &install_path)?, b"old exe");
+        }
+
+        Ok(())
+    }
+
+    #[test_case(OnExisting::Overwrite, Some(true))]
+    #[test_case(OnExisting::Skip, Some(false))]
+    #[test_case(OnExisting::Error, None)]
+    fn exe_installer_dry_run_on_existing(
+        on_existing: OnExisting,
+        expect_installed: Option<bool>,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        fs.create_dir_all(Path::new("/install"))?;
+        fs.create_file(&install_path)?.write_all(b"old exe")?;
+
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .on_existing(on_existing)
+            .filesystem(fs.clone());
+        let res =
+            installer.install_dry_run(&Download::new(PathBuf::from("test-data/project.tar.gz")));
+
+        // The dry run sees the existing executable, so it reports the same thing a real install
+        // would do with it.
+        match expect_installed {
+            Some(true) => assert_eq!(res?, vec![install_path.clone()]),
+            Some(false) => assert!(res?.is_empty()),
+            None => assert!(res.is_err()),
+        }
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, b"old exe");
+
+        Ok(())
+    }
+
+    #[test_case(OnExisting::Overwrite, true, true)]
+    #[test_case(OnExisting::Skip, true, false)]
+    #[test_case(OnExisting::Skip, false, true; "skip with an empty install root")]
+    #[test_case(OnExisting::Error, true, false)]
+    #[test_case(OnExisting::Error, false, true; "error with an empty install root")]
+    fn archive_installer_on_existing(
+        on_existing: OnExisting,
+        has_existing_file: bool,
+        expect_extracted: bool,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_root = PathBuf::from("/project");
+        fs.create_dir_all(&install_root)?;
+        if has_existing_file {
+            fs.create_file(&install_root.join("README.md"))?
+                .write_all(b"old readme")?;
+        }
+
+        let installer = ArchiveInstaller::new(install_root.clone())
+            .on_existing(on_existing)
+            .filesystem(fs.clone());
+        let res = installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")));
+
+        if on_existing == OnExisting::Error && has_existing_file {
+            assert!(res.is_err());
+        } else {
+            res?;
+        }
+        // The archive's top-level directory is only moved up into the install root when the
+        // install root was empty, so we just check whether anything new showed up.
+        let entries = fs.read_dir(&install_root)?;
+        assert_eq!(
+            entries.len() > usize::from(has_existing_file),
+            expect_extracted,
+        );
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-in-two-dirs.tar.gz", None, b"other")]
+    #[test_case(
+        "test-data/project-in-two-dirs.tar.gz",
+        Some("project/linux"),
+        b"exe";
+        "dir without dot slash"
+    )]
+    #[test_case(
+        "test-data/project-in-two-dirs.tar.gz",
+        Some("./project/linux/"),
+        b"exe";
+        "dir with dot slash"
+    )]
+    #[test_case("test-data/project-in-two-dirs.zip", None, b"other")]
+    #[test_case("test-data/project-in-two-dirs.zip", Some("project/linux"), b"exe")]
+    fn exe_installer_member_dir(
+        archive_path: &str,
+        member_dir: Option<&str>,
+        expect_contents: &[u8],
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .member_dir(member_dir.map(PathBuf::from))
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, expect_contents);
+
+        Ok(())
+    }
+
+    // The name-based matching would pick `project-linux` in these.
+    #[test_case(
+        "test-data/project-with-debug-partial-match.tar.gz",
+        "project/bin/project-linux-debug"
+    )]
+    #[test_case(
+        "test-data/project-with-debug-partial-match.zip",
+        "./project/bin/project-linux-debug"
+    )]
+    #[test_case(
+        "test-data/project-with-debug-partial-match.7z",
+        "project/bin/project-linux-debug"
+    )]
+    fn exe_installer_member_path(archive_path: &str, member_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .member_path(Some(PathBuf::from(member_path)))
+            .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+        assert_eq!(read_file(fs.as_ref(), &install_path)?, b"debug");
+
+        // A member with the same file name in a different directory is not a match.
+        let err = installer
+            .member_path(Some(PathBuf::from("bin/project-linux-debug")))
+            .install(&Download::new(PathBuf::from(archive_path)))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "could not find any files at the path bin/project-linux-debug in the downloaded \
+             archive file",
+        );
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-in-two-dirs.tar.gz")]
+    #[test_case("test-data/project-in-two-dirs.zip")]
+    fn exe_installer_reject_ambiguous_matches(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .reject_ambiguous_matches(true)
+        .filesystem(Arc::new(MemoryFs::new()));
+        let err = installer
+            .install(&Download::new(PathBuf::from(archive_path)))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "found more than one archive member that matches the executable name project: \
+             project/windows/project, project/linux/project",
+        );
+
+        // With a member dir, there's only one exact match left.
+        let installer = installer.member_dir(Some(PathBuf::from("project/linux")));
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_password() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let download = Download::new(PathBuf::from("test-data/project-with-encrypted-exe.zip"));
+        let exe_installer = |password: Option<&str>| {
+            ExeInstaller::new(
+                PathBuf::from("/install/project"),
+                vec!["project".to_string()],
+                false,
+            )
+            .zip_password(password.map(ToString::to_string))
+            .filesystem(fs.clone())
+        };
+
+        let err = exe_installer(None).install(&download).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the archive member project/bin/project is encrypted, and ubi cannot extract \
+             password-protected zip file members without the password",
+        );
+        let err = exe_installer(Some("wrong")).install(&download).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the zip file password is not correct for the archive member project/bin/project",
+        );
+        exe_installer(Some("hunter2")).install(&download)?;
+        assert_eq!(
+            read_file(fs.as_ref(), Path::new("/install/project"))?,
+            b"foo"
+        );
+
+        let installer = ArchiveInstaller::new(PathBuf::from("/install/archive"))
+            .zip_password(Some("hunter2".to_string()))
+            .filesystem(fs.clone());
+        installer.install(&download)?;
+        assert_eq!(
+            read_file(fs.as_ref(), Path::new("/install/archive/bin/project"))?,
+            b"foo",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_unsupported_compression() {
+        crate::test_case::init_logging();
+
+        // The executable in this zip file says it's compressed with WavPack, which the `zip` crate
+        // doesn't support.
+        let download = Download::new(PathBuf::from(
+            "test-data/project-unsupported-compression.zip",
+        ));
+        let err = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(Arc::new(MemoryFs::new()))
+        .install(&download)
+        .unwrap_err();
+        assert!(
+            err.to_string().starts_with(
+                "the archive member project/bin/project uses a zip file feature that ubi does not \
+                 support: "
+            ),
+            "{err}",
+        );
+    }
+
+    // The executable in these is a GNU sparse file, with a megabyte-long hole between its first
+    // and last lines.
+    #[test_case("test-data/project-with-sparse-exe.tar")]
+    #[test_case("test-data/project-with-sparse-exe.tar.gz")]
+    fn sparse_tar_entry(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let mut expect = b"#!/bin/sh\n".to_vec();
+        expect.resize(1 << 20, 0);
+        expect.extend(b"echo sparse\n");
+
+        let fs = Arc::new(MemoryFs::new());
+        let download = Download::new(PathBuf::from(archive_path));
+        ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(fs.clone())
+        .install(&download)?;
+        assert_eq!(
+            read_file(fs.as_ref(), Path::new("/install/project"))?,
+            expect
+        );
+
+        ArchiveInstaller::new(PathBuf::from("/install/archive"))
+            .filesystem(fs.clone())
+            .install(&download)?;
+        assert_eq!(
+            read_file(fs.as_ref(), Path::new("/install/archive/bin/project"))?,
+            expect,
+        );
+
+        Ok(())
+    }
+
+    // In these, `project/bin/project` is a hard link to `project/libexec/real-exe`, so the only
+    // entry with the executable's name has no contents of its own.
+    #[test_case("test-data/project-with-hard-link.tar")]
+    #[test_case("test-data/project-with-hard-link.tar.gz")]
+    fn hard_link_tar_entry(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let download = Download::new(PathBuf::from(archive_path));
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(fs.clone());
+        installer.install(&download)?;
+        assert_eq!(
+            read_file(fs.as_ref(), Path::new("/install/project"))?,
+            b"#!/bin/sh\necho hardlink\n",
+        );
+        assert_eq!(installer.estimated_size(&download)?, 24);
+        assert!(installer
+            .scan_archive(&download)?
+            .contains(&("project/bin/project".to_string(), MatchKind::Selected)));
+
+        Ok(())
+    }
+
+    #[test_case("test-data/project-with-debug-partial-match.tar.gz")]
+    #[test_case("test-data/project-with-debug-partial-match.zip")]
+    #[test_case("test-data/project-with-debug-partial-match.7z")]
+    fn exe_installer_reject_ambiguous_partial_matches(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .reject_ambiguous_partial_matches(true)
+        .filesystem(fs.clone());
+        let err = installer
+            .install(&Download::new(PathBuf::from(archive_path)))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "found more than one archive member that is a partial match for the executable name \
+             project: project-linux-debug, project-linux. Use the full name of the executable or \
+             an executable pattern to pick one of them.",
+        );
 
-        if self.should_move_up_one_dir()? {
-            Self::move_contents_up_one_dir(&self.install_root)?;
-        } else {
-            debug!("extracted archive did not contain a common top-level directory");
-        }
+        // An exact match isn't ambiguous.
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project-linux"),
+            vec!["project-linux".to_string()],
+            false,
+        )
+        .reject_ambiguous_partial_matches(true)
+        .filesystem(fs.clone());
+        installer.install(&Download::new(PathBuf::from(archive_path)))?;
+        assert_eq!(
+            read_file(fs.as_ref(), Path::new("/install/project-linux"))?,
+            b"exe"
+        );
 
         Ok(())
     }
 
-    fn extract_entire_tarball(&self, downloaded_file: &Path) -> Result<()> {
-        debug!("extracting entire tarball at {}", downloaded_file.display(),);
+    #[test_case("test-data/project-with-differently-named-exe.tar.gz", true, Ok(b"rg"))]
+    #[test_case("test-data/project-with-differently-named-exe.zip", true, Ok(b"rg"))]
+    #[test_case("test-data/project-with-differently-named-exe.tar.gz", false, Err(()))]
+    #[test_case("test-data/toolkit-with-scattered-exes.tar.gz", true, Err(()); "two executables")]
+    fn exe_installer_fall_back_to_sole_exe(
+        archive_path: &str,
+        fall_back: bool,
+        expect: Result<&[u8], ()>,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
 
-        let mut arch = tar_reader_for(downloaded_file)?;
-        arch.unpack(&self.install_root)?;
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/project");
+        let installer = ExeInstaller::new(install_path.clone(), vec!["project".to_string()], false)
+            .fall_back_to_sole_exe(fall_back)
+            .filesystem(fs.clone());
+        let res = installer.install(&Download::new(PathBuf::from(archive_path)));
+        match expect {
+            Ok(contents) => {
+                res?;
+                assert_eq!(read_file(fs.as_ref(), &install_path)?, contents);
+            }
+            Err(()) => assert!(res.is_err()),
+        }
 
         Ok(())
     }
 
-    // We do this because some projects use a top-level dir like `project-x86-64-Linux`, which is
-    // pretty annoying to work with. In this case, it's a lot easier to install this into
-    // `~/bin/project` so the directory tree ends up with the same structure on all platforms.
-    fn should_move_up_one_dir(&self) -> Result<bool> {
-        let mut prefixes: HashSet<OsString> = HashSet::new();
-        for entry in fs::read_dir(&self.install_root).with_context(|| {
-            format!(
-                "could not read {} after unpacking the tarball into this directory",
-                self.install_root.display(),
-            )
-        })? {
-            let full_path = entry
-                .context("could not get path for tarball entry")?
-                .path();
+    #[test_case(
+        "test-data/project-with-differently-named-exe.tar.gz",
+        "project",
+        &["rg"],
+        Ok(b"rg");
+        "exact match for an alternative"
+    )]
+    #[test_case(
+        "test-data/project-with-differently-named-exe.zip",
+        "project",
+        &["rg"],
+        Ok(b"rg");
+        "exact match for an alternative in a zip file"
+    )]
+    #[test_case(
+        "test-data/project-with-helper.tar.gz",
+        "other",
+        &["project", "project-helper"],
+        Ok(b"exe");
+        "earlier name wins over archive order"
+    )]
+    #[test_case(
+        "test-data/project-with-helper.zip",
+        "other",
+        &["project-helper", "project"],
+        Ok(b"helper");
+        "earlier name wins in a zip file"
+    )]
+    #[test_case(
+        "test-data/project-with-helper.tar.gz",
+        "project-h",
+        &["project"],
+        Ok(b"exe");
+        "exact match for an alternative wins over a partial match"
+    )]
+    #[test_case(
+        "test-data/project.tar.gz",
+        "other",
+        &["another"],
+        Err("could not find any files matching [other* another*] in the downloaded archive file");
+        "no match"
+    )]
+    fn exe_installer_alternative_exe_names(
+        archive_path: &str,
+        exe: &str,
+        alternatives: &[&str],
+        expect: Result<&[u8], &str>,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
 
-            // If the entry is a file in the top-level of the install dir, then there's no common
-            // directory prefix.
-            if full_path.is_file()
-                && full_path
-                    .parent()
-                    .expect("path of entry in install root somehow has no parent")
-                    == self.install_root
-            {
-                return Ok(false);
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = PathBuf::from("/install/").join(exe);
+        let installer = ExeInstaller::new(install_path.clone(), vec![exe.to_string()], false)
+            .alternative_exe_names(alternatives.iter().map(ToString::to_string).collect())
+            .filesystem(fs.clone());
+        installer.validate()?;
+        let res = installer.install(&Download::new(PathBuf::from(archive_path)));
+        match expect {
+            Ok(contents) => {
+                res?;
+                assert_eq!(read_file(fs.as_ref(), &install_path)?, contents);
             }
+            Err(msg) => assert_eq!(res.unwrap_err().to_string(), msg),
+        }
 
-            let path = if let Ok(path) = full_path.strip_prefix(&self.install_root) {
-                path
-            } else {
-                &full_path
-            };
+        Ok(())
+    }
 
-            if let Some(prefix) = path.components().next() {
-                prefixes.insert(prefix.as_os_str().to_os_string());
-            } else {
-                return Err(anyhow!("directory entry has no path components"));
+    #[cfg(target_family = "unix")]
+    #[test_case("test-data/project-script.tar.gz", &["--version"], None; "runs")]
+    #[test_case(
+        "test-data/project-script.tar.gz",
+        &["--bad"],
+        Some("failed (exit status: 2)\nstdout: \nstderr: unknown argument: --bad");
+        "exits with an error"
+    )]
+    #[test_case(
+        "test-data/project.tar.gz",
+        &["--version"],
+        Some("could not run the installed executable");
+        "not an executable"
+    )]
+    fn exe_installer_verify_runs(
+        archive_path: &str,
+        args: &[&str],
+        expect_err: Option<&str>,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+        let installer = ExeInstaller::new(install_path, vec!["project".to_string()], false)
+            .verify_runs(Some(args.iter().map(ToString::to_string).collect()));
+        let res = installer.install(&Download::new(PathBuf::from(archive_path)));
+        match expect_err {
+            None => {
+                res?;
+            }
+            Some(expect_err) => {
+                let err = format!("{:#}", res.unwrap_err());
+                assert!(err.contains(expect_err), "{err}");
             }
         }
 
-        // If all the entries
-        Ok(prefixes.len() == 1)
+        Ok(())
     }
 
-    fn move_contents_up_one_dir(path: &Path) -> Result<()> {
-        let mut entries = fs::read_dir(path)?;
-        let top_level_path = if let Some(dir_entry) = entries.next() {
-            let dir_entry = dir_entry?;
-            dir_entry.path()
-        } else {
-            return Err(anyhow!("no directory found in path"));
+    #[test]
+    fn exe_installer_post_install() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let fs = Arc::new(MemoryFs::new());
+        let called_with = Arc::new(Mutex::new(vec![]));
+        let installer = |fail: bool| {
+            let called_with = called_with.clone();
+            ExeInstaller::new(
+                PathBuf::from("/install/project"),
+                vec!["project".to_string()],
+                false,
+            )
+            .post_install(Some(PostInstall::new(move |paths| {
+                called_with.lock().unwrap().extend_from_slice(paths);
+                if fail {
+                    return Err(anyhow!("signing failed"));
+                }
+                Ok(())
+            })))
+            .filesystem(fs.clone())
         };
+        let download = Download::new(PathBuf::from("test-data/project.tar.gz"));
 
-        debug!(
-            "moving extracted archive contents up one directory from {} to {}",
-            top_level_path.display(),
-            path.display(),
+        installer(false).install_dry_run(&download)?;
+        assert!(called_with.lock().unwrap().is_empty());
+
+        installer(false).install(&download)?;
+        assert_eq!(
+            mem::take(&mut *called_with.lock().unwrap()),
+            vec![PathBuf::from("/install/project")],
         );
 
-        for entry in fs::read_dir(&top_level_path)? {
-            let entry = entry?;
-            let target = path.join(entry.file_name());
-            fs::rename(entry.path(), target)?;
-        }
+        let err = installer(true).install(&download).unwrap_err();
+        assert_eq!(err.to_string(), "signing failed");
 
-        fs::remove_dir(top_level_path)?;
+        // The callback isn't called when the executable can't be found.
+        let err = installer(false)
+            .member_path(Some(PathBuf::from("bin/other")))
+            .install(&download)
+            .unwrap_err();
+        assert!(err.to_string().starts_with("could not find"), "{err}");
+        assert_eq!(called_with.lock().unwrap().len(), 1);
 
         Ok(())
     }
 
-    fn extract_entire_zip(&self, downloaded_file: &Path) -> Result<()> {
-        debug!(
-            "extracting entire zip file at {}",
-            downloaded_file.display(),
-        );
-
-        let mut zip = ZipArchive::new(open_file(downloaded_file)?)?;
-        Ok(zip.extract(&self.install_root)?)
-    }
-}
+    #[cfg(target_family = "unix")]
+    #[test_case(&["sh", "-c", "test -x \"$0\""], None; "path added at the end")]
+    #[test_case(&["sh", "-c", "test -x \"$1\"", "sh", "{}"], None; "path placeholder")]
+    #[test_case(
+        &["sh", "-c", "echo no >&2; exit 3"],
+        Some("failed (exit status: 3)\nstdout: \nstderr: no");
+        "exits with an error"
+    )]
+    #[test_case(
+        &["ubi-no-such-command"],
+        Some("could not run the post-install command `ubi-no-such-command`");
+        "command not found"
+    )]
+    fn exe_installer_post_install_command(
+        command: &[&str],
+        expect_err: Option<&str>,
+    ) -> Result<()> {
+        crate::test_case::init_logging();
 
-impl Installer for ArchiveInstaller {
-    fn install(&self, download: &Download) -> Result<()> {
-        self.extract_entire_archive(&download.archive_path)?;
-        info!(
-            "Installed contents of archive file into {}",
-            self.install_root.display()
-        );
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+        let installer = ExeInstaller::new(install_path, vec!["project".to_string()], false)
+            .post_install(Some(PostInstall::command(
+                command.iter().map(ToString::to_string).collect(),
+            )));
+        let res = installer.install(&Download::new(PathBuf::from("test-data/project.tar.gz")));
+        match expect_err {
+            None => {
+                res?;
+            }
+            Some(expect_err) => {
+                let err = format!("{:#}", res.unwrap_err());
+                assert!(err.contains(expect_err), "{err}");
+            }
+        }
 
         Ok(())
     }
-}
 
-fn tar_reader_for(downloaded_file: &Path) -> Result<Archive<Box<dyn Read>>> {
-    let file = open_file(downloaded_file)?;
-
-    let ext = downloaded_file.extension();
-    match ext {
-        Some(ext) => match ext.to_str() {
-            Some("tar") => Ok(Archive::new(Box::new(file))),
-            Some("bz" | "tbz" | "bz2" | "tbz2") => Ok(Archive::new(Box::new(BzDecoder::new(file)))),
-            Some("gz" | "tgz") => Ok(Archive::new(Box::new(GzDecoder::new(file)))),
-            Some("xz" | "txz") => Ok(Archive::new(Box::new(XzDecoder::new(file)))),
-            Some(e) => Err(anyhow!(
-                "don't know how to uncompress a tarball with extension = {}",
-                e,
-            )),
-            None => Err(anyhow!(
-                "tarball {:?} has a non-UTF-8 extension",
-                downloaded_file,
-            )),
-        },
-        None => Ok(Archive::new(Box::new(file))),
-    }
-}
+    #[test]
+    fn exe_installer_member_dir_without_match() {
+        crate::test_case::init_logging();
 
-fn open_file(path: &Path) -> Result<File> {
-    File::open(path).with_context(|| format!("Failed to open file at {}", path.display()))
-}
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .member_dir(Some(PathBuf::from("project/bin")))
+        .filesystem(Arc::new(MemoryFs::new()));
+        let res = installer.install(&Download::new(PathBuf::from(
+            "test-data/project-in-two-dirs.tar.gz",
+        )));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[cfg(target_family = "unix")]
-    use std::os::unix::fs::PermissionsExt;
-    use tempfile::tempdir;
-    use test_case::test_case;
-    use test_log::test;
+        assert!(res.is_err());
+    }
 
-    #[test_case("test-data/project.AppImage", Some("AppImage"))]
-    #[test_case("test-data/project.bat", Some("bat"))]
-    #[test_case("test-data/project.bz", None)]
-    #[test_case("test-data/project.bz2", None)]
-    #[test_case("test-data/project.exe", Some("exe"))]
-    #[test_case("test-data/project.gz", None)]
-    #[test_case("test-data/project.pyz", Some("pyz"))]
-    #[test_case("test-data/project.tar", None)]
-    #[test_case("test-data/project.tar.bz", None)]
-    #[test_case("test-data/project.tar.bz2", None)]
-    #[test_case("test-data/project.tar.gz", None)]
-    #[test_case("test-data/project.tar.xz", None)]
-    #[test_case("test-data/project.xz", None)]
-    #[test_case("test-data/project.zip", None)]
-    #[test_case("test-data/project", None)]
-    // This tests a bug where zip files with partial matches before an exact match would pick the wrong file.
-    #[test_case("test-data/project-with-partial-before-exact.zip", None)]
-    // These are archive files that just contain a partial match for the expected executable.
-    #[test_case("test-data/project-with-partial-match.tar.gz", None)]
-    #[test_case("test-data/project-with-partial-match.zip", None)]
-    fn exe_installer(archive_path: &str, installed_extension: Option<&str>) -> Result<()> {
+    #[test_case("test-data/project-with-partial-match.tar.gz", "project-with-stuff"; "partial match in a tarball")]
+    #[test_case("test-data/project-with-partial-match.zip", "project-with-stuff"; "partial match in a zip file")]
+    #[test_case("test-data/project.tar.gz", "project"; "exact match")]
+    #[test_case("test-data/project.gz", "project"; "compressed executable")]
+    #[test_case("test-data/project", "project"; "bare executable")]
+    fn exe_installer_install_path_is_dir(archive_path: &str, expect_name: &str) -> Result<()> {
         crate::test_case::init_logging();
 
-        let td = tempdir()?;
-        let path_without_subdir = td.path().to_path_buf();
-        test_installer(
-            archive_path,
-            installed_extension,
-            path_without_subdir,
-            false,
-        )?;
+        for (install_path, dir_exists) in [("/install/", false), ("/install", true)] {
+            let fs = Arc::new(MemoryFs::new());
+            if dir_exists {
+                fs.create_dir_all(Path::new(install_path))?;
+            }
+            let installer = ExeInstaller::new(
+                PathBuf::from(install_path),
+                vec!["project".to_string()],
+                false,
+            )
+            .filesystem(fs.clone());
+            let paths = installer.install(&Download::new(PathBuf::from(archive_path)))?;
 
-        let td = tempdir()?;
-        let mut path_with_subdir = td.path().to_path_buf();
-        path_with_subdir.push("subdir");
-        test_installer(archive_path, installed_extension, path_with_subdir, false)
+            let expect_path = Path::new("/install").join(expect_name);
+            assert_eq!(paths, vec![expect_path.clone()]);
+            assert_eq!(read_file(fs.as_ref(), &expect_path)?, b"exe");
+        }
+
+        Ok(())
     }
 
-    // These tests check that we look for project.bat and project.exe in archive files when running
-    // on Windows.
-    #[test_case("test-data/windows-project-bat.tar.gz", "bat")]
-    #[test_case("test-data/windows-project-exe.tar.gz", "exe")]
-    #[test_case("test-data/windows-project-bat.zip", "bat")]
-    #[test_case("test-data/windows-project-exe.zip", "exe")]
-    // And these check that we match project-with-stuff.exe.
-    #[test_case("test-data/windows-project-exe-with-partial-match.tar.gz", "exe")]
-    #[test_case("test-data/windows-project-exe-with-partial-match.zip", "exe")]
-    fn exe_installer_on_windows(archive_path: &str, extension: &str) -> Result<()> {
+    #[test_case(false, "/install/project.pyz"; "install path")]
+    #[test_case(true, "/install/project-with-stored-name.pyz"; "install dir")]
+    fn exe_installer_gzip_stored_name(install_path_is_dir: bool, expect: &str) -> Result<()> {
         crate::test_case::init_logging();
 
-        let td = tempdir()?;
-        let install_dir = td.path().to_path_buf();
+        let fs = Arc::new(MemoryFs::new());
+        let install_path = if install_path_is_dir {
+            "/install/"
+        } else {
+            "/install/project"
+        };
+        let installer = ExeInstaller::new(
+            PathBuf::from(install_path),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(fs.clone());
+        let paths = installer.install(&Download::new(PathBuf::from(
+            "test-data/project-with-stored-name.gz",
+        )))?;
 
-        test_installer(archive_path, Some(extension), install_dir, true)
+        assert_eq!(paths, vec![PathBuf::from(expect)]);
+        assert_eq!(read_file(fs.as_ref(), Path::new(expect))?, b"exe");
+
+        Ok(())
     }
 
-    fn test_installer(
-        archive_path: &str,
-        installed_extension: Option<&str>,
-        install_dir: PathBuf,
-        is_windows: bool,
+    #[test_case(
+        "project.tar.gz",
+        Some(".tar.gz"),
+        Some("./project/bin/project"),
+        0o755
+    )]
+    // The zip file records a mode of 0o664 for the executable.
+    #[test_case("project.zip", Some(".zip"), Some("project/bin/project"), 0o775)]
+    #[test_case("project.gz", Some(".gz"), None, 0o755)]
+    #[test_case("project", None, None, 0o755)]
+    fn exe_installer_install_with_manifest(
+        file_name: &str,
+        expect_extension: Option<&str>,
+        expect_member: Option<&str>,
+        expect_mode: u32,
     ) -> Result<()> {
-        let exe_file_stem = "project";
-
-        let mut install_path = install_dir;
-        install_path.push("project");
-
-        let installer =
-            ExeInstaller::new(install_path.clone(), exe_file_stem.to_string(), is_windows);
-        installer.install(&Download {
-            // It doesn't matter what we use here. We're not actually going to
-            // put anything in this temp dir.
-            _temp_dir: tempdir()?,
-            archive_path: PathBuf::from(archive_path),
-        })?;
+        crate::test_case::init_logging();
 
-        let mut expect_install_path = install_path.clone();
-        if let Some(installed_extension) = installed_extension {
-            let path = PathBuf::from(format!("foo.{installed_extension}"));
-            let ext = Extension::from_path(&path).unwrap().unwrap();
-            if ext.should_preserve_extension_on_install() {
-                expect_install_path.set_extension(ext.extension_without_dot());
-            }
-        }
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(Arc::new(MemoryFs::new()));
+        let manifest = installer
+            .install_with_manifest(&Download::new(Path::new("test-data").join(file_name)))?;
 
-        assert!(
-            fs::exists(&expect_install_path)?,
-            "{} file exists",
-            expect_install_path.display()
+        assert_eq!(
+            manifest,
+            InstallManifest {
+                source: file_name.to_string(),
+                extension: expect_extension.map(str::to_string),
+                executables: vec![InstalledExecutable {
+                    member: expect_member.map(str::to_string),
+                    path: PathBuf::from("/install/project"),
+                    mode: expect_mode,
+                    size: 3,
+                }],
+            },
         );
-        // Testing the installed file's length is a shortcut to make sure we install the file we
-        // expected to install.
-        let expect_len = if expect_install_path.extension().unwrap_or_default() == "pyz" {
-            fs::metadata(archive_path)?.len()
-        } else {
-            3
-        };
-        let meta = expect_install_path.metadata()?;
-        assert_eq!(meta.len(), expect_len);
-        #[cfg(target_family = "unix")]
-        assert!(meta.permissions().mode() & 0o111 != 0);
 
         Ok(())
     }
 
-    #[test_case("test-data/project.tar")]
-    #[test_case("test-data/project.tar.bz")]
-    #[test_case("test-data/project.tar.bz2")]
     #[test_case("test-data/project.tar.gz")]
-    #[test_case("test-data/project.tar.xz")]
     #[test_case("test-data/project.zip")]
-    fn archive_installer(archive_path: &str) -> Result<()> {
+    #[test_case("test-data/project.gz")]
+    #[test_case("test-data/project")]
+    fn exe_installer_extract_to_bytes(archive_path: &str) -> Result<()> {
         crate::test_case::init_logging();
 
-        let td = tempdir()?;
-        let mut path_without_subdir = td.path().to_path_buf();
-        path_without_subdir.push("project");
-        let mut path_with_subdir = td.path().to_path_buf();
-        path_with_subdir.extend(&["subdir", "project"]);
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(fs.clone());
+        let contents = installer.extract_to_bytes(&Download::new(PathBuf::from(archive_path)))?;
 
-        for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(install_root.clone());
-            installer.install(&Download {
-                // It doesn't matter what we use here. We're not actually going to
-                // put anything in this temp dir.
-                _temp_dir: tempdir()?,
-                archive_path: PathBuf::from(archive_path),
-            })?;
+        assert_eq!(contents, b"exe");
+        assert_eq!(kind_of(fs.as_ref(), Path::new("/install/project")), None);
 
-            assert!(install_root.exists());
-            assert!(install_root.is_dir());
+        Ok(())
+    }
 
-            let bin_dir = install_root.join("bin");
-            assert!(bin_dir.exists());
-            assert!(bin_dir.is_dir());
+    #[test_case("test-data/project-with-debug-partial-match.tar.gz")]
+    #[test_case("test-data/project-with-debug-partial-match.zip")]
+    fn exe_installer_scan_archive(archive_path: &str) -> Result<()> {
+        crate::test_case::init_logging();
 
-            let exe = bin_dir.join("project");
-            assert!(exe.exists());
-            assert!(exe.is_file());
-        }
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        );
+        let report = installer.scan_archive(&Download::new(PathBuf::from(archive_path)))?;
+
+        assert_eq!(
+            report,
+            vec![
+                (
+                    "project/bin/project-linux-debug".to_string(),
+                    MatchKind::Partial,
+                ),
+                ("project/bin/project-linux".to_string(), MatchKind::Selected),
+            ],
+        );
 
         Ok(())
     }
 
-    // This tests a bug in the initial implementation where a tarball that just contained files
-    // caused us to try to move its contents up to a directory that didn't exist.
     #[test]
-    fn archive_installer_one_file_in_archive_root() -> Result<()> {
-        let td = tempdir()?;
-        let mut path_without_subdir = td.path().to_path_buf();
-        path_without_subdir.push("project");
-        let mut path_with_subdir = td.path().to_path_buf();
-        path_with_subdir.extend(&["subdir", "project"]);
-
-        for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(install_root.clone());
-            installer.install(&Download {
-                // It doesn't matter what we use here. We're not actually going to
-                // put anything in this temp dir.
-                _temp_dir: tempdir()?,
-                archive_path: PathBuf::from("test-data/project-with-one-file.tar.gz"),
-            })?;
+    fn exe_installer_scan_archive_without_match() -> Result<()> {
+        crate::test_case::init_logging();
 
-            assert!(install_root.exists());
-            assert!(install_root.is_dir());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/other"),
+            vec!["other".to_string()],
+            false,
+        );
+        let report =
+            installer.scan_archive(&Download::new(PathBuf::from("test-data/project.zip")))?;
 
-            let exe = install_root.join("project");
-            assert!(exe.exists());
-            assert!(exe.is_file());
-        }
+        assert!(!report.is_empty());
+        assert!(report.iter().all(|(_, kind)| *kind == MatchKind::NoMatch));
 
         Ok(())
     }
 
-    #[test]
-    fn archive_installer_no_root_path() -> Result<()> {
-        let td = tempdir()?;
-        let mut path_without_subdir = td.path().to_path_buf();
-        path_without_subdir.push("project");
-        let mut path_with_subdir = td.path().to_path_buf();
-        path_with_subdir.extend(&["subdir", "project"]);
-
-        for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(install_root.clone());
-            installer.install(&Download {
-                // It doesn't matter what we use here. We're not actually going to
-                // put anything in this temp dir.
-                _temp_dir: tempdir()?,
-                archive_path: PathBuf::from("test-data/no-shared-root.tar.gz"),
-            })?;
+    #[test_case("test-data/project", 3)]
+    #[test_case("test-data/project.gz", 3)]
+    #[test_case("test-data/project.tar.gz", 3)]
+    #[test_case("test-data/project.zip", 3)]
+    #[test_case("test-data/project.7z", 3)]
+    // Only the selected partial match is counted.
+    #[test_case("test-data/project-with-debug-partial-match.tar.gz", 3)]
+    #[test_case("test-data/project-with-debug-partial-match.zip", 3)]
+    #[test_case("test-data/project-with-sparse-exe.tar", 1_048_588)]
+    fn exe_installer_estimated_size(archive_path: &str, expect: u64) -> Result<()> {
+        crate::test_case::init_logging();
 
-            assert!(install_root.exists());
-            assert!(install_root.is_dir());
+        let fs = Arc::new(MemoryFs::new());
+        let installer = ExeInstaller::new(
+            PathBuf::from("/install/project"),
+            vec!["project".to_string()],
+            false,
+        )
+        .filesystem(fs.clone());
+        let size = installer.estimated_size(&Download::new(PathBuf::from(archive_path)))?;
+        assert_eq!(size, expect);
+        assert!(kind_of(fs.as_ref(), Path::new("/install/project")).is_none());
 
-            let bin_dir = install_root.join("bin");
-            assert!(bin_dir.exists());
-            assert!(bin_dir.is_dir());
+        Ok(())
+    }
 
-            let exe = bin_dir.join("project");
-            assert!(exe.exists());
-            assert!(exe.is_file());
+    #[test_case("test-data/project-with-helper.tar.gz", Some(15))]
+    #[test_case("test-data/project-with-helper.zip", Some(15))]
+    #[test_case("test-data/project-with-helper.7z", Some(15))]
+    #[test_case("test-data/project.gz", None)]
+    fn archive_installer_estimated_size(archive_path: &str, expect: Option<u64>) -> Result<()> {
+        crate::test_case::init_logging();
 
-            let readme = install_root.join("README.md");
-            assert!(readme.exists());
-            assert!(readme.is_file());
+        let fs = Arc::new(MemoryFs::new());
+        let installer =
+            ArchiveInstaller::new(PathBuf::from("/install/project")).filesystem(fs.clone());
+        let res = installer.estimated_size(&Download::new(PathBuf::from(archive_path)));
+        match expect {
+            Some(expect) => assert_eq!(res?, expect),
+            None => assert!(matches!(res, Err(InstallError::UnsupportedFormat { .. }))),
         }
+        assert!(kind_of(fs.as_ref(), Path::new("/install/project")).is_none());
 
         Ok(())
     }
+
+    fn kind_of(fs: &dyn FileSystem, path: &Path) -> Option<FileKind> {
+        fs.metadata(path).ok().map(|m| m.kind)
+    }
+
+    fn read_file(fs: &dyn FileSystem, path: &Path) -> Result<Vec<u8>> {
+        let mut contents = vec![];
+        fs.open_file(path)?.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
 }
@@ -36,6 +36,9 @@
 //! installed executable will use the name of the project instead. For files with a `.exe`, `.pyz`
 //! or `.AppImage`, the installed executable will be `$project_name.$extension`.
 //!
+//! If the name you give with [`UbiBuilder::exe`] already has an extension, that name is used as
+//! is. Its extension is never replaced with the extension of the file being installed.
+//!
 //! This is a bit inconsistent, but it's how `ubi` has behaved since it was created, and I find this
 //! to be the sanest behavior. Some projects, for example `rust-analyzer`, provide releases as
 //! executables with names like `rust-analyzer-x86_64-apple-darwin` and
@@ -55,22 +58,40 @@
 //! First it filters out assets with extensions it doesn't recognize. Right now this is anything that
 //! doesn't match one of the following:
 //!
+//! - `.7z`
+//! - `.a`
 //! - `.AppImage` (Linux only)
+//! - `.ar`
 //! - `.bat` (Windows only)
+//! - `.br`
 //! - `.bz`
 //! - `.bz2`
+//! - `.cab`
+//! - `.deb` (Linux only)
 //! - `.exe` (Windows only)
 //! - `.gz`
+//! - `.lz`
+//! - `.lz4`
+//! - `.lzma`
+//! - `.rpm` (Linux only)
 //! - `.tar`
+//! - `.tar.br`
 //! - `.tar.bz`
 //! - `.tar.bz2`
 //! - `.tar.gz`
+//! - `.tar.lz`
+//! - `.tar.lz4`
+//! - `.tar.lzma`
 //! - `.tar.xz`
+//! - `.tar.Z`
+//! - `.tar.zst`
 //! - `.tbz`
 //! - `.tgz`
 //! - `.txz`
 //! - `.xz`
+//! - `.Z`
 //! - `.zip`
+//! - `.zst`
 //! - No extension
 //!
 //! It tries to be careful about what constitutes an extension. It's common for release filenames to
@@ -131,7 +152,9 @@
 //! _rename_ the extracted file to `precious`. On Unix-like systems, these partial matches will only
 //! be considered if the file's mode includes an executable bit. On Windows, it looks for a partial
 //! match that is a `.bat` or `.exe` file, and the extracted file will be renamed to `precious.bat`
-//! or `precious.exe`.
+//! or `precious.exe`. Files with extensions for things that are never executables, like `.md`,
+//! `.json`, or `.txt`, are never partial matches. You can add more of these extensions with
+//! [`UbiBuilder::non_executable_extensions`].
 //!
 //! ## Features
 //!
@@ -139,22 +162,51 @@
 //!
 #![doc = document_features::document_features!()]
 
+mod appimage;
 mod arch;
+mod binary_arch;
 mod builder;
+mod checksum;
+mod completion;
 mod extension;
+mod filesystem;
 mod forge;
 mod github;
 mod gitlab;
+#[cfg(feature = "gpg")]
+mod gpg;
 mod installer;
+mod lzip;
+mod lzw;
+mod minisign;
 mod os;
 mod picker;
+#[cfg(feature = "slsa")]
+mod slsa;
 #[cfg(test)]
 mod test;
 #[cfg(test)]
 mod test_case;
 mod ubi;
 
-pub use crate::{builder::UbiBuilder, forge::ForgeType, ubi::Ubi};
+pub use crate::{
+    builder::UbiBuilder,
+    checksum::ChecksumAlgorithm,
+    completion::Shell,
+    filesystem::{FileKind, FileMetadata, FileSystem, MemoryFs, RealFs},
+    forge::ForgeType,
+    installer::{
+        executable_candidates, ExecutableCandidate, ExtractedPath, ExtractionReport, InstallError,
+        InstallManifest, InstalledExecutable, MatchKind, OnExisting, SkipReason, SkippedMember,
+    },
+    ubi::Ubi,
+};
+
+#[cfg(feature = "unstable-installer")]
+pub use crate::{
+    installer::{ArchiveInstaller, ExeInstaller, Installer, PathMapper, PostInstall, Progress},
+    ubi::Download,
+};
 
 // The version of the `ubi` crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,269 @@
+// An lzip file is one or more members, each of which is a 6 byte header, a raw LZMA stream that
+// ends with an end of stream marker, and a 20 byte trailer with the CRC32 and size of the
+// uncompressed data. The `xz2` crate can't decode raw LZMA streams, but it can decode the legacy
+// `.lzma` format, which is a raw LZMA stream with a 13 byte header. So we decode each member by
+// putting a `.lzma` header in front of its LZMA stream.
+//
+// See https://www.nongnu.org/lzip/manual/lzip_manual.html#File-format for details of the format.
+
+use flate2::Crc;
+use log::debug;
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    mem,
+};
+use xz2::stream::{Action, Status, Stream};
+
+const MAGIC: &[u8] = b"LZIP";
+const HEADER_LEN: usize = 6;
+const TRAILER_LEN: usize = 20;
+// lzip always uses the LZMA parameters lc=3, lp=0, and pb=2. The `.lzma` header encodes these as
+// `(pb * 5 + lp) * 9 + lc`.
+const LZMA_PROPERTIES: u8 = 0x5d;
+
+/// A reader that decompresses lzip data from the underlying reader. This checks the CRC32 and size
+/// in each member's trailer, returning an error if either doesn't match the decompressed data.
+pub(crate) struct LzipDecoder<R: Read> {
+    state: State<R>,
+    crc: Crc,
+    members: usize,
+}
+
+enum State<R: Read> {
+    Header(BufReader<R>),
+    Member(Prefixed<BufReader<R>>, Stream),
+    Done,
+}
+
+impl<R: Read> LzipDecoder<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        LzipDecoder {
+            state: State::Header(BufReader::new(reader)),
+            crc: Crc::new(),
+            members: 0,
+        }
+    }
+
+    // Reads the next member's header and returns a reader for its LZMA stream, along with a
+    // decoder for that stream. This returns `None` when there are no more members.
+    fn start_member(
+        &self,
+        mut reader: BufReader<R>,
+    ) -> io::Result<Option<(Prefixed<BufReader<R>>, Stream)>> {
+        if self.members > 0 && !reader.fill_buf()?.starts_with(MAGIC) {
+            // Like the `lzip` tool, we ignore anything after the last member.
+            if !reader.fill_buf()?.is_empty() {
+                debug!("ignoring trailing data after the last lzip member");
+            }
+            return Ok(None);
+        }
+
+        let mut header = [0; HEADER_LEN];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| invalid_data("the lzip data is too short to have a header"))?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_data("the lzip data does not start with `LZIP`"));
+        }
+        if header[4] != 1 {
+            return Err(invalid_data(&format!(
+                "the lzip data has an unsupported version, {}",
+                header[4],
+            )));
+        }
+
+        let mut lzma_header = [0; 13];
+        lzma_header[0] = LZMA_PROPERTIES;
+        lzma_header[1..5].copy_from_slice(&dictionary_size(header[5])?.to_le_bytes());
+        // An unknown uncompressed size, which means the stream ends with an end of stream marker.
+        lzma_header[5..].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let reader = Prefixed {
+            prefix: lzma_header,
+            pos: 0,
+            inner: reader,
+        };
+        Ok(Some((reader, Stream::new_lzma_decoder(u64::MAX)?)))
+    }
+
+    // Decodes as much of the member's LZMA stream into `buf` as we can. This returns the number of
+    // bytes decoded and whether we reached the end of the stream.
+    fn read_member(
+        reader: &mut Prefixed<BufReader<R>>,
+        stream: &mut Stream,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, bool)> {
+        loop {
+            let input = reader.fill_buf()?;
+            if input.is_empty() {
+                return Err(invalid_data("the lzip data ends in the middle of a member"));
+            }
+            let (total_in, total_out) = (stream.total_in(), stream.total_out());
+            let status = stream.process(input, buf, Action::Run)?;
+            reader.consume(usize::try_from(stream.total_in() - total_in).unwrap());
+            let n = usize::try_from(stream.total_out() - total_out).unwrap();
+            if n > 0 || buf.is_empty() || status == Status::StreamEnd {
+                return Ok((n, status == Status::StreamEnd));
+            }
+        }
+    }
+
+    fn finish_member(&mut self, reader: &mut BufReader<R>, stream: &Stream) -> io::Result<()> {
+        let mut trailer = [0; TRAILER_LEN];
+        reader
+            .read_exact(&mut trailer)
+            .map_err(|_| invalid_data("the lzip data is too short to have a member trailer"))?;
+
+        let expect_crc = u32::from_le_bytes(trailer[..4].try_into().unwrap());
+        if self.crc.sum() != expect_crc {
+            return Err(invalid_data(&format!(
+                "the CRC32 of the decompressed lzip data is {:08x}, but the trailer says it should \
+                 be {expect_crc:08x}",
+                self.crc.sum(),
+            )));
+        }
+        let size = stream.total_out();
+        let expect_size = u64::from_le_bytes(trailer[4..12].try_into().unwrap());
+        if size != expect_size {
+            return Err(invalid_data(&format!(
+                "the size of the decompressed lzip data is {size}, but the trailer says it should \
+                 be {expect_size}",
+            )));
+        }
+
+        self.crc.reset();
+        self.members += 1;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for LzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match mem::replace(&mut self.state, State::Done) {
+                State::Header(reader) => match self.start_member(reader)? {
+                    Some((reader, stream)) => self.state = State::Member(reader, stream),
+                    None => return Ok(0),
+                },
+                State::Member(mut reader, mut stream) => {
+                    let (n, end) = Self::read_member(&mut reader, &mut stream, buf)?;
+                    self.crc.update(&buf[..n]);
+                    if end {
+                        self.finish_member(&mut reader.inner, &stream)?;
+                        self.state = State::Header(reader.inner);
+                    } else {
+                        self.state = State::Member(reader, stream);
+                    }
+                    if n > 0 || buf.is_empty() {
+                        return Ok(n);
+                    }
+                }
+                State::Done => return Ok(0),
+            }
+        }
+    }
+}
+
+// The dictionary size is encoded as a power of 2 in the low 5 bits, minus some number of
+// sixteenths of that in the high 3 bits.
+fn dictionary_size(encoded: u8) -> io::Result<u32> {
+    let exponent = encoded & 0x1f;
+    if !(12..=29).contains(&exponent) {
+        return Err(invalid_data(&format!(
+            "the lzip data has an invalid dictionary size, {encoded:#04x}",
+        )));
+    }
+    let base = 1_u32 << exponent;
+    Ok(base - (base / 16) * u32::from(encoded >> 5))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// A reader that returns `prefix` before the contents of `inner`.
+struct Prefixed<R> {
+    prefix: [u8; 13],
+    pos: usize,
+    inner: R,
+}
+
+impl<R: BufRead> Read for Prefixed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Prefixed<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos < self.prefix.len() {
+            return Ok(&self.prefix[self.pos..]);
+        }
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.pos < self.prefix.len() {
+            self.pos += amt;
+        } else {
+            self.inner.consume(amt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use test_case::test_case;
+
+    fn decode(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = vec![];
+        LzipDecoder::new(data).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test_case(1, b"", b"exe"; "one member")]
+    #[test_case(2, b"", b"exeexe"; "two members")]
+    #[test_case(1, b"trailing data", b"exe"; "trailing data")]
+    fn decode_members(members: usize, trailing: &[u8], expect: &[u8]) -> io::Result<()> {
+        let mut data = fs::read("test-data/project.lz")?.repeat(members);
+        data.extend_from_slice(trailing);
+        assert_eq!(decode(&data)?, expect);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_bad_crc() -> io::Result<()> {
+        let mut data = fs::read("test-data/project.lz")?;
+        let crc_pos = data.len() - TRAILER_LEN;
+        data[crc_pos] ^= 0xff;
+        let err = decode(&data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err
+            .to_string()
+            .starts_with("the CRC32 of the decompressed lzip data is"));
+        Ok(())
+    }
+
+    #[test_case(b"LZMA\x01\x10"; "bad magic")]
+    #[test_case(b"LZIP\x02\x10"; "bad version")]
+    #[test_case(b"LZIP\x01\x05"; "bad dictionary size")]
+    #[test_case(b"LZI"; "too short")]
+    fn decode_bad_header(data: &[u8]) {
+        let err = decode(data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test_case(0x0c, 1 << 12)]
+    #[test_case(0x1d, 1 << 29)]
+    #[test_case(0x10 | (1 << 5), (1 << 16) - (1 << 12))]
+    fn dictionary_size(encoded: u8, expect: u32) -> io::Result<()> {
+        assert_eq!(super::dictionary_size(encoded)?, expect);
+        Ok(())
+    }
+}
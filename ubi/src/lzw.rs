@@ -0,0 +1,283 @@
+// A `.Z` file is made by the classic Unix `compress` tool. It's a 3 byte header followed by a
+// stream of LZW codes, packed least significant bit first. The codes start out 9 bits wide and get
+// one bit wider each time the code table fills up, up to the maximum width given in the header.
+// When the table is full at the maximum width, the compressor can send a clear code to reset it.
+//
+// One quirk of the format is that `compress` writes codes in groups of 8, which is a whole number
+// of bytes at any width, and when the width changes or the table is cleared, the rest of the
+// current group is padded out. We have to skip that padding the same way to stay in sync.
+//
+// See https://github.com/vapier/ncompress/blob/main/compress.c for the reference implementation.
+
+use std::io::{self, BufReader, Bytes, Read};
+
+const MAGIC: &[u8] = b"\x1f\x9d";
+const MAX_BITS_MASK: u8 = 0x1f;
+const RESERVED_MASK: u8 = 0x60;
+const BLOCK_MODE: u8 = 0x80;
+const INIT_BITS: u32 = 9;
+const MAX_BITS: u32 = 16;
+const CLEAR: u32 = 256;
+
+/// A reader that decompresses data made by `compress` from the underlying reader.
+pub(crate) struct LzwDecoder<R: Read> {
+    input: Bytes<BufReader<R>>,
+    header_read: bool,
+    max_bits: u32,
+    block_mode: bool,
+
+    bit_buf: u32,
+    bit_count: u32,
+    n_bits: u32,
+    // The number of codes read since the width last changed, which tells us how much padding to
+    // skip when it changes again.
+    codes_in_width: u32,
+
+    // The table has an entry for every code above 255 as the code for its prefix and its last
+    // byte. The first 256 codes are the bytes themselves.
+    prefix: Vec<u16>,
+    suffix: Vec<u8>,
+    next_code: u32,
+    prev_code: Option<u32>,
+    first_byte: u8,
+
+    // Each code can expand to many bytes, so we keep whatever didn't fit into the caller's buffer.
+    out: Vec<u8>,
+    out_pos: usize,
+    done: bool,
+}
+
+impl<R: Read> LzwDecoder<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        LzwDecoder {
+            input: BufReader::new(reader).bytes(),
+            header_read: false,
+            max_bits: MAX_BITS,
+            block_mode: true,
+            bit_buf: 0,
+            bit_count: 0,
+            n_bits: INIT_BITS,
+            codes_in_width: 0,
+            prefix: vec![],
+            suffix: vec![],
+            next_code: CLEAR + 1,
+            prev_code: None,
+            first_byte: 0,
+            out: vec![],
+            out_pos: 0,
+            done: false,
+        }
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let mut header = [0; 3];
+        for b in &mut header {
+            *b =
+                self.input.next().transpose()?.ok_or_else(|| {
+                    invalid_data("the compressed data is too short to have a header")
+                })?;
+        }
+        if &header[..2] != MAGIC {
+            return Err(invalid_data(
+                "the compressed data does not start with the `compress` magic bytes",
+            ));
+        }
+        let flags = header[2];
+        if flags & RESERVED_MASK != 0 {
+            return Err(invalid_data(&format!(
+                "the compressed data has unknown flags set, {flags:#04x}",
+            )));
+        }
+        self.max_bits = u32::from(flags & MAX_BITS_MASK);
+        if !(INIT_BITS..=MAX_BITS).contains(&self.max_bits) {
+            return Err(invalid_data(&format!(
+                "the compressed data has an invalid maximum code width, {}",
+                self.max_bits,
+            )));
+        }
+        self.block_mode = flags & BLOCK_MODE != 0;
+        self.next_code = if self.block_mode { CLEAR + 1 } else { CLEAR };
+
+        let table_len = 1 << self.max_bits;
+        self.prefix = vec![0; table_len];
+        self.suffix = vec![0; table_len];
+        for (i, s) in self.suffix.iter_mut().take(256).enumerate() {
+            *s = u8::try_from(i).unwrap();
+        }
+
+        self.header_read = true;
+        Ok(())
+    }
+
+    // Returns `None` when there aren't enough bits left for another code. Like `compress`, we
+    // ignore a partial code at the end of the data.
+    fn read_code(&mut self) -> io::Result<Option<u32>> {
+        while self.bit_count < self.n_bits {
+            let Some(b) = self.input.next().transpose()? else {
+                return Ok(None);
+            };
+            self.bit_buf |= u32::from(b) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let code = self.bit_buf & ((1 << self.n_bits) - 1);
+        self.bit_buf >>= self.n_bits;
+        self.bit_count -= self.n_bits;
+        self.codes_in_width += 1;
+        Ok(Some(code))
+    }
+
+    fn skip_to_end_of_group(&mut self) -> io::Result<()> {
+        while !self.codes_in_width.is_multiple_of(8) {
+            if self.read_code()?.is_none() {
+                break;
+            }
+        }
+        self.codes_in_width = 0;
+        Ok(())
+    }
+
+    fn max_code(&self) -> u32 {
+        if self.n_bits == self.max_bits {
+            1 << self.max_bits
+        } else {
+            (1 << self.n_bits) - 1
+        }
+    }
+
+    // Decodes the next code into `self.out`. This returns false when there are no more codes.
+    fn decode_next(&mut self) -> io::Result<bool> {
+        if self.next_code > self.max_code() {
+            self.skip_to_end_of_group()?;
+            self.n_bits += 1;
+        }
+
+        let Some(code) = self.read_code()? else {
+            return Ok(false);
+        };
+        let Some(prev_code) = self.prev_code else {
+            if code >= CLEAR {
+                return Err(invalid_data(&format!(
+                    "the compressed data starts with the code {code}, which is not a byte",
+                )));
+            }
+            self.first_byte = self.suffix[code as usize];
+            self.out.push(self.first_byte);
+            self.prev_code = Some(code);
+            return Ok(true);
+        };
+
+        if code == CLEAR && self.block_mode {
+            self.skip_to_end_of_group()?;
+            self.n_bits = INIT_BITS;
+            // The code after a clear creates a table entry for the clear code, which is never
+            // used, so this starts one below the first real entry.
+            self.next_code = CLEAR;
+            return Ok(true);
+        }
+
+        // This is the one case where the code isn't in the table yet, because the compressor used
+        // it right after creating it. Its string is the previous string plus that string's first
+        // byte.
+        let start = self.out.len();
+        let mut c = code;
+        if c >= self.next_code {
+            if c > self.next_code {
+                return Err(invalid_data(&format!(
+                    "the compressed data contains the code {code}, which is not in the table yet",
+                )));
+            }
+            self.out.push(self.first_byte);
+            c = prev_code;
+        }
+        while c > 255 {
+            self.out.push(self.suffix[c as usize]);
+            c = u32::from(self.prefix[c as usize]);
+        }
+        self.first_byte = self.suffix[c as usize];
+        self.out.push(self.first_byte);
+        self.out[start..].reverse();
+
+        if self.next_code < 1 << self.max_bits {
+            self.prefix[self.next_code as usize] = u16::try_from(prev_code).unwrap();
+            self.suffix[self.next_code as usize] = self.first_byte;
+            self.next_code += 1;
+        }
+        self.prev_code = Some(code);
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for LzwDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.header_read {
+            self.read_header()?;
+        }
+        if self.out_pos == self.out.len() {
+            self.out.clear();
+            self.out_pos = 0;
+            while !self.done && self.out.len() < buf.len() {
+                self.done = !self.decode_next()?;
+            }
+        }
+        let available = &self.out[self.out_pos..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.out_pos += n;
+        Ok(n)
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use test_case::test_case;
+
+    fn decode(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = vec![];
+        LzwDecoder::new(data).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn decode_exe() -> io::Result<()> {
+        assert_eq!(decode(&fs::read("test-data/project.Z")?)?, b"exe");
+        Ok(())
+    }
+
+    // This file was compressed with a maximum code width of 10 bits, so it has codes of every width
+    // from 9 to 10 bits, and several clear codes.
+    #[test]
+    fn decode_width_changes_and_clears() -> io::Result<()> {
+        let expect = (0..20_000_u32)
+            .map(|i| b'a' + u8::try_from((i * i / 3) % 26).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(decode(&fs::read("test-data/compress-10-bits.Z")?)?, expect);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_without_block_mode() -> io::Result<()> {
+        // These are the codes for "a", "b", 256 ("ab"), and 258 ("ab" + "a"), which is only a
+        // valid sequence when 256 is not the clear code.
+        assert_eq!(decode(b"\x1f\x9d\x10\x61\xc4\x00\x14\x08")?, b"abababa");
+        Ok(())
+    }
+
+    #[test_case(b"\x1f\x8b\x90"; "bad magic")]
+    #[test_case(b"\x1f\x9d\xb0"; "reserved flag")]
+    #[test_case(b"\x1f\x9d\x91"; "max bits too large")]
+    #[test_case(b"\x1f\x9d\x88"; "max bits too small")]
+    #[test_case(b"\x1f\x9d"; "too short")]
+    #[test_case(b"\x1f\x9d\x90\x00\x01"; "first code is not a byte")]
+    #[test_case(b"\x1f\x9d\x90\x65\x58\x02"; "code not in table")]
+    fn decode_bad_data(data: &[u8]) {
+        let err = decode(data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use minisign_verify::{PublicKey, Signature, StreamVerifier};
+use std::io::{self, Read};
+
+/// Parses a minisign public key given as base64, which is the second line of a `minisign.pub` file.
+pub(crate) fn parse_public_key(public_key: &str) -> Result<PublicKey> {
+    PublicKey::from_base64(public_key.trim())
+        .map_err(|e| anyhow!("the minisign public key `{public_key}` is not valid: {e}"))
+}
+
+/// Returns the ID of a base64 minisign public key, formatted the way the `minisign` tool shows it.
+pub(crate) fn key_id(public_key: &str) -> Result<String> {
+    let bytes = STANDARD.decode(public_key.trim())?;
+    let Some(id) = bytes.get(2..10) else {
+        return Err(anyhow!(
+            "the minisign public key `{public_key}` is too short"
+        ));
+    };
+    let id: [u8; 8] = id.try_into()?;
+    Ok(format!("{:016X}", u64::from_le_bytes(id)))
+}
+
+/// Checks that `signature`, the contents of a `.minisig` file, is a valid signature of the file
+/// read from `file` made with the key matching `public_key`. The `file_name` is only used in the
+/// error.
+pub(crate) fn verify_signature(
+    public_key: &str,
+    mut file: impl Read,
+    file_name: &str,
+    signature: &str,
+) -> Result<()> {
+    let key = parse_public_key(public_key)?;
+    let failed = |e: &dyn std::fmt::Display| -> anyhow::Error {
+        let key_id = key_id(public_key).unwrap_or_else(|_| public_key.to_string());
+        anyhow!(
+            "could not verify the minisign signature for {file_name} with the key {key_id}: {e}"
+        )
+    };
+
+    let signature = Signature::decode(signature).map_err(|e| failed(&e))?;
+    // Signatures made by current versions of minisign sign a hash of the file, which lets us
+    // verify them without reading the whole file into memory. Older versions signed the file
+    // itself.
+    let res = match key.verify_stream(&signature) {
+        Ok(mut verifier) => {
+            io::copy(&mut file, &mut VerifierWriter(&mut verifier))?;
+            verifier.finalize()
+        }
+        Err(minisign_verify::Error::UnsupportedLegacyMode) => {
+            let mut contents = vec![];
+            file.read_to_end(&mut contents)?;
+            key.verify(&contents, &signature, true)
+        }
+        Err(e) => Err(e),
+    };
+
+    res.map_err(|e| failed(&e))
+}
+
+struct VerifierWriter<'a, 'b>(&'a mut StreamVerifier<'b>);
+
+impl io::Write for VerifierWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::{self, File};
+    use test_case::test_case;
+
+    const PUBLIC_KEY: &str = "RWQBI0VniavN7+pKbGPinFIKvvVQexMuxfmVR3auvr57kkIe6mkURtIs";
+    // This has the same key ID as `PUBLIC_KEY`, but a different key.
+    const OTHER_PUBLIC_KEY: &str = "RWQBI0VniavN71hmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZm";
+
+    #[test]
+    fn key_id() -> Result<()> {
+        assert_eq!(super::key_id(PUBLIC_KEY)?, "EFCDAB8967452301");
+        Ok(())
+    }
+
+    #[test_case("test-data/project.tar.gz", PUBLIC_KEY, None; "valid")]
+    #[test_case(
+        "test-data/project.zip",
+        PUBLIC_KEY,
+        Some("signature for project.tar.gz with the key EFCDAB8967452301");
+        "wrong file"
+    )]
+    #[test_case(
+        "test-data/project.tar.gz",
+        OTHER_PUBLIC_KEY,
+        Some("signature for project.tar.gz with the key EFCDAB8967452301");
+        "wrong key"
+    )]
+    fn verify_signature(path: &str, public_key: &str, expect_err: Option<&str>) -> Result<()> {
+        let signature = fs::read_to_string("test-data/project.tar.gz.minisig")?;
+        let res =
+            super::verify_signature(public_key, File::open(path)?, "project.tar.gz", &signature);
+        match expect_err {
+            None => res?,
+            Some(expect_err) => {
+                let err = res.expect_err("verification fails").to_string();
+                assert!(err.contains(expect_err), "{err} contains {expect_err}");
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
         ppc64_re, ppc64le_re, riscv64_re, s390x_re, sparc64_re, x86_32_re, x86_64_re,
         ALL_ARCHES_RE,
     },
-    extension::Extension,
+    extension::{matching_custom_extension, Extension},
     os::{freebsd_re, fuchsia, illumos_re, linux_re, macos_re, netbsd_re, solaris_re, windows_re},
     ubi::Asset,
 };
@@ -23,6 +23,9 @@ pub(crate) struct AssetPicker<'a> {
     platform: Platform,
     is_musl: bool,
     archive_only: bool,
+    // Extensions that the user gave a decompressor command for. Assets with these are kept even
+    // though we don't know the extension.
+    custom_extensions: Vec<String>,
 }
 
 impl<'a> AssetPicker<'a> {
@@ -31,18 +34,55 @@ impl<'a> AssetPicker<'a> {
         platform: Platform,
         is_musl: bool,
         archive_only: bool,
+        custom_extensions: Vec<String>,
     ) -> Self {
         Self {
             matching,
             platform,
             is_musl,
             archive_only,
+            custom_extensions,
         }
     }
 
     pub(crate) fn pick_asset(&mut self, assets: Vec<Asset>) -> Result<Asset> {
         let all_names = assets.iter().map(|a| &a.name).join(", ");
 
+        // We only pick an asset in a fallback format, like a `.deb` or `.rpm` file, when none of
+        // the other assets work for this platform. A release that has one of these usually has a
+        // tarball or zip file as well, and this keeps us picking the same asset we did before we
+        // could install from the fallback formats.
+        let (fallbacks, assets): (Vec<_>, Vec<_>) = assets
+            .into_iter()
+            .partition(|a| has_fallback_format(&a.name));
+        if fallbacks.is_empty() {
+            return self.pick_asset_from(assets, &all_names, false);
+        }
+        // When the `matching` string is only in the names of assets in a fallback format, the user
+        // is asking for one of those.
+        let only_fallbacks_match = self.matching.is_some_and(|m| {
+            !assets.iter().any(|a| a.name.contains(m))
+                && fallbacks.iter().any(|a| a.name.contains(m))
+        });
+        if assets.is_empty() || only_fallbacks_match {
+            return self.pick_asset_from(fallbacks, &all_names, false);
+        }
+        self.pick_asset_from(assets, &all_names, true)
+            .or_else(|e| {
+                debug!("could not pick an asset that isn't in a fallback format ({e}), so trying the assets in those formats");
+                self.pick_asset_from(fallbacks, &all_names, false)
+                    .map_err(|_| e)
+            })
+    }
+
+    // When `has_fallbacks` is true, we don't take the only asset left after filtering by extension
+    // unless it also matches this platform, since one of the fallbacks might.
+    fn pick_asset_from(
+        &mut self,
+        assets: Vec<Asset>,
+        all_names: &str,
+        has_fallbacks: bool,
+    ) -> Result<Asset> {
         let mut assets = self.filter_by_extension(assets);
         if assets.is_empty() {
             let filter = if self.archive_only {
@@ -55,7 +95,7 @@ impl<'a> AssetPicker<'a> {
             ));
         }
 
-        if assets.len() == 1 {
+        if assets.len() == 1 && !has_fallbacks {
             debug!("there is only one asset to pick");
             return Ok(assets.remove(0));
         }
@@ -98,10 +138,26 @@ impl<'a> AssetPicker<'a> {
         assets
             .into_iter()
             .filter(|a| match Extension::from_path(Path::new(&a.name)) {
+                Err(_)
+                    if matching_custom_extension(
+                        &a.name,
+                        self.custom_extensions.iter().map(String::as_str),
+                    )
+                    .is_some() =>
+                {
+                    debug!("including this asset because it has a custom decompressor");
+                    true
+                }
                 Err(e) => {
                     debug!("skipping asset with invalid extension: {e}");
                     false
                 }
+                // We can install from a `.a` file, but a release asset with this extension is
+                // almost always a static library.
+                Ok(Some(Extension::A)) => {
+                    debug!("skipping asset because a `.a` file is usually a static library");
+                    false
+                }
                 Ok(Some(ext)) => {
                     debug!("found valid extension, `{}`", ext.extension());
                     if self.archive_only {
@@ -425,6 +481,10 @@ impl<'a> AssetPicker<'a> {
     }
 }
 
+fn has_fallback_format(name: &str) -> bool {
+    matches!(Extension::from_path(Path::new(name)), Ok(Some(ext)) if ext.is_fallback_format())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -550,6 +610,83 @@ mod test {
         1 ;
         "x86_64-unknown-linux-musl - pick the musl asset over unspecified libc on a musl platform"
     )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project_linux_amd64.deb", "project_linux_amd64.tar.gz"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - pick the tarball over a deb file"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project_darwin_amd64.tar.gz", "project_linux_amd64.deb"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - pick a deb file when no other asset matches"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project_linux_amd64.deb", "project_linux_amd64.tar.gz"],
+        Some(".deb"),
+        0 ;
+        "x86_64-unknown-linux-gnu - pick a deb file when matching is set"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project_linux_amd64.rpm", "project_linux_amd64.tar.gz"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - pick the tarball over an rpm file"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project_linux_amd64.deb", "project_linux_amd64.rpm", "project_linux_amd64.tar.gz"],
+        None,
+        2 ;
+        "x86_64-unknown-linux-gnu - pick the tarball over deb and rpm files"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project_darwin_amd64.tar.gz", "project_linux_amd64.rpm"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - pick an rpm file when no other asset matches"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["libproject_linux_amd64.a", "project_linux_amd64.tar.gz"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - never pick a static library"
+    )]
+    #[test_case(
+        "x86_64-unknown-linux-gnu",
+        &["project_linux_amd64.ar", "project_linux_amd64.tar.gz"],
+        None,
+        1 ;
+        "x86_64-unknown-linux-gnu - pick the tarball over an ar file"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-windows-x86_64.7z", "project-windows-x86_64.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the zip file over a 7z file"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-linux-x86_64.zip", "project-windows-x86_64.7z"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick a 7z file when no other asset matches"
+    )]
+    #[test_case(
+        "x86_64-pc-windows-msvc",
+        &["project-windows-x86_64.cab", "project-windows-x86_64.zip"],
+        None,
+        1 ;
+        "x86_64-pc-windows-msvc - pick the zip file over a cab file"
+    )]
     fn pick_asset(
         platform_name: &str,
         asset_names: &[&str],
@@ -566,6 +703,7 @@ mod test {
             platform,
             is_musl: platform_name.contains("musl"),
             archive_only: false,
+            custom_extensions: vec![],
         };
 
         let url = Url::parse("https://example.com")?;
@@ -574,6 +712,7 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                digest: None,
             })
             .collect::<Vec<_>>();
 
@@ -627,6 +766,7 @@ mod test {
             platform,
             is_musl: platform_name.contains("musl"),
             archive_only: true,
+            custom_extensions: vec![],
         };
 
         let url = Url::parse("https://example.com")?;
@@ -635,6 +775,7 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                digest: None,
             })
             .collect::<Vec<_>>();
 
@@ -644,6 +785,37 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn pick_asset_with_custom_extension() -> Result<()> {
+        crate::test_case::init_logging();
+
+        let platform = Platform::find("x86_64-unknown-linux-gnu")
+            .ok_or(anyhow!("invalid platform"))?
+            .clone();
+        let mut picker = AssetPicker {
+            matching: None,
+            platform,
+            is_musl: false,
+            archive_only: true,
+            custom_extensions: vec!["tar.foo".to_string()],
+        };
+
+        let url = Url::parse("https://example.com")?;
+        let assets = ["project-Linux-x86_64.bar", "project-Linux-x86_64.tar.foo"]
+            .iter()
+            .map(|name| Asset {
+                name: (*name).to_string(),
+                url: url.clone(),
+                digest: None,
+            })
+            .collect::<Vec<_>>();
+
+        let picked_asset = picker.pick_asset(assets)?;
+        assert_eq!(picked_asset.name, "project-Linux-x86_64.tar.foo");
+
+        Ok(())
+    }
+
     #[test_case(
         "x86_64-unknown-linux-gnu",
         false,
@@ -725,6 +897,7 @@ mod test {
             platform,
             is_musl: platform_name.contains("musl"),
             archive_only,
+            custom_extensions: vec![],
         };
 
         let url = Url::parse("https://example.com")?;
@@ -733,6 +906,7 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                digest: None,
             })
             .collect::<Vec<_>>();
 
@@ -0,0 +1,291 @@
+use crate::checksum::ChecksumAlgorithm;
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::debug;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::HashMap, fs::File, path::Path, str::FromStr};
+
+const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+const SLSA_PROVENANCE_PREFIX: &str = "https://slsa.dev/provenance/";
+
+// A DSSE envelope. See https://github.com/secure-systems-lab/dsse/blob/master/envelope.md.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "payloadType")]
+    payload_type: String,
+    payload: String,
+}
+
+// An in-toto statement. See https://github.com/in-toto/attestation/blob/main/spec/v1/statement.md.
+#[derive(Deserialize)]
+struct Statement {
+    subject: Vec<Subject>,
+    #[serde(rename = "predicateType")]
+    predicate_type: String,
+    #[serde(default)]
+    predicate: Value,
+}
+
+#[derive(Deserialize)]
+struct Subject {
+    #[serde(default)]
+    name: String,
+    digest: HashMap<String, String>,
+}
+
+/// Checks that the SLSA provenance in `provenance`, which was read from the release asset named
+/// `provenance_name`, says that it was made by the builder `builder_id` and lists the file at
+/// `path` as one of its subjects. The provenance can be a single DSSE envelope, a Sigstore bundle,
+/// or a JSON lines file with one envelope per line, like the `.intoto.jsonl` files that the SLSA
+/// GitHub generator makes. The `file_name` is only used in errors.
+///
+/// This does not check the signatures in the envelopes, which needs the Sigstore trust root, so
+/// nothing here proves who made the provenance. It only checks that the file matches what the
+/// provenance says about it.
+pub(crate) fn check_provenance_subject(
+    provenance: &str,
+    provenance_name: &str,
+    path: &Path,
+    file_name: &str,
+    builder_id: &str,
+) -> Result<()> {
+    let statements = provenance_statements(provenance)
+        .with_context(|| format!("could not parse the SLSA provenance in {provenance_name}"))?;
+    if statements.is_empty() {
+        return Err(anyhow!(
+            "the file {provenance_name} does not contain any SLSA provenance"
+        ));
+    }
+
+    let mut other_builders = vec![];
+    let mut from_builder = vec![];
+    for statement in statements {
+        match statement_builder_id(&statement) {
+            Some(id) if builder_id_matches(id, builder_id) => from_builder.push(statement),
+            Some(id) => other_builders.push(id.to_string()),
+            None => other_builders.push("no builder".to_string()),
+        }
+    }
+    if from_builder.is_empty() {
+        return Err(anyhow!(
+            "the SLSA provenance in {provenance_name} does not name the builder {builder_id}, \
+             it names {}",
+            other_builders.join(", "),
+        ));
+    }
+
+    // We only compute each kind of digest of the file once.
+    let mut digests: Vec<(ChecksumAlgorithm, String)> = vec![];
+    let mut mismatch = None;
+    for subject in from_builder.iter().flat_map(|s| &s.subject) {
+        for (algorithm, expect) in &subject.digest {
+            let Ok(algorithm) = ChecksumAlgorithm::from_str(algorithm) else {
+                continue;
+            };
+            let got = if let Some((_, got)) = digests.iter().find(|(a, _)| *a == algorithm) {
+                got.clone()
+            } else {
+                let got = algorithm.hex_digest(File::open(path)?)?;
+                digests.push((algorithm, got.clone()));
+                got
+            };
+            if got.eq_ignore_ascii_case(expect) {
+                debug!(
+                    "the SLSA provenance in {provenance_name} has a subject named {} with the {} \
+                     digest of {file_name}",
+                    subject.name,
+                    algorithm.as_ref(),
+                );
+                return Ok(());
+            }
+            if subject.name == file_name {
+                mismatch = Some((algorithm, expect.clone(), got));
+            }
+        }
+    }
+
+    if let Some((algorithm, expect, got)) = mismatch {
+        return Err(anyhow!(
+            "the SLSA provenance in {provenance_name} says that the {} digest of {file_name} is \
+             {expect}, but it is {got}",
+            algorithm.as_ref(),
+        ));
+    }
+    let Some((algorithm, got)) = digests.first() else {
+        return Err(anyhow!(
+            "the SLSA provenance in {provenance_name} does not have any subject digests that ubi \
+             knows how to check"
+        ));
+    };
+    Err(anyhow!(
+        "the SLSA provenance in {provenance_name} does not have a subject for {file_name}, which \
+         has the {} digest {got}",
+        algorithm.as_ref(),
+    ))
+}
+
+// Returns every SLSA provenance statement in the file. The file is a stream of JSON values, so
+// this handles a JSON lines file and a single, pretty-printed, JSON document the same way.
+// Statements that aren't SLSA provenance are skipped.
+fn provenance_statements(provenance: &str) -> Result<Vec<Statement>> {
+    let mut statements = vec![];
+    for value in serde_json::Deserializer::from_str(provenance).into_iter::<Value>() {
+        let mut value = value?;
+        // A Sigstore bundle wraps the envelope along with the material to verify its signature.
+        if let Some(envelope) = value.get_mut("dsseEnvelope") {
+            value = envelope.take();
+        }
+        let statement = if value.get("payload").is_some() {
+            let envelope: Envelope = serde_json::from_value(value)?;
+            if envelope.payload_type != IN_TOTO_PAYLOAD_TYPE {
+                debug!(
+                    "skipping an envelope with the payload type {}",
+                    envelope.payload_type,
+                );
+                continue;
+            }
+            serde_json::from_slice(&STANDARD.decode(envelope.payload.trim())?)?
+        } else {
+            serde_json::from_value::<Statement>(value)?
+        };
+        if !statement.predicate_type.starts_with(SLSA_PROVENANCE_PREFIX) {
+            debug!(
+                "skipping a statement with the predicate type {}",
+                statement.predicate_type,
+            );
+            continue;
+        }
+        statements.push(statement);
+    }
+
+    Ok(statements)
+}
+
+// SLSA provenance v0.2 has the builder at `predicate.builder.id`, and v1 moved it to
+// `predicate.runDetails.builder.id`.
+fn statement_builder_id(statement: &Statement) -> Option<&str> {
+    statement
+        .predicate
+        .pointer("/runDetails/builder/id")
+        .or_else(|| statement.predicate.pointer("/builder/id"))
+        .and_then(Value::as_str)
+}
+
+// A builder ID for a reusable workflow usually ends with the ref it was run from, like
+// `...generator_generic_slsa3.yml@refs/tags/v2.0.0`, so the ID we look for can leave that off.
+fn builder_id_matches(id: &str, expect: &str) -> bool {
+    id == expect
+        || id
+            .strip_prefix(expect)
+            .is_some_and(|rest| rest.starts_with('@'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    const BUILDER: &str = "https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_generic_slsa3.yml";
+    // The SHA-256 digest of `test-data/project.tar.gz`.
+    const DIGEST: &str = "539d9c66d338e329584755476f5a1e57a89f35b22f3d0979da14e61d8fa20447";
+
+    // The signature is empty because `check_provenance_subject` never looks at it. These tests
+    // only cover what the provenance says about the file, not who signed it.
+    fn envelope(statement: &Value) -> String {
+        serde_json::json!({
+            "payloadType": IN_TOTO_PAYLOAD_TYPE,
+            "payload": STANDARD.encode(statement.to_string()),
+            "signatures": [{ "keyid": "", "sig": "" }],
+        })
+        .to_string()
+    }
+
+    fn v02_statement(name: &str, digest: &str, builder: &str) -> Value {
+        serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v0.1",
+            "predicateType": "https://slsa.dev/provenance/v0.2",
+            "subject": [
+                { "name": "project-other.tar.gz", "digest": { "sha256": "00".repeat(32) } },
+                { "name": name, "digest": { "sha256": digest } },
+            ],
+            "predicate": { "builder": { "id": builder } },
+        })
+    }
+
+    fn v1_statement(name: &str, digest: &str, builder: &str) -> Value {
+        serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "subject": [{ "name": name, "digest": { "sha256": digest } }],
+            "predicate": { "runDetails": { "builder": { "id": builder } } },
+        })
+    }
+
+    #[test_case(&envelope(&v02_statement("project.tar.gz", DIGEST, BUILDER)), None; "v0.2 envelope")]
+    #[test_case(&envelope(&v1_statement("project.tar.gz", DIGEST, BUILDER)), None; "v1 envelope")]
+    #[test_case(
+        &serde_json::json!({
+            "mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json",
+            "dsseEnvelope": serde_json::from_str::<Value>(
+                &envelope(&v1_statement("project.tar.gz", DIGEST, BUILDER)),
+            ).unwrap(),
+        }).to_string(),
+        None;
+        "sigstore bundle"
+    )]
+    #[test_case(
+        &format!(
+            "{}\n{}\n",
+            envelope(&v1_statement("other.tar.gz", &"11".repeat(32), BUILDER)),
+            envelope(&v1_statement("project.tar.gz", DIGEST, BUILDER)),
+        ),
+        None;
+        "json lines"
+    )]
+    #[test_case(
+        &envelope(&v1_statement("project.tar.gz", DIGEST, &format!("{BUILDER}@refs/tags/v2.0.0"))),
+        None;
+        "builder with ref"
+    )]
+    #[test_case(
+        &envelope(&v1_statement("project.tar.gz", DIGEST, "https://example.com/builder")),
+        Some("does not name the builder");
+        "other builder"
+    )]
+    #[test_case(
+        &envelope(&v1_statement("project.tar.gz", DIGEST, &format!("{BUILDER}-evil"))),
+        Some("does not name the builder");
+        "builder with a longer name"
+    )]
+    #[test_case(
+        &envelope(&v1_statement("project.tar.gz", &"22".repeat(32), BUILDER)),
+        Some(&format!("says that the sha256 digest of project.tar.gz is {}, but it is {DIGEST}", "22".repeat(32)));
+        "wrong digest"
+    )]
+    #[test_case(
+        &envelope(&v1_statement("other.tar.gz", &"22".repeat(32), BUILDER)),
+        Some(&format!("does not have a subject for project.tar.gz, which has the sha256 digest {DIGEST}"));
+        "missing subject"
+    )]
+    #[test_case("", Some("does not contain any SLSA provenance"); "empty file")]
+    #[test_case("not json", Some("could not parse the SLSA provenance"); "not json")]
+    fn check_provenance_subject(provenance: &str, expect_err: Option<&str>) -> Result<()> {
+        let res = super::check_provenance_subject(
+            provenance,
+            "project.intoto.jsonl",
+            Path::new("test-data/project.tar.gz"),
+            "project.tar.gz",
+            BUILDER,
+        );
+        match expect_err {
+            None => res?,
+            Some(expect_err) => {
+                let err = format!("{:#}", res.expect_err("verification fails"));
+                assert!(err.contains(expect_err), "{err} contains {expect_err}");
+            }
+        }
+
+        Ok(())
+    }
+}
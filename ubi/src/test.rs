@@ -1033,3 +1033,229 @@ const OS_WITHOUT_ARCH_RESPONSE2: &str = r#"
     }
   ]
 }"#;
+
+#[test(tokio::test)]
+async fn verify_checksums_file() -> Result<()> {
+    struct Test {
+        checksums: String,
+        expect_err: Option<&'static str>,
+    }
+    let good = "539d9c66d338e329584755476f5a1e57a89f35b22f3d0979da14e61d8fa20447";
+    let bad = "0000000000000000000000000000000000000000000000000000000000000000";
+    let tests = [
+        Test {
+            checksums: format!("{good}  project-Linux-x86_64.tar.gz\n"),
+            expect_err: None,
+        },
+        Test {
+            checksums: format!("{good} *project-Linux-x86_64.tar.gz\n"),
+            expect_err: None,
+        },
+        Test {
+            checksums: format!("# checksums\n\nSHA256 (project-Linux-x86_64.tar.gz) = {good}\n"),
+            expect_err: None,
+        },
+        Test {
+            checksums: format!("{bad}  project-Linux-x86_64.tar.gz\n"),
+            expect_err: Some("but SHA256SUMS says it should be"),
+        },
+        Test {
+            checksums: format!("{good}  project-Linux-arm64.tar.gz\n"),
+            expect_err: Some("could not find project-Linux-x86_64.tar.gz in the checksums file"),
+        },
+    ];
+
+    let req = PlatformReq::from_str("x86_64-unknown-linux-gnu")?;
+    let platform = req.matching_platforms().next().unwrap();
+    for t in tests {
+        let mut server = Server::new_async().await;
+        let asset = server
+            .mock(
+                "GET",
+                "/test/project/releases/download/v1.0.0/project-Linux-x86_64.tar.gz",
+            )
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(std::fs::read("test-data/project.tar.gz")?)
+            .create_async()
+            .await;
+        let checksums = server
+            .mock("GET", "/test/project/releases/download/v1.0.0/SHA256SUMS")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(&t.checksums)
+            .create_async()
+            .await;
+
+        let install_dir = tempfile::tempdir()?;
+        // We use a hostname here because we can't tell what forge a URL belongs to without one.
+        let url = format!(
+            "{}/test/project/releases/download/v1.0.0/project-Linux-x86_64.tar.gz",
+            server.url().replace("127.0.0.1", "localhost"),
+        );
+        let mut ubi = UbiBuilder::new()
+            .url(&url)
+            .platform(platform)
+            .install_dir(install_dir.path())
+            .verify_checksums_file("SHA256SUMS")
+            .build()?;
+        let res = ubi.install_binary().await;
+
+        let exe = install_dir.path().join("project");
+        match t.expect_err {
+            None => {
+                assert_eq!(res?, vec![exe.clone()]);
+                assert!(exe.exists(), "{} exists", exe.display());
+            }
+            Some(expect_err) => {
+                let err = res.expect_err("install fails").to_string();
+                assert!(err.contains(expect_err), "{err} contains {expect_err}");
+                if t.checksums.starts_with(bad) {
+                    assert!(err.contains(good), "{err} contains the computed digest");
+                    assert!(err.contains(bad), "{err} contains the expected digest");
+                }
+                assert!(!exe.exists(), "{} does not exist", exe.display());
+            }
+        }
+
+        asset.assert_async().await;
+        checksums.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn verify_minisign_signature() -> Result<()> {
+    let public_key = "RWQBI0VniavN7+pKbGPinFIKvvVQexMuxfmVR3auvr57kkIe6mkURtIs";
+    let req = PlatformReq::from_str("x86_64-unknown-linux-gnu")?;
+    let platform = req.matching_platforms().next().unwrap();
+
+    for (archive, expect_ok) in [
+        ("test-data/project.tar.gz", true),
+        ("test-data/project-with-one-file.tar.gz", false),
+    ] {
+        let mut server = Server::new_async().await;
+        let url = server.url();
+        let release = server
+            .mock("GET", "/repos/test/project/releases/latest")
+            .match_header(ACCEPT.as_str(), "application/json")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(format!(
+                r#"{{
+  "assets": [
+    {{ "url": "{url}/assets/1", "name": "project-Linux-x86_64.tar.gz" }},
+    {{ "url": "{url}/assets/2", "name": "project-Linux-x86_64.tar.gz.minisig" }}
+  ]
+}}"#,
+            ))
+            .create_async()
+            .await;
+        let asset = server
+            .mock("GET", "/assets/1")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(std::fs::read(archive)?)
+            .create_async()
+            .await;
+        let signature = server
+            .mock("GET", "/assets/2")
+            .with_status(reqwest::StatusCode::OK.as_u16() as usize)
+            .with_body(std::fs::read("test-data/project.tar.gz.minisig")?)
+            .create_async()
+            .await;
+
+        let install_dir = tempfile::tempdir()?;
+        let mut ubi = UbiBuilder::new()
+            .project("test/project")
+            .platform(platform)
+            .api_base_url(&url)
+            .install_dir(install_dir.path())
+            .verify_minisign_signature(public_key)
+            .build()?;
+        let res = ubi.install_binary().await;
+
+        let exe = install_dir.path().join("project");
+        if expect_ok {
+            res?;
+            assert!(exe.exists(), "{} exists", exe.display());
+        } else {
+            let err = res.expect_err("install fails").to_string();
+            let expect_err = concat!(
+                "could not verify the minisign signature for project-Linux-x86_64.tar.gz",
+                " with the key EFCDAB8967452301",
+            );
+            assert!(err.contains(expect_err), "{err} contains {expect_err}");
+            assert!(!exe.exists(), "{} does not exist", exe.display());
+        }
+
+        release.assert_async().await;
+        asset.assert_async().await;
+        signature.assert_async().await;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn invalid_minisign_public_key() {
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .verify_minisign_signature("not a key")
+        .build();
+    assert!(res.is_err());
+}
+
+#[test]
+fn invalid_exe_pattern() {
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .exe_pattern("project-(")
+        .build();
+    assert!(res.is_err());
+}
+
+#[test(tokio::test)]
+async fn install_from_archive_file() -> Result<()> {
+    let td = tempfile::tempdir()?;
+    let archive_file = td.path().join("project.tar.gz");
+    std::fs::copy("test-data/project.tar.gz", &archive_file)?;
+    let install_dir = td.path().join("bin");
+    let req = PlatformReq::from_str("x86_64-unknown-linux-gnu")?;
+    let platform = req.matching_platforms().next().unwrap();
+
+    // The API base URL can't be reached, so this fails if anything is fetched from the forge.
+    let mut ubi = UbiBuilder::new()
+        .project("test/project")
+        .archive_file(&archive_file)
+        .install_dir(&install_dir)
+        .api_base_url("http://127.0.0.1:1")
+        .platform(platform)
+        .build()?;
+    let installed = ubi.install_binary().await?;
+    assert_eq!(installed, vec![install_dir.join("project")]);
+    assert!(install_dir.join("project").is_file());
+    assert!(archive_file.is_file(), "the archive file was left in place");
+
+    Ok(())
+}
+
+#[test]
+fn invalid_archive_file() {
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .archive_file("test-data/no-such-file.tar.gz")
+        .build();
+    assert!(res.is_err());
+
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .tag("v1.0.0")
+        .archive_file("test-data/project.tar.gz")
+        .build();
+    assert!(res.is_err());
+
+    let res = UbiBuilder::new()
+        .project("test/project")
+        .archive_file("test-data/project.tar.gz")
+        .verify_checksums_file("SHA256SUMS")
+        .build();
+    assert!(res.is_err());
+}
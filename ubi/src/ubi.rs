@@ -1,12 +1,32 @@
-use crate::{forge::Forge, installer::Installer, picker::AssetPicker};
-use anyhow::{anyhow, Result};
+#[cfg(feature = "gpg")]
+use crate::gpg;
+#[cfg(feature = "slsa")]
+use crate::slsa;
+use crate::{
+    checksum::{digest_from_checksums_file, parse_prefixed_digest, ChecksumAlgorithm},
+    extension::{matching_custom_extension, Extension},
+    forge::Forge,
+    installer::{
+        digest_mismatch_error, open_file, ExtractionReport, InstallManifest, Installer, MatchKind,
+        SkippedMember,
+    },
+    minisign,
+    picker::AssetPicker,
+};
+use anyhow::{anyhow, Context, Result};
 use log::debug;
 use reqwest::{
     header::{HeaderValue, ACCEPT},
     Client, StatusCode,
 };
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
 use tempfile::{tempdir, TempDir};
 use url::Url;
 
@@ -16,23 +36,150 @@ use url::Url;
 pub struct Ubi<'a> {
     forge: Box<dyn Forge + Send + Sync>,
     asset_url: Option<Url>,
+    archive_file: Option<PathBuf>,
     asset_picker: AssetPicker<'a>,
     installer: Box<dyn Installer>,
+    checks: AssetChecks<'a>,
     reqwest_client: Client,
+    custom_decompressors: HashMap<String, Vec<String>>,
+    temp_dir: Option<PathBuf>,
+    keep_archive: Option<PathBuf>,
+}
+
+/// The checks to run against a downloaded release asset before anything is installed from it.
+#[derive(Debug, Default)]
+pub(crate) struct AssetChecks<'a> {
+    /// The name of a checksums file asset, like `SHA256SUMS`.
+    pub(crate) checksums_file: Option<&'a str>,
+    /// The algorithm used for the digests in the checksums file. When this isn't set, it's
+    /// guessed from the checksums file's name and the length of the digest.
+    pub(crate) checksums_algorithm: Option<ChecksumAlgorithm>,
+    /// A base64 minisign public key. The signature is in the asset's `.minisig` sibling.
+    pub(crate) minisign_public_key: Option<&'a str>,
+    /// A file with an armored GPG key or keyring. The signature is in the asset's `.asc` or `.sig`
+    /// sibling.
+    #[cfg(feature = "gpg")]
+    pub(crate) gpg_key_file: Option<PathBuf>,
+    /// The name of an asset with SLSA provenance for the release, like
+    /// `project.intoto.jsonl`, and the ID of the builder that it has to name.
+    #[cfg(feature = "slsa")]
+    pub(crate) slsa_provenance: Option<(&'a str, &'a str)>,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub(crate) struct Asset {
     pub(crate) name: String,
     pub(crate) url: Url,
+    /// The asset's digest as reported by the forge, like `sha256:<hex>`. GitHub includes this for
+    /// assets uploaded since mid-2025.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) digest: Option<String>,
 }
 
+/// A downloaded release asset, ready to be installed by an [`Installer`].
+///
+/// This is only public when the `unstable-installer` feature is enabled, and it may change in any
+/// release.
 #[derive(Debug)]
-pub(crate) struct Download {
+pub struct Download {
     // We need to keep the temp dir around so that it's not deleted before
     // we're done with it.
-    pub(crate) _temp_dir: TempDir,
+    pub(crate) _temp_dir: Option<TempDir>,
     pub(crate) archive_path: PathBuf,
+    /// The size of the download according to the response's `Content-Length` header, if it had
+    /// one.
+    pub(crate) expected_len: Option<u64>,
+    /// The digest of the download according to the forge's release API, if it gave us one.
+    pub(crate) expected_digest: Option<(ChecksumAlgorithm, String)>,
+    /// A base64 minisign public key and the contents of a `.minisig` file that the installers
+    /// check the download against before extracting anything.
+    pub(crate) minisign_signature: Option<(String, String)>,
+}
+
+impl Download {
+    /// Creates a `Download` for a release asset that is already at `archive_path`. The file is
+    /// left in place after it's installed.
+    #[must_use]
+    pub fn new(archive_path: PathBuf) -> Self {
+        Download {
+            _temp_dir: None,
+            archive_path,
+            expected_len: None,
+            expected_digest: None,
+            minisign_signature: None,
+        }
+    }
+
+    /// Sets a minisign public key, given as the base64 key on the second line of a `minisign.pub`
+    /// file, and the contents of the `.minisig` file for this download. When this is set,
+    /// [`Installer::install`] and the other install methods check that the signature is valid
+    /// before extracting anything, and return an error with the key ID and file name if it isn't.
+    #[must_use]
+    pub fn minisign_signature(mut self, public_key: &str, signature: &str) -> Self {
+        self.minisign_signature = Some((public_key.to_string(), signature.to_string()));
+        self
+    }
+
+    /// Checks that the downloaded file is as big as the server said it would be. When a download
+    /// is cut short, this gives a much clearer error than the one we'd get from a decompressor
+    /// that hits the end of a truncated file.
+    pub(crate) fn check_complete(&self) -> Result<()> {
+        let Some(expected_len) = self.expected_len else {
+            return Ok(());
+        };
+        let len = fs::metadata(&self.archive_path)
+            .with_context(|| format!("could not read {}", self.archive_path.display()))?
+            .len();
+        if len != expected_len {
+            return Err(anyhow!(
+                "the download of {} is incomplete: it is {len} bytes, but the server said it would \
+                 be {expected_len} bytes",
+                self.archive_path
+                    .file_name()
+                    .unwrap_or(self.archive_path.as_os_str())
+                    .to_string_lossy(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the downloaded file, read from `file`, against the digest that the forge gave us
+    /// for it, if any. This is done before anything is extracted, so a corrupted or tampered
+    /// download is never unpacked.
+    pub(crate) fn verify_digest(&self, file: impl Read) -> Result<()> {
+        let Some((algorithm, expect)) = &self.expected_digest else {
+            return Ok(());
+        };
+
+        let got = algorithm.hex_digest(file)?;
+        if !got.eq_ignore_ascii_case(expect) {
+            return Err(digest_mismatch_error(&self.archive_path, *algorithm, expect, &got).into());
+        }
+        debug!(
+            "the {} digest of the download matches the one from the forge",
+            algorithm.as_ref(),
+        );
+
+        Ok(())
+    }
+
+    /// Checks the downloaded file, read from `file`, against its minisign signature, if it has
+    /// one. Like the digest, this is done before anything is extracted.
+    pub(crate) fn verify_minisign_signature(&self, file: impl Read) -> Result<()> {
+        let Some((public_key, signature)) = &self.minisign_signature else {
+            return Ok(());
+        };
+
+        let file_name = self
+            .archive_path
+            .file_name()
+            .unwrap_or(self.archive_path.as_os_str())
+            .to_string_lossy();
+        minisign::verify_signature(public_key, file, &file_name, signature)?;
+        debug!("the minisign signature for {file_name} is valid");
+
+        Ok(())
+    }
 }
 
 impl<'a> Ubi<'a> {
@@ -40,24 +187,59 @@ impl<'a> Ubi<'a> {
     pub(crate) fn new(
         forge: Box<dyn Forge + Send + Sync>,
         asset_url: Option<Url>,
+        archive_file: Option<PathBuf>,
         asset_picker: AssetPicker<'a>,
         installer: Box<dyn Installer>,
+        checks: AssetChecks<'a>,
         reqwest_client: Client,
     ) -> Ubi<'a> {
         Ubi {
             forge,
             asset_url,
+            archive_file,
             asset_picker,
             installer,
+            checks,
             reqwest_client,
+            custom_decompressors: HashMap::new(),
+            temp_dir: None,
+            keep_archive: None,
         }
     }
 
+    // The commands to decompress files with extensions that we don't know, keyed by extension.
+    pub(crate) fn custom_decompressors(
+        mut self,
+        custom_decompressors: HashMap<String, Vec<String>>,
+    ) -> Self {
+        self.custom_decompressors = custom_decompressors;
+        self
+    }
+
+    // The directory to create the temp dirs that downloads are written to in. When this isn't set,
+    // they're created in the system's temp directory.
+    pub(crate) fn temp_dir(mut self, temp_dir: Option<PathBuf>) -> Self {
+        self.temp_dir = temp_dir;
+        self
+    }
+
+    // Where to copy each downloaded release asset to, so it's kept after the temp dir it was
+    // downloaded to is deleted.
+    pub(crate) fn keep_archive(mut self, keep_archive: Option<PathBuf>) -> Self {
+        self.keep_archive = keep_archive;
+        self
+    }
+
     /// Install the binary. This will download the appropriate release asset from GitHub and unpack
     /// it. It will look for an executable (based on the name of the project or the explicitly set
     /// executable name) in the unpacked archive and write it to the install directory. It will also
     /// set the executable bit on the installed binary on platforms where this is necessary.
     ///
+    /// This returns the installed paths. When installing a single executable, this is the path to
+    /// that executable, which may have an extension like `.exe` that the install path you gave did
+    /// not. When `extract_all` is enabled, these are the top-level entries in the install
+    /// directory.
+    ///
     /// # Errors
     ///
     /// There are a number of cases where an error can be returned:
@@ -67,28 +249,371 @@ impl<'a> Ubi<'a> {
     ///   to increase these).
     /// * Unable to find the requested project.
     /// * Unable to find a match for the platform on which the code is running.
+    /// * Unable to find the downloaded release file in the checksums file, or its digest does not
+    ///   match, when `verify_checksums_file` is set.
+    /// * Unable to verify the minisign or GPG signature of the downloaded release file, when
+    ///   `verify_minisign_signature` or `verify_gpg_signature` is set.
     /// * Unable to unpack/uncompress the downloaded release file.
     /// * Unable to find an executable with the right name in a downloaded archive.
     /// * Unable to write the executable to the specified directory.
     /// * Unable to set executable permissions on the installed binary.
-    pub async fn install_binary(&mut self) -> Result<()> {
-        let asset = self.asset().await?;
-        let download = self.download_asset(&self.reqwest_client, asset).await?;
-        self.installer.install(&download)
+    pub async fn install_binary(&mut self) -> Result<Vec<PathBuf>> {
+        let download = self.download().await?;
+        Ok(self.installer.install(&download)?)
+    }
+
+    /// Does the same thing as [`Ubi::install_binary`] when `extract_all` is enabled, but also
+    /// returns every archive member that was not extracted, along with the reason it was skipped.
+    /// See [`crate::SkipReason`] for why a member may be skipped.
+    ///
+    /// # Errors
+    ///
+    /// This can return any of the errors that [`Ubi::install_binary`] can. It also returns an
+    /// error if `extract_all` is not enabled.
+    pub async fn install_binary_reporting_skipped(
+        &mut self,
+    ) -> Result<(Vec<PathBuf>, Vec<SkippedMember>)> {
+        let download = self.download().await?;
+        Ok(self.installer.install_reporting_skipped(&download)?)
+    }
+
+    /// Does everything that [`Ubi::install_binary`] does, including downloading the release asset
+    /// and finding the file(s) to install in it, but does not write anything to the install
+    /// directory. This returns the path of every file that `install_binary` would write.
+    ///
+    /// # Errors
+    ///
+    /// This can return any of the errors that [`Ubi::install_binary`] can, other than those that
+    /// come from writing the installed files.
+    pub async fn install_binary_dry_run(&mut self) -> Result<Vec<PathBuf>> {
+        let download = self.download().await?;
+        Ok(self.installer.install_dry_run(&download)?)
+    }
+
+    /// Downloads the release asset that [`Ubi::install_binary`] would install from and lists every
+    /// file in it, along with how each one matches the executable name. Nothing is extracted or
+    /// installed. This is useful for figuring out why `ubi` can't find an executable in an
+    /// archive, or why it picked the wrong one.
+    ///
+    /// # Errors
+    ///
+    /// This can return any of the errors that [`Ubi::install_binary_dry_run`] can. It also returns
+    /// an error if the release asset is not a tarball or zip file, or if `extract_all` is enabled.
+    pub async fn scan_archive(&mut self) -> Result<Vec<(String, MatchKind)>> {
+        let download = self.download().await?;
+        Ok(self.installer.scan_archive(&download)?)
+    }
+
+    /// Does the same thing as [`Ubi::install_binary`], but returns a record of what was installed
+    /// where. This includes the archive member each executable came from, along with its final
+    /// path, mode, and size. The record can be serialized as JSON for other tools to read.
+    ///
+    /// # Errors
+    ///
+    /// This can return any of the errors that [`Ubi::install_binary`] can. It also returns an
+    /// error if `extract_all` is enabled.
+    pub async fn install_binary_with_manifest(&mut self) -> Result<InstallManifest> {
+        let download = self.download().await?;
+        Ok(self.installer.install_with_manifest(&download)?)
+    }
+
+    /// Does the same thing as [`Ubi::install_binary`] when `extract_all` is enabled, but returns a
+    /// record of every file that was extracted and where it ended up. When every archive member is
+    /// in one top-level directory, its contents are moved up into the install directory, and the
+    /// record says which directory that was and where each moved file was first extracted to. It
+    /// also includes every archive member that was skipped, along with why.
+    ///
+    /// # Errors
+    ///
+    /// This can return any of the errors that [`Ubi::install_binary`] can. It also returns an
+    /// error if `extract_all` is not enabled.
+    pub async fn install_binary_with_extraction_report(&mut self) -> Result<ExtractionReport> {
+        let download = self.download().await?;
+        Ok(self.installer.install_with_extraction_report(&download)?)
     }
 
+    /// Does everything that [`Ubi::install_binary`] does, but returns the contents of the
+    /// executable instead of writing it to the install directory. This is useful if you want to
+    /// hash the executable, embed it, or write it somewhere yourself.
+    ///
+    /// # Errors
+    ///
+    /// This can return any of the errors that [`Ubi::install_binary_dry_run`] can. It also returns
+    /// an error if `extract_all` is enabled.
+    pub async fn extract_binary_to_bytes(&mut self) -> Result<Vec<u8>> {
+        let download = self.download().await?;
+        Ok(self.installer.extract_to_bytes(&download)?)
+    }
+
+    /// Downloads the release asset that [`Ubi::install_binary`] would install from and returns
+    /// roughly how many bytes installing it would write, without installing anything. You can
+    /// compare this to the free space in the install directory before calling `install_binary`.
+    ///
+    /// For a tarball or zip file, this uses the member sizes recorded in the archive, so nothing is
+    /// extracted.
+    ///
+    /// # Errors
+    ///
+    /// This can return any of the errors that [`Ubi::install_binary_dry_run`] can. When
+    /// `extract_all` is enabled, it also returns an error if the release asset is not a tarball,
+    /// zip file, or 7z file.
+    pub async fn estimated_install_size(&mut self) -> Result<u64> {
+        let download = self.download().await?;
+        Ok(self.installer.estimated_size(&download)?)
+    }
+
+    // Downloads the release asset to install. If any asset checks are set, this also checks the
+    // downloaded file with them, so nothing is installed from a file that doesn't pass.
+    async fn download(&mut self) -> Result<Download> {
+        if let Some(archive_file) = &self.archive_file {
+            debug!("installing from the local file {}", archive_file.display());
+            return self.run_custom_decompressor(Download::new(archive_file.clone()));
+        }
+
+        let (asset, all_assets) = self.assets().await?;
+        // We find all the sibling assets we need before downloading anything, so that a missing
+        // one is reported right away.
+        let checksums_asset = self
+            .checks
+            .checksums_file
+            .map(|name| sibling_asset(&asset, all_assets.as_deref(), name))
+            .transpose()?;
+        let minisig_asset = self
+            .checks
+            .minisign_public_key
+            .map(|_| {
+                sibling_asset(
+                    &asset,
+                    all_assets.as_deref(),
+                    &format!("{}.minisig", asset.name),
+                )
+            })
+            .transpose()?;
+        #[cfg(feature = "gpg")]
+        let gpg_signature_assets = self
+            .checks
+            .gpg_key_file
+            .as_ref()
+            .map(|_| gpg_signature_assets(&asset, all_assets.as_deref()))
+            .transpose()?;
+        #[cfg(feature = "slsa")]
+        let provenance_asset = self
+            .checks
+            .slsa_provenance
+            .map(|(name, _)| sibling_asset(&asset, all_assets.as_deref(), name))
+            .transpose()?;
+
+        let asset_name = asset.name.clone();
+        let mut download = self.download_asset(&self.reqwest_client, asset).await?;
+        if let Some(checksums_asset) = checksums_asset {
+            self.verify_checksum(&download, &asset_name, checksums_asset)
+                .await?;
+        }
+        if let (Some(public_key), Some(minisig_asset)) =
+            (self.checks.minisign_public_key, minisig_asset)
+        {
+            // The installer checks the signature before it extracts anything.
+            let signature = self.download_minisign_signature(minisig_asset).await?;
+            download = download.minisign_signature(public_key, &signature);
+        }
+        #[cfg(feature = "gpg")]
+        if let (Some(key_file), Some(signature_assets)) =
+            (&self.checks.gpg_key_file, gpg_signature_assets)
+        {
+            self.verify_gpg_signature(&download, &asset_name, key_file, signature_assets)
+                .await?;
+        }
+        #[cfg(feature = "slsa")]
+        if let (Some((_, builder_id)), Some(provenance_asset)) =
+            (self.checks.slsa_provenance, provenance_asset)
+        {
+            self.check_slsa_provenance_subject(
+                &download,
+                &asset_name,
+                builder_id,
+                provenance_asset,
+            )
+            .await?;
+        }
+        if let Some(keep_archive) = &self.keep_archive {
+            keep_downloaded_archive(&download, keep_archive)?;
+        }
+
+        self.run_custom_decompressor(download)
+    }
+
+    // If the download has an extension that we don't know, but that has a custom decompressor,
+    // this returns a `Download` for the output of that decompressor. Otherwise it returns the
+    // download as is.
+    fn run_custom_decompressor(&self, download: Download) -> Result<Download> {
+        if self.custom_decompressors.is_empty()
+            || Extension::from_path(&download.archive_path).is_ok()
+        {
+            return Ok(download);
+        }
+        let file_name = download
+            .archive_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let Some(ext) = matching_custom_extension(
+            &file_name,
+            self.custom_decompressors.keys().map(String::as_str),
+        ) else {
+            return Ok(download);
+        };
+
+        // The installers check the forge's digest and the minisign signature for the file they're
+        // given, but those are for the compressed file, so we have to check them first.
+        download.verify_digest(open_file(&download.archive_path, false)?)?;
+        download.verify_minisign_signature(open_file(&download.archive_path, false)?)?;
+        decompress_with_command(
+            &download,
+            ext,
+            &self.custom_decompressors[ext],
+            self.temp_dir.as_deref(),
+        )
+    }
+
+    #[cfg(test)]
     pub(crate) async fn asset(&mut self) -> Result<Asset> {
+        Ok(self.assets().await?.0)
+    }
+
+    // Returns the asset to install, along with all of the release's assets when we got them from
+    // the forge. When a URL was given, we don't know what other assets exist.
+    async fn assets(&mut self) -> Result<(Asset, Option<Vec<Asset>>)> {
         if let Some(url) = &self.asset_url {
-            return Ok(Asset {
-                name: url.path().split('/').last().unwrap().to_string(),
-                url: url.clone(),
-            });
+            return Ok((
+                Asset {
+                    name: url.path().split('/').last().unwrap().to_string(),
+                    url: url.clone(),
+                    digest: None,
+                },
+                None,
+            ));
         }
 
         let assets = self.forge.fetch_assets(&self.reqwest_client).await?;
-        let asset = self.asset_picker.pick_asset(assets)?;
+        let asset = self.asset_picker.pick_asset(assets.clone())?;
         debug!("picked asset named {}", asset.name);
-        Ok(asset)
+        Ok((asset, Some(assets)))
+    }
+
+    async fn verify_checksum(
+        &self,
+        download: &Download,
+        asset_name: &str,
+        checksums_asset: Asset,
+    ) -> Result<()> {
+        let checksums_name = checksums_asset.name.clone();
+        let checksums_download = self
+            .download_asset(&self.reqwest_client, checksums_asset)
+            .await?;
+        let contents = fs::read_to_string(&checksums_download.archive_path)?;
+        let Some((algorithm, expect)) = digest_from_checksums_file(
+            &contents,
+            &checksums_name,
+            asset_name,
+            self.checks.checksums_algorithm,
+        ) else {
+            if let Some(algorithm) = self.checks.checksums_algorithm {
+                return Err(anyhow!(
+                    "could not find a {} digest for {asset_name} in the checksums file \
+                     {checksums_name}",
+                    algorithm.as_ref(),
+                ));
+            }
+            return Err(anyhow!(
+                "could not find {asset_name} in the checksums file {checksums_name}",
+            ));
+        };
+
+        if !algorithm.is_valid_hex_digest(expect) {
+            return Err(anyhow!(
+                "the digest for {asset_name} in {checksums_name}, {expect}, is not a valid {} digest",
+                algorithm.as_ref(),
+            ));
+        }
+        let got = algorithm.hex_digest(File::open(&download.archive_path)?)?;
+        if !got.eq_ignore_ascii_case(expect) {
+            return Err(anyhow!(
+                "the {} digest of {} is {}, but {} says it should be {}",
+                algorithm.as_ref(),
+                asset_name,
+                got,
+                checksums_name,
+                expect,
+            ));
+        }
+        debug!(
+            "the {} digest of {} matches {}",
+            algorithm.as_ref(),
+            asset_name,
+            checksums_name,
+        );
+
+        Ok(())
+    }
+
+    async fn download_minisign_signature(&self, minisig_asset: Asset) -> Result<String> {
+        let signature_download = self
+            .download_asset(&self.reqwest_client, minisig_asset)
+            .await?;
+        Ok(fs::read_to_string(&signature_download.archive_path)?)
+    }
+
+    // When we don't know the release's assets, there may be more than one possible signature
+    // asset, so we use the first one that we can download.
+    #[cfg(feature = "gpg")]
+    async fn verify_gpg_signature(
+        &self,
+        download: &Download,
+        asset_name: &str,
+        key_file: &std::path::Path,
+        signature_assets: Vec<Asset>,
+    ) -> Result<()> {
+        let mut signature_download = Err(anyhow!("no GPG signature asset for {asset_name}"));
+        for signature_asset in signature_assets {
+            signature_download = self
+                .download_asset(&self.reqwest_client, signature_asset)
+                .await;
+            if signature_download.is_ok() {
+                break;
+            }
+        }
+        let signature_download = signature_download?;
+
+        gpg::verify_signature(
+            key_file,
+            &download.archive_path,
+            asset_name,
+            &signature_download.archive_path,
+        )
+    }
+
+    #[cfg(feature = "slsa")]
+    async fn check_slsa_provenance_subject(
+        &self,
+        download: &Download,
+        asset_name: &str,
+        builder_id: &str,
+        provenance_asset: Asset,
+    ) -> Result<()> {
+        let provenance_name = provenance_asset.name.clone();
+        let provenance_download = self
+            .download_asset(&self.reqwest_client, provenance_asset)
+            .await?;
+        slsa::check_provenance_subject(
+            &fs::read_to_string(&provenance_download.archive_path)?,
+            &provenance_name,
+            &download.archive_path,
+            asset_name,
+            builder_id,
+        )?;
+        debug!("the SLSA provenance in {provenance_name} lists {asset_name} as a subject");
+
+        Ok(())
     }
 
     async fn download_asset(&self, client: &Client, asset: Asset) -> Result<Download> {
@@ -110,7 +635,16 @@ impl<'a> Ubi<'a> {
             return Err(anyhow!(msg));
         }
 
-        let td = tempdir()?;
+        let expected_digest = asset
+            .digest
+            .as_deref()
+            .map(parse_prefixed_digest)
+            .transpose()
+            .with_context(|| format!("could not parse the digest for the {} asset", asset.name))?;
+
+        // When the response is compressed, `reqwest` decompresses it and this is `None`.
+        let expected_len = resp.content_length();
+        let td = new_temp_dir(self.temp_dir.as_deref())?;
         let mut archive_path = td.path().to_path_buf();
         archive_path.push(&asset.name);
         debug!("archive path is {}", archive_path.to_string_lossy());
@@ -122,9 +656,298 @@ impl<'a> Ubi<'a> {
             }
         }
 
-        Ok(Download {
-            _temp_dir: td,
+        let download = Download {
+            _temp_dir: Some(td),
             archive_path,
-        })
+            expected_len,
+            expected_digest,
+            minisign_signature: None,
+        };
+        download.check_complete()?;
+
+        Ok(download)
+    }
+}
+
+// Returns the release asset with the given name that was published alongside `asset`. When we
+// have the release's assets, this must be one of them. Otherwise, we assume it's at the same
+// location as `asset`.
+fn sibling_asset(asset: &Asset, all_assets: Option<&[Asset]>, name: &str) -> Result<Asset> {
+    if let Some(all_assets) = all_assets {
+        return all_assets
+            .iter()
+            .find(|a| a.name == name)
+            .cloned()
+            .ok_or_else(|| anyhow!("could not find a release asset named {name}"));
+    }
+
+    Ok(Asset {
+        name: name.to_string(),
+        url: asset.url.join(name)?,
+        digest: None,
+    })
+}
+
+// Returns the possible detached GPG signature assets for `asset`, which are named like the asset
+// plus `.asc` or `.sig`.
+#[cfg(feature = "gpg")]
+fn gpg_signature_assets(asset: &Asset, all_assets: Option<&[Asset]>) -> Result<Vec<Asset>> {
+    let names = [".asc", ".sig"].map(|ext| format!("{}{ext}", asset.name));
+    if let Some(all_assets) = all_assets {
+        return names
+            .iter()
+            .find_map(|name| all_assets.iter().find(|a| &a.name == name))
+            .map(|a| vec![a.clone()])
+            .ok_or_else(|| {
+                anyhow!(
+                    "could not find a release asset named {} or {}",
+                    names[0],
+                    names[1],
+                )
+            });
+    }
+
+    names
+        .iter()
+        .map(|name| sibling_asset(asset, None, name))
+        .collect()
+}
+
+// Copies the downloaded file to `keep_archive`. When that's an existing directory, the file is
+// copied into it with its own name. Otherwise, it's copied to that path, replacing any file that's
+// already there.
+fn keep_downloaded_archive(download: &Download, keep_archive: &Path) -> Result<PathBuf> {
+    let dest = if keep_archive.is_dir() {
+        let Some(file_name) = download.archive_path.file_name() else {
+            return Err(anyhow!(
+                "the downloaded file at {} has no file name",
+                download.archive_path.display(),
+            ));
+        };
+        keep_archive.join(file_name)
+    } else {
+        if let Some(parent) = keep_archive.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("could not create a directory at {}", parent.display()))?;
+        }
+        keep_archive.to_path_buf()
+    };
+
+    debug!(
+        "copying the downloaded file at {} to {}",
+        download.archive_path.display(),
+        dest.display(),
+    );
+    fs::copy(&download.archive_path, &dest).with_context(|| {
+        format!(
+            "could not copy the downloaded file at {} to {}",
+            download.archive_path.display(),
+            dest.display(),
+        )
+    })?;
+
+    Ok(dest)
+}
+
+// Creates a temp dir in `parent`, creating `parent` first if needed, or in the system's temp
+// directory when `parent` is `None`. Putting temp dirs on the same filesystem as the install
+// directory means that files can be renamed into place instead of being copied.
+pub(crate) fn new_temp_dir(parent: Option<&Path>) -> Result<TempDir> {
+    let Some(parent) = parent else {
+        return Ok(tempdir()?);
+    };
+    fs::create_dir_all(parent)
+        .with_context(|| format!("could not create a directory at {}", parent.display()))?;
+    tempfile::Builder::new()
+        .prefix("ubi-")
+        .tempdir_in(parent)
+        .with_context(|| format!("could not create a temp dir in {}", parent.display()))
+}
+
+// Runs `command` with the downloaded file as its stdin, and writes its stdout to a file named
+// after the downloaded file without `ext`, so `project.tar.foo` is decompressed to `project.tar`.
+fn decompress_with_command(
+    download: &Download,
+    ext: &str,
+    command: &[String],
+    temp_dir: Option<&Path>,
+) -> Result<Download> {
+    let file_name = download
+        .archive_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let decompressed_name = &file_name[..file_name.len() - ext.len() - 1];
+    let td = new_temp_dir(temp_dir)?;
+    let archive_path = td.path().join(decompressed_name);
+
+    let input = File::open(&download.archive_path)
+        .with_context(|| format!("could not open {}", download.archive_path.display()))?;
+    let output = File::create(&archive_path)
+        .with_context(|| format!("could not create {}", archive_path.display()))?;
+    let mut cmd = Command::new(&command[0]);
+    cmd.args(&command[1..])
+        .stdin(input)
+        .stdout(output)
+        .stderr(Stdio::piped());
+    debug!(
+        "decompressing {} to {} with {cmd:?}",
+        download.archive_path.display(),
+        archive_path.display(),
+    );
+    let result = cmd.output().with_context(|| {
+        format!(
+            "could not run the custom decompressor for the `{ext}` extension, `{}`",
+            command.join(" "),
+        )
+    })?;
+    if !result.status.success() {
+        return Err(anyhow!(
+            "the custom decompressor for the `{ext}` extension, `{}`, failed to decompress {} ({}): {}",
+            command.join(" "),
+            download.archive_path.display(),
+            result.status,
+            String::from_utf8_lossy(&result.stderr).trim(),
+        ));
+    }
+
+    Ok(Download {
+        _temp_dir: Some(td),
+        archive_path,
+        expected_len: None,
+        expected_digest: None,
+        minisign_signature: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn decompress_with_command() -> Result<()> {
+        let td = tempdir()?;
+        let archive_path = td.path().join("project.tar.foo");
+        fs::copy("test-data/project.tar.gz", &archive_path)?;
+
+        let command = vec!["gzip".to_string(), "-dc".to_string()];
+        let download = super::decompress_with_command(
+            &Download::new(archive_path),
+            "foo",
+            &command,
+            Some(&td.path().join("tmp")),
+        )?;
+        assert!(download.archive_path.starts_with(td.path().join("tmp")));
+        assert_eq!(
+            download.archive_path.file_name().and_then(|n| n.to_str()),
+            Some("project.tar"),
+        );
+        assert_eq!(
+            Extension::from_path(&download.archive_path)?,
+            Some(Extension::Tar)
+        );
+        let mut arch = binstall_tar::Archive::new(File::open(&download.archive_path)?);
+        assert_eq!(arch.entries()?.count(), 3);
+
+        let command = vec!["false".to_string()];
+        let err = super::decompress_with_command(
+            &Download::new(td.path().join("project.tar.foo")),
+            "foo",
+            &command,
+            None,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("the custom decompressor for the `foo` extension, `false`, failed"));
+
+        Ok(())
+    }
+
+    #[test_case(false, "project.tar.gz"; "into a directory")]
+    #[test_case(true, "kept/archive.tar.gz"; "to a file path")]
+    fn keep_downloaded_archive(to_file_path: bool, expect_path: &str) -> Result<()> {
+        let td = tempdir()?;
+        let archive_path = td.path().join("download").join("project.tar.gz");
+        fs::create_dir(td.path().join("download"))?;
+        fs::copy("test-data/project.tar.gz", &archive_path)?;
+        let keep_dir = td.path().join("keep");
+        fs::create_dir(&keep_dir)?;
+
+        let keep_archive = if to_file_path {
+            keep_dir.join("kept").join("archive.tar.gz")
+        } else {
+            keep_dir.clone()
+        };
+        let kept = super::keep_downloaded_archive(&Download::new(archive_path), &keep_archive)?;
+
+        assert_eq!(kept, keep_dir.join(expect_path));
+        assert_eq!(fs::read(&kept)?, fs::read("test-data/project.tar.gz")?);
+
+        Ok(())
+    }
+
+    #[test_case(None, None; "no expected length")]
+    #[test_case(Some(3), None; "complete")]
+    #[test_case(
+        Some(10),
+        Some("the download of project is incomplete: it is 3 bytes, but the server said it would be 10 bytes");
+        "incomplete"
+    )]
+    fn check_complete(expected_len: Option<u64>, expect_err: Option<&str>) -> Result<()> {
+        let td = tempdir()?;
+        let archive_path = td.path().join("project");
+        fs::write(&archive_path, "exe")?;
+        let download = Download {
+            _temp_dir: Some(td),
+            archive_path,
+            expected_len,
+            expected_digest: None,
+            minisign_signature: None,
+        };
+
+        assert_eq!(
+            download.check_complete().err().map(|e| e.to_string()),
+            expect_err.map(str::to_string),
+        );
+
+        Ok(())
+    }
+
+    #[test_case(None, None; "no expected digest")]
+    #[test_case(
+        Some("9095bdb859308b62acf04036ffd4adfe366d7f737d276eb6c46ae434f3816c9b"),
+        None;
+        "matching digest"
+    )]
+    #[test_case(
+        Some("0000000000000000000000000000000000000000000000000000000000000000"),
+        Some("the sha256 digest of {} is 9095bdb859308b62acf04036ffd4adfe366d7f737d276eb6c46ae434f3816c9b, but we expected 0000000000000000000000000000000000000000000000000000000000000000");
+        "mismatched digest"
+    )]
+    fn verify_digest(expect_digest: Option<&str>, expect_err: Option<&str>) -> Result<()> {
+        let td = tempdir()?;
+        let archive_path = td.path().join("project");
+        fs::write(&archive_path, "exe")?;
+        let expect_err = expect_err.map(|e| e.replace("{}", &archive_path.display().to_string()));
+        let download = Download {
+            _temp_dir: Some(td),
+            archive_path,
+            expected_len: None,
+            expected_digest: expect_digest.map(|d| (ChecksumAlgorithm::Sha256, d.to_string())),
+            minisign_signature: None,
+        };
+
+        assert_eq!(
+            download
+                .verify_digest(File::open(&download.archive_path)?)
+                .err()
+                .map(|e| e.to_string()),
+            expect_err,
+        );
+
+        Ok(())
     }
 }